@@ -0,0 +1 @@
+ALTER TABLE telegram_chats ADD COLUMN max_subscriptions INTEGER CHECK (max_subscriptions > 0);
@@ -0,0 +1 @@
+ALTER TABLE telegram_chats ADD COLUMN paused BOOLEAN NOT NULL DEFAULT FALSE;
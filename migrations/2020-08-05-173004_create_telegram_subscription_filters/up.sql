@@ -0,0 +1,12 @@
+CREATE TABLE telegram_subscription_filters (
+   chat_id BIGINT NOT NULL,
+   feed_id BIGINT NOT NULL,
+   keyword TEXT NOT NULL,
+   created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+   updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+   PRIMARY KEY(chat_id, feed_id, keyword),
+   FOREIGN KEY(chat_id, feed_id) REFERENCES telegram_subscriptions(chat_id, feed_id) ON DELETE CASCADE
+);
+
+ALTER TABLE telegram_subscription_filters
+      ADD CONSTRAINT telegram_subscription_filters_keyword_size CHECK (char_length(keyword) > 0);
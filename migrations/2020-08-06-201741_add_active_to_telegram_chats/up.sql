@@ -0,0 +1 @@
+ALTER TABLE telegram_chats ADD COLUMN active BOOLEAN NOT NULL DEFAULT TRUE;
@@ -0,0 +1 @@
+ALTER TABLE telegram_chats ADD COLUMN timezone_name TEXT;
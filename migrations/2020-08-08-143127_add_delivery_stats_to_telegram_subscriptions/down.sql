@@ -0,0 +1,2 @@
+ALTER TABLE telegram_subscriptions DROP COLUMN delivered_at;
+ALTER TABLE telegram_subscriptions DROP COLUMN delivered_count;
@@ -0,0 +1,2 @@
+ALTER TABLE telegram_subscriptions ADD COLUMN delivered_count BIGINT NOT NULL DEFAULT 0;
+ALTER TABLE telegram_subscriptions ADD COLUMN delivered_at TIMESTAMP WITH TIME ZONE;
@@ -0,0 +1 @@
+ALTER TABLE telegram_subscriptions DROP COLUMN initial_items_count;
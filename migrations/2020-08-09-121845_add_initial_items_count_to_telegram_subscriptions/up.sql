@@ -0,0 +1 @@
+ALTER TABLE telegram_subscriptions ADD COLUMN initial_items_count INTEGER NOT NULL DEFAULT 10;
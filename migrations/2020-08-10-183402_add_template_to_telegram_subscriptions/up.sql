@@ -0,0 +1 @@
+ALTER TABLE telegram_subscriptions ADD COLUMN template TEXT;
@@ -0,0 +1 @@
+ALTER TABLE telegram_subscriptions ADD COLUMN silent BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,3 @@
+ALTER TABLE telegram_chats DROP COLUMN digest_sent_at;
+ALTER TABLE telegram_chats DROP COLUMN digest_hour;
+ALTER TABLE telegram_chats DROP COLUMN digest;
@@ -0,0 +1,3 @@
+ALTER TABLE telegram_chats ADD COLUMN digest BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE telegram_chats ADD COLUMN digest_hour INTEGER NOT NULL DEFAULT 9;
+ALTER TABLE telegram_chats ADD COLUMN digest_sent_at TIMESTAMP WITH TIME ZONE;
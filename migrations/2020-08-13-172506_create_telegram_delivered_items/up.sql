@@ -0,0 +1,14 @@
+CREATE TABLE telegram_delivered_items (
+   chat_id BIGINT NOT NULL,
+   feed_id BIGINT NOT NULL,
+   title TEXT NOT NULL,
+   link TEXT NOT NULL,
+   published_at TIMESTAMP WITH TIME ZONE NOT NULL,
+   created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+   updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+   PRIMARY KEY(chat_id, feed_id, link),
+   FOREIGN KEY(chat_id, feed_id) REFERENCES telegram_subscriptions(chat_id, feed_id) ON DELETE CASCADE
+);
+
+CREATE INDEX telegram_delivered_items_chat_id_published_at_index
+       ON telegram_delivered_items(chat_id, published_at);
@@ -0,0 +1,3 @@
+DROP INDEX feed_items_feed_id_content_hash_index;
+
+ALTER TABLE feed_items DROP COLUMN content_hash;
@@ -0,0 +1,3 @@
+ALTER TABLE feed_items ADD COLUMN content_hash TEXT;
+
+CREATE INDEX feed_items_feed_id_content_hash_index ON feed_items(feed_id, content_hash);
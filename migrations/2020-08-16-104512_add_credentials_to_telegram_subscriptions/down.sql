@@ -0,0 +1,2 @@
+ALTER TABLE telegram_subscriptions DROP COLUMN auth_username;
+ALTER TABLE telegram_subscriptions DROP COLUMN auth_password;
@@ -0,0 +1,2 @@
+ALTER TABLE telegram_subscriptions ADD COLUMN auth_username TEXT;
+ALTER TABLE telegram_subscriptions ADD COLUMN auth_password TEXT;
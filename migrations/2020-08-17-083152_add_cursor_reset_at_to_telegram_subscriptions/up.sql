@@ -0,0 +1 @@
+ALTER TABLE telegram_subscriptions ADD COLUMN cursor_reset_at TIMESTAMP WITH TIME ZONE;
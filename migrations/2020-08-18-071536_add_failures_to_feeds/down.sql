@@ -0,0 +1,2 @@
+ALTER TABLE feeds DROP COLUMN failures_count;
+ALTER TABLE feeds DROP COLUMN dead;
@@ -0,0 +1,2 @@
+ALTER TABLE feeds ADD COLUMN failures_count INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE feeds ADD COLUMN dead BOOLEAN NOT NULL DEFAULT FALSE;
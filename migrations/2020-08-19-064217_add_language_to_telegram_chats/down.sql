@@ -0,0 +1 @@
+ALTER TABLE telegram_chats DROP COLUMN language;
@@ -0,0 +1,5 @@
+ALTER TABLE telegram_subscription_filters DROP CONSTRAINT telegram_subscription_filters_chat_id_feed_id_fkey;
+ALTER TABLE telegram_subscription_filters ADD CONSTRAINT telegram_subscription_filters_chat_id_feed_id_fkey FOREIGN KEY(chat_id, feed_id) REFERENCES telegram_subscriptions(chat_id, feed_id) ON DELETE CASCADE ON UPDATE CASCADE;
+
+ALTER TABLE telegram_delivered_items DROP CONSTRAINT telegram_delivered_items_chat_id_feed_id_fkey;
+ALTER TABLE telegram_delivered_items ADD CONSTRAINT telegram_delivered_items_chat_id_feed_id_fkey FOREIGN KEY(chat_id, feed_id) REFERENCES telegram_subscriptions(chat_id, feed_id) ON DELETE CASCADE ON UPDATE CASCADE;
@@ -0,0 +1 @@
+ALTER TABLE telegram_subscriptions DROP COLUMN min_delivery_interval_secs;
@@ -0,0 +1 @@
+ALTER TABLE telegram_subscriptions ADD COLUMN min_delivery_interval_secs INTEGER;
@@ -0,0 +1 @@
+ALTER TABLE telegram_subscriptions ADD COLUMN link_preview BOOLEAN NOT NULL DEFAULT TRUE;
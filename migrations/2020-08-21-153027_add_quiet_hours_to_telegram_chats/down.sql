@@ -0,0 +1,2 @@
+ALTER TABLE telegram_chats DROP COLUMN quiet_hours_start;
+ALTER TABLE telegram_chats DROP COLUMN quiet_hours_end;
@@ -0,0 +1,2 @@
+ALTER TABLE telegram_chats ADD COLUMN quiet_hours_start INTEGER;
+ALTER TABLE telegram_chats ADD COLUMN quiet_hours_end INTEGER;
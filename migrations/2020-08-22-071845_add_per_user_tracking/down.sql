@@ -0,0 +1,3 @@
+ALTER TABLE telegram_delivered_items DROP COLUMN user_id;
+ALTER TABLE telegram_subscriptions DROP COLUMN user_id;
+ALTER TABLE telegram_chats DROP COLUMN track_per_user;
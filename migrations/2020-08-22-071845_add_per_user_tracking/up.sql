@@ -0,0 +1,3 @@
+ALTER TABLE telegram_chats ADD COLUMN track_per_user BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE telegram_subscriptions ADD COLUMN user_id BIGINT;
+ALTER TABLE telegram_delivered_items ADD COLUMN user_id BIGINT;
@@ -0,0 +1 @@
+ALTER TABLE telegram_subscriptions ADD COLUMN snoozed_until TIMESTAMP WITH TIME ZONE;
@@ -0,0 +1 @@
+ALTER TABLE telegram_subscriptions ADD COLUMN full_text BOOLEAN;
@@ -0,0 +1 @@
+ALTER TABLE telegram_subscriptions ADD COLUMN deleted_at TIMESTAMP WITH TIME ZONE;
@@ -0,0 +1 @@
+ALTER TABLE telegram_chats ADD COLUMN forbidden_errors_count INTEGER NOT NULL DEFAULT 0;
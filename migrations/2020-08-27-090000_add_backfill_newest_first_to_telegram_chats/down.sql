@@ -0,0 +1 @@
+ALTER TABLE telegram_chats DROP COLUMN backfill_newest_first;
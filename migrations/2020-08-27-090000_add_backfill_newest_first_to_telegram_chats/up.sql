@@ -0,0 +1 @@
+ALTER TABLE telegram_chats ADD COLUMN backfill_newest_first BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,10 @@
+CREATE TABLE telegram_chat_watchwords (
+   chat_id BIGINT NOT NULL REFERENCES telegram_chats(id) ON DELETE CASCADE,
+   keyword TEXT NOT NULL,
+   created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+   updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+   PRIMARY KEY(chat_id, keyword)
+);
+
+ALTER TABLE telegram_chat_watchwords
+      ADD CONSTRAINT telegram_chat_watchwords_keyword_size CHECK (char_length(keyword) > 0);
@@ -0,0 +1 @@
+ALTER TABLE telegram_chats ADD COLUMN show_author BOOLEAN NOT NULL DEFAULT FALSE;
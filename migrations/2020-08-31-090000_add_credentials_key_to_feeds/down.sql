@@ -0,0 +1,4 @@
+DELETE FROM feeds WHERE credentials_key <> '';
+ALTER TABLE feeds DROP CONSTRAINT feeds_link_credentials_key_key;
+ALTER TABLE feeds ADD CONSTRAINT feeds_link_key UNIQUE (link);
+ALTER TABLE feeds DROP COLUMN credentials_key;
@@ -0,0 +1,33 @@
+ALTER TABLE feeds ADD COLUMN credentials_key TEXT NOT NULL DEFAULT '';
+ALTER TABLE feeds DROP CONSTRAINT feeds_link_key;
+ALTER TABLE feeds ADD CONSTRAINT feeds_link_credentials_key_key UNIQUE (link, credentials_key);
+
+-- every existing authenticated subscription gets a private copy of its feed
+INSERT INTO feeds (title, link, description, feed_type, synced_at, credentials_key)
+SELECT feeds.title, feeds.link, feeds.description, feeds.feed_type, feeds.synced_at, 'legacy-' || telegram_subscriptions.chat_id
+FROM telegram_subscriptions
+INNER JOIN feeds ON feeds.id = telegram_subscriptions.feed_id
+WHERE telegram_subscriptions.auth_username IS NOT NULL;
+
+INSERT INTO feed_items (feed_id, title, description, link, author, guid, publication_date, created_at, updated_at, content_hash)
+SELECT private_feeds.id, feed_items.title, feed_items.description, feed_items.link, feed_items.author, feed_items.guid, feed_items.publication_date, feed_items.created_at, feed_items.updated_at, feed_items.content_hash
+FROM telegram_subscriptions
+INNER JOIN feeds ON feeds.id = telegram_subscriptions.feed_id
+INNER JOIN feeds AS private_feeds ON private_feeds.link = feeds.link AND private_feeds.credentials_key = 'legacy-' || telegram_subscriptions.chat_id
+INNER JOIN feed_items ON feed_items.feed_id = feeds.id
+WHERE telegram_subscriptions.auth_username IS NOT NULL;
+
+-- items fetched with someone's credentials must not stay in the shared feed
+DELETE FROM feed_items
+WHERE feed_id IN (SELECT feed_id FROM telegram_subscriptions WHERE auth_username IS NOT NULL);
+
+UPDATE feeds SET etag = NULL, last_modified = NULL
+WHERE id IN (SELECT feed_id FROM telegram_subscriptions WHERE auth_username IS NOT NULL);
+
+UPDATE telegram_subscriptions
+SET feed_id = private_feeds.id
+FROM feeds, feeds AS private_feeds
+WHERE feeds.id = telegram_subscriptions.feed_id
+AND private_feeds.link = feeds.link
+AND private_feeds.credentials_key = 'legacy-' || telegram_subscriptions.chat_id
+AND telegram_subscriptions.auth_username IS NOT NULL;
@@ -0,0 +1 @@
+ALTER TABLE telegram_subscriptions ADD COLUMN html BOOLEAN NOT NULL DEFAULT FALSE;
@@ -1,23 +1,193 @@
+use crate::bot::api_pool;
+use crate::bot::blocklist;
+use crate::bot::commands_menu::{BotCommand, SetMyCommands};
+use crate::bot::i18n;
 use crate::bot::logic;
-use crate::bot::logic::{DeleteSubscriptionError, SubscriptionError};
+use crate::bot::logic::{
+    DeleteSubscriptionError, FilterError, MessageFormat, MoveSubscriptionError, SubscriptionError,
+    WatchwordError,
+};
+use crate::bot::metrics;
+use crate::bot::metrics::Metrics;
+use crate::bot::rate_limiter::RateLimiter;
+use crate::bot::webhook;
+use crate::bot::webhook::{BotMode, WebhookConfig};
 use crate::db;
+use crate::db::feeds;
+use crate::db::telegram;
 use crate::db::telegram::NewTelegramChat;
+use crate::sync::reader;
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use futures::stream::{FuturesUnordered, LocalBoxStream};
 use futures::StreamExt;
 use std::env;
+use std::fmt;
+use std::process;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 use telegram_bot::prelude::*;
+use telegram_bot::types::Request;
 use telegram_bot::{
-    Api, ChannelPost, Error, Message, MessageChat, MessageKind, MessageOrChannelPost, UpdateKind,
-    UserId,
+    Api, ChannelPost, ChatId, ChatMemberStatus, Document, Error, GetChatMember, GetMe,
+    InputFileUpload, Message, MessageChat, MessageKind, MessageOrChannelPost, ParseMode,
+    SendMessage, Update, UpdateKind, UserId,
 };
+use tokio::signal;
+use tokio::signal::unix::SignalKind;
+use tokio::task::JoinHandle;
+use tokio::time;
 
 static SUBSCRIBE: &str = "/subscribe";
 static LIST_SUBSCRIPTIONS: &str = "/list_subscriptions";
 static SET_TIMEZONE: &str = "/set_timezone";
 static GET_TIMEZONE: &str = "/get_timezone";
+static SET_LIMIT: &str = "/set_limit";
+static PAUSE: &str = "/pause";
+static RESUME: &str = "/resume";
+static ADD_FILTER: &str = "/add_filter";
+static REMOVE_FILTER: &str = "/remove_filter";
+static LIST_FILTERS: &str = "/list_filters";
+static WATCH: &str = "/watch";
+static UNWATCH: &str = "/unwatch";
+static WATCHWORDS: &str = "/watchwords";
+static PREVIEW: &str = "/preview";
+static STATS: &str = "/stats";
+static SET_FORMAT: &str = "/set_format";
+static RESET_FORMAT: &str = "/reset_format";
+static MUTE: &str = "/mute";
+static UNMUTE: &str = "/unmute";
+static EXPORT: &str = "/export";
+static IMPORT: &str = "/import";
+static CLONE_FROM: &str = "/clone_from";
+static FEED_INFO: &str = "/feed_info";
+static SET_DIGEST: &str = "/set_digest";
+static SEARCH: &str = "/search";
+static SET_TRUNCATE: &str = "/set_truncate";
+static WHOAMI: &str = "/whoami";
+static RESUBSCRIBE: &str = "/resubscribe";
+static LAST: &str = "/last";
+static SET_LANGUAGE: &str = "/set_language";
+static MOVE: &str = "/move";
+static VALIDATE: &str = "/validate";
+static SET_THROTTLE: &str = "/set_throttle";
+static UNSUBSCRIBE_ALL: &str = "/unsubscribe_all";
+static SET_PREVIEW: &str = "/set_preview";
+static HEALTH: &str = "/health";
+static SET_QUIET_HOURS: &str = "/set_quiet_hours";
+static TRACK_PER_USER: &str = "/track_per_user";
+static SET_PREFIX: &str = "/set_prefix";
+static RESET_PREFIX: &str = "/reset_prefix";
+static SNOOZE: &str = "/snooze";
+static FEEDBACK: &str = "/feedback";
+static TOP: &str = "/top";
+static SET_FULLTEXT: &str = "/set_fulltext";
+static SET_MARKDOWN: &str = "/set_markdown";
+static SET_HTML: &str = "/set_html";
+static TEST_FORMAT: &str = "/test_format";
+static RESTORE: &str = "/restore";
+static UNREAD: &str = "/unread";
+static RANDOM: &str = "/random";
+static BROADCAST: &str = "/broadcast";
+static POPULAR: &str = "/popular";
+static DISABLED_CHATS: &str = "/disabled_chats";
+static VERSION: &str = "/version";
+static SET_BACKFILL_ORDER: &str = "/set_backfill_order";
+static SET_AUTHOR: &str = "/set_author";
 static UNSUBSCRIBE: &str = "/unsubscribe";
 static HELP: &str = "/help";
 static START: &str = "/start";
 
+static BLOCKED_ERROR: &str = "Forbidden: bot was blocked by the user";
+static CHAT_NOT_FOUND: &str = "Bad Request: chat not found";
+static KICKED_ERROR: &str = "Forbidden: bot was kicked from the supergroup chat";
+static DEACTIVATED_ERROR: &str = "Forbidden: user is deactivated";
+static CHAT_UPGRADED_ERROR: &str = "Bad Request: group chat was upgraded to a supergroup chat";
+static BOT_IS_NOT_MEMBER: &str = "Forbidden: bot is not a member of the supergroup chat";
+static TOO_MANY_REQUESTS: &str = "Too Many Requests";
+
+static BROADCAST_DELAY_MILLIS: u64 = 50;
+static MAX_MESSAGE_LENGTH: usize = 4096;
+static FEEDBACK_PER_MINUTE: u32 = 1;
+static MAX_SEND_ATTEMPTS: u32 = 3;
+static DEFAULT_RETRY_AFTER_SECS: u64 = 1;
+static MAX_COMMAND_DESCRIPTION_LENGTH: usize = 256;
+
+struct Context {
+    limiter: RateLimiter,
+    feedback_limiter: RateLimiter,
+    subscription_limit: i64,
+    subscription_limit_ceiling: i32,
+    sync_interval_secs: u64,
+    delivery_interval_secs: u64,
+    pool: db::Pool,
+    bot_id: UserId,
+    metrics: Arc<Metrics>,
+    started_at: Instant,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum SendMessageError {
+    BotBlocked(String),
+    ChatNotFound(String),
+    RateLimited(String),
+    Other(String),
+}
+
+impl From<Error> for SendMessageError {
+    fn from(error: Error) -> Self {
+        SendMessageError::from_message(format!("{}", error))
+    }
+}
+
+impl SendMessageError {
+    fn from_message(error_message: String) -> Self {
+        if error_message == BLOCKED_ERROR
+            || error_message == KICKED_ERROR
+            || error_message == DEACTIVATED_ERROR
+            || error_message == BOT_IS_NOT_MEMBER
+        {
+            SendMessageError::BotBlocked(error_message)
+        } else if error_message == CHAT_NOT_FOUND || error_message.contains(CHAT_UPGRADED_ERROR) {
+            SendMessageError::ChatNotFound(error_message)
+        } else if error_message.contains(TOO_MANY_REQUESTS) {
+            SendMessageError::RateLimited(error_message)
+        } else {
+            SendMessageError::Other(error_message)
+        }
+    }
+
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            SendMessageError::RateLimited(error_message) => Some(Duration::from_secs(
+                parse_retry_after(error_message).unwrap_or(DEFAULT_RETRY_AFTER_SECS),
+            )),
+            _ => None,
+        }
+    }
+}
+
+fn parse_retry_after(error_message: &str) -> Option<u64> {
+    let position = error_message.to_lowercase().find("retry after ")?;
+
+    error_message[position + "retry after ".len()..]
+        .chars()
+        .take_while(|character| character.is_ascii_digit())
+        .collect::<String>()
+        .parse::<u64>()
+        .ok()
+}
+
+impl fmt::Display for SendMessageError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SendMessageError::BotBlocked(msg) => write!(f, "bot is blocked: {}", msg),
+            SendMessageError::ChatNotFound(msg) => write!(f, "chat not found: {}", msg),
+            SendMessageError::RateLimited(msg) => write!(f, "rate limited: {}", msg),
+            SendMessageError::Other(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
 impl From<MessageChat> for NewTelegramChat {
     fn from(message_chat: MessageChat) -> Self {
         match message_chat {
@@ -73,225 +243,2724 @@ impl From<MessageOrChannelPost> for NewTelegramChat {
     }
 }
 
-fn commands_string() -> String {
-    format!(
-        "{} - show the bot's description and contact information\n\
-         {} url - subscribe to feed\n\
-         {} url - unsubscribe from feed\n\
-         {} - list your subscriptions\n\
-         {} - show available commands\n\
-         {} - set your timezone. All received dates will be converted to this timezone. It should be offset in minutes from UTC. For example, if you live in UTC +10 timezone, offset is equal to 600\n\
-         {} - get your timezone\n",
-        START, SUBSCRIBE, UNSUBSCRIBE, LIST_SUBSCRIPTIONS, HELP, SET_TIMEZONE, GET_TIMEZONE
-    )
+fn commands() -> Vec<&'static str> {
+    vec![
+        START,
+        SUBSCRIBE,
+        UNSUBSCRIBE,
+        LIST_SUBSCRIPTIONS,
+        HELP,
+        SET_TIMEZONE,
+        GET_TIMEZONE,
+        SET_LIMIT,
+        PAUSE,
+        RESUME,
+        ADD_FILTER,
+        REMOVE_FILTER,
+        LIST_FILTERS,
+        WATCH,
+        UNWATCH,
+        WATCHWORDS,
+        PREVIEW,
+        STATS,
+        SET_FORMAT,
+        RESET_FORMAT,
+        MUTE,
+        UNMUTE,
+        EXPORT,
+        IMPORT,
+        CLONE_FROM,
+        FEED_INFO,
+        SET_DIGEST,
+        SEARCH,
+        SET_TRUNCATE,
+        WHOAMI,
+        RESUBSCRIBE,
+        LAST,
+        SET_LANGUAGE,
+        MOVE,
+        SET_THROTTLE,
+        UNSUBSCRIBE_ALL,
+        SET_PREVIEW,
+        HEALTH,
+        SET_QUIET_HOURS,
+        TRACK_PER_USER,
+        SET_AUTHOR,
+        SET_BACKFILL_ORDER,
+        SET_PREFIX,
+        RESET_PREFIX,
+        SNOOZE,
+        FEEDBACK,
+        TOP,
+        SET_FULLTEXT,
+        SET_MARKDOWN,
+        SET_HTML,
+        TEST_FORMAT,
+        RESTORE,
+        UNREAD,
+        RANDOM,
+        VERSION,
+    ]
+}
+
+fn command_specs(language: &str) -> Vec<(&'static str, String)> {
+    commands()
+        .into_iter()
+        .map(|command| {
+            let key = format!("command_description_{}", command.trim_start_matches('/'));
+
+            (command, i18n::t(language, &key))
+        })
+        .collect()
+}
+
+fn commands_string(language: &str) -> String {
+    command_specs(language)
+        .iter()
+        .map(|(command, description)| format!("{} {}\n", command, description))
+        .collect()
 }
 
-async fn help(api: Api, message: MessageOrChannelPost) -> Result<(), Error> {
-    let response = commands_string();
+fn bot_commands() -> Vec<BotCommand> {
+    command_specs(i18n::DEFAULT_LANGUAGE)
+        .iter()
+        .map(|(command, description)| BotCommand {
+            command: command.trim_start_matches('/').to_string(),
+            description: menu_description(description),
+        })
+        .collect()
+}
+
+fn menu_description(description: &str) -> String {
+    let description = description.trim_start_matches("- ");
+    let first_sentence = description.split(". ").next().unwrap_or(description);
+
+    first_sentence
+        .chars()
+        .take(MAX_COMMAND_DESCRIPTION_LENGTH)
+        .collect()
+}
+
+fn command_help(command: &str, language: &str) -> Option<String> {
+    let command = command.trim().trim_start_matches('/').to_lowercase();
+
+    if !commands()
+        .iter()
+        .any(|known| known.trim_start_matches('/') == command)
+    {
+        return None;
+    }
+
+    Some(i18n::t(language, &format!("command_help_{}", command)))
+}
+
+async fn help(
+    api: Api,
+    message: MessageOrChannelPost,
+    language: &str,
+    data: String,
+) -> Result<(), Error> {
+    let response = if data.is_empty() {
+        i18n::tf(
+            language,
+            "help_commands",
+            &[&commands_string(language), &HELP, &HELP],
+        )
+    } else {
+        match command_help(&data, language) {
+            Some(help) => help,
+            None => i18n::t(language, "help_unknown_command"),
+        }
+    };
 
-    api.send(message.text_reply(response)).await?;
+    send_reply(&api, &message, response).await?;
     Ok(())
 }
 
-async fn start(api: Api, message: MessageOrChannelPost) -> Result<(), Error> {
-    let response = format!(
-        "El Monitorro is feed reader as a Telegram bot.\n\
-         It supports RSS, Atom and JSON feeds.\n\n\
-         Available commands:\n\
-         {}\n\n\
-         Synchronization information.\n\
-         When you subscribe to a new feed, you'll receive 10 last messages from it. After that, you'll start receiving only new feed items.\n\
-         Feed updates check interval is 1 minute. Unread items delivery interval is also 1 minute.\n\
-         Currently, the number of subscriptions is limited to 20.\n\n\
-         Contact @Ayrat555 with your feedback, suggestions, found bugs, etc. The bot is open source. You can find it at https://github.com/ayrat555/el_monitorro\n\n\
-         Unlike other similar projects, El Monitorro is completely open and it's free of charge. I develop it in my free time and pay for hosting myself. Consider donating to the project - https://paypal.me/ayrat555",
-        commands_string()
+async fn bot_added(api: Api, message: MessageOrChannelPost, pool: &db::Pool) -> Result<(), Error> {
+    let chat_id = get_chat_id(&message);
+
+    match telegram::create_chat(&db::get_connection(pool), message.clone().into()) {
+        Ok(_) => log::info!("The bot was added to chat {}", chat_id),
+        Err(error) => log::error!("Failed to create chat {}: {}", chat_id, error),
+    }
+
+    help(api, message, i18n::DEFAULT_LANGUAGE, "".to_string()).await
+}
+
+fn bot_removed(message: &MessageOrChannelPost, pool: &db::Pool) {
+    let chat_id = get_chat_id(message);
+
+    match telegram::delete_chat_cascade(&db::get_connection(pool), chat_id) {
+        Ok(_) => log::info!(
+            "The bot was removed from chat {}, its subscriptions were deleted",
+            chat_id
+        ),
+        Err(error) => log::error!("Failed to delete chat {}: {}", chat_id, error),
+    }
+}
+
+async fn start(
+    api: Api,
+    message: MessageOrChannelPost,
+    language: &str,
+    context: &Context,
+) -> Result<(), Error> {
+    let response = start_text(
+        context.sync_interval_secs,
+        context.delivery_interval_secs,
+        context.subscription_limit,
+        language,
     );
 
-    api.send(message.text_reply(response)).await?;
+    send_reply(&api, &message, response).await?;
     Ok(())
 }
 
-pub async fn send_message(chat_id: i64, message: String) -> Result<(), Error> {
+fn start_text(
+    sync_interval_secs: u64,
+    delivery_interval_secs: u64,
+    subscription_limit: i64,
+    language: &str,
+) -> String {
+    i18n::tf(
+        language,
+        "start_text",
+        &[
+            &commands_string(language),
+            &logic::format_interval(sync_interval_secs, language),
+            &logic::format_interval(delivery_interval_secs, language),
+            &subscription_limit,
+        ],
+    )
+}
+
+pub async fn send_message(
+    chat_id: i64,
+    message: String,
+    disable_notification: bool,
+    disable_preview: bool,
+) -> Result<(), SendMessageError> {
+    send_message_as(
+        chat_id,
+        message,
+        disable_notification,
+        disable_preview,
+        MessageFormat::PlainText,
+    )
+    .await
+}
+
+pub async fn send_message_as(
+    chat_id: i64,
+    message: String,
+    disable_notification: bool,
+    disable_preview: bool,
+    format: MessageFormat,
+) -> Result<(), SendMessageError> {
     let user_id: UserId = chat_id.into();
-    let token = env::var("TELEGRAM_BOT_TOKEN").expect("TELEGRAM_BOT_TOKEN not set");
 
-    let api = Api::new(token);
+    for chunk in split_message(&message) {
+        let request = text_request(
+            user_id,
+            chunk.clone(),
+            disable_notification,
+            disable_preview,
+            format,
+        );
+
+        match send_through_pool(chat_id, request).await {
+            Err(error) if format != MessageFormat::PlainText && is_entities_error(&error) => {
+                log::warn!(
+                    "Failed to parse a {:?} message, sending it as plain text: {} {}",
+                    format,
+                    chat_id,
+                    error
+                );
 
-    api.send(user_id.text(message)).await?;
+                let request = text_request(
+                    user_id,
+                    chunk,
+                    disable_notification,
+                    disable_preview,
+                    MessageFormat::PlainText,
+                );
+
+                send_through_pool(chat_id, request).await?;
+            }
+            result => result?,
+        }
+    }
 
     Ok(())
 }
 
-async fn unknown_command(api: Api, message: MessageOrChannelPost) -> Result<(), Error> {
-    let response = "Unknown command. Use /help to show available commands".to_string();
+async fn send_through_pool(chat_id: i64, request: SendMessage<'static>) -> Result<(), Error> {
+    let error = match send_with_retry(api_pool::primary(), request.clone()).await {
+        Err(error) if is_rate_limit_error(&error) => error,
+        result => return result,
+    };
 
-    api.send(message.text_reply(response)).await?;
-    Ok(())
+    // the other bots may not be added to the chat, so their errors are never reported
+    for api in api_pool::fallbacks() {
+        match api.send(request.clone()).await {
+            Ok(_) => return Ok(()),
+            Err(fallback_error) => log::info!(
+                "Failed to deliver to chat {} with a fallback bot: {}",
+                chat_id,
+                fallback_error
+            ),
+        }
+    }
+
+    Err(error)
 }
 
-async fn subscribe(api: Api, message: MessageOrChannelPost, data: String) -> Result<(), Error> {
-    let response = match logic::create_subscription(
-        &db::establish_connection(),
-        message.clone().into(),
-        Some(data.clone()),
-    ) {
-        Ok(_subscription) => format!("Successfully subscribed to {}", data),
-        Err(SubscriptionError::DbError(_)) => {
-            "Something went wrong with the bot's storage".to_string()
+fn text_request(
+    user_id: UserId,
+    text: String,
+    disable_notification: bool,
+    disable_preview: bool,
+    format: MessageFormat,
+) -> SendMessage<'static> {
+    let mut request = user_id.text(text);
+
+    if disable_notification {
+        request.disable_notification();
+    }
+
+    if disable_preview {
+        request.disable_preview();
+    }
+
+    match format {
+        MessageFormat::PlainText => (),
+        MessageFormat::MarkdownV2 => {
+            request.parse_mode(ParseMode::MarkdownV2);
         }
-        Err(SubscriptionError::InvalidUrl) => "Invalid url".to_string(),
-        Err(SubscriptionError::RssUrlNotProvided) => "Url is not provided".to_string(),
-        Err(SubscriptionError::UrlIsNotFeed) => "Url is not a feed".to_string(),
-        Err(SubscriptionError::SubscriptionAlreadyExists) => {
-            "Susbscription already exists".to_string()
+        MessageFormat::Html => {
+            request.parse_mode(ParseMode::Html);
         }
-        Err(SubscriptionError::SubscriptionCountLimit) => {
-            "You exceeded the number of subscriptions".to_string()
+    }
+
+    request
+}
+
+fn is_entities_error(error: &Error) -> bool {
+    format!("{}", error).contains("can't parse entities")
+}
+
+fn is_rate_limit_error(error: &Error) -> bool {
+    format!("{}", error).contains(TOO_MANY_REQUESTS)
+}
+
+async fn send_with_retry<Req: Request + Clone>(api: &Api, request: Req) -> Result<(), Error> {
+    let mut attempt = 1;
+
+    loop {
+        match api.send(request.clone()).await {
+            Ok(_) => return Ok(()),
+            Err(error) => {
+                let send_error = SendMessageError::from_message(format!("{}", error));
+
+                match retry_delay(&send_error, attempt) {
+                    Some(delay) => {
+                        log::warn!(
+                            "Telegram asked to retry after {} seconds, attempt {} of {}",
+                            delay.as_secs(),
+                            attempt,
+                            MAX_SEND_ATTEMPTS
+                        );
+
+                        time::delay_for(delay).await;
+                        attempt += 1;
+                    }
+                    None => return Err(error),
+                }
+            }
         }
-        Err(SubscriptionError::TelegramError) => "Something went wrong with Telegram".to_string(),
-    };
+    }
+}
+
+fn retry_delay(error: &SendMessageError, attempt: u32) -> Option<Duration> {
+    if attempt >= MAX_SEND_ATTEMPTS {
+        return None;
+    }
+
+    error.retry_after()
+}
+
+async fn send_reply(
+    api: &Api,
+    message: &MessageOrChannelPost,
+    response: String,
+) -> Result<(), Error> {
+    for chunk in split_message(&response) {
+        send_with_retry(api, message.text_reply(chunk)).await?;
+    }
 
-    api.send(message.text_reply(response)).await?;
     Ok(())
 }
 
-async fn unsubscribe(api: Api, message: MessageOrChannelPost, data: String) -> Result<(), Error> {
-    let chat_id = get_chat_id(&message);
+fn split_message(message: &str) -> Vec<String> {
+    let mut chunks = vec![];
+    let mut rest = message.trim();
 
-    let response =
-        match logic::delete_subscription(&db::establish_connection(), chat_id.into(), data.clone())
-        {
-            Ok(_) => format!("Successfully unsubscribed from {}", data),
-            Err(DeleteSubscriptionError::DbError) => format!("Failed to unsubscribe from {}", data),
-            _ => "Subscription does not exist".to_string(),
-        };
+    while let Some((limit, _)) = rest.char_indices().nth(MAX_MESSAGE_LENGTH) {
+        let split_position = find_split_position(&rest[..limit]);
 
-    api.send(message.text_reply(response)).await?;
-    Ok(())
+        chunks.push(rest[..split_position].trim_end().to_string());
+        rest = rest[split_position..].trim_start();
+    }
+
+    if !rest.is_empty() || chunks.is_empty() {
+        chunks.push(rest.to_string());
+    }
+
+    chunks
 }
 
-async fn list_subscriptions(api: Api, message: MessageOrChannelPost) -> Result<(), Error> {
-    let chat_id = get_chat_id(&message);
+fn find_split_position(text: &str) -> usize {
+    let min_position = text.len() / 2;
+
+    let position = vec!["\n\n", "\n", " "]
+        .into_iter()
+        .filter_map(|separator| text.rfind(separator))
+        .find(|position| *position > min_position)
+        .unwrap_or(text.len());
+
+    match (text[..position].rfind('<'), text[..position].rfind('>')) {
+        (Some(open), None) if open > min_position => open,
+        (Some(open), Some(close)) if close < open && open > min_position => open,
+        _ => position,
+    }
+}
 
-    let response = logic::find_feeds_by_chat_id(&db::establish_connection(), chat_id.into());
+async fn unknown_command(
+    api: Api,
+    message: MessageOrChannelPost,
+    language: &str,
+) -> Result<(), Error> {
+    let response = i18n::t(language, "unknown_command");
 
-    api.send(message.text_reply(response)).await?;
+    send_reply(&api, &message, response).await?;
     Ok(())
 }
 
-async fn set_timezone(api: Api, message: MessageOrChannelPost, data: String) -> Result<(), Error> {
-    let chat_id = get_chat_id(&message);
+async fn slow_down(api: Api, message: MessageOrChannelPost, language: &str) -> Result<(), Error> {
+    let response = i18n::t(language, "slow_down");
+
+    send_reply(&api, &message, response).await?;
+    Ok(())
+}
+
+async fn subscribe(
+    api: Api,
+    message: MessageOrChannelPost,
+    language: &str,
+    data: String,
+    subscription_limit: i64,
+    pool: &db::Pool,
+) -> Result<(), Error> {
+    let response = match logic::parse_subscription_argument(data) {
+        Ok((url, initial_items_count)) => {
+            let rss_url = if url.is_empty() {
+                None
+            } else {
+                Some(url.clone())
+            };
+
+            let connection = db::get_connection(pool);
 
-    let response = match logic::set_timezone(&db::establish_connection(), chat_id, data) {
-        Ok(_) => "Your timezone was updated".to_string(),
-        Err(err_string) => err_string.to_string(),
+            match logic::create_subscription_with_warnings(
+                &connection,
+                message.clone().into(),
+                get_user_id(&message),
+                rss_url,
+                initial_items_count,
+                subscription_limit,
+            ) {
+                Ok((subscription, warnings)) => {
+                    let link = match feeds::find(&connection, subscription.feed_id) {
+                        Some(feed) => feed.link,
+                        None => url,
+                    };
+
+                    let mut response =
+                        i18n::tf(language, "subscribed", &[&reader::mask_credentials(&link)]);
+
+                    if let Some(warnings) = logic::format_feed_warnings(&warnings, language) {
+                        response = format!("{}\n\n{}", response, warnings);
+                    }
+
+                    match logic::subscription_usage(
+                        &connection,
+                        subscription.chat_id,
+                        subscription_limit,
+                        language,
+                    ) {
+                        Some(usage) => format!("{}\n\n{}", response, usage),
+                        None => response,
+                    }
+                }
+                Err(error) => subscription_error_message(error, language),
+            }
+        }
+        Err(error) => subscription_error_message(error, language),
     };
 
-    api.send(message.text_reply(response)).await?;
+    send_reply(&api, &message, response).await?;
     Ok(())
 }
 
-async fn get_timezone(api: Api, message: MessageOrChannelPost) -> Result<(), Error> {
-    let chat_id = get_chat_id(&message);
+async fn preview(
+    api: Api,
+    message: MessageOrChannelPost,
+    language: &str,
+    data: String,
+) -> Result<(), Error> {
+    let response = match logic::preview_feed(data, language) {
+        Ok(preview) => preview,
+        Err(error) => subscription_error_message(error, language),
+    };
+
+    send_reply(&api, &message, response).await?;
+    Ok(())
+}
 
-    let response = logic::get_timezone(&db::establish_connection(), chat_id);
+async fn validate(
+    api: Api,
+    message: MessageOrChannelPost,
+    language: &str,
+    data: String,
+) -> Result<(), Error> {
+    let response = match logic::parse_validation_urls(&data, language) {
+        Ok(urls) => format_validation_results(logic::validate_many(urls).await, language),
+        Err(err_string) => err_string,
+    };
 
-    api.send(message.text_reply(response)).await?;
+    send_reply(&api, &message, response).await?;
     Ok(())
 }
 
-fn process_message(api: Api, orig_message: Message) {
-    match orig_message.kind {
-        MessageKind::Text { ref data, .. } => {
-            let command = data.clone();
-            let message = MessageOrChannelPost::Message(orig_message.clone());
+fn format_validation_results(
+    results: Vec<(String, Result<String, SubscriptionError>)>,
+    language: &str,
+) -> String {
+    results
+        .into_iter()
+        .map(|(url, result)| match result {
+            Ok(feed_type) => format!("{} - OK, {}", reader::mask_credentials(&url), feed_type),
+            Err(error) => format!(
+                "{} - {}",
+                reader::mask_credentials(&url),
+                subscription_error_message(error, language)
+            ),
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
 
-            log::info!("{:?} wrote: {}", get_chat_id(&message), command);
+fn subscription_error_message(error: SubscriptionError, language: &str) -> String {
+    match error {
+        SubscriptionError::DbError(_) => i18n::t(language, "storage_error"),
+        SubscriptionError::InvalidUrl => i18n::t(language, "invalid_url"),
+        SubscriptionError::BlockedDomain => i18n::t(language, "blocked_domain"),
+        SubscriptionError::UnsafeUrl => i18n::t(language, "unsafe_url"),
+        SubscriptionError::CredentialsNotSupported => {
+            i18n::t(language, "credentials_not_supported")
+        }
+        SubscriptionError::RssUrlNotProvided => i18n::tf(
+            language,
+            "url_not_provided",
+            &[&format!(
+                "{} https://www.feedforall.com/sample-feed.xml",
+                SUBSCRIBE
+            )],
+        ),
+        SubscriptionError::UrlIsNotFeed => i18n::t(language, "url_is_not_feed"),
+        SubscriptionError::FetchFailed => i18n::t(language, "url_unreachable"),
+        SubscriptionError::SubscriptionAlreadyExists(subscribed_at) => {
+            i18n::tf(language, "already_subscribed", &[&subscribed_at])
+        }
+        SubscriptionError::SubscriptionCountLimit => {
+            i18n::t(language, "subscription_limit_exceeded")
+        }
+        SubscriptionError::InvalidInitialItemsCount => i18n::t(language, "initial_items_invalid"),
+        SubscriptionError::TelegramError => i18n::t(language, "telegram_error"),
+        SubscriptionError::SeveralFeedsFound(links) => format!(
+            "{}\n{}",
+            i18n::t(language, "several_feeds_found"),
+            links.join("\n")
+        ),
+    }
+}
+
+async fn unsubscribe(
+    api: Api,
+    message: MessageOrChannelPost,
+    language: &str,
+    data: String,
+    pool: &db::Pool,
+) -> Result<(), Error> {
+    let chat_id = get_chat_id(&message);
 
-            tokio::spawn(process_message_or_channel_post(api, message, command));
+    let db_connection = db::get_connection(pool);
+
+    let result = match data.parse::<usize>() {
+        Ok(index) => logic::delete_subscription_by_index(&db_connection, chat_id, index),
+        Err(_) => logic::delete_subscription(&db_connection, chat_id, data.clone()),
+    };
+
+    let response = match result {
+        Ok(link) => format!(
+            "{}\n{}",
+            i18n::tf(language, "unsubscribed", &[&link]),
+            i18n::tf(language, "restore_hint", &[&RESTORE])
+        ),
+        Err(DeleteSubscriptionError::DbError) => i18n::tf(language, "unsubscribe_failed", &[&data]),
+        Err(DeleteSubscriptionError::FeedNotFound) => i18n::tf(language, "feed_unknown", &[&data]),
+        Err(DeleteSubscriptionError::ChatNotFound) => i18n::t(language, "no_subscriptions_yet"),
+        Err(DeleteSubscriptionError::AmbiguousLink(links)) => format!(
+            "{}\n{}",
+            i18n::t(language, "ambiguous_link"),
+            links.join("\n")
+        ),
+        Err(DeleteSubscriptionError::SubscriptionNotFound) => {
+            i18n::t(language, "not_subscribed_list_hint")
         }
-        _ => (),
     };
+
+    send_reply(&api, &message, response).await?;
+    Ok(())
 }
 
-fn process_channel_post(api: Api, post: ChannelPost) {
-    match post.kind {
-        MessageKind::Text { ref data, .. } => {
-            let command = data.clone();
+async fn move_subscription(
+    api: Api,
+    message: MessageOrChannelPost,
+    language: &str,
+    data: String,
+    pool: &db::Pool,
+) -> Result<(), Error> {
+    let chat_id = get_chat_id(&message);
 
-            tokio::spawn(process_message_or_channel_post(
-                api,
-                MessageOrChannelPost::ChannelPost(post.clone()),
-                command,
-            ));
+    let response = match parse_arguments(&data).as_slice() {
+        [old_link, new_link] => match logic::move_subscription(
+            &db::get_connection(pool),
+            chat_id,
+            old_link.clone(),
+            new_link.clone(),
+        ) {
+            Ok(feed) => i18n::tf(language, "subscription_moved", &[&feed.link]),
+            Err(error) => move_subscription_error_message(error, language),
+        },
+        [] | [_] => {
+            move_subscription_error_message(MoveSubscriptionError::UrlNotProvided, language)
         }
-        _ => (),
+        _ => wrong_arguments_message(MOVE, language),
     };
+
+    send_reply(&api, &message, response).await?;
+    Ok(())
 }
 
-async fn process_message_or_channel_post(
+fn move_subscription_error_message(error: MoveSubscriptionError, language: &str) -> String {
+    match error {
+        MoveSubscriptionError::DbError(_) => i18n::t(language, "storage_error"),
+        MoveSubscriptionError::UrlNotProvided => i18n::tf(
+            language,
+            "move_urls_not_provided",
+            &[&format!(
+                "{} http://example.com/feed.xml https://example.com/feed.xml",
+                MOVE
+            )],
+        ),
+        MoveSubscriptionError::SubscriptionNotFound => i18n::t(language, "subscription_not_found"),
+        MoveSubscriptionError::SameFeed => i18n::t(language, "move_same_feed"),
+        MoveSubscriptionError::SubscriptionAlreadyExists => {
+            i18n::t(language, "move_already_subscribed")
+        }
+        MoveSubscriptionError::InvalidUrl => i18n::t(language, "invalid_url"),
+        MoveSubscriptionError::BlockedDomain => i18n::t(language, "blocked_domain"),
+        MoveSubscriptionError::UnsafeUrl => i18n::t(language, "unsafe_url"),
+        MoveSubscriptionError::CredentialsNotSupported => {
+            i18n::t(language, "credentials_not_supported")
+        }
+        MoveSubscriptionError::UrlIsNotFeed => i18n::t(language, "url_is_not_feed"),
+        MoveSubscriptionError::FetchFailed => i18n::t(language, "url_unreachable"),
+    }
+}
+
+async fn unsubscribe_all(
     api: Api,
     message: MessageOrChannelPost,
-    command_string: String,
+    language: &str,
+    data: String,
+    pool: &db::Pool,
 ) -> Result<(), Error> {
-    let command = &command_string;
-
-    if command.contains(SUBSCRIBE) {
-        let argument = parse_argument(command, SUBSCRIBE);
-        tokio::spawn(subscribe(api, message, argument));
-    } else if command.contains(LIST_SUBSCRIPTIONS) {
-        tokio::spawn(list_subscriptions(api, message));
-    } else if command.contains(UNSUBSCRIBE) {
-        let argument = parse_argument(command, UNSUBSCRIBE);
-        tokio::spawn(unsubscribe(api, message, argument));
-    } else if command.contains(HELP) {
-        tokio::spawn(help(api, message));
-    } else if command.contains(START) {
-        tokio::spawn(start(api, message));
-    } else if command.contains(SET_TIMEZONE) {
-        let argument = parse_argument(command, SET_TIMEZONE);
-        tokio::spawn(set_timezone(api, message, argument));
-    } else if command.contains(GET_TIMEZONE) {
-        tokio::spawn(get_timezone(api, message));
-    } else {
-        tokio::spawn(unknown_command(api, message));
-    }
+    let chat_id = get_chat_id(&message);
+
+    let response = logic::unsubscribe_all(&db::get_connection(pool), chat_id, data, language);
 
+    send_reply(&api, &message, response).await?;
     Ok(())
 }
 
-fn get_chat_id(message: &MessageOrChannelPost) -> i64 {
-    match message {
-        MessageOrChannelPost::Message(message) => message.chat.id().into(),
-        MessageOrChannelPost::ChannelPost(post) => post.chat.id.into(),
-    }
+async fn list_subscriptions(
+    api: Api,
+    message: MessageOrChannelPost,
+    language: &str,
+    data: String,
+    pool: &db::Pool,
+) -> Result<(), Error> {
+    let chat_id = get_chat_id(&message);
+
+    let response = logic::find_feeds_by_chat_id(&db::get_connection(pool), chat_id, data, language);
+
+    send_reply(&api, &message, response).await?;
+    Ok(())
 }
 
-fn parse_argument(full_command: &str, command: &str) -> String {
-    full_command.replace(command, "").trim().to_string()
+async fn set_timezone(
+    api: Api,
+    message: MessageOrChannelPost,
+    language: &str,
+    data: String,
+    pool: &db::Pool,
+) -> Result<(), Error> {
+    let response =
+        match logic::set_timezone(&db::get_connection(pool), message.clone().into(), data) {
+            Ok(offset) if is_group_chat(&message) => format!(
+                "{}\n{}",
+                i18n::t(language, "timezone_group_updated"),
+                timezone_confirmation(offset, Utc::now(), language)
+            ),
+            Ok(offset) => format!(
+                "{}\n{}",
+                i18n::t(language, "timezone_updated"),
+                timezone_confirmation(offset, Utc::now(), language)
+            ),
+            Err(error) => i18n::t(language, error),
+        };
+
+    send_reply(&api, &message, response).await?;
+    Ok(())
 }
 
-pub async fn start_bot() -> Result<(), Error> {
-    let token = env::var("TELEGRAM_BOT_TOKEN").expect("TELEGRAM_BOT_TOKEN not set");
+fn timezone_confirmation(offset_minutes: i32, now: DateTime<Utc>, language: &str) -> String {
+    let sign = if offset_minutes < 0 { '-' } else { '+' };
+    let local_time = now + ChronoDuration::minutes(offset_minutes as i64);
+    let offset = format!(
+        "UTC{}{:02}:{:02}",
+        sign,
+        offset_minutes.abs() / 60,
+        offset_minutes.abs() % 60
+    );
 
-    let api = Api::new(token);
-    let mut stream = api.stream();
+    i18n::tf(
+        language,
+        "timezone_confirmation",
+        &[&offset, &local_time.format("%H:%M")],
+    )
+}
 
-    log::info!("Starting a bot");
+async fn set_language(
+    api: Api,
+    message: MessageOrChannelPost,
+    data: String,
+    pool: &db::Pool,
+) -> Result<(), Error> {
+    let language = chat_language(&message, pool);
 
-    while let Some(update) = stream.next().await {
-        let update = update?;
-        match update.kind {
-            UpdateKind::Message(message) => {
-                process_message(api.clone(), message);
-            }
-            UpdateKind::ChannelPost(message) => {
-                process_channel_post(api.clone(), message);
-            }
-            _ => (),
+    match logic::set_language(
+        &db::get_connection(pool),
+        message.clone().into(),
+        data,
+        &language,
+    ) {
+        Ok(new_language) => {
+            let response = i18n::tf(&new_language, "language_set", &[&new_language]);
+
+            send_reply(&api, &message, response).await?;
+        }
+        Err(err_string) => {
+            send_reply(&api, &message, err_string).await?;
         }
     }
 
     Ok(())
 }
+
+async fn get_timezone(
+    api: Api,
+    message: MessageOrChannelPost,
+    language: &str,
+    pool: &db::Pool,
+) -> Result<(), Error> {
+    let chat_id = get_chat_id(&message);
+
+    let response = logic::get_timezone(&db::get_connection(pool), chat_id, language);
+
+    send_reply(&api, &message, response).await?;
+    Ok(())
+}
+
+async fn set_limit(
+    api: Api,
+    message: MessageOrChannelPost,
+    language: &str,
+    data: String,
+    subscription_limit_ceiling: i32,
+    pool: &db::Pool,
+) -> Result<(), Error> {
+    let chat_id = get_chat_id(&message);
+
+    let response = match logic::set_limit(
+        &db::get_connection(pool),
+        chat_id,
+        data,
+        subscription_limit_ceiling,
+        language,
+    ) {
+        Ok(limit) => i18n::tf(language, "limit_set", &[&limit]),
+        Err(err_string) => err_string,
+    };
+
+    send_reply(&api, &message, response).await?;
+    Ok(())
+}
+
+async fn pause(
+    api: Api,
+    message: MessageOrChannelPost,
+    language: &str,
+    pool: &db::Pool,
+) -> Result<(), Error> {
+    let chat_id = get_chat_id(&message);
+
+    let response = logic::pause(&db::get_connection(pool), chat_id, language);
+
+    send_reply(&api, &message, response).await?;
+    Ok(())
+}
+
+async fn resume(
+    api: Api,
+    message: MessageOrChannelPost,
+    language: &str,
+    pool: &db::Pool,
+) -> Result<(), Error> {
+    let chat_id = get_chat_id(&message);
+
+    let response = logic::resume(&db::get_connection(pool), chat_id, language);
+
+    send_reply(&api, &message, response).await?;
+    Ok(())
+}
+
+async fn add_filter(
+    api: Api,
+    message: MessageOrChannelPost,
+    language: &str,
+    data: String,
+    pool: &db::Pool,
+) -> Result<(), Error> {
+    let chat_id = get_chat_id(&message);
+    let (link, keyword) = parse_link_arguments(&data);
+
+    let response = match logic::add_filter(&db::get_connection(pool), chat_id, link, keyword) {
+        Ok(filter) => i18n::tf(language, "filter_added", &[&filter.keyword]),
+        Err(error) => filter_error_message(error, language),
+    };
+
+    send_reply(&api, &message, response).await?;
+    Ok(())
+}
+
+async fn remove_filter(
+    api: Api,
+    message: MessageOrChannelPost,
+    language: &str,
+    data: String,
+    pool: &db::Pool,
+) -> Result<(), Error> {
+    let chat_id = get_chat_id(&message);
+    let (link, keyword) = parse_link_arguments(&data);
+
+    let response =
+        match logic::remove_filter(&db::get_connection(pool), chat_id, link, keyword.clone()) {
+            Ok(_) => i18n::tf(language, "filter_removed", &[&keyword.trim()]),
+            Err(error) => filter_error_message(error, language),
+        };
+
+    send_reply(&api, &message, response).await?;
+    Ok(())
+}
+
+async fn list_filters(
+    api: Api,
+    message: MessageOrChannelPost,
+    language: &str,
+    data: String,
+    pool: &db::Pool,
+) -> Result<(), Error> {
+    let chat_id = get_chat_id(&message);
+
+    let response = match logic::list_filters(&db::get_connection(pool), chat_id, data) {
+        Ok(keywords) => {
+            if keywords.is_empty() {
+                i18n::t(language, "no_filters")
+            } else {
+                keywords.join("\n")
+            }
+        }
+        Err(error) => filter_error_message(error, language),
+    };
+
+    send_reply(&api, &message, response).await?;
+    Ok(())
+}
+
+async fn watch(
+    api: Api,
+    message: MessageOrChannelPost,
+    language: &str,
+    data: String,
+    pool: &db::Pool,
+) -> Result<(), Error> {
+    let response =
+        match logic::add_watchword(&db::get_connection(pool), message.clone().into(), data) {
+            Ok(watchword) => i18n::tf(language, "watching", &[&watchword.keyword]),
+            Err(error) => watchword_error_message(error, language),
+        };
+
+    send_reply(&api, &message, response).await?;
+    Ok(())
+}
+
+async fn unwatch(
+    api: Api,
+    message: MessageOrChannelPost,
+    language: &str,
+    data: String,
+    pool: &db::Pool,
+) -> Result<(), Error> {
+    let chat_id = get_chat_id(&message);
+
+    let response = match logic::remove_watchword(&db::get_connection(pool), chat_id, data.clone()) {
+        Ok(_) => i18n::tf(language, "stopped_watching", &[&data.trim().to_lowercase()]),
+        Err(error) => watchword_error_message(error, language),
+    };
+
+    send_reply(&api, &message, response).await?;
+    Ok(())
+}
+
+async fn watchwords(
+    api: Api,
+    message: MessageOrChannelPost,
+    language: &str,
+    pool: &db::Pool,
+) -> Result<(), Error> {
+    let chat_id = get_chat_id(&message);
+
+    let response = match logic::list_watchwords(&db::get_connection(pool), chat_id) {
+        Ok(keywords) => {
+            if keywords.is_empty() {
+                i18n::t(language, "no_watchwords")
+            } else {
+                keywords.join("\n")
+            }
+        }
+        Err(error) => watchword_error_message(error, language),
+    };
+
+    send_reply(&api, &message, response).await?;
+    Ok(())
+}
+
+async fn stats(
+    api: Api,
+    message: MessageOrChannelPost,
+    language: &str,
+    pool: &db::Pool,
+) -> Result<(), Error> {
+    let chat_id = get_chat_id(&message);
+
+    let response = logic::chat_stats(&db::get_connection(pool), chat_id, language);
+
+    send_reply(&api, &message, response).await?;
+    Ok(())
+}
+
+async fn health(
+    api: Api,
+    message: MessageOrChannelPost,
+    language: &str,
+    pool: &db::Pool,
+) -> Result<(), Error> {
+    let chat_id = get_chat_id(&message);
+
+    let response = logic::chat_health(&db::get_connection(pool), chat_id, language);
+
+    send_reply(&api, &message, response).await?;
+    Ok(())
+}
+
+async fn whoami(
+    api: Api,
+    message: MessageOrChannelPost,
+    language: &str,
+    pool: &db::Pool,
+) -> Result<(), Error> {
+    let chat: NewTelegramChat = message.clone().into();
+
+    let response = logic::describe_chat(&db::get_connection(pool), chat.id, &chat.kind, language);
+
+    send_reply(&api, &message, response).await?;
+    Ok(())
+}
+
+async fn broadcast(
+    api: Api,
+    message: MessageOrChannelPost,
+    language: &str,
+    data: String,
+    pool: &db::Pool,
+) -> Result<(), Error> {
+    let response = if data.is_empty() {
+        i18n::t(language, "broadcast_message_missing")
+    } else {
+        match telegram::all_chat_ids(&db::get_connection(pool)) {
+            Ok(chat_ids) => {
+                let mut sent_count = 0;
+
+                for chat_id in chat_ids {
+                    match send_message(chat_id, data.clone(), false, false).await {
+                        Ok(_) => sent_count += 1,
+                        Err(error) => log::error!("Failed to broadcast to {}: {}", chat_id, error),
+                    }
+
+                    time::delay_for(Duration::from_millis(BROADCAST_DELAY_MILLIS)).await;
+                }
+
+                i18n::tf(language, "broadcast_sent", &[&sent_count])
+            }
+            Err(_) => i18n::t(language, "broadcast_failed"),
+        }
+    };
+
+    send_reply(&api, &message, response).await?;
+    Ok(())
+}
+
+async fn popular(
+    api: Api,
+    message: MessageOrChannelPost,
+    language: &str,
+    data: String,
+    pool: &db::Pool,
+) -> Result<(), Error> {
+    let response = logic::popular_feeds(&db::get_connection(pool), data, language);
+
+    send_reply(&api, &message, response).await?;
+    Ok(())
+}
+
+async fn disabled_chats(
+    api: Api,
+    message: MessageOrChannelPost,
+    language: &str,
+    pool: &db::Pool,
+) -> Result<(), Error> {
+    let response = logic::disabled_chats(&db::get_connection(pool), language);
+
+    send_reply(&api, &message, response).await?;
+    Ok(())
+}
+
+async fn feedback(
+    api: Api,
+    message: MessageOrChannelPost,
+    language: &str,
+    data: String,
+    limiter: &RateLimiter,
+) -> Result<(), Error> {
+    let chat_id = get_chat_id(&message);
+
+    let response = match (admin_user_id(), logic::format_feedback(chat_id, &data)) {
+        (None, _) => i18n::t(language, "feedback_not_accepted"),
+        (_, Err(error)) => i18n::t(language, error),
+        (Some(_), Ok(_)) if !limiter.check(chat_id) => i18n::t(language, "feedback_too_often"),
+        (Some(admin_id), Ok(feedback)) => match send_message(admin_id, feedback, false, true).await
+        {
+            Ok(_) => i18n::t(language, "feedback_sent"),
+            Err(error) => {
+                log::error!("Failed to forward feedback from {}: {}", chat_id, error);
+
+                i18n::t(language, "feedback_failed")
+            }
+        },
+    };
+
+    send_reply(&api, &message, response).await?;
+    Ok(())
+}
+
+fn admin_user_id() -> Option<i64> {
+    env::var("ADMIN_USER_ID")
+        .ok()
+        .and_then(|value| value.trim().parse::<i64>().ok())
+}
+
+fn is_admin(message: &MessageOrChannelPost) -> bool {
+    match (admin_user_id(), get_user_id(message)) {
+        (Some(admin_id), Some(user_id)) => admin_id == user_id,
+        _ => false,
+    }
+}
+
+async fn set_format(
+    api: Api,
+    message: MessageOrChannelPost,
+    language: &str,
+    data: String,
+    pool: &db::Pool,
+) -> Result<(), Error> {
+    let chat_id = get_chat_id(&message);
+    let (link, template) = parse_link_arguments(&data);
+
+    let response = match logic::set_format(&db::get_connection(pool), chat_id, link, template) {
+        Ok(_) => i18n::t(language, "format_updated"),
+        Err(error) => i18n::t(language, error),
+    };
+
+    send_reply(&api, &message, response).await?;
+    Ok(())
+}
+
+async fn reset_format(
+    api: Api,
+    message: MessageOrChannelPost,
+    language: &str,
+    data: String,
+    pool: &db::Pool,
+) -> Result<(), Error> {
+    let chat_id = get_chat_id(&message);
+
+    let response = match logic::reset_format(&db::get_connection(pool), chat_id, data) {
+        Ok(_) => i18n::t(language, "format_reset"),
+        Err(error) => i18n::t(language, error),
+    };
+
+    send_reply(&api, &message, response).await?;
+    Ok(())
+}
+
+async fn mute(
+    api: Api,
+    message: MessageOrChannelPost,
+    language: &str,
+    data: String,
+    pool: &db::Pool,
+) -> Result<(), Error> {
+    let chat_id = get_chat_id(&message);
+
+    let response =
+        match logic::set_subscription_silent(&db::get_connection(pool), chat_id, data, true) {
+            Ok(_) => i18n::t(language, "muted"),
+            Err(error) => i18n::t(language, error),
+        };
+
+    send_reply(&api, &message, response).await?;
+    Ok(())
+}
+
+async fn unmute(
+    api: Api,
+    message: MessageOrChannelPost,
+    language: &str,
+    data: String,
+    pool: &db::Pool,
+) -> Result<(), Error> {
+    let chat_id = get_chat_id(&message);
+
+    let response =
+        match logic::set_subscription_silent(&db::get_connection(pool), chat_id, data, false) {
+            Ok(_) => i18n::t(language, "unmuted"),
+            Err(error) => i18n::t(language, error),
+        };
+
+    send_reply(&api, &message, response).await?;
+    Ok(())
+}
+
+async fn set_throttle(
+    api: Api,
+    message: MessageOrChannelPost,
+    language: &str,
+    data: String,
+    pool: &db::Pool,
+) -> Result<(), Error> {
+    let chat_id = get_chat_id(&message);
+
+    let response = match parse_arguments(&data).as_slice() {
+        [link, interval] => match logic::set_throttle(
+            &db::get_connection(pool),
+            chat_id,
+            link.clone(),
+            interval.clone(),
+            language,
+        ) {
+            Ok(0) => i18n::t(language, "throttle_disabled"),
+            Ok(interval) => i18n::tf(language, "throttle_set", &[&interval]),
+            Err(err_string) => err_string,
+        },
+        _ => wrong_arguments_message(SET_THROTTLE, language),
+    };
+
+    send_reply(&api, &message, response).await?;
+    Ok(())
+}
+
+async fn top(
+    api: Api,
+    message: MessageOrChannelPost,
+    language: &str,
+    data: String,
+    pool: &db::Pool,
+) -> Result<(), Error> {
+    let chat_id = get_chat_id(&message);
+
+    let response = logic::top_feeds(&db::get_connection(pool), chat_id, data, language);
+
+    send_reply(&api, &message, response).await?;
+    Ok(())
+}
+
+async fn version(
+    api: Api,
+    message: MessageOrChannelPost,
+    language: &str,
+    context: &Context,
+) -> Result<(), Error> {
+    let response = version_text(
+        option_env!("GIT_COMMIT"),
+        context.started_at.elapsed(),
+        language,
+    );
+
+    send_reply(&api, &message, response).await?;
+    Ok(())
+}
+
+fn version_text(commit: Option<&str>, uptime: Duration, language: &str) -> String {
+    let version = match commit {
+        Some(commit) if !commit.trim().is_empty() => {
+            format!("{} ({})", env!("CARGO_PKG_VERSION"), commit.trim())
+        }
+        _ => env!("CARGO_PKG_VERSION").to_string(),
+    };
+
+    format!(
+        "{}\n{}",
+        i18n::tf(language, "version", &[&version]),
+        i18n::tf(
+            language,
+            "uptime",
+            &[&logic::format_uptime(uptime.as_secs())]
+        )
+    )
+}
+
+async fn random(
+    api: Api,
+    message: MessageOrChannelPost,
+    language: &str,
+    pool: &db::Pool,
+) -> Result<(), Error> {
+    let chat_id = get_chat_id(&message);
+
+    let response = match logic::random_item(&db::get_connection(pool), chat_id) {
+        Ok(item) => item,
+        Err(error) => i18n::t(language, error),
+    };
+
+    send_reply(&api, &message, response).await?;
+    Ok(())
+}
+
+async fn unread(
+    api: Api,
+    message: MessageOrChannelPost,
+    language: &str,
+    pool: &db::Pool,
+) -> Result<(), Error> {
+    let chat_id = get_chat_id(&message);
+
+    let response = logic::unread(&db::get_connection(pool), chat_id, language);
+
+    send_reply(&api, &message, response).await?;
+    Ok(())
+}
+
+async fn snooze(
+    api: Api,
+    message: MessageOrChannelPost,
+    language: &str,
+    data: String,
+    pool: &db::Pool,
+) -> Result<(), Error> {
+    let chat_id = get_chat_id(&message);
+
+    let response = match parse_arguments(&data).as_slice() {
+        [link, duration] => logic::snooze(
+            &db::get_connection(pool),
+            chat_id,
+            link.clone(),
+            duration.clone(),
+            language,
+        ),
+        _ => wrong_arguments_message(SNOOZE, language),
+    };
+
+    send_reply(&api, &message, response).await?;
+    Ok(())
+}
+
+async fn set_preview(
+    api: Api,
+    message: MessageOrChannelPost,
+    language: &str,
+    data: String,
+    pool: &db::Pool,
+) -> Result<(), Error> {
+    let chat_id = get_chat_id(&message);
+
+    let response = match parse_arguments(&data).as_slice() {
+        [link, value] => match logic::set_link_preview(
+            &db::get_connection(pool),
+            chat_id,
+            link.clone(),
+            value.clone(),
+        ) {
+            Ok(true) => i18n::t(language, "link_preview_on"),
+            Ok(false) => i18n::t(language, "link_preview_off"),
+            Err(error) => i18n::t(language, error),
+        },
+        _ => wrong_arguments_message(SET_PREVIEW, language),
+    };
+
+    send_reply(&api, &message, response).await?;
+    Ok(())
+}
+
+async fn set_fulltext(
+    api: Api,
+    message: MessageOrChannelPost,
+    language: &str,
+    data: String,
+    pool: &db::Pool,
+) -> Result<(), Error> {
+    let chat_id = get_chat_id(&message);
+
+    let response = match parse_arguments(&data).as_slice() {
+        [link, value] => match logic::set_full_text(
+            &db::get_connection(pool),
+            chat_id,
+            link.clone(),
+            value.clone(),
+        ) {
+            Ok(true) => i18n::t(language, "full_text_on"),
+            Ok(false) => i18n::t(language, "full_text_off"),
+            Err(error) => i18n::t(language, error),
+        },
+        _ => wrong_arguments_message(SET_FULLTEXT, language),
+    };
+
+    send_reply(&api, &message, response).await?;
+    Ok(())
+}
+
+async fn set_markdown(
+    api: Api,
+    message: MessageOrChannelPost,
+    language: &str,
+    data: String,
+    pool: &db::Pool,
+) -> Result<(), Error> {
+    let chat_id = get_chat_id(&message);
+
+    let response = match parse_arguments(&data).as_slice() {
+        [link, value] => match logic::set_markdown(
+            &db::get_connection(pool),
+            chat_id,
+            link.clone(),
+            value.clone(),
+        ) {
+            Ok(true) => i18n::t(language, "markdown_on"),
+            Ok(false) => i18n::t(language, "markdown_off"),
+            Err(error) => i18n::t(language, error),
+        },
+        _ => wrong_arguments_message(SET_MARKDOWN, language),
+    };
+
+    send_reply(&api, &message, response).await?;
+    Ok(())
+}
+
+async fn set_html(
+    api: Api,
+    message: MessageOrChannelPost,
+    language: &str,
+    data: String,
+    pool: &db::Pool,
+) -> Result<(), Error> {
+    let chat_id = get_chat_id(&message);
+
+    let response = match parse_arguments(&data).as_slice() {
+        [link, value] => match logic::set_html(
+            &db::get_connection(pool),
+            chat_id,
+            link.clone(),
+            value.clone(),
+        ) {
+            Ok(true) => i18n::t(language, "html_on"),
+            Ok(false) => i18n::t(language, "html_off"),
+            Err(error) => i18n::t(language, error),
+        },
+        _ => wrong_arguments_message(SET_HTML, language),
+    };
+
+    send_reply(&api, &message, response).await?;
+    Ok(())
+}
+
+async fn resubscribe(
+    api: Api,
+    message: MessageOrChannelPost,
+    language: &str,
+    data: String,
+    pool: &db::Pool,
+) -> Result<(), Error> {
+    let chat_id = get_chat_id(&message);
+
+    let response = match logic::reset_subscription_cursor(&db::get_connection(pool), chat_id, data)
+    {
+        Ok(count) => i18n::tf(language, "resubscribed", &[&count]),
+        Err(error) => i18n::t(language, error),
+    };
+
+    send_reply(&api, &message, response).await?;
+    Ok(())
+}
+
+async fn last(
+    api: Api,
+    message: MessageOrChannelPost,
+    language: &str,
+    data: String,
+    pool: &db::Pool,
+) -> Result<(), Error> {
+    let chat_id = get_chat_id(&message);
+
+    let response = match logic::fetch_last_item(&db::get_connection(pool), chat_id, data, language)
+    {
+        Ok(item) => item,
+        Err(error) => i18n::t(language, error),
+    };
+
+    send_reply(&api, &message, response).await?;
+    Ok(())
+}
+
+async fn restore(
+    api: Api,
+    message: MessageOrChannelPost,
+    language: &str,
+    data: String,
+    context: &Context,
+) -> Result<(), Error> {
+    let chat_id = get_chat_id(&message);
+
+    let response = match logic::restore_subscription(
+        &db::get_connection(&context.pool),
+        chat_id,
+        data,
+        context.subscription_limit,
+    ) {
+        Ok(link) => i18n::tf(language, "restored", &[&link]),
+        Err(error) => i18n::t(language, error),
+    };
+
+    send_reply(&api, &message, response).await?;
+    Ok(())
+}
+
+async fn test_format(
+    api: Api,
+    message: MessageOrChannelPost,
+    language: &str,
+    data: String,
+    pool: &db::Pool,
+) -> Result<(), Error> {
+    let chat_id = get_chat_id(&message);
+
+    let response = match logic::test_format(&db::get_connection(pool), chat_id, data) {
+        Ok(item) => item,
+        Err(error) => i18n::t(language, error),
+    };
+
+    send_reply(&api, &message, response).await?;
+    Ok(())
+}
+
+async fn export(
+    api: Api,
+    message: MessageOrChannelPost,
+    language: &str,
+    pool: &db::Pool,
+) -> Result<(), Error> {
+    let chat_id = get_chat_id(&message);
+
+    match logic::export_opml(&db::get_connection(pool), chat_id) {
+        Ok(opml) => {
+            let file = InputFileUpload::with_data(opml.into_bytes(), "subscriptions.opml");
+
+            api.send(message.document_reply(file)).await?;
+        }
+        Err(error) => {
+            send_reply(&api, &message, i18n::t(language, error)).await?;
+        }
+    }
+
+    Ok(())
+}
+
+async fn clone_from(
+    api: Api,
+    message: MessageOrChannelPost,
+    language: &str,
+    data: String,
+    context: &Context,
+) -> Result<(), Error> {
+    let response = match data.trim().parse::<i64>() {
+        Ok(source_chat_id) => {
+            if is_admin(&message) || is_member_of_chat(&api, &message, source_chat_id).await {
+                logic::clone_subscriptions(
+                    &db::get_connection(&context.pool),
+                    source_chat_id,
+                    message.clone().into(),
+                    context.subscription_limit,
+                    language,
+                )
+            } else {
+                i18n::t(language, "clone_not_member")
+            }
+        }
+        Err(_) => wrong_arguments_message(CLONE_FROM, language),
+    };
+
+    send_reply(&api, &message, response).await?;
+    Ok(())
+}
+
+async fn is_member_of_chat(api: &Api, message: &MessageOrChannelPost, chat_id: i64) -> bool {
+    let user_id = match get_user_id(message) {
+        Some(user_id) => user_id,
+        None => return false,
+    };
+
+    if user_id == chat_id {
+        return true;
+    }
+
+    let request = GetChatMember::new(ChatId::new(chat_id), UserId::new(user_id));
+
+    match api.send(request).await {
+        Ok(member) => matches!(
+            member.status,
+            ChatMemberStatus::Creator
+                | ChatMemberStatus::Administrator
+                | ChatMemberStatus::Member
+                | ChatMemberStatus::Restricted
+        ),
+        Err(error) => {
+            log::warn!(
+                "Failed to check if {} is a member of {}: {}",
+                user_id,
+                chat_id,
+                error
+            );
+
+            false
+        }
+    }
+}
+
+async fn import(
+    api: Api,
+    message: MessageOrChannelPost,
+    language: &str,
+    document: Document,
+    subscription_limit: i64,
+    pool: &db::Pool,
+) -> Result<(), Error> {
+    let response = match download_document(&api, &document).await {
+        Some(opml) => {
+            import_document(
+                message.clone().into(),
+                opml,
+                subscription_limit,
+                pool.clone(),
+                language,
+            )
+            .await
+        }
+        None => i18n::t(language, "download_failed"),
+    };
+
+    send_reply(&api, &message, response).await?;
+    Ok(())
+}
+
+// feeds are fetched while subscribing, so the import doesn't run on the bot's threads
+async fn import_document(
+    new_chat: NewTelegramChat,
+    opml: String,
+    subscription_limit: i64,
+    pool: db::Pool,
+    language: &str,
+) -> String {
+    let summary_language = language.to_string();
+
+    let result = tokio::task::spawn_blocking(move || {
+        match logic::import_opml(
+            &db::get_connection(&pool),
+            new_chat,
+            &opml,
+            subscription_limit,
+        ) {
+            Ok(results) => logic::format_import_summary(&results, &summary_language),
+            Err(error) => i18n::t(&summary_language, error),
+        }
+    })
+    .await;
+
+    result.unwrap_or_else(|error| {
+        log::error!("Failed to import subscriptions: {}", error);
+
+        i18n::t(language, "import_failed")
+    })
+}
+
+async fn import_hint(api: Api, message: MessageOrChannelPost, language: &str) -> Result<(), Error> {
+    let response = i18n::tf(language, "import_hint", &[&IMPORT]);
+
+    send_reply(&api, &message, response).await?;
+    Ok(())
+}
+
+async fn download_document(api: &Api, document: &Document) -> Option<String> {
+    let file = match api.send(document.get_file()).await {
+        Ok(file) => file,
+        Err(error) => {
+            log::error!("Failed to fetch file info {}", error);
+            return None;
+        }
+    };
+
+    let url = file.get_url(api_pool::primary_token())?;
+
+    match tokio::task::spawn_blocking(move || reader::read_url(&url)).await {
+        Ok(Ok(data)) => String::from_utf8(data).ok(),
+        Ok(Err(error)) => {
+            log::error!("Failed to download file {:?}", error);
+            None
+        }
+        Err(error) => {
+            log::error!("Failed to download file {}", error);
+            None
+        }
+    }
+}
+
+fn is_opml_document(document: &Document, caption: &Option<String>) -> bool {
+    let has_import_caption = caption
+        .as_ref()
+        .map_or(false, |caption| caption.contains(IMPORT));
+
+    let has_opml_extension = document
+        .file_name
+        .as_ref()
+        .map_or(false, |name| name.to_lowercase().ends_with(".opml"));
+
+    has_import_caption || has_opml_extension
+}
+
+async fn set_truncate(
+    api: Api,
+    message: MessageOrChannelPost,
+    language: &str,
+    data: String,
+    pool: &db::Pool,
+) -> Result<(), Error> {
+    let chat_id = get_chat_id(&message);
+
+    let response = logic::set_truncate(&db::get_connection(pool), chat_id, data, language);
+
+    send_reply(&api, &message, response).await?;
+    Ok(())
+}
+
+async fn search(
+    api: Api,
+    message: MessageOrChannelPost,
+    language: &str,
+    data: String,
+    pool: &db::Pool,
+) -> Result<(), Error> {
+    let chat_id = get_chat_id(&message);
+
+    let response = logic::search_items(&db::get_connection(pool), chat_id, data, language);
+
+    send_reply(&api, &message, response).await?;
+    Ok(())
+}
+
+async fn set_digest(
+    api: Api,
+    message: MessageOrChannelPost,
+    language: &str,
+    data: String,
+    pool: &db::Pool,
+) -> Result<(), Error> {
+    let chat_id = get_chat_id(&message);
+
+    let response = logic::set_digest(&db::get_connection(pool), chat_id, data, language);
+
+    send_reply(&api, &message, response).await?;
+    Ok(())
+}
+
+async fn set_quiet_hours(
+    api: Api,
+    message: MessageOrChannelPost,
+    language: &str,
+    data: String,
+    pool: &db::Pool,
+) -> Result<(), Error> {
+    let chat_id = get_chat_id(&message);
+
+    let response = logic::set_quiet_hours(&db::get_connection(pool), chat_id, data, language);
+
+    send_reply(&api, &message, response).await?;
+    Ok(())
+}
+
+async fn track_per_user(
+    api: Api,
+    message: MessageOrChannelPost,
+    language: &str,
+    data: String,
+    pool: &db::Pool,
+) -> Result<(), Error> {
+    let response = logic::set_track_per_user(
+        &db::get_connection(pool),
+        message.clone().into(),
+        data,
+        language,
+    );
+
+    send_reply(&api, &message, response).await?;
+    Ok(())
+}
+
+async fn set_author(
+    api: Api,
+    message: MessageOrChannelPost,
+    language: &str,
+    data: String,
+    pool: &db::Pool,
+) -> Result<(), Error> {
+    let response = logic::set_author(
+        &db::get_connection(pool),
+        message.clone().into(),
+        data,
+        language,
+    );
+
+    send_reply(&api, &message, response).await?;
+    Ok(())
+}
+
+async fn set_backfill_order(
+    api: Api,
+    message: MessageOrChannelPost,
+    language: &str,
+    data: String,
+    pool: &db::Pool,
+) -> Result<(), Error> {
+    let response = logic::set_backfill_order(
+        &db::get_connection(pool),
+        message.clone().into(),
+        data,
+        language,
+    );
+
+    send_reply(&api, &message, response).await?;
+    Ok(())
+}
+
+async fn set_prefix(
+    api: Api,
+    message: MessageOrChannelPost,
+    language: &str,
+    data: String,
+    pool: &db::Pool,
+) -> Result<(), Error> {
+    let chat_id = get_chat_id(&message);
+
+    let response = logic::set_prefix(&db::get_connection(pool), chat_id, data, language);
+
+    send_reply(&api, &message, response).await?;
+    Ok(())
+}
+
+async fn reset_prefix(
+    api: Api,
+    message: MessageOrChannelPost,
+    language: &str,
+    pool: &db::Pool,
+) -> Result<(), Error> {
+    let chat_id = get_chat_id(&message);
+
+    let response = logic::reset_prefix(&db::get_connection(pool), chat_id, language);
+
+    send_reply(&api, &message, response).await?;
+    Ok(())
+}
+
+async fn feed_info(
+    api: Api,
+    message: MessageOrChannelPost,
+    language: &str,
+    data: String,
+    pool: &db::Pool,
+) -> Result<(), Error> {
+    let chat_id = get_chat_id(&message);
+
+    let response = match logic::feed_info(&db::get_connection(pool), chat_id, data, language) {
+        Ok(info) => info,
+        Err(error) => i18n::t(language, error),
+    };
+
+    send_reply(&api, &message, response).await?;
+    Ok(())
+}
+
+fn filter_error_message(error: FilterError, language: &str) -> String {
+    match error {
+        FilterError::DbError(_) => i18n::t(language, "storage_error"),
+        FilterError::KeywordNotProvided => i18n::t(language, "keyword_missing"),
+        FilterError::SubscriptionNotFound => i18n::t(language, "subscription_not_found"),
+        FilterError::FilterAlreadyExists => i18n::t(language, "filter_already_exists"),
+        FilterError::FilterNotFound => i18n::t(language, "filter_not_found"),
+    }
+}
+
+fn watchword_error_message(error: WatchwordError, language: &str) -> String {
+    match error {
+        WatchwordError::DbError(_) => i18n::t(language, "storage_error"),
+        WatchwordError::KeywordNotProvided => i18n::t(language, "keyword_missing"),
+        WatchwordError::WatchwordAlreadyExists => i18n::t(language, "watchword_already_exists"),
+        WatchwordError::WatchwordNotFound => i18n::t(language, "watchword_not_found"),
+        WatchwordError::WatchwordCountLimit => {
+            i18n::tf(language, "watchword_limit", &[&logic::MAX_WATCHWORDS_COUNT])
+        }
+    }
+}
+
+fn parse_arguments(data: &str) -> Vec<String> {
+    data.split_whitespace()
+        .map(|argument| argument.to_string())
+        .collect()
+}
+
+fn wrong_arguments_message(command: &str, language: &str) -> String {
+    match command_help(command, language) {
+        Some(help) => format!("{}\n\n{}", i18n::t(language, "wrong_arguments"), help),
+        None => i18n::t(language, "wrong_arguments"),
+    }
+}
+
+fn parse_link_arguments(data: &str) -> (String, String) {
+    let mut parts = data.trim().splitn(2, char::is_whitespace);
+
+    let link = parts.next().unwrap_or("").to_string();
+    let keyword = parts.next().unwrap_or("").to_string();
+
+    (link, keyword)
+}
+
+async fn process_message(
+    api: Api,
+    context: Arc<Context>,
+    orig_message: Message,
+) -> Result<(), Error> {
+    let message = MessageOrChannelPost::Message(orig_message.clone());
+
+    match orig_message.kind {
+        MessageKind::Text { ref data, .. } => {
+            let command = data.clone();
+
+            log::info!("{:?} wrote: {}", get_chat_id(&message), command);
+
+            if orig_message.forward.is_some() && is_private_chat(&message) {
+                if let Some(url) = logic::extract_url(data) {
+                    let language = chat_language(&message, &context.pool);
+
+                    return url_hint(api, message, &language, url).await;
+                }
+            }
+
+            process_message_or_channel_post(api, context, message, command).await
+        }
+        MessageKind::Document {
+            ref data,
+            ref caption,
+        } if is_opml_document(data, caption) => {
+            let language = chat_language(&message, &context.pool);
+
+            import(
+                api,
+                message,
+                &language,
+                data.clone(),
+                context.subscription_limit,
+                &context.pool,
+            )
+            .await
+        }
+        MessageKind::NewChatMembers { ref data } => {
+            if data.iter().any(|user| user.id == context.bot_id) {
+                bot_added(api, message, &context.pool).await
+            } else {
+                Ok(())
+            }
+        }
+        MessageKind::GroupChatCreated | MessageKind::SupergroupChatCreated => {
+            bot_added(api, message, &context.pool).await
+        }
+        MessageKind::LeftChatMember { ref data } => {
+            if data.id == context.bot_id {
+                bot_removed(&message, &context.pool);
+            }
+
+            Ok(())
+        }
+        ref kind => process_caption(api, context, message, kind).await,
+    }
+}
+
+async fn process_channel_post(
+    api: Api,
+    context: Arc<Context>,
+    post: ChannelPost,
+) -> Result<(), Error> {
+    let message = MessageOrChannelPost::ChannelPost(post.clone());
+
+    match post.kind {
+        MessageKind::Text { ref data, .. } => {
+            let command = data.clone();
+
+            process_message_or_channel_post(api, context, message, command).await
+        }
+        MessageKind::Document {
+            ref data,
+            ref caption,
+        } if is_opml_document(data, caption) => {
+            let language = chat_language(&message, &context.pool);
+
+            import(
+                api,
+                message,
+                &language,
+                data.clone(),
+                context.subscription_limit,
+                &context.pool,
+            )
+            .await
+        }
+        ref kind => process_caption(api, context, message, kind).await,
+    }
+}
+
+async fn process_caption(
+    api: Api,
+    context: Arc<Context>,
+    message: MessageOrChannelPost,
+    kind: &MessageKind,
+) -> Result<(), Error> {
+    let caption = match message_text(kind) {
+        Some(caption) => caption.clone(),
+        None => return Ok(()),
+    };
+
+    if caption.trim_start().starts_with('/') {
+        process_message_or_channel_post(api, context, message, caption).await
+    } else if is_private_chat(&message) {
+        match logic::extract_url(&caption) {
+            Some(url) => {
+                let language = chat_language(&message, &context.pool);
+
+                url_hint(api, message, &language, url).await
+            }
+            None => Ok(()),
+        }
+    } else {
+        Ok(())
+    }
+}
+
+fn message_text(kind: &MessageKind) -> Option<&String> {
+    match kind {
+        MessageKind::Text { ref data, .. } => Some(data),
+        MessageKind::Document { ref caption, .. } => caption.as_ref(),
+        MessageKind::Photo { ref caption, .. } => caption.as_ref(),
+        MessageKind::Video { ref caption, .. } => caption.as_ref(),
+        _ => None,
+    }
+}
+
+fn is_private_chat(message: &MessageOrChannelPost) -> bool {
+    match message {
+        MessageOrChannelPost::Message(message) => matches!(message.chat, MessageChat::Private(_)),
+        MessageOrChannelPost::ChannelPost(_) => false,
+    }
+}
+
+fn is_group_chat(message: &MessageOrChannelPost) -> bool {
+    match message {
+        MessageOrChannelPost::Message(message) => matches!(
+            message.chat,
+            MessageChat::Group(_) | MessageChat::Supergroup(_)
+        ),
+        MessageOrChannelPost::ChannelPost(_) => false,
+    }
+}
+
+async fn url_hint(
+    api: Api,
+    message: MessageOrChannelPost,
+    language: &str,
+    url: String,
+) -> Result<(), Error> {
+    let response = i18n::tf(language, "url_hint", &[&url, &SUBSCRIBE]);
+
+    send_reply(&api, &message, response).await?;
+    Ok(())
+}
+
+async fn process_message_or_channel_post(
+    api: Api,
+    context: Arc<Context>,
+    message: MessageOrChannelPost,
+    command_string: String,
+) -> Result<(), Error> {
+    let language = chat_language(&message, &context.pool);
+
+    if !context.limiter.check(get_chat_id(&message)) {
+        return slow_down(api, message, &language).await;
+    }
+
+    let (command, mut argument) = parse_command(&command_string);
+
+    context.metrics.increment_command(command_label(&command));
+
+    if command == SUBSCRIBE {
+        if argument.is_empty() {
+            argument = replied_message_url(&message).unwrap_or_default();
+        }
+
+        subscribe(
+            api,
+            message,
+            &language,
+            argument,
+            context.subscription_limit,
+            &context.pool,
+        )
+        .await?;
+    } else if command == LIST_SUBSCRIPTIONS {
+        list_subscriptions(api, message, &language, argument, &context.pool).await?;
+    } else if command == CLONE_FROM {
+        clone_from(api, message, &language, argument, &context).await?;
+    } else if command == UNSUBSCRIBE_ALL {
+        unsubscribe_all(api, message, &language, argument, &context.pool).await?;
+    } else if command == UNSUBSCRIBE {
+        unsubscribe(api, message, &language, argument, &context.pool).await?;
+    } else if command == HELP {
+        help(api, message, &language, argument).await?;
+    } else if command == START {
+        start(api, message, &language, &context).await?;
+    } else if command == SET_TIMEZONE {
+        set_timezone(api, message, &language, argument, &context.pool).await?;
+    } else if command == GET_TIMEZONE {
+        get_timezone(api, message, &language, &context.pool).await?;
+    } else if command == SET_LIMIT {
+        set_limit(
+            api,
+            message,
+            &language,
+            argument,
+            context.subscription_limit_ceiling,
+            &context.pool,
+        )
+        .await?;
+    } else if command == PAUSE {
+        pause(api, message, &language, &context.pool).await?;
+    } else if command == RESUME {
+        resume(api, message, &language, &context.pool).await?;
+    } else if command == ADD_FILTER {
+        add_filter(api, message, &language, argument, &context.pool).await?;
+    } else if command == REMOVE_FILTER {
+        remove_filter(api, message, &language, argument, &context.pool).await?;
+    } else if command == LIST_FILTERS {
+        list_filters(api, message, &language, argument, &context.pool).await?;
+    } else if command == WATCH {
+        watch(api, message, &language, argument, &context.pool).await?;
+    } else if command == UNWATCH {
+        unwatch(api, message, &language, argument, &context.pool).await?;
+    } else if command == WATCHWORDS {
+        watchwords(api, message, &language, &context.pool).await?;
+    } else if command == SET_PREVIEW {
+        set_preview(api, message, &language, argument, &context.pool).await?;
+    } else if command == SET_THROTTLE {
+        set_throttle(api, message, &language, argument, &context.pool).await?;
+    } else if command == MOVE {
+        move_subscription(api, message, &language, argument, &context.pool).await?;
+    } else if command == SET_LANGUAGE {
+        set_language(api, message, argument, &context.pool).await?;
+    } else if command == PREVIEW {
+        preview(api, message, &language, argument).await?;
+    } else if command == SET_FORMAT {
+        set_format(api, message, &language, argument, &context.pool).await?;
+    } else if command == RESET_FORMAT {
+        reset_format(api, message, &language, argument, &context.pool).await?;
+    } else if command == MUTE {
+        mute(api, message, &language, argument, &context.pool).await?;
+    } else if command == UNMUTE {
+        unmute(api, message, &language, argument, &context.pool).await?;
+    } else if command == FEED_INFO {
+        feed_info(api, message, &language, argument, &context.pool).await?;
+    } else if command == SET_DIGEST {
+        set_digest(api, message, &language, argument, &context.pool).await?;
+    } else if command == SEARCH {
+        search(api, message, &language, argument, &context.pool).await?;
+    } else if command == SET_TRUNCATE {
+        set_truncate(api, message, &language, argument, &context.pool).await?;
+    } else if command == WHOAMI {
+        whoami(api, message, &language, &context.pool).await?;
+    } else if command == RESUBSCRIBE {
+        resubscribe(api, message, &language, argument, &context.pool).await?;
+    } else if command == LAST {
+        last(api, message, &language, argument, &context.pool).await?;
+    } else if command == IMPORT {
+        import_hint(api, message, &language).await?;
+    } else if command == EXPORT {
+        export(api, message, &language, &context.pool).await?;
+    } else if command == STATS {
+        stats(api, message, &language, &context.pool).await?;
+    } else if command == SET_AUTHOR {
+        set_author(api, message, &language, argument, &context.pool).await?;
+    } else if command == SET_BACKFILL_ORDER {
+        set_backfill_order(api, message, &language, argument, &context.pool).await?;
+    } else if command == TRACK_PER_USER {
+        track_per_user(api, message, &language, argument, &context.pool).await?;
+    } else if command == SET_QUIET_HOURS {
+        set_quiet_hours(api, message, &language, argument, &context.pool).await?;
+    } else if command == HEALTH {
+        health(api, message, &language, &context.pool).await?;
+    } else if command == SET_PREFIX {
+        set_prefix(api, message, &language, argument, &context.pool).await?;
+    } else if command == RESET_PREFIX {
+        reset_prefix(api, message, &language, &context.pool).await?;
+    } else if command == SNOOZE {
+        snooze(api, message, &language, argument, &context.pool).await?;
+    } else if command == TOP {
+        top(api, message, &language, argument, &context.pool).await?;
+    } else if command == SET_MARKDOWN {
+        set_markdown(api, message, &language, argument, &context.pool).await?;
+    } else if command == SET_HTML {
+        set_html(api, message, &language, argument, &context.pool).await?;
+    } else if command == SET_FULLTEXT {
+        set_fulltext(api, message, &language, argument, &context.pool).await?;
+    } else if command == TEST_FORMAT {
+        test_format(api, message, &language, argument, &context.pool).await?;
+    } else if command == VERSION {
+        version(api, message, &language, &context).await?;
+    } else if command == RANDOM {
+        random(api, message, &language, &context.pool).await?;
+    } else if command == UNREAD {
+        unread(api, message, &language, &context.pool).await?;
+    } else if command == RESTORE {
+        restore(api, message, &language, argument, &context).await?;
+    } else if command == FEEDBACK {
+        feedback(api, message, &language, argument, &context.feedback_limiter).await?;
+    } else if command == BROADCAST && is_admin(&message) {
+        broadcast(api, message, &language, argument, &context.pool).await?;
+    } else if command == VALIDATE && is_admin(&message) {
+        validate(api, message, &language, argument).await?;
+    } else if command == DISABLED_CHATS && is_admin(&message) {
+        disabled_chats(api, message, &language, &context.pool).await?;
+    } else if command == POPULAR && is_admin(&message) {
+        popular(api, message, &language, argument, &context.pool).await?;
+    } else {
+        unknown_command(api, message, &language).await?;
+    }
+
+    Ok(())
+}
+
+fn chat_language(message: &MessageOrChannelPost, pool: &db::Pool) -> String {
+    logic::chat_language(&db::get_connection(pool), get_chat_id(message))
+}
+
+fn command_label(command: &str) -> &str {
+    if command_help(command, i18n::DEFAULT_LANGUAGE).is_some()
+        || command == BROADCAST
+        || command == POPULAR
+        || command == DISABLED_CHATS
+        || command == VALIDATE
+    {
+        command
+    } else {
+        "unknown"
+    }
+}
+
+fn get_chat_id(message: &MessageOrChannelPost) -> i64 {
+    match message {
+        MessageOrChannelPost::Message(message) => message.chat.id().into(),
+        MessageOrChannelPost::ChannelPost(post) => post.chat.id.into(),
+    }
+}
+
+fn get_user_id(message: &MessageOrChannelPost) -> Option<i64> {
+    match message {
+        MessageOrChannelPost::Message(message) => Some(message.from.id.into()),
+        MessageOrChannelPost::ChannelPost(_) => None,
+    }
+}
+
+fn replied_message_url(message: &MessageOrChannelPost) -> Option<String> {
+    let reply = match message {
+        MessageOrChannelPost::Message(message) => message.reply_to_message.as_ref(),
+        MessageOrChannelPost::ChannelPost(post) => post.reply_to_message.as_ref(),
+    }?;
+
+    let kind = match reply.as_ref() {
+        MessageOrChannelPost::Message(message) => &message.kind,
+        MessageOrChannelPost::ChannelPost(post) => &post.kind,
+    };
+
+    message_text(kind).and_then(|text| logic::extract_url(text))
+}
+
+fn parse_command(text: &str) -> (String, String) {
+    let mut parts = text.trim().splitn(2, char::is_whitespace);
+
+    let command = parts
+        .next()
+        .unwrap_or("")
+        .split('@')
+        .next()
+        .unwrap_or("")
+        .to_lowercase();
+    let argument = parts.next().unwrap_or("").trim().to_string();
+
+    (command, argument)
+}
+
+pub async fn start_bot() -> Result<(), Error> {
+    if db::wait_for_database().is_err() {
+        log::error!("The database is unavailable, the bot is stopped");
+
+        process::exit(1);
+    }
+
+    api_pool::load();
+
+    let api = api_pool::primary().clone();
+    let bot = api.send(GetMe).await?;
+    let pool = db::create_connection_pool();
+    let metrics = Arc::new(Metrics::default());
+
+    metrics::spawn_server(metrics.clone(), pool.clone());
+    blocklist::load();
+
+    if let Err(error) = api.send(SetMyCommands::new(bot_commands())).await {
+        log::error!("Failed to set the bot's commands: {}", error);
+    }
+
+    let context = Arc::new(Context {
+        limiter: RateLimiter::from_env(),
+        feedback_limiter: RateLimiter::new(FEEDBACK_PER_MINUTE),
+        subscription_limit: logic::subscription_limit_from_env(),
+        subscription_limit_ceiling: logic::subscription_limit_ceiling_from_env(),
+        sync_interval_secs: logic::sync_interval_secs_from_env(),
+        delivery_interval_secs: logic::delivery_interval_secs_from_env(),
+        pool,
+        bot_id: bot.id,
+        metrics,
+        started_at: Instant::now(),
+    });
+    let mut stream: LocalBoxStream<Result<Update, Error>> = match webhook::bot_mode() {
+        BotMode::Polling => api.stream().boxed_local(),
+        BotMode::Webhook => webhook::spawn_server(WebhookConfig::from_env())
+            .map(Ok)
+            .boxed_local(),
+    };
+    let mut tasks = FuturesUnordered::new();
+
+    let shutdown = shutdown_signal();
+    tokio::pin!(shutdown);
+
+    log::info!("Starting a bot");
+
+    loop {
+        tokio::select! {
+            update = stream.next() => {
+                let update = match update {
+                    Some(update) => update?,
+                    None => break,
+                };
+
+                if let Some(task) = dispatch_update(&api, &context, update) {
+                    tasks.push(task);
+                }
+            }
+            Some(_) = tasks.next(), if !tasks.is_empty() => (),
+            _ = &mut shutdown => {
+                log::info!("Received a shutdown signal, stopping the bot");
+                break;
+            }
+        }
+    }
+
+    let count = tasks.len();
+
+    while tasks.next().await.is_some() {}
+
+    log::info!("Awaited {} in-flight tasks before shutting down", count);
+
+    Ok(())
+}
+
+fn dispatch_update(
+    api: &Api,
+    context: &Arc<Context>,
+    update: Update,
+) -> Option<JoinHandle<Result<(), Error>>> {
+    match update.kind {
+        UpdateKind::Message(message) => Some(tokio::spawn(process_message(
+            api.clone(),
+            context.clone(),
+            message,
+        ))),
+        UpdateKind::ChannelPost(message) => Some(tokio::spawn(process_channel_post(
+            api.clone(),
+            context.clone(),
+            message,
+        ))),
+        _ => None,
+    }
+}
+
+async fn shutdown_signal() {
+    let mut terminate =
+        signal::unix::signal(SignalKind::terminate()).expect("Failed to listen for SIGTERM");
+
+    tokio::select! {
+        _ = signal::ctrl_c() => (),
+        _ = terminate.recv() => (),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SendMessageError;
+    use chrono::{TimeZone, Utc};
+    use std::time::Duration;
+
+    #[test]
+    fn parse_command_lowercases_command_and_keeps_argument() {
+        assert_eq!(
+            super::parse_command("/SubScribe https://Example.com/Feed.xml"),
+            (
+                "/subscribe".to_string(),
+                "https://Example.com/Feed.xml".to_string()
+            )
+        );
+        assert_eq!(
+            super::parse_command("  /HELP  "),
+            ("/help".to_string(), "".to_string())
+        );
+        assert_eq!(
+            super::parse_command("/Stats@el_monitorro_bot"),
+            ("/stats".to_string(), "".to_string())
+        );
+    }
+
+    #[test]
+    fn start_text_shows_configured_intervals_and_limit() {
+        let text = super::start_text(300, 60, 30, "en");
+
+        assert!(text.contains(
+            "Feed updates check interval is 5 minutes. Unread items delivery interval is 1 minute."
+        ));
+        assert!(text.contains("By default, the number of subscriptions is limited to 30."));
+    }
+
+    #[test]
+    fn command_help_finds_help_by_command_name() {
+        assert!(super::command_help("subscribe", "en")
+            .unwrap()
+            .starts_with("/subscribe url number\n\nSubscribes"));
+        assert!(super::command_help("subscribe", "ru")
+            .unwrap()
+            .starts_with("/subscribe url number\n\nПодписывает"));
+        assert_eq!(
+            super::command_help("/Subscribe", "en"),
+            super::command_help("subscribe", "en")
+        );
+        assert!(super::command_help("unknown", "en").is_none());
+        assert!(super::command_help("unknown_command", "en").is_none());
+    }
+
+    #[test]
+    fn commands_string_lists_command_specs() {
+        let commands = super::commands_string("en");
+
+        assert_eq!(commands.lines().count(), super::command_specs("en").len());
+        assert!(
+            commands.starts_with("/start - show the bot's description and contact information\n")
+        );
+        assert!(commands.contains("{{feed_title}}"));
+    }
+
+    #[test]
+    fn bot_commands_fit_telegram_limits() {
+        let commands = super::bot_commands();
+
+        assert_eq!(commands[0].command, "start".to_string());
+        assert_eq!(
+            commands[0].description,
+            "show the bot's description and contact information".to_string()
+        );
+        assert_eq!(
+            commands[1].description,
+            "url number - subscribe to feed".to_string()
+        );
+
+        for command in commands {
+            assert!(!command.command.starts_with('/'));
+            assert!(!command.description.is_empty());
+            assert!(command.description.chars().count() <= 256);
+        }
+    }
+
+    #[test]
+    fn parse_command_strips_bot_mention_in_groups() {
+        assert_eq!(
+            super::parse_command("/subscribe@el_monitorro_bot http://x"),
+            (super::SUBSCRIBE.to_string(), "http://x".to_string())
+        );
+        assert_eq!(
+            super::parse_command("/subscribe@el_monitorro_bot"),
+            (super::SUBSCRIBE.to_string(), "".to_string())
+        );
+    }
+
+    #[test]
+    fn subscription_error_message_shows_usage_when_url_is_not_provided() {
+        assert_eq!(
+            super::subscription_error_message(super::SubscriptionError::RssUrlNotProvided, "en"),
+            "Url is not provided. For example, /subscribe https://www.feedforall.com/sample-feed.xml"
+                .to_string()
+        );
+    }
+
+    #[test]
+    fn subscription_error_message_shows_when_existing_subscription_was_created() {
+        assert_eq!(
+            super::subscription_error_message(
+                super::SubscriptionError::SubscriptionAlreadyExists(
+                    "2020-08-08 13:15:00 +03:00".to_string()
+                ),
+                "en"
+            ),
+            "You already subscribed to this feed on 2020-08-08 13:15:00 +03:00".to_string()
+        );
+    }
+
+    #[test]
+    fn subscription_error_message_lists_discovered_feeds() {
+        assert_eq!(
+            super::subscription_error_message(
+                super::SubscriptionError::SeveralFeedsFound(vec![
+                    "https://example.com/feed.xml".to_string(),
+                    "https://example.com/atom.xml".to_string()
+                ]),
+                "en"
+            ),
+            "Several feeds were found on the page. Subscribe to one of them:\nhttps://example.com/feed.xml\nhttps://example.com/atom.xml"
+                .to_string()
+        );
+    }
+
+    #[test]
+    fn subscription_error_message_is_translated() {
+        assert_eq!(
+            super::subscription_error_message(super::SubscriptionError::RssUrlNotProvided, "ru"),
+            "Не указан url. Например, /subscribe https://www.feedforall.com/sample-feed.xml"
+                .to_string()
+        );
+        assert_eq!(
+            super::wrong_arguments_message("unknown", "ru"),
+            "Неверное количество аргументов".to_string()
+        );
+    }
+
+    #[test]
+    fn parse_command_ignores_command_strings_embedded_in_urls() {
+        assert_eq!(
+            super::parse_command("/subscribe http://example.com/list_subscriptions"),
+            (
+                super::SUBSCRIBE.to_string(),
+                "http://example.com/list_subscriptions".to_string()
+            )
+        );
+        assert_eq!(
+            super::parse_command("/preview https://example.com/unsubscribe?next=/set_timezone"),
+            (
+                super::PREVIEW.to_string(),
+                "https://example.com/unsubscribe?next=/set_timezone".to_string()
+            )
+        );
+        assert_eq!(
+            super::parse_command("/set_format https://example.com/reset_format {{title}}"),
+            (
+                super::SET_FORMAT.to_string(),
+                "https://example.com/reset_format {{title}}".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn parse_command_distinguishes_commands_sharing_substrings() {
+        let pairs = vec![
+            ("/set_timezone 600", super::SET_TIMEZONE),
+            ("/get_timezone", super::GET_TIMEZONE),
+            ("/set_format url {{title}}", super::SET_FORMAT),
+            ("/reset_format url", super::RESET_FORMAT),
+            ("/mute url", super::MUTE),
+            ("/unmute url", super::UNMUTE),
+            ("/subscribe url", super::SUBSCRIBE),
+            ("/unsubscribe url", super::UNSUBSCRIBE),
+            ("/list_subscriptions", super::LIST_SUBSCRIPTIONS),
+        ];
+
+        for (text, expected) in pairs {
+            assert_eq!(super::parse_command(text).0, expected);
+        }
+    }
+
+    #[test]
+    fn parse_command_does_not_match_commands_inside_arguments() {
+        let (command, argument) =
+            super::parse_command("/unsubscribe https://example.com/subscribe/feed");
+
+        assert_eq!(command, "/unsubscribe");
+        assert_ne!(command, super::SUBSCRIBE);
+        assert_eq!(argument, "https://example.com/subscribe/feed");
+    }
+
+    #[test]
+    fn parse_arguments_splits_arguments_on_whitespace() {
+        assert_eq!(
+            super::parse_arguments("  https://example.com/feed.xml \n 600  "),
+            vec![
+                "https://example.com/feed.xml".to_string(),
+                "600".to_string()
+            ]
+        );
+        assert!(super::parse_arguments("   ").is_empty());
+    }
+
+    #[test]
+    fn wrong_arguments_message_shows_command_help() {
+        assert!(super::wrong_arguments_message(super::SET_THROTTLE, "en")
+            .starts_with("Wrong number of arguments\n\n/set_throttle url seconds"));
+    }
+
+    #[test]
+    fn split_message_does_not_split_short_message() {
+        assert_eq!(
+            super::split_message("Short message"),
+            vec!["Short message".to_string()]
+        );
+    }
+
+    #[test]
+    fn split_message_splits_long_message_on_word_boundaries() {
+        let message = "word ".repeat(2000);
+
+        let chunks = super::split_message(&message);
+
+        assert_eq!(chunks.len(), 3);
+        assert!(chunks.iter().all(|chunk| chunk.chars().count() <= 4096));
+        assert!(chunks.iter().all(|chunk| chunk.ends_with("word")));
+        assert_eq!(chunks.join(" "), message.trim());
+    }
+
+    #[test]
+    fn split_message_prefers_paragraph_boundaries() {
+        let paragraph = "word ".repeat(500);
+        let message = vec![paragraph.trim(); 4].join("\n\n");
+
+        let chunks = super::split_message(&message);
+
+        assert_eq!(chunks.len(), 4);
+        assert_eq!(chunks[0], paragraph.trim());
+        assert_eq!(chunks.join("\n\n"), message);
+    }
+
+    #[test]
+    fn split_message_splits_message_without_whitespace() {
+        let message = "a".repeat(10_000);
+
+        let chunks = super::split_message(&message);
+
+        assert_eq!(
+            chunks
+                .iter()
+                .map(|chunk| chunk.len())
+                .collect::<Vec<usize>>(),
+            vec![4096, 4096, 1808]
+        );
+    }
+
+    #[test]
+    fn split_message_does_not_split_tags() {
+        let message = format!(
+            "{}<a href=\"https://example.com\">link</a>",
+            "a".repeat(4080)
+        );
+
+        let chunks = super::split_message(&message);
+
+        assert_eq!(chunks[0], "a".repeat(4080));
+        assert!(chunks[1].starts_with("<a href"));
+    }
+
+    #[test]
+    fn send_message_error_detects_blocked_bot() {
+        let result =
+            SendMessageError::from_message("Forbidden: bot was blocked by the user".to_string());
+
+        assert_eq!(
+            result,
+            SendMessageError::BotBlocked("Forbidden: bot was blocked by the user".to_string())
+        );
+    }
+
+    #[test]
+    fn send_message_error_detects_missing_chat() {
+        let result = SendMessageError::from_message("Bad Request: chat not found".to_string());
+
+        assert_eq!(
+            result,
+            SendMessageError::ChatNotFound("Bad Request: chat not found".to_string())
+        );
+    }
+
+    #[test]
+    fn send_message_error_detects_rate_limit() {
+        let result =
+            SendMessageError::from_message("Too Many Requests: retry after 35".to_string());
+
+        assert_eq!(
+            result,
+            SendMessageError::RateLimited("Too Many Requests: retry after 35".to_string())
+        );
+    }
+
+    #[test]
+    fn timezone_confirmation_shows_offset_and_local_time() {
+        let now = Utc.ymd(2020, 8, 25).and_hms(4, 32, 0);
+
+        assert_eq!(
+            super::timezone_confirmation(600, now, "en"),
+            "Set to UTC+10:00, current local time is 14:32".to_string()
+        );
+        assert_eq!(
+            super::timezone_confirmation(-210, now, "en"),
+            "Set to UTC-03:30, current local time is 01:02".to_string()
+        );
+        assert_eq!(
+            super::timezone_confirmation(0, now, "en"),
+            "Set to UTC+00:00, current local time is 04:32".to_string()
+        );
+    }
+
+    #[test]
+    fn version_text_shows_version_commit_and_uptime() {
+        assert_eq!(
+            super::version_text(Some("abc1234"), Duration::from_secs(3 * 60 * 60 + 60), "en"),
+            format!(
+                "Version: {} (abc1234)\nUptime: 3h 1m",
+                env!("CARGO_PKG_VERSION")
+            )
+        );
+        assert_eq!(
+            super::version_text(None, Duration::from_secs(30), "en"),
+            format!("Version: {}\nUptime: 0m", env!("CARGO_PKG_VERSION"))
+        );
+    }
+
+    #[test]
+    fn send_message_error_parses_retry_after() {
+        let error = SendMessageError::from_message("Too Many Requests: retry after 35".to_string());
+
+        assert_eq!(error.retry_after(), Some(Duration::from_secs(35)));
+        assert_eq!(
+            SendMessageError::RateLimited("Too Many Requests".to_string()).retry_after(),
+            Some(Duration::from_secs(1))
+        );
+        assert_eq!(
+            SendMessageError::Other("Bad Request: message is too long".to_string()).retry_after(),
+            None
+        );
+    }
+
+    #[test]
+    fn retry_delay_stops_after_max_attempts() {
+        let error = SendMessageError::RateLimited("Too Many Requests: retry after 5".to_string());
+
+        assert_eq!(super::retry_delay(&error, 1), Some(Duration::from_secs(5)));
+        assert_eq!(super::retry_delay(&error, 2), Some(Duration::from_secs(5)));
+        assert_eq!(super::retry_delay(&error, 3), None);
+    }
+
+    #[test]
+    fn send_message_error_falls_back_to_other_errors() {
+        let result = SendMessageError::from_message("Bad Request: message is too long".to_string());
+
+        assert_eq!(
+            result,
+            SendMessageError::Other("Bad Request: message is too long".to_string())
+        );
+    }
+}
@@ -0,0 +1,81 @@
+use lazy_static::lazy_static;
+use std::env;
+use telegram_bot::Api;
+
+lazy_static! {
+    static ref API_POOL: ApiPool = ApiPool::from_env();
+}
+
+pub struct ApiPool {
+    tokens: Vec<String>,
+    apis: Vec<Api>,
+}
+
+impl ApiPool {
+    pub fn from_env() -> Self {
+        let value = env::var("TELEGRAM_BOT_TOKEN").expect("TELEGRAM_BOT_TOKEN not set");
+
+        ApiPool::new(parse_tokens(&value))
+    }
+
+    pub fn new(tokens: Vec<String>) -> Self {
+        if tokens.is_empty() {
+            panic!("TELEGRAM_BOT_TOKEN must contain at least one token");
+        }
+
+        let apis = tokens.iter().map(Api::new).collect();
+
+        ApiPool { tokens, apis }
+    }
+
+    pub fn primary(&self) -> &Api {
+        &self.apis[0]
+    }
+
+    pub fn primary_token(&self) -> &str {
+        &self.tokens[0]
+    }
+
+    pub fn fallbacks(&self) -> &[Api] {
+        &self.apis[1..]
+    }
+}
+
+pub fn load() {
+    lazy_static::initialize(&API_POOL);
+
+    log::info!("Loaded {} bot tokens", API_POOL.apis.len());
+}
+
+pub fn primary() -> &'static Api {
+    API_POOL.primary()
+}
+
+pub fn primary_token() -> &'static str {
+    API_POOL.primary_token()
+}
+
+pub fn fallbacks() -> &'static [Api] {
+    API_POOL.fallbacks()
+}
+
+fn parse_tokens(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(|token| token.trim().to_string())
+        .filter(|token| !token.is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn parse_tokens_splits_comma_separated_tokens() {
+        assert_eq!(super::parse_tokens("token"), vec!["token".to_string()]);
+        assert_eq!(
+            super::parse_tokens(" first, second ,,"),
+            vec!["first".to_string(), "second".to_string()]
+        );
+        assert!(super::parse_tokens(" , ").is_empty());
+    }
+}
@@ -0,0 +1,118 @@
+use lazy_static::lazy_static;
+use std::env;
+use url::Url;
+
+lazy_static! {
+    static ref BLOCKLIST: Blocklist = Blocklist::from_env();
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Blocklist {
+    domains: Vec<String>,
+    wildcards: Vec<String>,
+}
+
+impl Blocklist {
+    pub fn from_env() -> Self {
+        Blocklist::parse(&env::var("BLOCKED_DOMAINS").unwrap_or_default())
+    }
+
+    pub fn parse(value: &str) -> Self {
+        let mut domains = vec![];
+        let mut wildcards = vec![];
+
+        for entry in value.split(|c: char| c == ',' || c.is_whitespace()) {
+            let entry = normalize_host(entry);
+
+            if entry.is_empty() {
+                continue;
+            }
+
+            if entry.starts_with("*.") {
+                wildcards.push(entry[1..].to_string());
+            } else {
+                domains.push(entry);
+            }
+        }
+
+        Blocklist { domains, wildcards }
+    }
+
+    pub fn is_blocked(&self, url: &Url) -> bool {
+        let host = match url.host_str() {
+            Some(host) => normalize_host(host),
+            None => return false,
+        };
+
+        self.domains.iter().any(|domain| *domain == host)
+            || self
+                .wildcards
+                .iter()
+                .any(|suffix| host.ends_with(suffix.as_str()))
+    }
+}
+
+pub fn load() {
+    lazy_static::initialize(&BLOCKLIST);
+
+    log::info!(
+        "Loaded {} blocked domains",
+        BLOCKLIST.domains.len() + BLOCKLIST.wildcards.len()
+    );
+}
+
+pub fn is_blocked(url: &Url) -> bool {
+    BLOCKLIST.is_blocked(url)
+}
+
+fn normalize_host(host: &str) -> String {
+    host.trim().trim_end_matches('.').to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Blocklist;
+    use url::Url;
+
+    fn is_blocked(blocklist: &Blocklist, url: &str) -> bool {
+        blocklist.is_blocked(&Url::parse(url).unwrap())
+    }
+
+    #[test]
+    fn parse_splits_domains_and_wildcards() {
+        assert_eq!(
+            Blocklist::parse(" Example.com, *.spam.org\nexample.net. ,,"),
+            Blocklist {
+                domains: vec!["example.com".to_string(), "example.net".to_string()],
+                wildcards: vec![".spam.org".to_string()]
+            }
+        );
+    }
+
+    #[test]
+    fn is_blocked_matches_exact_domains() {
+        let blocklist = Blocklist::parse("example.com");
+
+        assert!(is_blocked(&blocklist, "https://example.com/feed.xml"));
+        assert!(is_blocked(&blocklist, "http://EXAMPLE.com:8080/rss"));
+        assert!(!is_blocked(&blocklist, "https://www.example.com/feed.xml"));
+        assert!(!is_blocked(&blocklist, "https://notexample.com/feed.xml"));
+    }
+
+    #[test]
+    fn is_blocked_matches_wildcard_subdomains() {
+        let blocklist = Blocklist::parse("*.example.com");
+
+        assert!(is_blocked(&blocklist, "https://www.example.com/feed.xml"));
+        assert!(is_blocked(&blocklist, "https://a.b.example.com/feed.xml"));
+        assert!(!is_blocked(&blocklist, "https://example.com/feed.xml"));
+        assert!(!is_blocked(&blocklist, "https://badexample.com/feed.xml"));
+    }
+
+    #[test]
+    fn is_blocked_allows_everything_with_empty_blocklist() {
+        let blocklist = Blocklist::parse("");
+
+        assert!(!is_blocked(&blocklist, "https://example.com/feed.xml"));
+    }
+}
@@ -0,0 +1,48 @@
+use serde::Serialize;
+use telegram_bot::types::{
+    Error, HttpRequest, JsonRequestType, JsonTrueToUnitResponse, Request, RequestType, RequestUrl,
+};
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BotCommand {
+    pub command: String,
+    pub description: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SetMyCommands {
+    commands: Vec<BotCommand>,
+}
+
+impl SetMyCommands {
+    pub fn new(commands: Vec<BotCommand>) -> Self {
+        SetMyCommands { commands }
+    }
+}
+
+impl Request for SetMyCommands {
+    type Type = JsonRequestType<Self>;
+    type Response = JsonTrueToUnitResponse;
+
+    fn serialize(&self) -> Result<HttpRequest, Error> {
+        Self::Type::serialize(RequestUrl::method("setMyCommands"), self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BotCommand, SetMyCommands};
+
+    #[test]
+    fn set_my_commands_is_serialized_as_telegram_json() {
+        let request = SetMyCommands::new(vec![BotCommand {
+            command: "start".to_string(),
+            description: "show the bot's description".to_string(),
+        }]);
+
+        assert_eq!(
+            serde_json::to_string(&request).unwrap(),
+            r#"{"commands":[{"command":"start","description":"show the bot's description"}]}"#
+        );
+    }
+}
@@ -1,28 +1,30 @@
 use crate::bot::api;
+use crate::bot::api::SendMessageError;
+use crate::bot::i18n;
+use crate::bot::logic;
+use crate::bot::logic::MessageFormat;
 use crate::db;
 use crate::db::feeds;
 use crate::db::telegram;
+use crate::db::telegram::{NewTelegramDeliveredItem, NewTelegramSubscription};
 use crate::models::feed_item::FeedItem;
+use crate::models::telegram_chat::TelegramChat;
 use crate::models::telegram_subscription::TelegramSubscription;
-use chrono::offset::FixedOffset;
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Duration, Timelike, Utc};
 
 use diesel::result::Error;
+use diesel::PgConnection;
 use tokio::time;
 
+static BATCH_SEPARATOR: &str = "\n\n---\n\n";
+static MARKDOWN_BATCH_SEPARATOR: &str = "\n\n\\-\\-\\-\n\n";
+
 pub struct DeliverJob {}
 
 pub struct DeliverJobError {
     msg: String,
 }
 
-static BLOCKED_ERROR: &str = "Forbidden: bot was blocked by the user";
-static CHAT_NOT_FOUND: &str = "Bad Request: chat not found";
-static KICKED_ERROR: &str = "Forbidden: bot was kicked from the supergroup chat";
-static DEACTIVATED_ERROR: &str = "Forbidden: user is deactivated";
-static CHAT_UPGRADED_ERROR: &str = "Bad Request: group chat was upgraded to a supergroup chat";
-static BOT_IS_NOT_MEMBER: &str = "Forbidden: bot is not a member of the supergroup chat";
-
 impl From<Error> for DeliverJobError {
     fn from(error: Error) -> Self {
         let msg = format!("{:?}", error);
@@ -41,6 +43,7 @@ impl DeliverJob {
         let mut current_subscriptions: Vec<TelegramSubscription>;
         let mut page = 1;
         let mut total_number = 0;
+        let now = db::current_time();
 
         log::info!("Started delivering feed items");
 
@@ -56,7 +59,9 @@ impl DeliverJob {
             total_number += current_subscriptions.len();
 
             for subscription in current_subscriptions {
-                tokio::spawn(deliver_subscription_updates(subscription));
+                if delivery_is_due(&subscription, now) {
+                    tokio::spawn(deliver_subscription_updates(subscription));
+                }
             }
         }
 
@@ -65,6 +70,12 @@ impl DeliverJob {
             total_number
         );
 
+        for chat in telegram::find_digest_chats(&db_connection)? {
+            if digest_is_due(&chat, now) {
+                tokio::spawn(deliver_digest(chat));
+            }
+        }
+
         Ok(())
     }
 }
@@ -73,30 +84,34 @@ async fn deliver_subscription_updates(
     subscription: TelegramSubscription,
 ) -> Result<(), DeliverJobError> {
     let connection = db::establish_connection();
-    let feed_items = telegram::find_undelivered_feed_items(&connection, &subscription)?;
-    let undelivered_count = telegram::count_undelivered_feed_items(&connection, &subscription);
     let chat_id = subscription.chat_id;
 
+    // the chat can be removed by the cleaner after its subscriptions were fetched
+    let chat = match telegram::find_chat(&connection, chat_id) {
+        Some(chat) => chat,
+        None => {
+            log::info!("Skipped delivery to removed chat {}", chat_id);
+            return Ok(());
+        }
+    };
+
+    let feed_items = telegram::find_undelivered_feed_items(&connection, &subscription)?;
+    let undelivered_count = telegram::count_undelivered_feed_items(&connection, &subscription)?;
+    let silent = subscription.silent || logic::in_quiet_hours(&chat, db::current_time());
+
     if feed_items.len() < undelivered_count as usize {
-        let message = format!(
-            "You have {} unread items, below {} last items",
-            undelivered_count,
-            feed_items.len()
+        let message = i18n::tf(
+            &logic::language_of_chat(&chat),
+            "unread_items_truncated",
+            &[&undelivered_count, &feed_items.len()],
         );
 
-        match api::send_message(chat_id, message).await {
+        match api::send_message(chat_id, message, silent, !subscription.link_preview).await {
             Ok(_) => (),
             Err(error) => {
-                let error_message = format!("{}", error);
-
-                log::error!("Failed to deliver updates: {} {}", chat_id, error_message);
+                log::error!("Failed to deliver updates: {} {}", chat_id, error);
 
-                if bot_blocked(&error_message) {
-                    match telegram::remove_chat(&connection, chat_id) {
-                        Ok(_) => log::info!("Successfully removed chat {}", chat_id),
-                        Err(error) => log::error!("Failed to remove a chat {}", error),
-                    }
-                };
+                handle_send_error(&connection, chat_id, &error);
 
                 return Err(DeliverJobError {
                     msg: format!("Failed to send updates : {}", error),
@@ -106,64 +121,67 @@ async fn deliver_subscription_updates(
     }
 
     if !feed_items.is_empty() {
-        let chat = telegram::find_chat(&connection, chat_id).unwrap();
-
-        let offset = match chat.utc_offset_minutes {
-            None => FixedOffset::west(0),
-            Some(value) => {
-                if value > 0 {
-                    FixedOffset::east(value * 60)
-                } else {
-                    FixedOffset::west(-value * 60)
-                }
-            }
-        };
-
         let feed = feeds::find(&connection, subscription.feed_id).unwrap();
         let feed_title = match feed.title {
             Some(title) => {
-                let feed_title = truncate(&title, 50);
+                let feed_title = logic::truncate(&title, 50);
 
                 Some(feed_title)
             }
             None => None,
         };
 
-        let mut messages = feed_items
+        let keywords = subscription_keywords(&connection, &subscription)?;
+
+        let mut delivered_items = feed_items
+            .iter()
+            .filter(|item| logic::item_matches_filters(item, &keywords))
+            .collect::<Vec<&FeedItem>>();
+
+        let newest_first = subscription.last_delivered_at.is_none() && chat.backfill_newest_first;
+
+        sort_for_delivery(&mut delivered_items, newest_first);
+
+        let format = logic::message_format(&subscription);
+        let render = match format {
+            MessageFormat::PlainText => logic::render_item,
+            MessageFormat::MarkdownV2 => logic::render_markdown_item,
+            MessageFormat::Html => logic::render_html_item,
+        };
+
+        let messages = delivered_items
             .iter()
             .map(|item| {
-                let date = item.publication_date.with_timezone(&offset);
-
-                if feed_title.is_some() {
-                    format!(
-                        "{}\n\n{}\n\n{}\n\n{}\n\n",
-                        feed_title.clone().unwrap(),
-                        item.title,
-                        date,
-                        item.link
-                    )
+                let message = render(
+                    subscription.template.as_deref(),
+                    &feed_title,
+                    item,
+                    &logic::format_timestamp(item.publication_date, &chat),
+                    logic::description_limit(&chat),
+                    chat.item_prefix.as_deref(),
+                    subscription.full_text,
+                );
+
+                if chat.show_author {
+                    logic::append_author(message, item, format)
                 } else {
-                    format!("{}\n\n{}\n\n{}\n\n", item.title, date, item.link)
+                    message
                 }
             })
             .collect::<Vec<String>>();
 
-        messages.reverse();
+        let delivered_count = messages.len() as i64;
+        let throttled = subscription.min_delivery_interval_secs.is_some();
 
-        for message in messages.into_iter() {
-            match api::send_message(chat_id, message).await {
+        for message in batch_messages(messages, throttled, format).into_iter() {
+            match api::send_message_as(chat_id, message, silent, !subscription.link_preview, format)
+                .await
+            {
                 Ok(_) => (),
                 Err(error) => {
-                    let error_message = format!("{}", error);
+                    log::error!("Failed to deliver updates: {} {}", chat_id, error);
 
-                    log::error!("Failed to deliver updates: {}", error_message);
-
-                    if bot_blocked(&error_message) {
-                        match telegram::remove_chat(&connection, chat_id) {
-                            Ok(_) => log::info!("Successfully removed chat {}", chat_id),
-                            Err(error) => log::error!("Failed to remove a chat {}", error),
-                        }
-                    };
+                    handle_send_error(&connection, chat_id, &error);
 
                     return Err(DeliverJobError {
                         msg: format!("Failed to send updates : {}", error),
@@ -172,39 +190,284 @@ async fn deliver_subscription_updates(
             };
         }
 
-        match telegram::set_subscription_last_delivered_at(
+        if subscription.last_delivered_at.is_some() {
+            deliver_watchword_matches(
+                &connection,
+                chat_id,
+                &feed_title,
+                &feed_items,
+                silent,
+                &logic::language_of_chat(&chat),
+            )
+            .await;
+        }
+
+        reset_forbidden_errors(&connection, &chat);
+        save_delivered_items(&connection, &subscription, &delivered_items);
+
+        let subscription = match telegram::set_subscription_last_delivered_at(
             &connection,
             &subscription,
             get_max_publication_date(feed_items),
         ) {
-            Ok(_) => (),
+            Ok(subscription) => subscription,
             Err(error) => {
                 log::error!("Failed to set last_delivered_at: {}", error);
                 return Err(DeliverJobError {
                     msg: format!("Failed to set last_delivered_at : {}", error),
                 });
             }
+        };
+
+        if delivered_count > 0 {
+            match telegram::increment_delivered_count(&connection, &subscription, delivered_count) {
+                Ok(_) => (),
+                Err(error) => {
+                    log::error!("Failed to update delivery stats: {}", error);
+                    return Err(DeliverJobError {
+                        msg: format!("Failed to update delivery stats : {}", error),
+                    });
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+async fn deliver_digest(chat: TelegramChat) -> Result<(), DeliverJobError> {
+    let connection = db::establish_connection();
+    let subscriptions = telegram::find_subscriptions_for_chat(&connection, chat.id)?;
+    let mut feed_items = vec![];
+
+    for subscription in subscriptions {
+        let items = telegram::find_undelivered_feed_items(&connection, &subscription)?;
+
+        if !items.is_empty() {
+            let feed = feeds::find(&connection, subscription.feed_id).unwrap();
+            let keywords = subscription_keywords(&connection, &subscription)?;
+            let feed_title = match feed.title {
+                Some(title) if !title.is_empty() => logic::truncate(&title, 50),
+                _ => feed.link,
+            };
+
+            feed_items.push((subscription, feed_title, keywords, items));
+        }
+    }
+
+    let sections = feed_items
+        .iter()
+        .map(|(_, feed_title, keywords, items)| {
+            let mut items = items
+                .iter()
+                .filter(|item| logic::item_matches_filters(item, keywords))
+                .collect::<Vec<&FeedItem>>();
+
+            sort_for_delivery(&mut items, false);
+
+            (feed_title.clone(), items)
+        })
+        .collect::<Vec<(String, Vec<&FeedItem>)>>();
+
+    if sections.iter().any(|(_, items)| !items.is_empty()) {
+        let message = logic::format_digest(&sections, &logic::language_of_chat(&chat));
+
+        if let Err(error) = api::send_message(chat.id, message, false, false).await {
+            log::error!("Failed to deliver a digest: {} {}", chat.id, error);
+
+            handle_send_error(&connection, chat.id, &error);
+
+            return Err(DeliverJobError {
+                msg: format!("Failed to send a digest : {}", error),
+            });
         }
     }
 
+    reset_forbidden_errors(&connection, &chat);
+
+    for ((subscription, _, _, _), (_, items)) in feed_items.iter().zip(sections.iter()) {
+        save_delivered_items(&connection, subscription, items);
+    }
+
+    let delivered_counts = sections
+        .iter()
+        .map(|(_, items)| items.len() as i64)
+        .collect::<Vec<i64>>();
+
+    for ((subscription, _, _, items), delivered_count) in
+        feed_items.into_iter().zip(delivered_counts)
+    {
+        let subscription = telegram::set_subscription_last_delivered_at(
+            &connection,
+            &subscription,
+            get_max_publication_date(items),
+        )?;
+
+        if delivered_count > 0 {
+            telegram::increment_delivered_count(&connection, &subscription, delivered_count)?;
+        }
+    }
+
+    telegram::set_digest_sent_at(&connection, &chat, db::current_time())?;
+
     Ok(())
 }
 
-fn truncate(s: &str, max_chars: usize) -> String {
-    match s.char_indices().nth(max_chars) {
-        None => String::from(s),
-        Some((idx, _)) => {
-            let mut string = String::from(&s[..idx]);
+async fn deliver_watchword_matches(
+    connection: &PgConnection,
+    chat_id: i64,
+    feed_title: &Option<String>,
+    feed_items: &[FeedItem],
+    silent: bool,
+    language: &str,
+) {
+    let watchwords = match telegram::find_watchwords(connection, chat_id) {
+        Ok(watchwords) => watchwords
+            .into_iter()
+            .map(|watchword| watchword.keyword)
+            .collect::<Vec<String>>(),
+        Err(error) => {
+            log::error!("Failed to find watchwords: {} {}", chat_id, error);
+            return;
+        }
+    };
+
+    if watchwords.is_empty() {
+        return;
+    }
+
+    let mut items = feed_items.iter().collect::<Vec<&FeedItem>>();
+
+    sort_for_delivery(&mut items, false);
+
+    for item in items {
+        let keywords = logic::matching_watchwords(item, &watchwords);
+
+        if keywords.is_empty() {
+            continue;
+        }
+
+        let message = logic::format_watchword_match(feed_title, item, &keywords, language);
+
+        if let Err(error) = api::send_message(chat_id, message, silent, false).await {
+            log::error!("Failed to deliver a watchword match: {} {}", chat_id, error);
+
+            handle_send_error(connection, chat_id, &error);
+
+            return;
+        }
+    }
+}
+
+fn save_delivered_items(
+    connection: &PgConnection,
+    subscription: &TelegramSubscription,
+    items: &[&FeedItem],
+) {
+    let new_items = items
+        .iter()
+        .map(|item| NewTelegramDeliveredItem {
+            chat_id: subscription.chat_id,
+            feed_id: subscription.feed_id,
+            title: item.title.clone(),
+            link: item.link.clone(),
+            published_at: item.publication_date,
+            user_id: subscription.user_id,
+        })
+        .collect::<Vec<NewTelegramDeliveredItem>>();
+
+    if let Err(error) = telegram::create_delivered_items(connection, new_items) {
+        log::error!(
+            "Failed to save delivered items of {} for {}: {}",
+            subscription.feed_id,
+            subscription.chat_id,
+            error
+        );
+    }
+}
 
-            string.push_str("...");
+fn subscription_keywords(
+    connection: &PgConnection,
+    subscription: &TelegramSubscription,
+) -> Result<Vec<String>, Error> {
+    let keywords = telegram::find_filters(
+        connection,
+        NewTelegramSubscription {
+            chat_id: subscription.chat_id,
+            feed_id: subscription.feed_id,
+            user_id: None,
+        },
+    )?
+    .into_iter()
+    .map(|filter| filter.keyword)
+    .collect::<Vec<String>>();
+
+    Ok(keywords)
+}
 
-            string
+fn delivery_is_due(subscription: &TelegramSubscription, now: DateTime<Utc>) -> bool {
+    if subscription
+        .snoozed_until
+        .map_or(false, |snoozed_until| now < snoozed_until)
+    {
+        return false;
+    }
+
+    match (
+        subscription.min_delivery_interval_secs,
+        subscription.delivered_at,
+    ) {
+        (Some(interval), Some(delivered_at)) => {
+            now >= delivered_at + Duration::seconds(interval as i64)
         }
+        _ => true,
+    }
+}
+
+fn batch_messages(messages: Vec<String>, throttled: bool, format: MessageFormat) -> Vec<String> {
+    if throttled && messages.len() > 1 {
+        let separator = match format {
+            MessageFormat::MarkdownV2 => MARKDOWN_BATCH_SEPARATOR,
+            MessageFormat::PlainText | MessageFormat::Html => BATCH_SEPARATOR,
+        };
+
+        vec![messages.join(separator)]
+    } else {
+        messages
+    }
+}
+
+fn sort_for_delivery(items: &mut Vec<&FeedItem>, newest_first: bool) {
+    items.sort_by(|item1, item2| {
+        item1
+            .publication_date
+            .cmp(&item2.publication_date)
+            .then_with(|| item1.created_at.cmp(&item2.created_at))
+    });
+
+    if newest_first {
+        items.reverse();
+    }
+}
+
+fn digest_is_due(chat: &TelegramChat, now: DateTime<Utc>) -> bool {
+    let offset = logic::chat_offset(chat, &now);
+    let local_now = now.with_timezone(&offset);
+
+    if local_now.hour() as i32 != chat.digest_hour {
+        return false;
+    }
+
+    match chat.digest_sent_at {
+        None => true,
+        Some(sent_at) => sent_at.with_timezone(&offset).date() != local_now.date(),
     }
 }
 
 pub async fn deliver_updates() {
-    let mut interval = time::interval(std::time::Duration::from_secs(60));
+    let mut interval = time::interval(std::time::Duration::from_secs(
+        logic::delivery_interval_secs_from_env(),
+    ));
     loop {
         interval.tick().await;
         match DeliverJob::new().execute() {
@@ -214,13 +477,67 @@ pub async fn deliver_updates() {
     }
 }
 
-fn bot_blocked(error_message: &str) -> bool {
-    error_message == BLOCKED_ERROR
-        || error_message == CHAT_NOT_FOUND
-        || error_message == KICKED_ERROR
-        || error_message == DEACTIVATED_ERROR
-        || error_message == BOT_IS_NOT_MEMBER
-        || error_message.contains(CHAT_UPGRADED_ERROR)
+fn handle_send_error(connection: &PgConnection, chat_id: i64, error: &SendMessageError) {
+    match error {
+        SendMessageError::BotBlocked(_) => handle_forbidden_error(connection, chat_id),
+        SendMessageError::ChatNotFound(_) => {
+            match telegram::mark_chat_inactive(connection, chat_id) {
+                Ok(_) => log::info!("Marked chat {} as inactive", chat_id),
+                Err(error) => log::error!("Failed to mark chat {} as inactive {}", chat_id, error),
+            }
+        }
+        _ => (),
+    }
+}
+
+fn handle_forbidden_error(connection: &PgConnection, chat_id: i64) {
+    let chat = match telegram::increment_forbidden_errors_count(connection, chat_id) {
+        Ok(chat) => chat,
+        Err(error) => {
+            log::error!(
+                "Failed to count a forbidden error of chat {} {}",
+                chat_id,
+                error
+            );
+            return;
+        }
+    };
+
+    if !should_disable_chat(&chat) {
+        log::info!(
+            "Chat {} forbade deliveries {} times in a row",
+            chat_id,
+            chat.forbidden_errors_count
+        );
+        return;
+    }
+
+    match telegram::set_paused(connection, &chat, true) {
+        Ok(_) => log::info!(
+            "Paused deliveries to chat {} after {} forbidden errors in a row",
+            chat_id,
+            chat.forbidden_errors_count
+        ),
+        Err(error) => log::error!("Failed to pause deliveries to chat {} {}", chat_id, error),
+    }
+}
+
+fn should_disable_chat(chat: &TelegramChat) -> bool {
+    !chat.paused && chat.forbidden_errors_count >= telegram::MAX_FORBIDDEN_ERRORS_COUNT
+}
+
+fn reset_forbidden_errors(connection: &PgConnection, chat: &TelegramChat) {
+    if chat.forbidden_errors_count == 0 {
+        return;
+    }
+
+    if let Err(error) = telegram::reset_forbidden_errors_count(connection, chat) {
+        log::error!(
+            "Failed to reset forbidden errors of chat {} {}",
+            chat.id,
+            error
+        );
+    }
 }
 
 fn get_max_publication_date(items: Vec<FeedItem>) -> DateTime<Utc> {
@@ -233,10 +550,243 @@ fn get_max_publication_date(items: Vec<FeedItem>) -> DateTime<Utc> {
 
 #[cfg(test)]
 mod tests {
+    use crate::bot::logic::MessageFormat;
     use crate::db;
     use crate::models::feed_item::FeedItem;
+    use crate::models::telegram_chat::TelegramChat;
+    use crate::models::telegram_subscription::TelegramSubscription;
     use chrono::{DateTime, Utc};
 
+    fn build_digest_chat(sent_at: Option<&str>) -> TelegramChat {
+        TelegramChat {
+            id: 1,
+            kind: "private".to_string(),
+            username: None,
+            first_name: None,
+            last_name: None,
+            created_at: db::current_time(),
+            updated_at: db::current_time(),
+            title: None,
+            utc_offset_minutes: Some(180),
+            max_subscriptions: None,
+            paused: false,
+            active: true,
+            timezone_name: None,
+            digest: true,
+            digest_hour: 9,
+            digest_sent_at: sent_at
+                .map(|value| DateTime::parse_from_rfc3339(value).unwrap().into()),
+            description_limit: None,
+            language: None,
+            quiet_hours_start: None,
+            quiet_hours_end: None,
+            track_per_user: false,
+            item_prefix: None,
+            forbidden_errors_count: 0,
+            backfill_newest_first: false,
+            show_author: false,
+        }
+    }
+
+    fn build_throttled_subscription(
+        interval: Option<i32>,
+        delivered_at: Option<&str>,
+    ) -> TelegramSubscription {
+        TelegramSubscription {
+            chat_id: 1,
+            feed_id: 1,
+            last_delivered_at: None,
+            created_at: db::current_time(),
+            updated_at: db::current_time(),
+            delivered_count: 0,
+            delivered_at: delivered_at
+                .map(|value| DateTime::parse_from_rfc3339(value).unwrap().into()),
+            initial_items_count: 10,
+            template: None,
+            silent: false,
+            auth_username: None,
+            auth_password: None,
+            cursor_reset_at: None,
+            min_delivery_interval_secs: interval,
+            link_preview: true,
+            user_id: None,
+            snoozed_until: None,
+            full_text: None,
+            deleted_at: None,
+            markdown: false,
+            html: false,
+        }
+    }
+
+    #[test]
+    fn should_disable_chat_after_max_forbidden_errors() {
+        let mut chat = build_digest_chat(None);
+
+        chat.forbidden_errors_count = 2;
+        assert!(!super::should_disable_chat(&chat));
+
+        chat.forbidden_errors_count = 3;
+        assert!(super::should_disable_chat(&chat));
+
+        chat.paused = true;
+        assert!(!super::should_disable_chat(&chat));
+    }
+
+    #[test]
+    fn delivery_is_due_waits_for_the_interval_to_pass() {
+        let now: DateTime<Utc> = DateTime::parse_from_rfc3339("2020-08-12T06:30:00Z")
+            .unwrap()
+            .into();
+
+        let not_throttled = build_throttled_subscription(None, Some("2020-08-12T06:29:00Z"));
+        let never_delivered = build_throttled_subscription(Some(600), None);
+        let delivered_recently =
+            build_throttled_subscription(Some(600), Some("2020-08-12T06:25:00Z"));
+        let delivered_long_ago =
+            build_throttled_subscription(Some(600), Some("2020-08-12T06:20:00Z"));
+
+        assert!(super::delivery_is_due(&not_throttled, now));
+        assert!(super::delivery_is_due(&never_delivered, now));
+        assert!(!super::delivery_is_due(&delivered_recently, now));
+        assert!(super::delivery_is_due(&delivered_long_ago, now));
+    }
+
+    #[test]
+    fn delivery_is_due_skips_snoozed_subscriptions() {
+        let now: DateTime<Utc> = DateTime::parse_from_rfc3339("2020-08-12T06:30:00Z")
+            .unwrap()
+            .into();
+
+        let mut subscription = build_throttled_subscription(None, None);
+
+        subscription.snoozed_until = Some(now + chrono::Duration::minutes(1));
+        assert!(!super::delivery_is_due(&subscription, now));
+
+        subscription.snoozed_until = Some(now);
+        assert!(super::delivery_is_due(&subscription, now));
+    }
+
+    #[test]
+    fn batch_messages_coalesces_throttled_messages_in_order() {
+        let messages = vec!["first".to_string(), "second".to_string()];
+
+        assert_eq!(
+            super::batch_messages(messages.clone(), false, MessageFormat::PlainText),
+            messages.clone()
+        );
+        assert_eq!(
+            super::batch_messages(messages, true, MessageFormat::PlainText),
+            vec!["first\n\n---\n\nsecond".to_string()]
+        );
+        assert_eq!(
+            super::batch_messages(vec!["only".to_string()], true, MessageFormat::PlainText),
+            vec!["only".to_string()]
+        );
+    }
+
+    #[test]
+    fn batch_messages_escapes_separator_for_markdown() {
+        let messages = vec!["*first*".to_string(), "*second*".to_string()];
+
+        assert_eq!(
+            super::batch_messages(messages, true, MessageFormat::MarkdownV2),
+            vec!["*first*\n\n\\-\\-\\-\n\n*second*".to_string()]
+        );
+    }
+
+    #[test]
+    fn digest_is_due_checks_hour_in_chat_timezone() {
+        let chat = build_digest_chat(None);
+
+        let at_nine_local: DateTime<Utc> = DateTime::parse_from_rfc3339("2020-08-12T06:30:00Z")
+            .unwrap()
+            .into();
+        let at_nine_utc: DateTime<Utc> = DateTime::parse_from_rfc3339("2020-08-12T09:30:00Z")
+            .unwrap()
+            .into();
+
+        assert!(super::digest_is_due(&chat, at_nine_local));
+        assert!(!super::digest_is_due(&chat, at_nine_utc));
+    }
+
+    #[test]
+    fn digest_is_due_sends_digest_once_a_day() {
+        let now: DateTime<Utc> = DateTime::parse_from_rfc3339("2020-08-12T06:30:00Z")
+            .unwrap()
+            .into();
+
+        let sent_today = build_digest_chat(Some("2020-08-12T06:01:00Z"));
+        let sent_yesterday = build_digest_chat(Some("2020-08-11T06:01:00Z"));
+
+        assert!(!super::digest_is_due(&sent_today, now));
+        assert!(super::digest_is_due(&sent_yesterday, now));
+    }
+
+    fn build_feed_item(link: &str, publication_date: &str) -> FeedItem {
+        FeedItem {
+            feed_id: 1,
+            title: link.to_string(),
+            description: None,
+            link: link.to_string(),
+            author: None,
+            guid: None,
+            publication_date: DateTime::parse_from_rfc3339(publication_date)
+                .unwrap()
+                .into(),
+            created_at: db::current_time(),
+            updated_at: db::current_time(),
+            content_hash: None,
+        }
+    }
+
+    #[test]
+    fn sort_for_delivery_orders_items_from_oldest_to_newest() {
+        let newest = build_feed_item("newest", "2020-08-12T10:00:00Z");
+        let oldest = build_feed_item("oldest", "2020-08-10T10:00:00Z");
+        let middle = build_feed_item("middle", "2020-08-11T10:00:00Z");
+
+        let mut items = vec![&middle, &newest, &oldest];
+
+        super::sort_for_delivery(&mut items, false);
+
+        assert_eq!(
+            items
+                .iter()
+                .map(|item| item.link.as_str())
+                .collect::<Vec<&str>>(),
+            vec!["oldest", "middle", "newest"]
+        );
+
+        let messages = items
+            .iter()
+            .map(|item| item.title.clone())
+            .collect::<Vec<String>>();
+
+        assert_eq!(
+            super::batch_messages(messages, true, MessageFormat::PlainText),
+            vec!["oldest\n\n---\n\nmiddle\n\n---\n\nnewest".to_string()]
+        );
+    }
+
+    #[test]
+    fn sort_for_delivery_orders_backfill_from_newest_to_oldest() {
+        let newest = build_feed_item("newest", "2020-08-12T10:00:00Z");
+        let oldest = build_feed_item("oldest", "2020-08-10T10:00:00Z");
+        let middle = build_feed_item("middle", "2020-08-11T10:00:00Z");
+
+        let mut items = vec![&middle, &oldest, &newest];
+
+        super::sort_for_delivery(&mut items, true);
+
+        assert_eq!(
+            items
+                .iter()
+                .map(|item| item.link.as_str())
+                .collect::<Vec<&str>>(),
+            vec!["newest", "middle", "oldest"]
+        );
+    }
+
     #[test]
     fn get_max_publication_date_finds_max_publication_date_in_feed_items_vector() {
         let feed_item1 = FeedItem {
@@ -251,6 +801,7 @@ mod tests {
                 .into(),
             created_at: db::current_time(),
             updated_at: db::current_time(),
+            content_hash: None,
         };
 
         let feed_item2 = FeedItem {
@@ -266,6 +817,7 @@ mod tests {
                 .into(),
             created_at: db::current_time(),
             updated_at: db::current_time(),
+            content_hash: None,
         };
 
         let feed_items = vec![feed_item1, feed_item2];
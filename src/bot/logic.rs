@@ -1,250 +1,6360 @@
+use crate::bot::blocklist;
+use crate::bot::i18n;
+use crate::bot::validation_cache;
+use crate::db;
+use crate::db::credentials;
+use crate::db::credentials::CredentialsError;
 use crate::db::feeds;
+use crate::db::feeds::FeedInfo;
 use crate::db::telegram;
-use crate::db::telegram::{NewTelegramChat, NewTelegramSubscription};
+use crate::db::telegram::{
+    ChatStats, FeedHealth, LatestFeedItem, NewTelegramChat, NewTelegramChatWatchword,
+    NewTelegramSubscription, NewTelegramSubscriptionFilter,
+};
+use crate::models::feed::Feed;
+use crate::models::feed_item::FeedItem;
+use crate::models::telegram_chat::TelegramChat;
+use crate::models::telegram_chat_watchword::TelegramChatWatchword;
+use crate::models::telegram_delivered_item::TelegramDeliveredItem;
 use crate::models::telegram_subscription::TelegramSubscription;
+use crate::models::telegram_subscription_filter::TelegramSubscriptionFilter;
 use crate::sync::reader;
+use crate::sync::reader::{
+    AddressCheck, Credentials, FeedValidationError, FeedWarning, ValidatedFeed,
+};
+use crate::sync::FetchedFeedItem;
+use chrono::{DateTime, FixedOffset, Offset, TimeZone, Timelike, Utc};
+use chrono_tz::Tz;
 use diesel::{Connection, PgConnection};
+use futures::stream;
+use futures::StreamExt;
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use std::env;
 use url::Url;
 
+static DEFAULT_SUBSCRIPTION_LIMIT: i64 = 20;
+static DEFAULT_SUBSCRIPTION_LIMIT_CEILING: i32 = 100;
+static SUBSCRIPTIONS_PAGE_SIZE: usize = 10;
+static MAX_INITIAL_ITEMS_COUNT: i32 = 50;
+static SEARCH_RESULTS_LIMIT: usize = 10;
+static DEFAULT_POPULAR_FEEDS_COUNT: i64 = 10;
+static MAX_POPULAR_FEEDS_COUNT: i64 = 50;
+static DEFAULT_DESCRIPTION_LIMIT: usize = 300;
+static MAX_DESCRIPTION_LIMIT: usize = 3000;
+static MAX_VALIDATED_URLS: usize = 20;
+static VALIDATION_CONCURRENCY: usize = 5;
+static MAX_DELIVERY_INTERVAL_SECS: i32 = 86400;
+static UNSUBSCRIBE_ALL_CONFIRMATION: &str = "confirm";
+static CURSOR_RESET_INTERVAL_MINUTES: i64 = 60;
+static MAX_ITEM_PREFIX_LENGTH: usize = 10;
+pub static MAX_WATCHWORDS_COUNT: usize = 20;
+static MAX_SNOOZE_DAYS: i64 = 30;
+static MAX_FEEDBACK_LENGTH: usize = 1000;
+static DEFAULT_TOP_FEEDS_COUNT: usize = 5;
+static MAX_TOP_FEEDS_COUNT: usize = 20;
+static DEFAULT_SYNC_INTERVAL_SECS: u64 = 60;
+static DEFAULT_DELIVERY_INTERVAL_SECS: u64 = 60;
+static DEFAULT_TEMPLATE: &str = "{{feed_title}}\n\n{{title}}\n\n{{published_at}}\n\n{{link}}";
+static FULL_TEXT_TEMPLATE: &str =
+    "{{feed_title}}\n\n{{title}}\n\n{{published_at}}\n\n{{description}}\n\n{{link}}";
+static TITLE_AND_LINK_TEMPLATE: &str = "{{title}}\n\n{{link}}";
+static MARKDOWN_TEMPLATE: &str = "*{{feed_title}}*\n\n[{{title}}]({{link}})\n\n{{published_at}}";
+static MARKDOWN_FULL_TEXT_TEMPLATE: &str =
+    "*{{feed_title}}*\n\n[{{title}}]({{link}})\n\n{{published_at}}\n\n{{description}}";
+static MARKDOWN_TITLE_AND_LINK_TEMPLATE: &str = "[{{title}}]({{link}})";
+static MARKDOWN_RESERVED_CHARACTERS: &str = "_*[]()~`>#+-=|{}.!\\";
+static HTML_TEMPLATE: &str =
+    "<b>{{feed_title}}</b>\n\n<a href=\"{{link}}\">{{title}}</a>\n\n{{published_at}}";
+static HTML_FULL_TEXT_TEMPLATE: &str =
+    "<b>{{feed_title}}</b>\n\n<a href=\"{{link}}\">{{title}}</a>\n\n{{published_at}}\n\n{{description}}";
+static HTML_TITLE_AND_LINK_TEMPLATE: &str = "<a href=\"{{link}}\">{{title}}</a>";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HtmlMode {
+    PlainText,
+    TelegramHtml,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MessageFormat {
+    PlainText,
+    MarkdownV2,
+    Html,
+}
+
 #[derive(Debug, PartialEq)]
 pub enum SubscriptionError {
     DbError(diesel::result::Error),
     InvalidUrl,
+    BlockedDomain,
+    UnsafeUrl,
     UrlIsNotFeed,
+    FetchFailed,
     RssUrlNotProvided,
-    SubscriptionAlreadyExists,
+    SubscriptionAlreadyExists(String),
     SubscriptionCountLimit,
+    InvalidInitialItemsCount,
     TelegramError,
+    SeveralFeedsFound(Vec<String>),
+    CredentialsNotSupported,
 }
 
+#[derive(Debug, PartialEq)]
 pub enum DeleteSubscriptionError {
     FeedNotFound,
     ChatNotFound,
     SubscriptionNotFound,
+    AmbiguousLink(Vec<String>),
     DbError,
 }
 
+#[derive(Debug, PartialEq)]
+pub enum MoveSubscriptionError {
+    DbError(diesel::result::Error),
+    UrlNotProvided,
+    SubscriptionNotFound,
+    SameFeed,
+    SubscriptionAlreadyExists,
+    InvalidUrl,
+    BlockedDomain,
+    UnsafeUrl,
+    UrlIsNotFeed,
+    FetchFailed,
+    CredentialsNotSupported,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum FilterError {
+    DbError(diesel::result::Error),
+    KeywordNotProvided,
+    SubscriptionNotFound,
+    FilterAlreadyExists,
+    FilterNotFound,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum WatchwordError {
+    DbError(diesel::result::Error),
+    KeywordNotProvided,
+    WatchwordAlreadyExists,
+    WatchwordNotFound,
+    WatchwordCountLimit,
+}
+
 impl From<diesel::result::Error> for SubscriptionError {
     fn from(error: diesel::result::Error) -> Self {
         SubscriptionError::DbError(error)
     }
 }
 
-pub fn find_feeds_by_chat_id(db_connection: &PgConnection, chat_id: i64) -> String {
-    match telegram::find_feeds_by_chat_id(db_connection, chat_id) {
-        Err(_) => "Couldn't fetch your subscriptions".to_string(),
-        Ok(feeds) => {
-            let response = feeds
-                .into_iter()
-                .map(|feed| feed.link)
-                .collect::<Vec<String>>()
-                .join("\n");
-            if response == "" {
-                "You don't have any subscriptions".to_string()
+impl From<diesel::result::Error> for MoveSubscriptionError {
+    fn from(error: diesel::result::Error) -> Self {
+        MoveSubscriptionError::DbError(error)
+    }
+}
+
+impl From<diesel::result::Error> for FilterError {
+    fn from(error: diesel::result::Error) -> Self {
+        FilterError::DbError(error)
+    }
+}
+
+impl From<diesel::result::Error> for WatchwordError {
+    fn from(error: diesel::result::Error) -> Self {
+        WatchwordError::DbError(error)
+    }
+}
+
+pub fn find_feeds_by_chat_id(
+    db_connection: &PgConnection,
+    chat_id: i64,
+    data: String,
+    language: &str,
+) -> String {
+    let (feed_type, page) = match parse_list_arguments(data) {
+        Ok(arguments) => arguments,
+        Err(error) => return i18n::t(language, error),
+    };
+
+    match telegram::find_feeds_by_chat_id(db_connection, chat_id, feed_type.as_deref()) {
+        Err(_) => i18n::t(language, "subscriptions_fetch_failed"),
+        Ok(feeds) if feeds.is_empty() && feed_type.is_some() => i18n::tf(
+            language,
+            "no_subscriptions_of_type",
+            &[&feed_type_name(&feed_type.unwrap())],
+        ),
+        Ok(feeds) => format_feeds_page(feeds, page, language),
+    }
+}
+
+fn parse_list_arguments(data: String) -> Result<(Option<String>, usize), &'static str> {
+    let mut parts = data.split_whitespace();
+
+    let (feed_type, page) = match parts.next() {
+        None => return Ok((None, 1)),
+        Some(part) => {
+            let feed_type = part.to_lowercase();
+
+            match feed_type.as_str() {
+                "rss" | "atom" | "json" => (Some(feed_type), parts.next()),
+                _ => (None, Some(part)),
+            }
+        }
+    };
+
+    if parts.next().is_some() {
+        return Err("wrong_list_arguments");
+    }
+
+    let page = parse_page(page.unwrap_or("").to_string())?;
+
+    Ok((feed_type, page))
+}
+
+fn parse_page(data: String) -> Result<usize, &'static str> {
+    if data.is_empty() {
+        return Ok(1);
+    }
+
+    match data.parse::<usize>() {
+        Ok(page) if page > 0 => Ok(page),
+        _ => Err("page_not_positive"),
+    }
+}
+
+fn format_feeds_page(feeds: Vec<Feed>, page: usize, language: &str) -> String {
+    if feeds.is_empty() {
+        return i18n::t(language, "no_subscriptions");
+    }
+
+    let total_pages = (feeds.len() + SUBSCRIPTIONS_PAGE_SIZE - 1) / SUBSCRIPTIONS_PAGE_SIZE;
+
+    if page > total_pages {
+        return i18n::tf(language, "page_not_found", &[&page, &total_pages]);
+    }
+
+    let response = feeds
+        .into_iter()
+        .enumerate()
+        .skip((page - 1) * SUBSCRIPTIONS_PAGE_SIZE)
+        .take(SUBSCRIPTIONS_PAGE_SIZE)
+        .map(|(index, feed)| {
+            format!(
+                "{}. [{}] {}",
+                index + 1,
+                feed_type_name(&feed.feed_type),
+                feed_description(&feed)
+            )
+        })
+        .collect::<Vec<String>>()
+        .join("\n");
+
+    if total_pages > 1 {
+        format!(
+            "{}\n\n{}",
+            response,
+            i18n::tf(language, "page_of", &[&page, &total_pages])
+        )
+    } else {
+        response
+    }
+}
+
+pub fn set_timezone(
+    db_connection: &PgConnection,
+    new_chat: NewTelegramChat,
+    data: String,
+) -> Result<i32, &'static str> {
+    let (offset, timezone_name) = validate_offset(data)?;
+
+    let result = db_connection.transaction::<TelegramChat, diesel::result::Error, _>(|| {
+        let chat = telegram::create_chat(db_connection, new_chat)?;
+
+        match timezone_name {
+            Some(name) => telegram::set_timezone_name(db_connection, &chat, name, offset),
+            None => telegram::set_utc_offset_minutes(db_connection, &chat, offset),
+        }
+    });
+
+    match result {
+        Ok(_) => Ok(offset),
+        Err(_) => Err("timezone_set_failed"),
+    }
+}
+
+pub fn get_timezone(db_connection: &PgConnection, chat_id: i64, language: &str) -> String {
+    match telegram::find_chat(db_connection, chat_id) {
+        None => i18n::t(language, "timezone_not_set"),
+        Some(chat) => match (chat.timezone_name, chat.utc_offset_minutes) {
+            (Some(name), _) => i18n::tf(language, "timezone_name", &[&name]),
+            (None, Some(value)) => i18n::tf(language, "timezone_offset", &[&value]),
+            (None, None) => i18n::t(language, "timezone_not_set"),
+        },
+    }
+}
+
+pub fn set_language(
+    db_connection: &PgConnection,
+    new_chat: NewTelegramChat,
+    data: String,
+    language: &str,
+) -> Result<String, String> {
+    let new_language = data.trim().to_lowercase();
+
+    if !i18n::is_supported(&new_language) {
+        return Err(i18n::tf(
+            language,
+            "language_not_supported",
+            &[&i18n::LANGUAGES.join(", ")],
+        ));
+    }
+
+    let result = db_connection.transaction::<TelegramChat, diesel::result::Error, _>(|| {
+        let chat = telegram::create_chat(db_connection, new_chat)?;
+
+        telegram::set_language(db_connection, &chat, new_language.clone())
+    });
+
+    match result {
+        Ok(_) => Ok(new_language),
+        Err(_) => Err(i18n::t(language, "language_set_failed")),
+    }
+}
+
+pub fn chat_language(db_connection: &PgConnection, chat_id: i64) -> String {
+    match telegram::find_chat(db_connection, chat_id) {
+        Some(chat) => language_of_chat(&chat),
+        None => i18n::DEFAULT_LANGUAGE.to_string(),
+    }
+}
+
+pub fn language_of_chat(chat: &TelegramChat) -> String {
+    chat.language
+        .clone()
+        .filter(|language| i18n::is_supported(language))
+        .unwrap_or_else(|| i18n::DEFAULT_LANGUAGE.to_string())
+}
+
+fn feed_description(feed: &Feed) -> String {
+    match &feed.title {
+        Some(title) if !title.is_empty() => format!("{} — {}", title, feed.link),
+        _ => feed.link.clone(),
+    }
+}
+
+pub fn export_opml(db_connection: &PgConnection, chat_id: i64) -> Result<String, &'static str> {
+    match telegram::find_feeds_by_chat_id(db_connection, chat_id, None) {
+        Ok(feeds) if feeds.is_empty() => Err("no_subscriptions"),
+        Ok(feeds) => Ok(format_opml(&feeds)),
+        Err(_) => Err("export_failed"),
+    }
+}
+
+fn format_opml(feeds: &[Feed]) -> String {
+    let mut lines = vec![
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>".to_string(),
+        "<opml version=\"2.0\">".to_string(),
+        "  <head>".to_string(),
+        "    <title>El Monitorro subscriptions</title>".to_string(),
+        "  </head>".to_string(),
+        "  <body>".to_string(),
+    ];
+
+    for feed in feeds {
+        let title = escape_xml(feed.title.as_deref().unwrap_or(&feed.link));
+
+        lines.push(format!(
+            "    <outline type=\"rss\" text=\"{}\" title=\"{}\" xmlUrl=\"{}\"/>",
+            title,
+            title,
+            escape_xml(&feed.link)
+        ));
+    }
+
+    lines.push("  </body>".to_string());
+    lines.push("</opml>".to_string());
+
+    lines.join("\n")
+}
+
+fn escape_xml(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+pub fn import_opml(
+    db_connection: &PgConnection,
+    new_chat: NewTelegramChat,
+    opml: &str,
+    subscription_limit: i64,
+) -> Result<Vec<(String, Result<TelegramSubscription, SubscriptionError>)>, &'static str> {
+    let urls = parse_opml(opml)?;
+
+    if urls.is_empty() {
+        return Err("opml_without_feeds");
+    }
+
+    // a chat can't have more subscriptions than its limit, so the urls after it are not fetched
+    let limit = telegram::find_chat(db_connection, new_chat.id)
+        .map_or(subscription_limit, |chat| {
+            chat_subscription_limit(&chat, subscription_limit)
+        });
+
+    let results = urls
+        .into_iter()
+        .enumerate()
+        .map(|(index, url)| {
+            let result = if (index as i64) < limit {
+                create_subscription(
+                    db_connection,
+                    new_chat.clone(),
+                    None,
+                    Some(url.clone()),
+                    None,
+                    subscription_limit,
+                )
             } else {
-                response
+                Err(SubscriptionError::SubscriptionCountLimit)
+            };
+
+            (url, result)
+        })
+        .collect();
+
+    Ok(results)
+}
+
+fn parse_opml(opml: &str) -> Result<Vec<String>, &'static str> {
+    let mut reader = Reader::from_str(opml);
+    let mut buf = Vec::new();
+    let mut urls: Vec<String> = vec![];
+
+    reader.trim_text(true);
+
+    loop {
+        match reader.read_event(&mut buf) {
+            Ok(Event::Start(ref element)) | Ok(Event::Empty(ref element))
+                if element.name() == b"outline" =>
+            {
+                for attribute in element.attributes() {
+                    let attribute = match attribute {
+                        Ok(attribute) => attribute,
+                        Err(_) => return Err("opml_invalid"),
+                    };
+
+                    if attribute.key != b"xmlUrl" {
+                        continue;
+                    }
+
+                    match attribute.unescape_and_decode_value(&reader) {
+                        Ok(url) => {
+                            let url = url.trim().to_string();
+
+                            if !url.is_empty() && !urls.contains(&url) {
+                                urls.push(url);
+                            }
+                        }
+                        Err(_) => return Err("opml_invalid"),
+                    }
+                }
+            }
+            Ok(Event::Eof) => break,
+            Err(_) => return Err("opml_invalid"),
+            _ => (),
+        }
+
+        buf.clear();
+    }
+
+    Ok(urls)
+}
+
+pub fn format_import_summary(
+    results: &[(String, Result<TelegramSubscription, SubscriptionError>)],
+    language: &str,
+) -> String {
+    let mut subscribed_count = 0;
+    let mut duplicates_count = 0;
+    let mut over_limit_count = 0;
+    let mut invalid_count = 0;
+
+    for (_url, result) in results {
+        match result {
+            Ok(_) => subscribed_count += 1,
+            Err(SubscriptionError::SubscriptionAlreadyExists(_)) => duplicates_count += 1,
+            Err(SubscriptionError::SubscriptionCountLimit) => over_limit_count += 1,
+            Err(_) => invalid_count += 1,
+        }
+    }
+
+    let mut summary = i18n::tf(
+        language,
+        "import_summary",
+        &[&subscribed_count, &duplicates_count, &invalid_count],
+    );
+
+    if over_limit_count > 0 {
+        summary.push_str(&i18n::tf(
+            language,
+            "over_subscription_limit",
+            &[&over_limit_count],
+        ));
+    }
+
+    summary
+}
+
+pub fn clone_subscriptions(
+    db_connection: &PgConnection,
+    source_chat_id: i64,
+    new_chat: NewTelegramChat,
+    subscription_limit: i64,
+    language: &str,
+) -> String {
+    if source_chat_id == new_chat.id {
+        return i18n::t(language, "clone_same_chat");
+    }
+
+    let source_subscriptions =
+        match telegram::find_subscriptions_for_chat(db_connection, source_chat_id) {
+            Ok(subscriptions) if subscriptions.is_empty() => {
+                return i18n::t(language, "clone_source_empty")
+            }
+            Ok(subscriptions) => subscriptions,
+            Err(_) => return i18n::t(language, "clone_failed"),
+        };
+
+    let chat_id = new_chat.id;
+
+    // all subscriptions are copied or none of them
+    let result = db_connection.transaction::<_, diesel::result::Error, _>(|| {
+        let chat = telegram::create_chat(db_connection, new_chat)?;
+
+        let mut copied_count = 0;
+        let mut duplicates_count = 0;
+        let mut over_limit_count = 0;
+        let mut with_credentials_count = 0;
+
+        for source in &source_subscriptions {
+            // credentials belong to the source chat, its private feeds aren't shared
+            if source.auth_username.is_some() {
+                with_credentials_count += 1;
+                continue;
+            }
+
+            let subscription = NewTelegramSubscription {
+                chat_id: chat.id,
+                feed_id: source.feed_id,
+                user_id: None,
+            };
+
+            if telegram::find_subscription(db_connection, subscription).is_some() {
+                duplicates_count += 1;
+                continue;
+            }
+
+            if check_number_of_subscriptions(db_connection, &chat, subscription_limit).is_err() {
+                over_limit_count += 1;
+                continue;
+            }
+
+            telegram::copy_subscription(db_connection, source, chat.id)?;
+            copied_count += 1;
+        }
+
+        Ok((
+            copied_count,
+            duplicates_count,
+            over_limit_count,
+            with_credentials_count,
+        ))
+    });
+
+    let (copied_count, duplicates_count, over_limit_count, with_credentials_count) = match result {
+        Ok(counts) => counts,
+        Err(error) => {
+            log::error!(
+                "Failed to copy subscriptions from {} to {}: {}",
+                source_chat_id,
+                chat_id,
+                error
+            );
+
+            return i18n::t(language, "clone_failed");
+        }
+    };
+
+    log::info!(
+        "Chat {} copied {} subscriptions from {}",
+        chat_id,
+        copied_count,
+        source_chat_id
+    );
+
+    let mut summary = i18n::tf(
+        language,
+        "clone_summary",
+        &[&copied_count, &duplicates_count],
+    );
+
+    if over_limit_count > 0 {
+        summary.push_str(&i18n::tf(
+            language,
+            "over_subscription_limit",
+            &[&over_limit_count],
+        ));
+    }
+
+    if with_credentials_count > 0 {
+        summary.push_str(&format!(
+            "\n{}",
+            i18n::tf(
+                language,
+                "clone_credentials_skipped",
+                &[&with_credentials_count]
+            )
+        ));
+    }
+
+    summary
+}
+
+pub fn unsubscribe_all(
+    db_connection: &PgConnection,
+    chat_id: i64,
+    data: String,
+    language: &str,
+) -> String {
+    let chat = match telegram::find_chat(db_connection, chat_id) {
+        None => return i18n::t(language, "no_subscriptions"),
+        Some(chat) => chat,
+    };
+
+    let count = telegram::count_subscriptions_for_chat(db_connection, chat.id);
+
+    if count == 0 {
+        return i18n::t(language, "no_subscriptions");
+    }
+
+    if data.trim() != UNSUBSCRIBE_ALL_CONFIRMATION {
+        return i18n::tf(
+            language,
+            "unsubscribe_all_confirmation",
+            &[&count, &UNSUBSCRIBE_ALL_CONFIRMATION],
+        );
+    }
+
+    match telegram::remove_all_subscriptions_for_chat(db_connection, chat.id) {
+        Ok(removed) => {
+            log::info!("Chat {} unsubscribed from {} feeds", chat.id, removed);
+
+            i18n::tf(language, "unsubscribed_from_all", &[&removed])
+        }
+        Err(error) => {
+            log::error!(
+                "Chat {} failed to unsubscribe from all feeds: {}",
+                chat.id,
+                error
+            );
+
+            i18n::t(language, "unsubscribe_all_failed")
+        }
+    }
+}
+
+pub fn pause(db_connection: &PgConnection, chat_id: i64, language: &str) -> String {
+    match telegram::find_chat(db_connection, chat_id) {
+        None => i18n::t(language, "no_subscriptions"),
+        Some(chat) => {
+            if chat.paused {
+                return i18n::t(language, "deliveries_already_paused");
+            }
+
+            match telegram::set_paused(db_connection, &chat, true) {
+                Ok(_) => i18n::t(language, "deliveries_paused"),
+                Err(_) => i18n::t(language, "pause_failed"),
             }
         }
     }
-}
+}
+
+pub fn resume(db_connection: &PgConnection, chat_id: i64, language: &str) -> String {
+    let chat = match telegram::find_chat(db_connection, chat_id) {
+        None => return i18n::t(language, "no_subscriptions"),
+        Some(chat) => chat,
+    };
+
+    if !chat.paused {
+        return i18n::t(language, "deliveries_not_paused");
+    }
+
+    match resume_chat(db_connection, &chat) {
+        Ok(0) => i18n::t(language, "deliveries_resumed"),
+        Ok(skipped_count) => i18n::tf(
+            language,
+            "deliveries_resumed_with_skipped",
+            &[&skipped_count],
+        ),
+        Err(_) => i18n::t(language, "resume_failed"),
+    }
+}
+
+pub fn search_items(
+    db_connection: &PgConnection,
+    chat_id: i64,
+    query: String,
+    language: &str,
+) -> String {
+    let query = query.trim();
+
+    if query.is_empty() {
+        return i18n::t(language, "search_query_missing");
+    }
+
+    match telegram::search_delivered_items(
+        db_connection,
+        chat_id,
+        query,
+        SEARCH_RESULTS_LIMIT as i64 + 1,
+    ) {
+        Ok(items) if items.is_empty() => i18n::t(language, "search_nothing_found"),
+        Ok(items) => format_search_results(&items, language),
+        Err(_) => i18n::t(language, "search_failed"),
+    }
+}
+
+pub fn popular_feeds(db_connection: &PgConnection, data: String, language: &str) -> String {
+    let count = match data.trim() {
+        "" => DEFAULT_POPULAR_FEEDS_COUNT,
+        value => match value.parse::<i64>() {
+            Ok(count) if count > 0 => count.min(MAX_POPULAR_FEEDS_COUNT),
+            _ => return i18n::t(language, "popular_feeds_count_invalid"),
+        },
+    };
+
+    match feeds::most_subscribed(db_connection, count) {
+        Ok(feeds) if feeds.is_empty() => i18n::t(language, "no_subscriptions_at_all"),
+        Ok(feeds) => format_popular_feeds(&feeds),
+        Err(_) => i18n::t(language, "popular_feeds_failed"),
+    }
+}
+
+pub fn disabled_chats(db_connection: &PgConnection, language: &str) -> String {
+    match telegram::find_disabled_chats(db_connection) {
+        Ok(chats) if chats.is_empty() => i18n::t(language, "no_disabled_chats"),
+        Ok(chats) => format_disabled_chats(&chats, language),
+        Err(_) => i18n::t(language, "disabled_chats_failed"),
+    }
+}
+
+fn format_disabled_chats(chats: &[TelegramChat], language: &str) -> String {
+    chats
+        .iter()
+        .enumerate()
+        .map(|(index, chat)| {
+            let name = chat
+                .username
+                .as_ref()
+                .map(|username| format!("@{}", username))
+                .or_else(|| chat.title.clone())
+                .or_else(|| chat.first_name.clone())
+                .unwrap_or_default();
+
+            i18n::tf(
+                language,
+                "disabled_chat",
+                &[&(index + 1), &chat.id, &name, &chat.forbidden_errors_count],
+            )
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+fn format_popular_feeds(feeds: &[(Feed, i64)]) -> String {
+    feeds
+        .iter()
+        .enumerate()
+        .map(|(index, (feed, subscriptions_count))| {
+            format!("{}. {} - {}", index + 1, subscriptions_count, feed.link)
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+fn format_search_results(items: &[TelegramDeliveredItem], language: &str) -> String {
+    let results = items
+        .iter()
+        .take(SEARCH_RESULTS_LIMIT)
+        .enumerate()
+        .map(|(index, item)| format!("{}. {}\n{}", index + 1, item.title, item.link))
+        .collect::<Vec<String>>()
+        .join("\n\n");
+
+    if items.len() > SEARCH_RESULTS_LIMIT {
+        format!(
+            "{}\n\n{}",
+            results,
+            i18n::tf(
+                language,
+                "search_results_truncated",
+                &[&SEARCH_RESULTS_LIMIT]
+            )
+        )
+    } else {
+        results
+    }
+}
+
+pub fn set_truncate(
+    db_connection: &PgConnection,
+    chat_id: i64,
+    data: String,
+    language: &str,
+) -> String {
+    let limit = match data.trim().parse::<usize>() {
+        Ok(limit) if limit > 0 && limit <= MAX_DESCRIPTION_LIMIT => limit,
+        _ => {
+            return i18n::tf(
+                language,
+                "description_length_invalid",
+                &[&MAX_DESCRIPTION_LIMIT],
+            )
+        }
+    };
+
+    let chat = match telegram::find_chat(db_connection, chat_id) {
+        None => return i18n::t(language, "no_subscriptions"),
+        Some(chat) => chat,
+    };
+
+    match telegram::set_description_limit(db_connection, &chat, limit as i32) {
+        Ok(_) => i18n::tf(language, "descriptions_truncated", &[&limit]),
+        Err(_) => i18n::t(language, "description_length_failed"),
+    }
+}
+
+pub fn set_prefix(
+    db_connection: &PgConnection,
+    chat_id: i64,
+    data: String,
+    language: &str,
+) -> String {
+    let prefix = match validate_item_prefix(&data) {
+        Ok(prefix) => prefix,
+        Err(error) => return i18n::t(language, error),
+    };
+
+    let chat = match telegram::find_chat(db_connection, chat_id) {
+        None => return i18n::t(language, "no_subscriptions"),
+        Some(chat) => chat,
+    };
+
+    match telegram::set_item_prefix(db_connection, &chat, Some(prefix.clone())) {
+        Ok(_) => i18n::tf(language, "prefix_set", &[&prefix]),
+        Err(_) => i18n::t(language, "prefix_set_failed"),
+    }
+}
+
+pub fn reset_prefix(db_connection: &PgConnection, chat_id: i64, language: &str) -> String {
+    let chat = match telegram::find_chat(db_connection, chat_id) {
+        None => return i18n::t(language, "no_subscriptions"),
+        Some(chat) => chat,
+    };
+
+    match telegram::set_item_prefix(db_connection, &chat, None) {
+        Ok(_) => i18n::t(language, "prefix_removed"),
+        Err(_) => i18n::t(language, "prefix_remove_failed"),
+    }
+}
+
+fn validate_item_prefix(data: &str) -> Result<String, &'static str> {
+    let prefix = data.trim();
+
+    if prefix.is_empty() {
+        return Err("prefix_missing");
+    }
+
+    if prefix.chars().count() > MAX_ITEM_PREFIX_LENGTH {
+        return Err("prefix_too_long");
+    }
+
+    Ok(prefix.to_string())
+}
+
+pub fn description_limit(chat: &TelegramChat) -> usize {
+    chat.description_limit
+        .map_or(DEFAULT_DESCRIPTION_LIMIT, |limit| limit as usize)
+}
+
+pub fn truncate_description(description: &str, max_chars: usize) -> String {
+    truncate(&sanitize_html(description, HtmlMode::PlainText), max_chars)
+}
+
+pub fn sanitize_html(html: &str, mode: HtmlMode) -> String {
+    let mut result = String::with_capacity(html.len());
+    let mut open_tags: Vec<String> = vec![];
+    let mut rest = html;
+
+    while let Some(start) = rest.find('<') {
+        push_text(&mut result, &rest[..start], mode);
+
+        let tag_and_rest = &rest[start + 1..];
+        let is_tag = tag_and_rest.chars().next().map_or(false, |character| {
+            character.is_ascii_alphabetic() || character == '/' || character == '!'
+        });
+
+        match tag_and_rest.find('>') {
+            Some(end) if is_tag => {
+                push_tag(&mut result, &mut open_tags, &tag_and_rest[..end], mode);
+                rest = &tag_and_rest[end + 1..];
+            }
+            _ => {
+                push_text(&mut result, "<", mode);
+                rest = tag_and_rest;
+            }
+        }
+    }
+
+    push_text(&mut result, rest, mode);
+
+    while let Some(name) = open_tags.pop() {
+        result.push_str(&format!("</{}>", name));
+    }
+
+    collapse_whitespace(&result)
+}
+
+fn push_text(result: &mut String, text: &str, mode: HtmlMode) {
+    let text = decode_html_entities(text);
+
+    match mode {
+        HtmlMode::PlainText => result.push_str(&text),
+        HtmlMode::TelegramHtml => result.push_str(&escape_html(&text)),
+    }
+}
+
+fn push_tag(result: &mut String, open_tags: &mut Vec<String>, tag: &str, mode: HtmlMode) {
+    let closing = tag.starts_with('/');
+    let name = tag
+        .trim_start_matches('/')
+        .chars()
+        .take_while(|character| character.is_ascii_alphanumeric())
+        .collect::<String>()
+        .to_lowercase();
+
+    if is_block_tag(&name) {
+        result.push(' ');
+        return;
+    }
+
+    if mode == HtmlMode::PlainText {
+        return;
+    }
+
+    let name = match name.as_str() {
+        "b" | "strong" => "b",
+        "i" | "em" => "i",
+        "u" | "ins" => "u",
+        "s" | "strike" | "del" => "s",
+        "a" => "a",
+        "code" => "code",
+        "pre" => "pre",
+        _ => return,
+    };
+
+    if closing {
+        if let Some(position) = open_tags.iter().rposition(|open_tag| open_tag == name) {
+            for open_tag in open_tags.drain(position..).rev() {
+                result.push_str(&format!("</{}>", open_tag));
+            }
+        }
+    } else if name == "a" {
+        if let Some(href) = attribute_value(tag, "href") {
+            result.push_str(&format!(
+                "<a href=\"{}\">",
+                escape_html(&decode_html_entities(&href))
+            ));
+            open_tags.push(name.to_string());
+        }
+    } else {
+        result.push_str(&format!("<{}>", name));
+        open_tags.push(name.to_string());
+    }
+}
+
+fn attribute_value(tag: &str, attribute: &str) -> Option<String> {
+    let lowercase_tag = tag.to_lowercase();
+    let position = lowercase_tag.find(&format!("{}=", attribute))?;
+    let value = &tag[position + attribute.len() + 1..];
+
+    let value = match value.chars().next()? {
+        quote @ '"' | quote @ '\'' => value[1..].split(quote).next()?,
+        _ => value
+            .split(|character: char| character.is_whitespace())
+            .next()?,
+    };
+
+    Some(value.to_string())
+}
+
+fn is_block_tag(name: &str) -> bool {
+    matches!(
+        name,
+        "br" | "p"
+            | "div"
+            | "li"
+            | "ul"
+            | "ol"
+            | "tr"
+            | "blockquote"
+            | "h1"
+            | "h2"
+            | "h3"
+            | "h4"
+            | "h5"
+            | "h6"
+    )
+}
+
+fn decode_html_entities(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find('&') {
+        result.push_str(&rest[..start]);
+
+        let entity_and_rest = &rest[start + 1..];
+        let decoded = entity_and_rest
+            .find(';')
+            .filter(|end| *end <= 10)
+            .and_then(|end| decode_html_entity(&entity_and_rest[..end]).map(|value| (end, value)));
+
+        match decoded {
+            Some((end, value)) => {
+                result.push(value);
+                rest = &entity_and_rest[end + 1..];
+            }
+            None => {
+                result.push('&');
+                rest = entity_and_rest;
+            }
+        }
+    }
+
+    result.push_str(rest);
+
+    result
+}
+
+fn decode_html_entity(entity: &str) -> Option<char> {
+    let code = if entity.starts_with("#x") || entity.starts_with("#X") {
+        u32::from_str_radix(&entity[2..], 16).ok()?
+    } else if entity.starts_with('#') {
+        entity[1..].parse::<u32>().ok()?
+    } else {
+        return match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "nbsp" => Some(' '),
+            "ndash" => Some('–'),
+            "mdash" => Some('—'),
+            "hellip" => Some('…'),
+            "laquo" => Some('«'),
+            "raquo" => Some('»'),
+            "lsquo" => Some('‘'),
+            "rsquo" => Some('’'),
+            "ldquo" => Some('“'),
+            "rdquo" => Some('”'),
+            "copy" => Some('©'),
+            _ => None,
+        };
+    };
+
+    std::char::from_u32(code)
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+pub fn escape_markdown_v2(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+
+    for character in text.chars() {
+        if MARKDOWN_RESERVED_CHARACTERS.contains(character) {
+            result.push('\\');
+        }
+
+        result.push(character);
+    }
+
+    result
+}
+
+fn escape_markdown_v2_url(url: &str) -> String {
+    url.replace('\\', "\\\\").replace(')', "\\)")
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<&str>>().join(" ")
+}
+
+pub fn set_digest(
+    db_connection: &PgConnection,
+    chat_id: i64,
+    data: String,
+    language: &str,
+) -> String {
+    let (digest, hour) = match parse_digest_arguments(data) {
+        Ok(arguments) => arguments,
+        Err(error) => return i18n::t(language, error),
+    };
+
+    let chat = match telegram::find_chat(db_connection, chat_id) {
+        None => return i18n::t(language, "no_subscriptions"),
+        Some(chat) => chat,
+    };
+
+    let hour = hour.unwrap_or(chat.digest_hour);
+
+    match telegram::set_digest(db_connection, &chat, digest, hour) {
+        Ok(_) if digest => i18n::tf(language, "digest_enabled", &[&hour]),
+        Ok(_) => i18n::t(language, "digest_disabled"),
+        Err(_) => i18n::t(language, "digest_failed"),
+    }
+}
+
+fn parse_digest_arguments(data: String) -> Result<(bool, Option<i32>), &'static str> {
+    let parts = data.split_whitespace().collect::<Vec<&str>>();
+
+    match parts.as_slice() {
+        ["on"] => Ok((true, None)),
+        ["off"] => Ok((false, None)),
+        ["on", hour] => match hour.parse::<i32>() {
+            Ok(hour) if (0..24).contains(&hour) => Ok((true, Some(hour))),
+            _ => Err("digest_hour_invalid"),
+        },
+        _ => Err("digest_usage"),
+    }
+}
+
+pub fn set_track_per_user(
+    db_connection: &PgConnection,
+    new_chat: NewTelegramChat,
+    data: String,
+    language: &str,
+) -> String {
+    if new_chat.kind != "group" && new_chat.kind != "supergroup" {
+        return i18n::t(language, "track_per_user_groups_only");
+    }
+
+    let track_per_user = match data.trim().to_lowercase().as_str() {
+        "on" => true,
+        "off" => false,
+        _ => return i18n::t(language, "track_per_user_usage"),
+    };
+
+    let result = telegram::create_chat(db_connection, new_chat)
+        .and_then(|chat| telegram::set_track_per_user(db_connection, &chat, track_per_user));
+
+    match result {
+        Ok(_) if track_per_user => i18n::t(language, "track_per_user_enabled"),
+        Ok(_) => i18n::t(language, "track_per_user_disabled"),
+        Err(_) => i18n::t(language, "track_per_user_failed"),
+    }
+}
+
+pub fn set_backfill_order(
+    db_connection: &PgConnection,
+    new_chat: NewTelegramChat,
+    data: String,
+    language: &str,
+) -> String {
+    let newest_first = match data.trim().to_lowercase().as_str() {
+        "oldest" => false,
+        "newest" => true,
+        _ => return i18n::t(language, "backfill_order_usage"),
+    };
+
+    let result = telegram::create_chat(db_connection, new_chat)
+        .and_then(|chat| telegram::set_backfill_newest_first(db_connection, &chat, newest_first));
+
+    match result {
+        Ok(_) if newest_first => i18n::t(language, "backfill_newest_first"),
+        Ok(_) => i18n::t(language, "backfill_oldest_first"),
+        Err(_) => i18n::t(language, "backfill_order_failed"),
+    }
+}
+
+pub fn set_author(
+    db_connection: &PgConnection,
+    new_chat: NewTelegramChat,
+    data: String,
+    language: &str,
+) -> String {
+    let show_author = match data.trim().to_lowercase().as_str() {
+        "on" => true,
+        "off" => false,
+        _ => return i18n::t(language, "author_usage"),
+    };
+
+    let result = telegram::create_chat(db_connection, new_chat)
+        .and_then(|chat| telegram::set_show_author(db_connection, &chat, show_author));
+
+    match result {
+        Ok(_) if show_author => i18n::t(language, "author_shown"),
+        Ok(_) => i18n::t(language, "author_hidden"),
+        Err(_) => i18n::t(language, "author_failed"),
+    }
+}
+
+pub fn set_quiet_hours(
+    db_connection: &PgConnection,
+    chat_id: i64,
+    data: String,
+    language: &str,
+) -> String {
+    let quiet_hours = match parse_quiet_hours_arguments(data) {
+        Ok(quiet_hours) => quiet_hours,
+        Err(error) => return i18n::t(language, error),
+    };
+
+    let chat = match telegram::find_chat(db_connection, chat_id) {
+        None => return i18n::t(language, "no_subscriptions"),
+        Some(chat) => chat,
+    };
+
+    match telegram::set_quiet_hours(db_connection, &chat, quiet_hours) {
+        Ok(_) => match quiet_hours {
+            Some((start, end)) => i18n::tf(language, "quiet_hours_set", &[&start, &end]),
+            None => i18n::t(language, "quiet_hours_disabled"),
+        },
+        Err(_) => i18n::t(language, "quiet_hours_failed"),
+    }
+}
+
+fn parse_quiet_hours_arguments(data: String) -> Result<Option<(i32, i32)>, &'static str> {
+    let parts = data.split_whitespace().collect::<Vec<&str>>();
+
+    match parts.as_slice() {
+        ["off"] => Ok(None),
+        [start, end] => match (start.parse::<i32>(), end.parse::<i32>()) {
+            (Ok(start), Ok(end)) if (0..24).contains(&start) && (0..24).contains(&end) => {
+                if start == end {
+                    Err("quiet_hours_same")
+                } else {
+                    Ok(Some((start, end)))
+                }
+            }
+            _ => Err("quiet_hours_invalid"),
+        },
+        _ => Err("quiet_hours_usage"),
+    }
+}
+
+pub fn in_quiet_hours(chat: &TelegramChat, now: DateTime<Utc>) -> bool {
+    match (chat.quiet_hours_start, chat.quiet_hours_end) {
+        (Some(start), Some(end)) => {
+            let hour = now.with_timezone(&chat_offset(chat, &now)).hour() as i32;
+
+            is_within_quiet_hours(start, end, hour)
+        }
+        _ => false,
+    }
+}
+
+fn is_within_quiet_hours(start: i32, end: i32, hour: i32) -> bool {
+    if start <= end {
+        start <= hour && hour < end
+    } else {
+        hour >= start || hour < end
+    }
+}
+
+pub fn format_digest(sections: &[(String, Vec<&FeedItem>)], language: &str) -> String {
+    let mut lines = vec![i18n::t(language, "digest_header")];
+
+    for (feed_title, items) in sections.iter().filter(|(_, items)| !items.is_empty()) {
+        lines.push("".to_string());
+        lines.push(feed_title.clone());
+
+        for item in items {
+            lines.push(format!(
+                "• {}\n{}",
+                sanitize_html(&item.title, HtmlMode::PlainText),
+                item.link
+            ));
+        }
+    }
+
+    lines.join("\n")
+}
+
+fn resume_chat(
+    db_connection: &PgConnection,
+    chat: &TelegramChat,
+) -> Result<i64, diesel::result::Error> {
+    db_connection.transaction::<i64, diesel::result::Error, _>(|| {
+        let subscriptions = telegram::find_subscriptions_for_chat(db_connection, chat.id)?;
+        let mut skipped_count = 0;
+
+        for subscription in subscriptions {
+            skipped_count += telegram::count_undelivered_feed_items(db_connection, &subscription)?;
+
+            telegram::set_subscription_last_delivered_at(
+                db_connection,
+                &subscription,
+                db::current_time(),
+            )?;
+        }
+
+        let chat = telegram::reset_forbidden_errors_count(db_connection, chat)?;
+
+        telegram::set_paused(db_connection, &chat, false)?;
+
+        Ok(skipped_count)
+    })
+}
+
+pub fn chat_stats(db_connection: &PgConnection, chat_id: i64, language: &str) -> String {
+    match telegram::find_chat(db_connection, chat_id) {
+        None => i18n::t(language, "no_subscriptions"),
+        Some(chat) => match telegram::chat_stats(db_connection, chat.id) {
+            Ok(stats) => format_chat_stats(stats, &chat, language),
+            Err(_) => i18n::t(language, "stats_failed"),
+        },
+    }
+}
+
+fn format_chat_stats(stats: ChatStats, chat: &TelegramChat, language: &str) -> String {
+    [
+        i18n::tf(
+            language,
+            "stats_subscriptions",
+            &[&stats.subscriptions_count],
+        ),
+        i18n::tf(language, "stats_delivered_items", &[&stats.delivered_count]),
+        i18n::tf(
+            language,
+            "stats_last_delivery",
+            &[&format_time(stats.delivered_at, chat, language)],
+        ),
+    ]
+    .join("\n")
+}
+
+pub fn chat_health(db_connection: &PgConnection, chat_id: i64, language: &str) -> String {
+    let chat = match telegram::find_chat(db_connection, chat_id) {
+        None => return i18n::t(language, "no_subscriptions"),
+        Some(chat) => chat,
+    };
+
+    match telegram::subscriptions_with_health(db_connection, chat.id) {
+        Ok(health) if health.is_empty() => i18n::t(language, "no_subscriptions"),
+        Ok(health) => format_chat_health(&health, &chat, language),
+        Err(_) => i18n::t(language, "health_failed"),
+    }
+}
+
+pub fn top_feeds(
+    db_connection: &PgConnection,
+    chat_id: i64,
+    data: String,
+    language: &str,
+) -> String {
+    let count = match data.trim() {
+        "" => DEFAULT_TOP_FEEDS_COUNT,
+        value => match value.parse::<usize>() {
+            Ok(count) if count > 0 => count.min(MAX_TOP_FEEDS_COUNT),
+            _ => return i18n::t(language, "popular_feeds_count_invalid"),
+        },
+    };
+
+    let chat = match telegram::find_chat(db_connection, chat_id) {
+        None => return i18n::t(language, "no_subscriptions"),
+        Some(chat) => chat,
+    };
+
+    match telegram::latest_items_of_subscriptions(db_connection, chat.id, count) {
+        Ok(items) if items.is_empty() => i18n::t(language, "no_feed_items"),
+        Ok(items) => format_top_feeds(&items, &chat),
+        Err(_) => i18n::t(language, "feeds_fetch_failed"),
+    }
+}
+
+pub fn random_item(db_connection: &PgConnection, chat_id: i64) -> Result<String, &'static str> {
+    let chat = match telegram::find_chat(db_connection, chat_id) {
+        Some(chat) => chat,
+        None => return Err("no_items"),
+    };
+
+    let item = match telegram::random_feed_item(db_connection, chat.id) {
+        Ok(Some(item)) => item,
+        Ok(None) => return Err("no_items"),
+        Err(_) => return Err("feeds_fetch_failed"),
+    };
+
+    let subscription = telegram::find_subscription(
+        db_connection,
+        NewTelegramSubscription {
+            chat_id: chat.id,
+            feed_id: item.feed_id,
+            user_id: None,
+        },
+    );
+
+    let (feed, subscription) = match (feeds::find(db_connection, item.feed_id), subscription) {
+        (Some(feed), Some(subscription)) => (feed, subscription),
+        _ => return Err("no_items"),
+    };
+
+    let feed_title = feed.title.map(|title| truncate(&title, 50));
+
+    let message = render_item(
+        subscription.template.as_deref(),
+        &feed_title,
+        &item,
+        &format_timestamp(item.publication_date, &chat),
+        description_limit(&chat),
+        chat.item_prefix.as_deref(),
+        subscription.full_text,
+    );
+
+    if chat.show_author {
+        Ok(append_author(message, &item, MessageFormat::PlainText))
+    } else {
+        Ok(message)
+    }
+}
+
+pub fn unread(db_connection: &PgConnection, chat_id: i64, language: &str) -> String {
+    match telegram::unread_counts(db_connection, chat_id) {
+        Ok(counts) if counts.is_empty() => i18n::t(language, "no_subscriptions"),
+        Ok(counts) => format_unread_counts(&counts, language),
+        Err(_) => i18n::t(language, "feeds_fetch_failed"),
+    }
+}
+
+fn format_unread_counts(counts: &[(Feed, i64)], language: &str) -> String {
+    let lines = counts
+        .iter()
+        .map(|(feed, count)| {
+            let feed_title = match &feed.title {
+                Some(title) if !title.trim().is_empty() => truncate(title, 50),
+                _ => feed.link.clone(),
+            };
+
+            format!("{}: {}", feed_title, count)
+        })
+        .collect::<Vec<String>>()
+        .join("\n");
+
+    let total: i64 = counts.iter().map(|(_, count)| count).sum();
+
+    format!(
+        "{}\n{}\n\n{}",
+        i18n::t(language, "unread_header"),
+        lines,
+        i18n::tf(language, "unread_total", &[&total])
+    )
+}
+
+fn format_top_feeds(items: &[LatestFeedItem], chat: &TelegramChat) -> String {
+    items
+        .iter()
+        .enumerate()
+        .map(|(index, item)| {
+            let feed_title = match &item.feed_title {
+                Some(title) if !title.trim().is_empty() => truncate(title, 50),
+                _ => item.link.clone(),
+            };
+
+            format!(
+                "{}. {} | {}\n{}",
+                index + 1,
+                feed_title,
+                format_timestamp(item.published_at, chat),
+                item.item_title
+            )
+        })
+        .collect::<Vec<String>>()
+        .join("\n\n")
+}
+
+fn format_chat_health(health: &[FeedHealth], chat: &TelegramChat, language: &str) -> String {
+    let mut lines = vec![i18n::t(language, "health_header")];
+
+    for feed in health {
+        let status = if feed.dead {
+            i18n::t(language, "health_dead")
+        } else if feed.failures_count > 0 {
+            i18n::tf(language, "health_failing", &[&feed.failures_count])
+        } else {
+            i18n::t(language, "health_ok")
+        };
+
+        lines.push(format!(
+            "{} | {} | {}",
+            status,
+            format_time(feed.synced_at, chat, language),
+            feed.link
+        ));
+    }
+
+    lines.join("\n")
+}
+
+pub fn describe_chat(
+    db_connection: &PgConnection,
+    chat_id: i64,
+    kind: &str,
+    language: &str,
+) -> String {
+    let registered = telegram::find_chat(db_connection, chat_id).is_some();
+
+    format_chat_description(chat_id, kind, registered, language)
+}
+
+fn format_chat_description(chat_id: i64, kind: &str, registered: bool, language: &str) -> String {
+    [
+        i18n::tf(language, "chat_id", &[&chat_id]),
+        i18n::tf(language, "chat_type", &[&kind]),
+        i18n::t(
+            language,
+            if registered {
+                "chat_registered"
+            } else {
+                "chat_not_registered"
+            },
+        ),
+    ]
+    .join("\n")
+}
+
+pub fn chat_offset(chat: &TelegramChat, date: &DateTime<Utc>) -> FixedOffset {
+    if let Some(name) = &chat.timezone_name {
+        if let Ok(timezone) = name.parse::<Tz>() {
+            return timezone.offset_from_utc_datetime(&date.naive_utc()).fix();
+        }
+    }
+
+    match chat.utc_offset_minutes {
+        None => FixedOffset::west(0),
+        Some(value) => {
+            if value > 0 {
+                FixedOffset::east(value * 60)
+            } else {
+                FixedOffset::west(-value * 60)
+            }
+        }
+    }
+}
+
+pub fn format_timestamp(utc: DateTime<Utc>, chat: &TelegramChat) -> String {
+    utc.with_timezone(&chat_offset(chat, &utc)).to_string()
+}
+
+fn format_time(time: Option<DateTime<Utc>>, chat: &TelegramChat, language: &str) -> String {
+    match time {
+        Some(value) => format_timestamp(value, chat),
+        None => i18n::t(language, "never"),
+    }
+}
+
+pub fn feed_info(
+    db_connection: &PgConnection,
+    chat_id: i64,
+    link: String,
+    language: &str,
+) -> Result<String, &'static str> {
+    let subscription = match find_subscription_by_link(db_connection, chat_id, link) {
+        Some(subscription) => subscription,
+        None => return Err("not_subscribed"),
+    };
+
+    let (feed, chat) = match (
+        feeds::find(db_connection, subscription.feed_id),
+        telegram::find_chat(db_connection, chat_id),
+    ) {
+        (Some(feed), Some(chat)) => (feed, chat),
+        _ => return Err("not_subscribed"),
+    };
+
+    match feeds::feed_info(db_connection, feed) {
+        Ok(info) => Ok(format_feed_info(&info, &subscription, &chat, language)),
+        Err(_) => Err("feed_info_failed"),
+    }
+}
+
+fn format_feed_info(
+    info: &FeedInfo,
+    subscription: &TelegramSubscription,
+    chat: &TelegramChat,
+    language: &str,
+) -> String {
+    let mut lines = vec![
+        i18n::tf(
+            language,
+            "feed_title",
+            &[&info.feed.title.as_deref().unwrap_or("-")],
+        ),
+        i18n::tf(language, "feed_link", &[&info.feed.link]),
+        i18n::tf(
+            language,
+            "feed_type",
+            &[&feed_type_name(&info.feed.feed_type)],
+        ),
+        i18n::tf(
+            language,
+            "feed_added",
+            &[&format_timestamp(info.feed.created_at, chat)],
+        ),
+        i18n::tf(
+            language,
+            "feed_subscribed",
+            &[&format_timestamp(subscription.created_at, chat)],
+        ),
+        i18n::tf(
+            language,
+            "feed_last_fetched",
+            &[&format_timestamp(info.feed.updated_at, chat)],
+        ),
+        i18n::tf(
+            language,
+            "feed_last_synced",
+            &[&format_time(info.feed.synced_at, chat, language)],
+        ),
+    ];
+
+    if let Some(error) = &info.feed.error {
+        lines.push(i18n::tf(
+            language,
+            "feed_last_error",
+            &[&truncate(error, 100)],
+        ));
+    }
+
+    lines.push(i18n::tf(
+        language,
+        "feed_latest_item",
+        &[&format_time(info.last_item_published_at, chat, language)],
+    ));
+    lines.push(i18n::tf(
+        language,
+        "feed_stored_items",
+        &[&info.items_count],
+    ));
+    lines.push(i18n::tf(
+        language,
+        "feed_delivered_items",
+        &[&subscription.delivered_count],
+    ));
+
+    lines.join("\n")
+}
+
+fn feed_type_name(feed_type: &str) -> String {
+    match feed_type {
+        "rss" => "RSS".to_string(),
+        "atom" => "Atom".to_string(),
+        "json" => "JSON".to_string(),
+        _ => feed_type.to_string(),
+    }
+}
+
+pub fn set_limit(
+    db_connection: &PgConnection,
+    chat_id: i64,
+    data: String,
+    subscription_limit_ceiling: i32,
+    language: &str,
+) -> Result<i32, String> {
+    let limit = validate_limit(data, subscription_limit_ceiling, language)?;
+
+    match telegram::find_chat(db_connection, chat_id) {
+        None => Err(i18n::t(language, "limit_without_subscriptions")),
+        Some(chat) => match telegram::set_max_subscriptions(db_connection, &chat, limit) {
+            Ok(_) => Ok(limit),
+            Err(_) => Err(i18n::t(language, "limit_failed")),
+        },
+    }
+}
+
+pub fn subscription_limit_from_env() -> i64 {
+    parse_subscription_limit(env::var("SUBSCRIPTION_LIMIT").ok())
+}
+
+fn parse_subscription_limit(value: Option<String>) -> i64 {
+    match value {
+        None => DEFAULT_SUBSCRIPTION_LIMIT,
+        Some(value) => match value.parse::<i64>() {
+            Ok(limit) if limit > 0 => limit,
+            _ => panic!(
+                "SUBSCRIPTION_LIMIT must be a positive number, {} was given",
+                value
+            ),
+        },
+    }
+}
+
+pub fn sync_interval_secs_from_env() -> u64 {
+    parse_interval_secs(
+        "SYNC_INTERVAL_SECONDS",
+        env::var("SYNC_INTERVAL_SECONDS").ok(),
+        DEFAULT_SYNC_INTERVAL_SECS,
+    )
+}
+
+pub fn delivery_interval_secs_from_env() -> u64 {
+    parse_interval_secs(
+        "DELIVERY_INTERVAL_SECONDS",
+        env::var("DELIVERY_INTERVAL_SECONDS").ok(),
+        DEFAULT_DELIVERY_INTERVAL_SECS,
+    )
+}
+
+fn parse_interval_secs(name: &str, value: Option<String>, default: u64) -> u64 {
+    match value {
+        None => default,
+        Some(value) => match value.parse::<u64>() {
+            Ok(interval) if interval > 0 => interval,
+            _ => panic!(
+                "{} must be a positive number of seconds, {} was given",
+                name, value
+            ),
+        },
+    }
+}
+
+pub fn format_interval(secs: u64, language: &str) -> String {
+    match secs {
+        1 => i18n::t(language, "interval_second"),
+        60 => i18n::t(language, "interval_minute"),
+        secs if secs % 60 == 0 => i18n::tf(language, "interval_minutes", &[&(secs / 60)]),
+        secs => i18n::tf(language, "interval_seconds", &[&secs]),
+    }
+}
+
+pub fn format_uptime(secs: u64) -> String {
+    let days = secs / (24 * 60 * 60);
+    let hours = secs % (24 * 60 * 60) / (60 * 60);
+    let minutes = secs % (60 * 60) / 60;
+
+    match (days, hours) {
+        (0, 0) => format!("{}m", minutes),
+        (0, _) => format!("{}h {}m", hours, minutes),
+        _ => format!("{}d {}h {}m", days, hours, minutes),
+    }
+}
+
+pub fn subscription_limit_ceiling_from_env() -> i32 {
+    match env::var("MAX_SUBSCRIPTION_LIMIT") {
+        Ok(value) => value
+            .parse::<i32>()
+            .unwrap_or(DEFAULT_SUBSCRIPTION_LIMIT_CEILING),
+        Err(_) => DEFAULT_SUBSCRIPTION_LIMIT_CEILING,
+    }
+}
+
+fn validate_limit(limit_string: String, ceiling: i32, language: &str) -> Result<i32, String> {
+    let limit = match limit_string.parse::<i32>() {
+        Ok(result) => result,
+        Err(_) => return Err(i18n::t(language, "not_a_number")),
+    };
+
+    if limit < 1 || limit > ceiling {
+        return Err(i18n::tf(language, "limit_invalid", &[&ceiling]));
+    }
+
+    Ok(limit)
+}
+
+fn validate_offset(offset_string: String) -> Result<(i32, Option<String>), &'static str> {
+    let offset = match offset_string.parse::<i32>() {
+        Ok(result) => result,
+        Err(_) => return validate_timezone_name(&offset_string),
+    };
+
+    if offset % 30 != 0 {
+        return Err("offset_not_divisible");
+    }
+
+    if offset < -720 || offset > 840 {
+        return Err("offset_out_of_range");
+    }
+
+    Ok((offset, None))
+}
+
+fn validate_timezone_name(name: &str) -> Result<(i32, Option<String>), &'static str> {
+    match name.parse::<Tz>() {
+        Ok(timezone) => {
+            let offset = Utc::now()
+                .with_timezone(&timezone)
+                .offset()
+                .fix()
+                .local_minus_utc()
+                / 60;
+
+            Ok((offset, Some(timezone.name().to_string())))
+        }
+        Err(_) => Err("timezone_invalid"),
+    }
+}
+
+pub fn delete_subscription(
+    db_connection: &PgConnection,
+    chat_id: i64,
+    link: String,
+) -> Result<String, DeleteSubscriptionError> {
+    let (link, _) = reader::split_credentials(&link);
+
+    let chat = match telegram::find_chat(db_connection, chat_id) {
+        Some(chat) => chat,
+        None => {
+            log::info!(
+                "Chat {} failed to unsubscribe from {}: chat not found",
+                chat_id,
+                link
+            );
+
+            return Err(DeleteSubscriptionError::ChatNotFound);
+        }
+    };
+
+    let exact_feed = feeds::find_chat_feed(db_connection, chat.id, link.clone())
+        .or_else(|| feeds::find_by_link(db_connection, link.clone()));
+
+    let subscribed_feed = exact_feed.clone().filter(|feed| {
+        telegram::find_subscription(
+            db_connection,
+            NewTelegramSubscription {
+                chat_id: chat.id,
+                feed_id: feed.id,
+                user_id: None,
+            },
+        )
+        .is_some()
+    });
+
+    let feed = match subscribed_feed {
+        Some(feed) => feed,
+        None => {
+            let mut candidates =
+                match feeds::find_by_link_fuzzy(db_connection, chat.id, link.clone()) {
+                    Ok(candidates) => candidates,
+                    Err(error) => {
+                        log::error!(
+                            "Chat {} failed to unsubscribe from {}: {:?}",
+                            chat_id,
+                            link,
+                            error
+                        );
+
+                        return Err(DeleteSubscriptionError::DbError);
+                    }
+                };
+
+            match candidates.len() {
+                0 if exact_feed.is_some() => {
+                    log::info!("Chat {} is not subscribed to {}", chat_id, link);
+
+                    return Err(DeleteSubscriptionError::SubscriptionNotFound);
+                }
+                0 => {
+                    log::info!(
+                        "Chat {} tried to unsubscribe from unknown {}",
+                        chat_id,
+                        link
+                    );
+
+                    return Err(DeleteSubscriptionError::FeedNotFound);
+                }
+                1 => {
+                    log::info!(
+                        "Chat {} unsubscribes from {} matched by {}",
+                        chat_id,
+                        candidates[0].link,
+                        link
+                    );
+
+                    candidates.remove(0)
+                }
+                _ => {
+                    log::info!(
+                        "Chat {} used an ambiguous link {} to unsubscribe",
+                        chat_id,
+                        link
+                    );
+
+                    return Err(DeleteSubscriptionError::AmbiguousLink(
+                        candidates.into_iter().map(|feed| feed.link).collect(),
+                    ));
+                }
+            }
+        }
+    };
+
+    let telegram_subscription = NewTelegramSubscription {
+        chat_id: chat.id,
+        feed_id: feed.id,
+        user_id: None,
+    };
+
+    match telegram::remove_subscription(db_connection, telegram_subscription) {
+        Ok(_) => {
+            log::info!("Chat {} unsubscribed from {}", chat_id, feed.link);
+
+            Ok(feed.link)
+        }
+        Err(error) => {
+            log::error!(
+                "Chat {} failed to unsubscribe from {}: {:?}",
+                chat_id,
+                feed.link,
+                error
+            );
+
+            Err(DeleteSubscriptionError::DbError)
+        }
+    }
+}
+
+pub fn restore_subscription(
+    db_connection: &PgConnection,
+    chat_id: i64,
+    link: String,
+    subscription_limit: i64,
+) -> Result<String, &'static str> {
+    let not_found = "restore_not_found";
+
+    let (chat, feed) = match (
+        telegram::find_chat(db_connection, chat_id),
+        feeds::find_chat_feed(db_connection, chat_id, link),
+    ) {
+        (Some(chat), Some(feed)) => (chat, feed),
+        _ => return Err(not_found),
+    };
+
+    let new_subscription = NewTelegramSubscription {
+        chat_id: chat.id,
+        feed_id: feed.id,
+        user_id: None,
+    };
+
+    let subscription = match telegram::find_restorable_subscription(db_connection, new_subscription)
+    {
+        Some(subscription) => subscription,
+        None => return Err(not_found),
+    };
+
+    if check_number_of_subscriptions(db_connection, &chat, subscription_limit).is_err() {
+        return Err("subscription_limit_exceeded");
+    }
+
+    match telegram::restore_subscription(db_connection, &subscription) {
+        Ok(_) => {
+            log::info!(
+                "Chat {} restored the subscription to {}",
+                chat_id,
+                feed.link
+            );
+
+            Ok(feed.link)
+        }
+        Err(_) => Err("restore_failed"),
+    }
+}
+
+pub fn delete_subscription_by_index(
+    db_connection: &PgConnection,
+    chat_id: i64,
+    index: usize,
+) -> Result<String, DeleteSubscriptionError> {
+    let feeds = match telegram::find_feeds_by_chat_id(db_connection, chat_id, None) {
+        Ok(feeds) => feeds,
+        Err(_) => return Err(DeleteSubscriptionError::DbError),
+    };
+
+    if index == 0 || index > feeds.len() {
+        return Err(DeleteSubscriptionError::SubscriptionNotFound);
+    }
+
+    let link = feeds[index - 1].link.clone();
+
+    delete_subscription(db_connection, chat_id, link)
+}
+
+pub fn add_filter(
+    db_connection: &PgConnection,
+    chat_id: i64,
+    link: String,
+    keyword: String,
+) -> Result<TelegramSubscriptionFilter, FilterError> {
+    let keyword = normalize_keyword(&keyword)?;
+    let subscription = find_filter_subscription(db_connection, chat_id, link)?;
+
+    let filters = telegram::find_filters(db_connection, subscription)?;
+
+    if filters.iter().any(|filter| filter.keyword == keyword) {
+        return Err(FilterError::FilterAlreadyExists);
+    }
+
+    let new_filter = NewTelegramSubscriptionFilter {
+        chat_id: subscription.chat_id,
+        feed_id: subscription.feed_id,
+        keyword,
+    };
+
+    Ok(telegram::create_filter(db_connection, new_filter)?)
+}
+
+pub fn remove_filter(
+    db_connection: &PgConnection,
+    chat_id: i64,
+    link: String,
+    keyword: String,
+) -> Result<(), FilterError> {
+    let keyword = normalize_keyword(&keyword)?;
+    let subscription = find_filter_subscription(db_connection, chat_id, link)?;
+
+    let filter = NewTelegramSubscriptionFilter {
+        chat_id: subscription.chat_id,
+        feed_id: subscription.feed_id,
+        keyword,
+    };
+
+    match telegram::remove_filter(db_connection, filter)? {
+        0 => Err(FilterError::FilterNotFound),
+        _ => Ok(()),
+    }
+}
+
+pub fn list_filters(
+    db_connection: &PgConnection,
+    chat_id: i64,
+    link: String,
+) -> Result<Vec<String>, FilterError> {
+    let subscription = find_filter_subscription(db_connection, chat_id, link)?;
+
+    let keywords = telegram::find_filters(db_connection, subscription)?
+        .into_iter()
+        .map(|filter| filter.keyword)
+        .collect::<Vec<String>>();
+
+    Ok(keywords)
+}
+
+pub fn item_matches_filters(item: &FeedItem, keywords: &[String]) -> bool {
+    if keywords.is_empty() {
+        return true;
+    }
+
+    let title = item.title.to_lowercase();
+    let description = item
+        .description
+        .as_ref()
+        .map_or_else(|| "".to_string(), |value| value.to_lowercase());
+
+    keywords.iter().any(|keyword| {
+        let keyword = keyword.to_lowercase();
+
+        title.contains(&keyword) || description.contains(&keyword)
+    })
+}
+
+pub fn add_watchword(
+    db_connection: &PgConnection,
+    new_chat: NewTelegramChat,
+    keyword: String,
+) -> Result<TelegramChatWatchword, WatchwordError> {
+    let keyword = normalize_keyword(&keyword).map_err(|_| WatchwordError::KeywordNotProvided)?;
+    let chat = telegram::create_chat(db_connection, new_chat)?;
+
+    let watchwords = telegram::find_watchwords(db_connection, chat.id)?;
+
+    if watchwords
+        .iter()
+        .any(|watchword| watchword.keyword == keyword)
+    {
+        return Err(WatchwordError::WatchwordAlreadyExists);
+    }
+
+    if watchwords.len() >= MAX_WATCHWORDS_COUNT {
+        return Err(WatchwordError::WatchwordCountLimit);
+    }
+
+    let new_watchword = NewTelegramChatWatchword {
+        chat_id: chat.id,
+        keyword,
+    };
+
+    Ok(telegram::create_watchword(db_connection, new_watchword)?)
+}
+
+pub fn remove_watchword(
+    db_connection: &PgConnection,
+    chat_id: i64,
+    keyword: String,
+) -> Result<(), WatchwordError> {
+    let keyword = normalize_keyword(&keyword).map_err(|_| WatchwordError::KeywordNotProvided)?;
+
+    let watchword = NewTelegramChatWatchword { chat_id, keyword };
+
+    match telegram::remove_watchword(db_connection, watchword)? {
+        0 => Err(WatchwordError::WatchwordNotFound),
+        _ => Ok(()),
+    }
+}
+
+pub fn list_watchwords(
+    db_connection: &PgConnection,
+    chat_id: i64,
+) -> Result<Vec<String>, WatchwordError> {
+    let keywords = telegram::find_watchwords(db_connection, chat_id)?
+        .into_iter()
+        .map(|watchword| watchword.keyword)
+        .collect::<Vec<String>>();
+
+    Ok(keywords)
+}
+
+pub fn matching_watchwords(item: &FeedItem, watchwords: &[String]) -> Vec<String> {
+    watchwords
+        .iter()
+        .filter(|watchword| item_matches_filters(item, std::slice::from_ref(*watchword)))
+        .cloned()
+        .collect()
+}
+
+pub fn format_watchword_match(
+    feed_title: &Option<String>,
+    item: &FeedItem,
+    keywords: &[String],
+    language: &str,
+) -> String {
+    let mut lines = vec![i18n::tf(
+        language,
+        "watchword_mentions",
+        &[&keywords.join("\", \"")],
+    )];
+
+    lines.push("".to_string());
+
+    if let Some(feed_title) = feed_title {
+        lines.push(sanitize_html(feed_title, HtmlMode::PlainText));
+    }
+
+    lines.push(sanitize_html(&item.title, HtmlMode::PlainText));
+    lines.push(item.link.clone());
+
+    lines.join("\n")
+}
+
+fn normalize_keyword(keyword: &str) -> Result<String, FilterError> {
+    let keyword = keyword.trim().to_lowercase();
+
+    if keyword.is_empty() {
+        return Err(FilterError::KeywordNotProvided);
+    }
+
+    Ok(keyword)
+}
+
+fn find_filter_subscription(
+    db_connection: &PgConnection,
+    chat_id: i64,
+    link: String,
+) -> Result<NewTelegramSubscription, FilterError> {
+    match find_subscription_by_link(db_connection, chat_id, link) {
+        Some(subscription) => Ok(NewTelegramSubscription {
+            chat_id: subscription.chat_id,
+            feed_id: subscription.feed_id,
+            user_id: None,
+        }),
+        None => Err(FilterError::SubscriptionNotFound),
+    }
+}
+
+fn find_subscription_by_link(
+    db_connection: &PgConnection,
+    chat_id: i64,
+    link: String,
+) -> Option<TelegramSubscription> {
+    let feed = feeds::find_chat_feed(db_connection, chat_id, link)?;
+
+    telegram::find_subscription(
+        db_connection,
+        NewTelegramSubscription {
+            chat_id,
+            feed_id: feed.id,
+            user_id: None,
+        },
+    )
+}
+
+pub fn set_format(
+    db_connection: &PgConnection,
+    chat_id: i64,
+    link: String,
+    template: String,
+) -> Result<(), &'static str> {
+    let template = template.trim();
+
+    if template.is_empty() {
+        return Err("template_missing");
+    }
+
+    let subscription = match find_subscription_by_link(db_connection, chat_id, link) {
+        Some(subscription) => subscription,
+        None => return Err("subscription_not_found"),
+    };
+
+    match telegram::set_template(db_connection, &subscription, Some(template.to_string())) {
+        Ok(_) => Ok(()),
+        Err(_) => Err("format_set_failed"),
+    }
+}
+
+pub fn reset_format(
+    db_connection: &PgConnection,
+    chat_id: i64,
+    link: String,
+) -> Result<(), &'static str> {
+    let subscription = match find_subscription_by_link(db_connection, chat_id, link) {
+        Some(subscription) => subscription,
+        None => return Err("subscription_not_found"),
+    };
+
+    match telegram::set_template(db_connection, &subscription, None) {
+        Ok(_) => Ok(()),
+        Err(_) => Err("format_reset_failed"),
+    }
+}
+
+pub fn set_subscription_silent(
+    db_connection: &PgConnection,
+    chat_id: i64,
+    link: String,
+    silent: bool,
+) -> Result<(), &'static str> {
+    let subscription = match find_subscription_by_link(db_connection, chat_id, link) {
+        Some(subscription) => subscription,
+        None => return Err("subscription_not_found"),
+    };
+
+    match telegram::set_silent(db_connection, &subscription, silent) {
+        Ok(_) => Ok(()),
+        Err(_) => Err("subscription_update_failed"),
+    }
+}
+
+pub fn snooze(
+    db_connection: &PgConnection,
+    chat_id: i64,
+    link: String,
+    data: String,
+    language: &str,
+) -> String {
+    let snoozed_until = if data.trim().eq_ignore_ascii_case("off") {
+        None
+    } else {
+        match parse_duration(&data) {
+            Some(duration) => Some(db::current_time() + duration),
+            None => return i18n::tf(language, "snooze_duration_invalid", &[&MAX_SNOOZE_DAYS]),
+        }
+    };
+
+    let subscription = match find_subscription_by_link(db_connection, chat_id, link) {
+        Some(subscription) => subscription,
+        None => return i18n::t(language, "subscription_not_found"),
+    };
+
+    let chat = match telegram::find_chat(db_connection, chat_id) {
+        Some(chat) => chat,
+        None => return i18n::t(language, "subscription_not_found"),
+    };
+
+    match telegram::set_snoozed_until(db_connection, &subscription, snoozed_until) {
+        Ok(_) => match snoozed_until {
+            Some(until) => i18n::tf(
+                language,
+                "snoozed_until",
+                &[&format_timestamp(until, &chat)],
+            ),
+            None => i18n::t(language, "snooze_removed"),
+        },
+        Err(_) => i18n::t(language, "subscription_update_failed"),
+    }
+}
+
+pub fn format_feedback(chat_id: i64, data: &str) -> Result<String, &'static str> {
+    let text = data.trim();
+
+    if text.is_empty() {
+        return Err("feedback_missing");
+    }
+
+    if text.chars().count() > MAX_FEEDBACK_LENGTH {
+        return Err("feedback_too_long");
+    }
+
+    Ok(format!("Feedback from chat {}:\n\n{}", chat_id, text))
+}
+
+pub fn parse_duration(data: &str) -> Option<chrono::Duration> {
+    let data = data.trim().to_lowercase();
+    let unit = data.chars().last()?;
+    let amount = data[..data.len() - unit.len_utf8()].parse::<i64>().ok()?;
+
+    let minutes = match unit {
+        'm' => amount,
+        'h' => amount.checked_mul(60)?,
+        'd' => amount.checked_mul(24 * 60)?,
+        _ => return None,
+    };
+
+    if minutes > 0 && minutes <= MAX_SNOOZE_DAYS * 24 * 60 {
+        Some(chrono::Duration::minutes(minutes))
+    } else {
+        None
+    }
+}
+
+pub fn set_link_preview(
+    db_connection: &PgConnection,
+    chat_id: i64,
+    link: String,
+    data: String,
+) -> Result<bool, &'static str> {
+    let link_preview = match data.trim().to_lowercase().as_str() {
+        "on" => true,
+        "off" => false,
+        _ => return Err("link_preview_usage"),
+    };
+
+    let subscription = match find_subscription_by_link(db_connection, chat_id, link) {
+        Some(subscription) => subscription,
+        None => return Err("subscription_not_found"),
+    };
+
+    match telegram::set_link_preview(db_connection, &subscription, link_preview) {
+        Ok(_) => Ok(link_preview),
+        Err(_) => Err("subscription_update_failed"),
+    }
+}
+
+pub fn set_full_text(
+    db_connection: &PgConnection,
+    chat_id: i64,
+    link: String,
+    data: String,
+) -> Result<bool, &'static str> {
+    let full_text = match data.trim().to_lowercase().as_str() {
+        "on" => true,
+        "off" => false,
+        _ => return Err("full_text_usage"),
+    };
+
+    let subscription = match find_subscription_by_link(db_connection, chat_id, link) {
+        Some(subscription) => subscription,
+        None => return Err("subscription_not_found"),
+    };
+
+    match telegram::set_full_text(db_connection, &subscription, full_text) {
+        Ok(_) => Ok(full_text),
+        Err(_) => Err("subscription_update_failed"),
+    }
+}
+
+pub fn set_markdown(
+    db_connection: &PgConnection,
+    chat_id: i64,
+    link: String,
+    data: String,
+) -> Result<bool, &'static str> {
+    let markdown = match data.trim().to_lowercase().as_str() {
+        "on" => true,
+        "off" => false,
+        _ => return Err("markdown_usage"),
+    };
+
+    let subscription = match find_subscription_by_link(db_connection, chat_id, link) {
+        Some(subscription) => subscription,
+        None => return Err("subscription_not_found"),
+    };
+
+    match telegram::set_markdown(db_connection, &subscription, markdown) {
+        Ok(_) => Ok(markdown),
+        Err(_) => Err("subscription_update_failed"),
+    }
+}
+
+pub fn set_html(
+    db_connection: &PgConnection,
+    chat_id: i64,
+    link: String,
+    data: String,
+) -> Result<bool, &'static str> {
+    let html = match data.trim().to_lowercase().as_str() {
+        "on" => true,
+        "off" => false,
+        _ => return Err("html_usage"),
+    };
+
+    let subscription = match find_subscription_by_link(db_connection, chat_id, link) {
+        Some(subscription) => subscription,
+        None => return Err("subscription_not_found"),
+    };
+
+    match telegram::set_html(db_connection, &subscription, html) {
+        Ok(_) => Ok(html),
+        Err(_) => Err("subscription_update_failed"),
+    }
+}
+
+pub fn set_throttle(
+    db_connection: &PgConnection,
+    chat_id: i64,
+    link: String,
+    data: String,
+    language: &str,
+) -> Result<i32, String> {
+    let interval = match data.trim().parse::<i32>() {
+        Ok(interval) if interval >= 0 && interval <= MAX_DELIVERY_INTERVAL_SECS => interval,
+        _ => {
+            return Err(i18n::tf(
+                language,
+                "throttle_invalid",
+                &[&MAX_DELIVERY_INTERVAL_SECS],
+            ))
+        }
+    };
+
+    let subscription = match find_subscription_by_link(db_connection, chat_id, link) {
+        Some(subscription) => subscription,
+        None => return Err(i18n::t(language, "subscription_not_found")),
+    };
+
+    let interval_secs = if interval == 0 { None } else { Some(interval) };
+
+    match telegram::set_min_delivery_interval(db_connection, &subscription, interval_secs) {
+        Ok(_) => Ok(interval),
+        Err(_) => Err(i18n::t(language, "subscription_update_failed")),
+    }
+}
+
+pub fn fetch_last_item(
+    db_connection: &PgConnection,
+    chat_id: i64,
+    link: String,
+    language: &str,
+) -> Result<String, &'static str> {
+    render_last_item(db_connection, chat_id, link)
+        .map(|(message, already_delivered)| format_last_item(message, already_delivered, language))
+}
+
+pub fn test_format(
+    db_connection: &PgConnection,
+    chat_id: i64,
+    link: String,
+) -> Result<String, &'static str> {
+    render_last_item(db_connection, chat_id, link).map(|(message, _)| message)
+}
+
+fn render_last_item(
+    db_connection: &PgConnection,
+    chat_id: i64,
+    link: String,
+) -> Result<(String, bool), &'static str> {
+    let subscription = match find_subscription_by_link(db_connection, chat_id, link) {
+        Some(subscription) => subscription,
+        None => return Err("subscription_not_found"),
+    };
+
+    let (feed, chat) = match (
+        feeds::find(db_connection, subscription.feed_id),
+        telegram::find_chat(db_connection, chat_id),
+    ) {
+        (Some(feed), Some(chat)) => (feed, chat),
+        _ => return Err("subscription_not_found"),
+    };
+
+    let credentials = subscription_credentials(&subscription);
+
+    let fetched_feed = match reader::read_feed(&feed.link, &feed.feed_type, credentials.as_ref()) {
+        Ok(fetched_feed) => fetched_feed,
+        Err(_) => return Err("feed_unreachable"),
+    };
+
+    let item = match fetched_feed
+        .items
+        .into_iter()
+        .max_by_key(|item| item.publication_date)
+    {
+        Some(item) => item,
+        None => return Err("feed_without_items"),
+    };
+
+    let already_delivered = subscription
+        .last_delivered_at
+        .map_or(false, |delivered_at| item.publication_date <= delivered_at);
+
+    let feed_item = FeedItem {
+        feed_id: feed.id,
+        title: item.title,
+        description: item.description,
+        link: item.link,
+        author: item.author,
+        guid: item.guid,
+        publication_date: item.publication_date,
+        created_at: db::current_time(),
+        updated_at: db::current_time(),
+        content_hash: None,
+    };
+
+    let date = format_timestamp(feed_item.publication_date, &chat);
+    let feed_title = feed.title.map(|title| truncate(&title, 50));
+
+    let mut message = render_item(
+        subscription.template.as_deref(),
+        &feed_title,
+        &feed_item,
+        &date,
+        description_limit(&chat),
+        chat.item_prefix.as_deref(),
+        subscription.full_text,
+    );
+
+    if chat.show_author {
+        message = append_author(message, &feed_item, MessageFormat::PlainText);
+    }
+
+    Ok((message, already_delivered))
+}
+
+fn subscription_credentials(subscription: &TelegramSubscription) -> Option<Credentials> {
+    let username = subscription.auth_username.as_ref()?;
+    let stored_password = subscription.auth_password.clone().unwrap_or_default();
+
+    match credentials::decrypt_password(&stored_password) {
+        Ok(password) => Some(Credentials {
+            username: username.clone(),
+            password,
+        }),
+        Err(error) => {
+            log::error!(
+                "Failed to decrypt credentials of chat {} for feed {}: {:?}",
+                subscription.chat_id,
+                subscription.feed_id,
+                error
+            );
+
+            None
+        }
+    }
+}
+
+// returns the key of the feed the credentials are fetched with and the credentials
+// with the password encrypted for storing
+fn prepare_credentials(
+    credentials: Option<&Credentials>,
+) -> Result<(String, Option<Credentials>), CredentialsError> {
+    match credentials {
+        None => Ok(("".to_string(), None)),
+        Some(credentials) => Ok((
+            credentials::credentials_key(credentials)?,
+            Some(Credentials {
+                username: credentials.username.clone(),
+                password: credentials::encrypt_password(&credentials.password)?,
+            }),
+        )),
+    }
+}
+
+fn format_last_item(message: String, already_delivered: bool, language: &str) -> String {
+    if already_delivered {
+        format!(
+            "{}\n\n{}",
+            i18n::t(language, "newest_item_delivered"),
+            message
+        )
+    } else {
+        message
+    }
+}
+
+pub fn reset_subscription_cursor(
+    db_connection: &PgConnection,
+    chat_id: i64,
+    link: String,
+) -> Result<i32, &'static str> {
+    let subscription = match find_subscription_by_link(db_connection, chat_id, link) {
+        Some(subscription) => subscription,
+        None => return Err("subscription_not_found"),
+    };
+
+    if !cursor_reset_allowed(subscription.cursor_reset_at, db::current_time()) {
+        return Err("resubscribed_recently");
+    }
+
+    match telegram::reset_subscription_cursor(db_connection, &subscription) {
+        Ok(subscription) => Ok(subscription.initial_items_count),
+        Err(_) => Err("resubscribe_failed"),
+    }
+}
+
+fn cursor_reset_allowed(cursor_reset_at: Option<DateTime<Utc>>, now: DateTime<Utc>) -> bool {
+    match cursor_reset_at {
+        None => true,
+        Some(reset_at) => {
+            now - reset_at >= chrono::Duration::minutes(CURSOR_RESET_INTERVAL_MINUTES)
+        }
+    }
+}
+
+pub fn render_item(
+    template: Option<&str>,
+    feed_title: &Option<String>,
+    item: &FeedItem,
+    published_at: &str,
+    description_limit: usize,
+    prefix: Option<&str>,
+    full_text: Option<bool>,
+) -> String {
+    let template = match full_text {
+        Some(true) => template.unwrap_or(FULL_TEXT_TEMPLATE),
+        Some(false) => TITLE_AND_LINK_TEMPLATE,
+        None => template.unwrap_or(DEFAULT_TEMPLATE),
+    };
+    let result = render_template(
+        template,
+        feed_title,
+        item,
+        published_at,
+        description_limit,
+        full_text,
+        MessageFormat::PlainText,
+    );
+
+    match prefix {
+        Some(prefix) => format!("{} {}", prefix, result.trim()),
+        None => result.trim().to_string(),
+    }
+}
+
+pub fn render_markdown_item(
+    template: Option<&str>,
+    feed_title: &Option<String>,
+    item: &FeedItem,
+    published_at: &str,
+    description_limit: usize,
+    prefix: Option<&str>,
+    full_text: Option<bool>,
+) -> String {
+    let template = match full_text {
+        Some(true) => template.unwrap_or(MARKDOWN_FULL_TEXT_TEMPLATE),
+        Some(false) => MARKDOWN_TITLE_AND_LINK_TEMPLATE,
+        None => template.unwrap_or(MARKDOWN_TEMPLATE),
+    };
+    let result = render_template(
+        template,
+        feed_title,
+        item,
+        published_at,
+        description_limit,
+        full_text,
+        MessageFormat::MarkdownV2,
+    );
+
+    match prefix {
+        Some(prefix) => format!("{} {}", escape_markdown_v2(prefix), result.trim()),
+        None => result.trim().to_string(),
+    }
+}
+
+pub fn render_html_item(
+    template: Option<&str>,
+    feed_title: &Option<String>,
+    item: &FeedItem,
+    published_at: &str,
+    description_limit: usize,
+    prefix: Option<&str>,
+    full_text: Option<bool>,
+) -> String {
+    let template = match full_text {
+        Some(true) => template.unwrap_or(HTML_FULL_TEXT_TEMPLATE),
+        Some(false) => HTML_TITLE_AND_LINK_TEMPLATE,
+        None => template.unwrap_or(HTML_TEMPLATE),
+    };
+    let result = render_template(
+        template,
+        feed_title,
+        item,
+        published_at,
+        description_limit,
+        full_text,
+        MessageFormat::Html,
+    );
+
+    match prefix {
+        Some(prefix) => format!("{} {}", escape_html(prefix), result.trim()),
+        None => result.trim().to_string(),
+    }
+}
+
+pub fn message_format(subscription: &TelegramSubscription) -> MessageFormat {
+    if subscription.html {
+        MessageFormat::Html
+    } else if subscription.markdown {
+        MessageFormat::MarkdownV2
+    } else {
+        MessageFormat::PlainText
+    }
+}
+
+pub fn append_author(message: String, item: &FeedItem, format: MessageFormat) -> String {
+    let author = match &item.author {
+        Some(author) if !author.trim().is_empty() => {
+            sanitize_html(author.trim(), HtmlMode::PlainText)
+        }
+        _ => return message,
+    };
+
+    let author = match format {
+        MessageFormat::PlainText => author,
+        MessageFormat::MarkdownV2 => escape_markdown_v2(&author),
+        MessageFormat::Html => escape_html(&author),
+    };
+
+    format!("{}\n\n✍️ {}", message, author)
+}
+
+fn render_template(
+    template: &str,
+    feed_title: &Option<String>,
+    item: &FeedItem,
+    published_at: &str,
+    description_limit: usize,
+    full_text: Option<bool>,
+    format: MessageFormat,
+) -> String {
+    let mut result = String::new();
+    let mut rest = template;
+
+    while let Some(start) = rest.find("{{") {
+        result.push_str(&rest[..start]);
+
+        let placeholder_and_rest = &rest[start + 2..];
+
+        match placeholder_and_rest.find("}}") {
+            Some(end) => {
+                let value = match placeholder_and_rest[..end].trim() {
+                    "feed_title" => feed_title.as_ref().map_or_else(String::new, |title| {
+                        sanitize_html(title, HtmlMode::PlainText)
+                    }),
+                    "title" => sanitize_html(&item.title, HtmlMode::PlainText),
+                    "link" => item.link.clone(),
+                    "published_at" => published_at.to_string(),
+                    // the full text keeps the feed's formatting which is already escaped
+                    "description" if full_text == Some(true) && format == MessageFormat::Html => {
+                        if let Some(description) = &item.description {
+                            result.push_str(&sanitize_html(description, HtmlMode::TelegramHtml));
+                        }
+
+                        rest = &placeholder_and_rest[end + 2..];
+
+                        continue;
+                    }
+                    "description" => {
+                        item.description
+                            .as_ref()
+                            .map_or_else(String::new, |description| {
+                                if full_text == Some(true) {
+                                    sanitize_html(description, HtmlMode::PlainText)
+                                } else {
+                                    truncate_description(description, description_limit)
+                                }
+                            })
+                    }
+                    _ => {
+                        result.push_str(&rest[start..start + end + 4]);
+                        rest = &placeholder_and_rest[end + 2..];
+
+                        continue;
+                    }
+                };
+
+                match format {
+                    MessageFormat::PlainText => result.push_str(&value),
+                    MessageFormat::MarkdownV2 if result.ends_with("](") => {
+                        result.push_str(&escape_markdown_v2_url(&value))
+                    }
+                    MessageFormat::MarkdownV2 => result.push_str(&escape_markdown_v2(&value)),
+                    MessageFormat::Html => result.push_str(&escape_html(&value)),
+                }
+
+                rest = &placeholder_and_rest[end + 2..];
+            }
+            None => {
+                result.push_str(&rest[start..]);
+                rest = "";
+            }
+        }
+    }
+
+    result.push_str(rest);
+
+    result
+}
+
+pub fn create_subscription(
+    db_connection: &PgConnection,
+    new_chat: NewTelegramChat,
+    user_id: Option<i64>,
+    rss_url: Option<String>,
+    initial_items_count: Option<i32>,
+    subscription_limit: i64,
+) -> Result<TelegramSubscription, SubscriptionError> {
+    create_subscription_with_warnings(
+        db_connection,
+        new_chat,
+        user_id,
+        rss_url,
+        initial_items_count,
+        subscription_limit,
+    )
+    .map(|(subscription, _)| subscription)
+}
+
+pub fn create_subscription_with_warnings(
+    db_connection: &PgConnection,
+    new_chat: NewTelegramChat,
+    user_id: Option<i64>,
+    rss_url: Option<String>,
+    initial_items_count: Option<i32>,
+    subscription_limit: i64,
+) -> Result<(TelegramSubscription, Vec<FeedWarning>), SubscriptionError> {
+    if rss_url.is_none() {
+        return Err(SubscriptionError::RssUrlNotProvided);
+    }
+
+    let (url, credentials) = reader::split_credentials(&clean_url(&rss_url.unwrap()));
+    let chat_id = new_chat.id;
+
+    let (credentials_key, stored_credentials) = match prepare_credentials(credentials.as_ref()) {
+        Ok(prepared) => prepared,
+        Err(error) => {
+            log::warn!(
+                "Chat {} failed to subscribe to {}: credentials can't be stored {:?}",
+                chat_id,
+                url,
+                error
+            );
+
+            return Err(SubscriptionError::CredentialsNotSupported);
+        }
+    };
+
+    let existing_chat = telegram::find_chat(db_connection, chat_id);
+
+    let check_allowed = |url: &str| match &existing_chat {
+        Some(chat) => check_subscription_allowed(db_connection, chat, url, subscription_limit),
+        None => Ok(()),
+    };
+
+    check_allowed(&url)?;
+
+    let (url, validated_feed) =
+        match discover_and_validate_url(&url, credentials.as_ref(), check_allowed) {
+            Ok(result) => result,
+            Err(error @ SubscriptionError::SubscriptionAlreadyExists(_))
+            | Err(error @ SubscriptionError::SubscriptionCountLimit) => return Err(error),
+            Err(error) => {
+                log::warn!(
+                    "Chat {} failed to subscribe to {}: validation failed with {:?}",
+                    chat_id,
+                    url,
+                    error
+                );
+
+                return Err(error);
+            }
+        };
+
+    let ValidatedFeed {
+        feed: fetched_feed,
+        warnings,
+    } = validated_feed;
+
+    let result = db_connection.transaction::<TelegramSubscription, SubscriptionError, _>(|| {
+        let chat = telegram::create_chat(db_connection, new_chat)?;
+
+        check_subscription_allowed(db_connection, &chat, &url, subscription_limit)?;
+
+        if feeds::find_by_link_and_credentials_key(db_connection, url.clone(), &credentials_key)
+            .is_some()
+        {
+            log::info!("Chat {} reuses the existing feed {}", chat_id, url);
+        } else {
+            log::info!("Chat {} creates a new feed {}", chat_id, url);
+        }
+
+        let mut feed = feeds::create_with_credentials_key(
+            db_connection,
+            url.clone(),
+            fetched_feed.feed_type,
+            credentials_key,
+        )?;
+
+        if feed.dead {
+            feed = feeds::reset_failures(db_connection, &feed)?;
+        }
+
+        if feed.title.is_none() && !fetched_feed.title.is_empty() {
+            feed = feeds::set_title(db_connection, &feed, fetched_feed.title)?;
+        }
+
+        let new_telegram_subscription = NewTelegramSubscription {
+            chat_id: chat.id,
+            feed_id: feed.id,
+            user_id: if chat.track_per_user { user_id } else { None },
+        };
+
+        let mut subscription =
+            telegram::create_subscription(db_connection, new_telegram_subscription)?;
+
+        if let Some(count) = initial_items_count {
+            subscription = telegram::set_initial_items_count(
+                db_connection,
+                &subscription,
+                count.max(1).min(MAX_INITIAL_ITEMS_COUNT),
+            )?;
+        }
+
+        if let Some(credentials) = stored_credentials {
+            subscription = telegram::set_credentials(
+                db_connection,
+                &subscription,
+                credentials.username,
+                credentials.password,
+            )?;
+        }
+
+        Ok(subscription)
+    });
+
+    match &result {
+        Ok(_) => log::info!("Chat {} subscribed to {}", chat_id, url),
+        Err(SubscriptionError::SubscriptionAlreadyExists(_)) => {
+            log::info!("Chat {} is already subscribed to {}", chat_id, url)
+        }
+        Err(SubscriptionError::SubscriptionCountLimit) => (),
+        Err(error) => log::error!(
+            "Chat {} failed to subscribe to {}: {:?}",
+            chat_id,
+            url,
+            error
+        ),
+    }
+
+    result.map(|subscription| (subscription, warnings))
+}
+
+pub fn format_feed_warnings(warnings: &[FeedWarning], language: &str) -> Option<String> {
+    if warnings.is_empty() {
+        return None;
+    }
+
+    let lines = warnings
+        .iter()
+        .map(|warning| match warning {
+            FeedWarning::MissingDates => i18n::t(language, "feed_warning_missing_dates"),
+            FeedWarning::InvalidEncoding => i18n::t(language, "feed_warning_invalid_encoding"),
+        })
+        .collect::<Vec<String>>();
+
+    Some(lines.join("\n"))
+}
+
+pub fn move_subscription(
+    db_connection: &PgConnection,
+    chat_id: i64,
+    old_link: String,
+    new_link: String,
+) -> Result<Feed, MoveSubscriptionError> {
+    if old_link.trim().is_empty() || new_link.trim().is_empty() {
+        return Err(MoveSubscriptionError::UrlNotProvided);
+    }
+
+    let (old_link, _) = reader::split_credentials(&clean_url(&old_link));
+    let (url, credentials) = reader::split_credentials(&clean_url(&new_link));
+
+    let subscription = match find_subscription_by_link(db_connection, chat_id, old_link) {
+        Some(subscription) => subscription,
+        None => return Err(MoveSubscriptionError::SubscriptionNotFound),
+    };
+
+    let validation_credentials = credentials
+        .clone()
+        .or_else(|| subscription_credentials(&subscription));
+
+    let (credentials_key, stored_credentials) =
+        match prepare_credentials(validation_credentials.as_ref()) {
+            Ok(prepared) => prepared,
+            Err(error) => {
+                log::warn!(
+                    "Chat {} failed to move a subscription to {}: credentials can't be stored {:?}",
+                    chat_id,
+                    url,
+                    error
+                );
+
+                return Err(MoveSubscriptionError::CredentialsNotSupported);
+            }
+        };
+
+    if let Some(feed) =
+        feeds::find_by_link_and_credentials_key(db_connection, url.clone(), &credentials_key)
+    {
+        check_subscription_can_be_moved(db_connection, &subscription, &feed)?;
+    }
+
+    let fetched_feed = match validate_rss_url(&url, validation_credentials.as_ref()) {
+        Ok(validated_feed) => validated_feed.feed,
+        Err(error) => {
+            log::warn!(
+                "Chat {} failed to move a subscription to {}: validation failed with {:?}",
+                chat_id,
+                url,
+                error
+            );
+
+            return Err(match error {
+                SubscriptionError::InvalidUrl => MoveSubscriptionError::InvalidUrl,
+                SubscriptionError::BlockedDomain => MoveSubscriptionError::BlockedDomain,
+                SubscriptionError::UnsafeUrl => MoveSubscriptionError::UnsafeUrl,
+                SubscriptionError::UrlIsNotFeed | SubscriptionError::SeveralFeedsFound(_) => {
+                    MoveSubscriptionError::UrlIsNotFeed
+                }
+                _ => MoveSubscriptionError::FetchFailed,
+            });
+        }
+    };
+
+    let result = db_connection.transaction::<Feed, MoveSubscriptionError, _>(|| {
+        let mut feed = feeds::create_with_credentials_key(
+            db_connection,
+            url.clone(),
+            fetched_feed.feed_type,
+            credentials_key,
+        )?;
+
+        check_subscription_can_be_moved(db_connection, &subscription, &feed)?;
+
+        if feed.dead {
+            feed = feeds::reset_failures(db_connection, &feed)?;
+        }
+
+        if feed.title.is_none() && !fetched_feed.title.is_empty() {
+            feed = feeds::set_title(db_connection, &feed, fetched_feed.title)?;
+        }
+
+        let moved_subscription =
+            telegram::set_subscription_feed(db_connection, &subscription, feed.id)?;
+
+        if let (Some(_), Some(credentials)) = (credentials, stored_credentials) {
+            telegram::set_credentials(
+                db_connection,
+                &moved_subscription,
+                credentials.username,
+                credentials.password,
+            )?;
+        }
+
+        Ok(feed)
+    });
+
+    match &result {
+        Ok(feed) => log::info!(
+            "Chat {} moved its subscription from feed {} to {}",
+            chat_id,
+            subscription.feed_id,
+            feed.link
+        ),
+        Err(error) => log::error!(
+            "Chat {} failed to move its subscription to {}: {:?}",
+            chat_id,
+            url,
+            error
+        ),
+    }
+
+    result
+}
+
+fn check_subscription_can_be_moved(
+    db_connection: &PgConnection,
+    subscription: &TelegramSubscription,
+    feed: &Feed,
+) -> Result<(), MoveSubscriptionError> {
+    if feed.id == subscription.feed_id {
+        return Err(MoveSubscriptionError::SameFeed);
+    }
+
+    let existing_subscription = telegram::find_subscription(
+        db_connection,
+        NewTelegramSubscription {
+            chat_id: subscription.chat_id,
+            feed_id: feed.id,
+            user_id: None,
+        },
+    );
+
+    match existing_subscription {
+        Some(_) => Err(MoveSubscriptionError::SubscriptionAlreadyExists),
+        None => Ok(()),
+    }
+}
+
+pub fn extract_url(text: &str) -> Option<String> {
+    text.split_whitespace()
+        .find(|word| word.starts_with("http://") || word.starts_with("https://"))
+        .map(|word| word.to_string())
+}
+
+pub fn parse_subscription_argument(
+    data: String,
+) -> Result<(String, Option<i32>), SubscriptionError> {
+    let parts = data.split_whitespace().collect::<Vec<&str>>();
+
+    match parts.as_slice() {
+        [] => Ok(("".to_string(), None)),
+        [url] => Ok((url.to_string(), None)),
+        [url, count] => match count.parse::<i32>() {
+            Ok(value) => Ok((url.to_string(), Some(value))),
+            Err(_) => Err(SubscriptionError::InvalidInitialItemsCount),
+        },
+        _ => Err(SubscriptionError::InvalidInitialItemsCount),
+    }
+}
+
+pub fn preview_feed(rss_url: String, language: &str) -> Result<String, SubscriptionError> {
+    let (rss_url, credentials) = reader::split_credentials(&clean_url(&rss_url));
+    let fetched_feed = validate_rss_url(&rss_url, credentials.as_ref())?.feed;
+
+    let latest_item = fetched_feed
+        .items
+        .iter()
+        .max_by(|item1, item2| item1.publication_date.cmp(&item2.publication_date));
+
+    match latest_item {
+        None => Ok(i18n::tf(language, "preview_no_items", &[&rss_url])),
+        Some(item) => Ok(format_preview(&fetched_feed.title, item)),
+    }
+}
+
+pub fn parse_validation_urls(data: &str, language: &str) -> Result<Vec<String>, String> {
+    let urls = data
+        .split_whitespace()
+        .map(|url| url.to_string())
+        .collect::<Vec<String>>();
+
+    if urls.is_empty() {
+        return Err(i18n::t(language, "urls_missing"));
+    }
+
+    if urls.len() > MAX_VALIDATED_URLS {
+        return Err(i18n::tf(language, "too_many_urls", &[&MAX_VALIDATED_URLS]));
+    }
+
+    Ok(urls)
+}
+
+pub async fn validate_many(urls: Vec<String>) -> Vec<(String, Result<String, SubscriptionError>)> {
+    stream::iter(urls)
+        .map(|url| async move {
+            let task_url = url.clone();
+            let result = tokio::task::spawn_blocking(move || validate_feed_type(&task_url))
+                .await
+                .unwrap_or_else(|_| Err(SubscriptionError::FetchFailed));
+
+            (url, result)
+        })
+        .buffered(VALIDATION_CONCURRENCY)
+        .collect()
+        .await
+}
+
+fn validate_feed_type(url: &str) -> Result<String, SubscriptionError> {
+    let (url, credentials) = reader::split_credentials(&clean_url(url));
+
+    validate_rss_url(&url, credentials.as_ref())
+        .map(|validated_feed| feed_type_name(&validated_feed.feed.feed_type))
+}
+
+fn format_preview(feed_title: &str, item: &FetchedFeedItem) -> String {
+    let mut parts: Vec<String> = vec![];
+
+    if !feed_title.is_empty() {
+        parts.push(truncate(feed_title, 50));
+    }
+
+    parts.push(item.title.clone());
+
+    if let Some(description) = &item.description {
+        if !description.trim().is_empty() {
+            parts.push(truncate(description.trim(), 300));
+        }
+    }
+
+    parts.push(item.link.clone());
+
+    parts.join("\n\n")
+}
+
+pub fn truncate(s: &str, max_chars: usize) -> String {
+    match s.char_indices().nth(max_chars) {
+        None => String::from(s),
+        Some((idx, _)) => {
+            let mut string = String::from(&s[..idx]);
+
+            string.push_str("...");
+
+            string
+        }
+    }
+}
+
+fn discover_and_validate_url<F>(
+    url: &str,
+    credentials: Option<&Credentials>,
+    check_allowed: F,
+) -> Result<(String, ValidatedFeed), SubscriptionError>
+where
+    F: Fn(&str) -> Result<(), SubscriptionError>,
+{
+    match validate_cached_rss_url(url, credentials) {
+        Ok(validated_feed) => Ok((url.to_string(), validated_feed)),
+        Err(SubscriptionError::SeveralFeedsFound(feeds)) if feeds.len() == 1 => {
+            let feed_url = feeds[0].clone();
+
+            log::info!("Discovered the feed {} on {}", feed_url, url);
+
+            check_allowed(&feed_url)?;
+
+            match validate_cached_rss_url(&feed_url, credentials) {
+                Ok(validated_feed) => Ok((feed_url, validated_feed)),
+                Err(SubscriptionError::SeveralFeedsFound(_)) => {
+                    Err(SubscriptionError::UrlIsNotFeed)
+                }
+                Err(error) => Err(error),
+            }
+        }
+        Err(error) => Err(error),
+    }
+}
+
+fn validate_cached_rss_url(
+    rss_url: &str,
+    credentials: Option<&Credentials>,
+) -> Result<ValidatedFeed, SubscriptionError> {
+    if credentials.is_some() {
+        return validate_rss_url(rss_url, credentials);
+    }
+
+    validation_cache::validate(&clean_url(rss_url), || {
+        validate_rss_url(rss_url, credentials)
+    })
+}
+
+fn validate_rss_url(
+    rss_url: &str,
+    credentials: Option<&Credentials>,
+) -> Result<ValidatedFeed, SubscriptionError> {
+    let rss_url = clean_url(rss_url);
+
+    match Url::parse(&rss_url) {
+        Ok(url) if blocklist::is_blocked(&url) => Err(SubscriptionError::BlockedDomain),
+        Ok(url) => match reader::check_address(&url) {
+            AddressCheck::Internal => Err(SubscriptionError::UnsafeUrl),
+            AddressCheck::Unresolvable => Err(SubscriptionError::FetchFailed),
+            AddressCheck::Public(_) => match reader::validate_rss_url(&rss_url, credentials) {
+                Ok(validated_feed) => Ok(validated_feed),
+                Err(FeedValidationError::FetchFailed(_)) => Err(SubscriptionError::FetchFailed),
+                Err(FeedValidationError::UrlIsNotFeed) => Err(SubscriptionError::UrlIsNotFeed),
+                Err(FeedValidationError::FeedsDiscovered(feeds)) => {
+                    Err(SubscriptionError::SeveralFeedsFound(feeds))
+                }
+            },
+        },
+        _ => Err(SubscriptionError::InvalidUrl),
+    }
+}
+
+fn clean_url(url: &str) -> String {
+    let mut cleaned = url.trim();
+
+    if cleaned.starts_with('<') && cleaned.ends_with('>') {
+        cleaned = cleaned[1..cleaned.len() - 1].trim();
+    }
+
+    loop {
+        let trimmed = cleaned.trim_end_matches(|c: char| c == '.' || c == ',' || c == ';');
+        let trimmed = if trimmed.ends_with(')')
+            && trimmed.matches('(').count() < trimmed.matches(')').count()
+        {
+            &trimmed[..trimmed.len() - 1]
+        } else {
+            trimmed
+        };
+
+        if trimmed == cleaned {
+            break;
+        }
+
+        cleaned = trimmed.trim_end();
+    }
+
+    cleaned.to_string()
+}
+
+fn check_subscription_allowed(
+    connection: &PgConnection,
+    chat: &TelegramChat,
+    url: &str,
+    subscription_limit: i64,
+) -> Result<(), SubscriptionError> {
+    if let Some(feed) = feeds::find_chat_feed(connection, chat.id, url.to_string()) {
+        check_if_subscription_exists(
+            connection,
+            chat,
+            NewTelegramSubscription {
+                chat_id: chat.id,
+                feed_id: feed.id,
+                user_id: None,
+            },
+        )?;
+    }
+
+    if let Err(error) = check_number_of_subscriptions(connection, chat, subscription_limit) {
+        log::warn!(
+            "Chat {} hit the subscription limit while subscribing to {}",
+            chat.id,
+            url
+        );
+
+        return Err(error);
+    }
+
+    Ok(())
+}
+
+fn check_if_subscription_exists(
+    connection: &PgConnection,
+    chat: &TelegramChat,
+    subscription: NewTelegramSubscription,
+) -> Result<(), SubscriptionError> {
+    match telegram::find_subscription(connection, subscription) {
+        None => Ok(()),
+        Some(existing) => Err(SubscriptionError::SubscriptionAlreadyExists(
+            format_timestamp(existing.created_at, chat),
+        )),
+    }
+}
+
+fn check_number_of_subscriptions(
+    connection: &PgConnection,
+    chat: &TelegramChat,
+    subscription_limit: i64,
+) -> Result<(), SubscriptionError> {
+    let result = telegram::count_subscriptions_for_chat(connection, chat.id);
+    let limit = chat_subscription_limit(chat, subscription_limit);
+
+    if result < limit {
+        Ok(())
+    } else {
+        Err(SubscriptionError::SubscriptionCountLimit)
+    }
+}
+
+fn chat_subscription_limit(chat: &TelegramChat, subscription_limit: i64) -> i64 {
+    chat.max_subscriptions
+        .map_or(subscription_limit, |value| value as i64)
+}
+
+pub fn subscription_usage(
+    db_connection: &PgConnection,
+    chat_id: i64,
+    subscription_limit: i64,
+    language: &str,
+) -> Option<String> {
+    let chat = telegram::find_chat(db_connection, chat_id)?;
+    let count = telegram::count_subscriptions_for_chat(db_connection, chat.id);
+
+    Some(i18n::tf(
+        language,
+        "subscription_usage",
+        &[&count, &chat_subscription_limit(&chat, subscription_limit)],
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::HtmlMode;
+    use super::MessageFormat;
+    use crate::db;
+    use crate::db::feed_items;
+    use crate::db::feeds;
+    use crate::db::feeds::FeedInfo;
+    use crate::db::telegram;
+    use crate::db::telegram::{ChatStats, FeedHealth, NewTelegramChat, NewTelegramSubscription};
+    use crate::models::feed::Feed;
+    use crate::models::feed_item::FeedItem;
+    use crate::models::telegram_chat::TelegramChat;
+    use crate::models::telegram_delivered_item::TelegramDeliveredItem;
+    use crate::models::telegram_subscription::TelegramSubscription;
+    use crate::sync::reader::FeedWarning;
+    use crate::sync::FetchedFeedItem;
+    use chrono::Duration;
+    use diesel::connection::Connection;
+
+    #[test]
+    fn create_subscription_creates_new_subscription() {
+        let db_connection = db::establish_connection();
+        let new_chat = NewTelegramChat {
+            id: 42,
+            kind: "private".to_string(),
+            username: Some("Username".to_string()),
+            first_name: Some("First".to_string()),
+            last_name: Some("Last".to_string()),
+            title: None,
+        };
+
+        db_connection.test_transaction::<(), super::SubscriptionError, _>(|| {
+            let subscription = super::create_subscription(
+                &db_connection,
+                new_chat,
+                None,
+                Some("https://feeds.npr.org/1004/feed.json".to_string()),
+                None,
+                super::DEFAULT_SUBSCRIPTION_LIMIT,
+            )
+            .unwrap();
+
+            let feed = feeds::find(&db_connection, subscription.feed_id).unwrap();
+            assert_eq!(feed.title, Some("World".to_string()));
+            assert!(telegram::find_chat(&db_connection, subscription.chat_id).is_some());
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn move_subscription_moves_subscription_to_new_feed() {
+        let db_connection = db::establish_connection();
+        let new_chat = NewTelegramChat {
+            id: 42,
+            kind: "private".to_string(),
+            username: Some("Username".to_string()),
+            first_name: Some("First".to_string()),
+            last_name: Some("Last".to_string()),
+            title: None,
+        };
+
+        db_connection.test_transaction::<(), super::SubscriptionError, _>(|| {
+            let subscription = super::create_subscription(
+                &db_connection,
+                new_chat,
+                None,
+                Some("https://feeds.npr.org/1004/feed.json".to_string()),
+                None,
+                super::DEFAULT_SUBSCRIPTION_LIMIT,
+            )
+            .unwrap();
+
+            super::add_filter(
+                &db_connection,
+                subscription.chat_id,
+                "https://feeds.npr.org/1004/feed.json".to_string(),
+                "rust".to_string(),
+            )
+            .unwrap();
+
+            let feed = super::move_subscription(
+                &db_connection,
+                subscription.chat_id,
+                "https://feeds.npr.org/1004/feed.json".to_string(),
+                "https://www.feedforall.com/sample-feed.xml".to_string(),
+            )
+            .unwrap();
+
+            assert_eq!(feed.link, "https://www.feedforall.com/sample-feed.xml");
+            assert_eq!(
+                super::list_filters(
+                    &db_connection,
+                    subscription.chat_id,
+                    "https://www.feedforall.com/sample-feed.xml".to_string()
+                ),
+                Ok(vec!["rust".to_string()])
+            );
+            assert!(telegram::find_subscription(
+                &db_connection,
+                NewTelegramSubscription {
+                    chat_id: subscription.chat_id,
+                    feed_id: subscription.feed_id,
+                    user_id: None,
+                }
+            )
+            .is_none());
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn move_subscription_fails_for_unknown_or_same_feed() {
+        let db_connection = db::establish_connection();
+        let new_chat = NewTelegramChat {
+            id: 42,
+            kind: "private".to_string(),
+            username: Some("Username".to_string()),
+            first_name: Some("First".to_string()),
+            last_name: Some("Last".to_string()),
+            title: None,
+        };
+
+        db_connection.test_transaction::<(), super::SubscriptionError, _>(|| {
+            let chat = telegram::create_chat(&db_connection, new_chat).unwrap();
+            let feed = feeds::create(
+                &db_connection,
+                "https://example.com/feed.xml".to_string(),
+                "rss".to_string(),
+            )
+            .unwrap();
+
+            telegram::create_subscription(
+                &db_connection,
+                NewTelegramSubscription {
+                    chat_id: chat.id,
+                    feed_id: feed.id,
+                    user_id: None,
+                },
+            )
+            .unwrap();
+
+            assert_eq!(
+                super::move_subscription(
+                    &db_connection,
+                    chat.id,
+                    "https://example.com/feed.xml".to_string(),
+                    "".to_string()
+                ),
+                Err(super::MoveSubscriptionError::UrlNotProvided)
+            );
+            assert_eq!(
+                super::move_subscription(
+                    &db_connection,
+                    chat.id,
+                    "https://example.com/other.xml".to_string(),
+                    "https://example.com/new.xml".to_string()
+                ),
+                Err(super::MoveSubscriptionError::SubscriptionNotFound)
+            );
+            assert_eq!(
+                super::move_subscription(
+                    &db_connection,
+                    chat.id,
+                    "https://example.com/feed.xml".to_string(),
+                    "https://example.com/feed.xml".to_string()
+                ),
+                Err(super::MoveSubscriptionError::SameFeed)
+            );
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn parse_validation_urls_splits_urls_by_whitespace() {
+        assert_eq!(
+            super::parse_validation_urls(
+                "https://example.com/rss\nhttps://example.com/atom  x",
+                "en"
+            ),
+            Ok(vec![
+                "https://example.com/rss".to_string(),
+                "https://example.com/atom".to_string(),
+                "x".to_string()
+            ])
+        );
+        assert_eq!(
+            super::parse_validation_urls("  ", "en"),
+            Err("Urls are not provided".to_string())
+        );
+
+        let too_many = vec!["https://example.com/rss"; super::MAX_VALIDATED_URLS + 1].join(" ");
+
+        assert_eq!(
+            super::parse_validation_urls(&too_many, "en"),
+            Err("At most 20 urls can be validated at once".to_string())
+        );
+    }
+
+    #[test]
+    fn validate_many_keeps_the_order_of_urls() {
+        let mut runtime = tokio::runtime::Runtime::new().unwrap();
+
+        let results = runtime.block_on(super::validate_many(vec![
+            "11".to_string(),
+            "https://www.feedforall.com/sample-feed.xml".to_string(),
+        ]));
+
+        assert_eq!(
+            results,
+            vec![
+                ("11".to_string(), Err(super::SubscriptionError::InvalidUrl)),
+                (
+                    "https://www.feedforall.com/sample-feed.xml".to_string(),
+                    Ok("RSS".to_string())
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn create_subscription_fails_to_create_chat_when_rss_url_is_invalid() {
+        let db_connection = db::establish_connection();
+        let new_chat = NewTelegramChat {
+            id: 42,
+            kind: "private".to_string(),
+            username: Some("Username".to_string()),
+            first_name: Some("First".to_string()),
+            last_name: Some("Last".to_string()),
+            title: None,
+        };
+
+        db_connection.test_transaction::<(), super::SubscriptionError, _>(|| {
+            let result = super::create_subscription(
+                &db_connection,
+                new_chat,
+                None,
+                Some("11".to_string()),
+                None,
+                super::DEFAULT_SUBSCRIPTION_LIMIT,
+            );
+            assert_eq!(result.err(), Some(super::SubscriptionError::InvalidUrl));
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn create_subscription_fails_to_create_chat_when_rss_url_is_unreachable() {
+        let db_connection = db::establish_connection();
+        let new_chat = NewTelegramChat {
+            id: 42,
+            kind: "private".to_string(),
+            username: Some("Username".to_string()),
+            first_name: Some("First".to_string()),
+            last_name: Some("Last".to_string()),
+            title: None,
+        };
+
+        db_connection.test_transaction::<(), super::SubscriptionError, _>(|| {
+            let result = super::create_subscription(
+                &db_connection,
+                new_chat,
+                None,
+                Some("http://el-monitorro.invalid/rss".to_string()),
+                None,
+                super::DEFAULT_SUBSCRIPTION_LIMIT,
+            );
+            assert_eq!(result.err(), Some(super::SubscriptionError::FetchFailed));
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn create_subscription_fails_to_create_chat_when_rss_url_is_not_rss() {
+        let db_connection = db::establish_connection();
+        let new_chat = NewTelegramChat {
+            id: 42,
+            kind: "private".to_string(),
+            username: Some("Username".to_string()),
+            first_name: Some("First".to_string()),
+            last_name: Some("Last".to_string()),
+            title: None,
+        };
+
+        db_connection.test_transaction::<(), super::SubscriptionError, _>(|| {
+            let result = super::create_subscription(
+                &db_connection,
+                new_chat,
+                None,
+                Some("http://google.com".to_string()),
+                None,
+                super::DEFAULT_SUBSCRIPTION_LIMIT,
+            );
+            assert_eq!(result.err(), Some(super::SubscriptionError::UrlIsNotFeed));
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn create_subscription_fails_to_create_a_subscription_if_it_already_exists() {
+        let db_connection = db::establish_connection();
+        let new_chat = NewTelegramChat {
+            id: 42,
+            kind: "private".to_string(),
+            username: Some("Username".to_string()),
+            first_name: Some("First".to_string()),
+            last_name: Some("Last".to_string()),
+            title: None,
+        };
+
+        db_connection.test_transaction::<(), super::SubscriptionError, _>(|| {
+            let subscription = super::create_subscription(
+                &db_connection,
+                new_chat.clone(),
+                None,
+                Some("https://feeds.npr.org/1004/feed.json".to_string()),
+                None,
+                super::DEFAULT_SUBSCRIPTION_LIMIT,
+            )
+            .unwrap();
+
+            assert!(feeds::find(&db_connection, subscription.feed_id).is_some());
+            assert!(telegram::find_chat(&db_connection, subscription.chat_id).is_some());
+
+            let result = super::create_subscription(
+                &db_connection,
+                new_chat,
+                None,
+                Some("https://feeds.npr.org/1004/feed.json".to_string()),
+                None,
+                super::DEFAULT_SUBSCRIPTION_LIMIT,
+            );
+            let chat = telegram::find_chat(&db_connection, subscription.chat_id).unwrap();
+
+            assert_eq!(
+                result.err(),
+                Some(super::SubscriptionError::SubscriptionAlreadyExists(
+                    super::format_timestamp(subscription.created_at, &chat)
+                ))
+            );
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn create_subscription_updates_chat_profile_of_existing_chat() {
+        let db_connection = db::establish_connection();
+        let new_chat = NewTelegramChat {
+            id: 42,
+            kind: "private".to_string(),
+            username: Some("Username".to_string()),
+            first_name: Some("First".to_string()),
+            last_name: Some("Last".to_string()),
+            title: None,
+        };
+        let renamed_chat = NewTelegramChat {
+            username: Some("NewUsername".to_string()),
+            first_name: Some("NewFirst".to_string()),
+            ..new_chat.clone()
+        };
+
+        db_connection.test_transaction::<(), super::SubscriptionError, _>(|| {
+            super::create_subscription(
+                &db_connection,
+                new_chat,
+                None,
+                Some("https://feeds.npr.org/1004/feed.json".to_string()),
+                None,
+                super::DEFAULT_SUBSCRIPTION_LIMIT,
+            )
+            .unwrap();
+
+            let result = super::create_subscription(
+                &db_connection,
+                renamed_chat,
+                None,
+                Some("https://feeds.npr.org/1004/feed.json".to_string()),
+                None,
+                super::DEFAULT_SUBSCRIPTION_LIMIT,
+            );
+            assert!(matches!(
+                result.err(),
+                Some(super::SubscriptionError::SubscriptionAlreadyExists(_))
+            ));
+
+            let chat = telegram::find_chat(&db_connection, 42).unwrap();
+
+            assert_eq!(chat.username, Some("NewUsername".to_string()));
+            assert_eq!(chat.first_name, Some("NewFirst".to_string()));
+            assert_eq!(chat.last_name, Some("Last".to_string()));
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn create_subscription_fails_to_create_a_subscription_if_it_exists_for_equivalent_url() {
+        let db_connection = db::establish_connection();
+        let new_chat = NewTelegramChat {
+            id: 42,
+            kind: "private".to_string(),
+            username: Some("Username".to_string()),
+            first_name: Some("First".to_string()),
+            last_name: Some("Last".to_string()),
+            title: None,
+        };
+
+        db_connection.test_transaction::<(), super::SubscriptionError, _>(|| {
+            super::create_subscription(
+                &db_connection,
+                new_chat.clone(),
+                None,
+                Some("https://feeds.npr.org/1004/feed.json".to_string()),
+                None,
+                super::DEFAULT_SUBSCRIPTION_LIMIT,
+            )
+            .unwrap();
+
+            let result = super::create_subscription(
+                &db_connection,
+                new_chat,
+                None,
+                Some("HTTPS://FEEDS.npr.org:443/1004/feed.json".to_string()),
+                None,
+                super::DEFAULT_SUBSCRIPTION_LIMIT,
+            );
+            assert!(matches!(
+                result.err(),
+                Some(super::SubscriptionError::SubscriptionAlreadyExists(_))
+            ));
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    #[ignore]
+    fn create_subscription_fails_to_create_a_subscription_if_it_already_has_5_suscriptions() {
+        let db_connection = db::establish_connection();
+        let new_chat = NewTelegramChat {
+            id: 42,
+            kind: "private".to_string(),
+            username: Some("Username".to_string()),
+            first_name: Some("First".to_string()),
+            last_name: Some("Last".to_string()),
+            title: None,
+        };
+
+        db_connection.test_transaction::<(), super::SubscriptionError, _>(|| {
+            for rss_url in vec![
+                "https://rss.nytimes.com/services/xml/rss/nyt/HomePage.xml",
+                "https://www.eurekalert.org/rss/technology_engineering.xml",
+                "https://www.sciencedaily.com/rss/matter_energy/engineering.xml",
+                "https://www.france24.com/fr/france/rss",
+                "http://feeds.reuters.com/reuters/technologyNews",
+            ] {
+                assert!(super::create_subscription(
+                    &db_connection,
+                    new_chat.clone(),
+                    None,
+                    Some(rss_url.to_string()),
+                    None,
+                    super::DEFAULT_SUBSCRIPTION_LIMIT
+                )
+                .is_ok());
+            }
+
+            let result = super::create_subscription(
+                &db_connection,
+                new_chat,
+                None,
+                Some("http://www.engadget.com/rss.xml".to_string()),
+                None,
+                super::DEFAULT_SUBSCRIPTION_LIMIT,
+            );
+
+            assert_eq!(
+                result.err(),
+                Some(super::SubscriptionError::SubscriptionCountLimit)
+            );
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn create_subscription_does_not_reactivate_chat_when_subscription_is_rejected() {
+        let db_connection = db::establish_connection();
+        let new_chat = NewTelegramChat {
+            id: 42,
+            kind: "private".to_string(),
+            username: Some("Username".to_string()),
+            first_name: Some("First".to_string()),
+            last_name: Some("Last".to_string()),
+            title: None,
+        };
+
+        db_connection.test_transaction::<(), super::SubscriptionError, _>(|| {
+            let chat = telegram::create_chat(&db_connection, new_chat.clone()).unwrap();
+            telegram::set_max_subscriptions(&db_connection, &chat, 0).unwrap();
+            telegram::mark_chat_inactive(&db_connection, chat.id).unwrap();
+
+            let result = super::create_subscription(
+                &db_connection,
+                new_chat,
+                None,
+                Some("https://www.feedforall.com/sample-feed.xml".to_string()),
+                None,
+                super::DEFAULT_SUBSCRIPTION_LIMIT,
+            );
+
+            assert_eq!(
+                result.err(),
+                Some(super::SubscriptionError::SubscriptionCountLimit)
+            );
+            assert!(!telegram::find_chat(&db_connection, chat.id).unwrap().active);
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn create_subscription_fails_to_create_a_subscription_if_chat_limit_is_reached() {
+        let db_connection = db::establish_connection();
+        let new_chat = NewTelegramChat {
+            id: 42,
+            kind: "private".to_string(),
+            username: Some("Username".to_string()),
+            first_name: Some("First".to_string()),
+            last_name: Some("Last".to_string()),
+            title: None,
+        };
+
+        db_connection.test_transaction::<(), super::SubscriptionError, _>(|| {
+            let chat = telegram::create_chat(&db_connection, new_chat.clone()).unwrap();
+            telegram::set_max_subscriptions(&db_connection, &chat, 1).unwrap();
+
+            assert!(super::create_subscription(
+                &db_connection,
+                new_chat.clone(),
+                None,
+                Some("https://feeds.npr.org/1004/feed.json".to_string()),
+                None,
+                super::DEFAULT_SUBSCRIPTION_LIMIT
+            )
+            .is_ok());
+
+            let result = super::create_subscription(
+                &db_connection,
+                new_chat,
+                None,
+                Some("https://www.feedforall.com/sample-feed.xml".to_string()),
+                None,
+                super::DEFAULT_SUBSCRIPTION_LIMIT,
+            );
+
+            assert_eq!(
+                result.err(),
+                Some(super::SubscriptionError::SubscriptionCountLimit)
+            );
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn subscription_usage_shows_used_subscriptions_and_chat_limit() {
+        let db_connection = db::establish_connection();
+        let new_chat = NewTelegramChat {
+            id: 42,
+            kind: "private".to_string(),
+            username: Some("Username".to_string()),
+            first_name: Some("First".to_string()),
+            last_name: Some("Last".to_string()),
+            title: None,
+        };
+
+        db_connection.test_transaction::<(), super::SubscriptionError, _>(|| {
+            assert_eq!(
+                super::subscription_usage(&db_connection, 42, 20, "en"),
+                None
+            );
+
+            let chat = telegram::create_chat(&db_connection, new_chat).unwrap();
+            let feed =
+                feeds::create(&db_connection, "Link".to_string(), "rss".to_string()).unwrap();
+            telegram::create_subscription(
+                &db_connection,
+                NewTelegramSubscription {
+                    chat_id: chat.id,
+                    feed_id: feed.id,
+                    user_id: None,
+                },
+            )
+            .unwrap();
+
+            assert_eq!(
+                super::subscription_usage(&db_connection, chat.id, 20, "en"),
+                Some("You have 1 of 20 subscriptions used".to_string())
+            );
+
+            telegram::set_max_subscriptions(&db_connection, &chat, 30).unwrap();
+
+            assert_eq!(
+                super::subscription_usage(&db_connection, chat.id, 20, "en"),
+                Some("You have 1 of 30 subscriptions used".to_string())
+            );
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn create_subscription_returns_db_error_if_insert_fails() {
+        let db_connection = db::establish_connection();
+        let new_chat = NewTelegramChat {
+            id: 42,
+            kind: "private".to_string(),
+            username: Some("Username".to_string()),
+            first_name: Some("First".to_string()),
+            last_name: Some("Last".to_string()),
+            title: None,
+        };
+
+        db_connection.test_transaction::<(), super::SubscriptionError, _>(|| {
+            db_connection.execute("SET TRANSACTION READ ONLY")?;
+
+            let result = super::create_subscription(
+                &db_connection,
+                new_chat,
+                None,
+                Some("https://feeds.npr.org/1004/feed.json".to_string()),
+                None,
+                super::DEFAULT_SUBSCRIPTION_LIMIT,
+            );
+
+            match result {
+                Err(super::SubscriptionError::DbError(_)) => (),
+                other => panic!("expected a db error, got {:?}", other),
+            }
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn create_subscription_does_not_create_a_feed_if_chat_limit_is_reached() {
+        let db_connection = db::establish_connection();
+        let new_chat = NewTelegramChat {
+            id: 42,
+            kind: "private".to_string(),
+            username: Some("Username".to_string()),
+            first_name: Some("First".to_string()),
+            last_name: Some("Last".to_string()),
+            title: None,
+        };
+
+        db_connection.test_transaction::<(), super::SubscriptionError, _>(|| {
+            let chat = telegram::create_chat(&db_connection, new_chat.clone()).unwrap();
+            telegram::set_max_subscriptions(&db_connection, &chat, 0).unwrap();
+
+            let link = "http://unreachable.invalid/feed.xml".to_string();
+
+            let result = super::create_subscription(
+                &db_connection,
+                new_chat,
+                None,
+                Some(link.clone()),
+                None,
+                super::DEFAULT_SUBSCRIPTION_LIMIT,
+            );
+
+            assert_eq!(
+                result.err(),
+                Some(super::SubscriptionError::SubscriptionCountLimit)
+            );
+            assert!(feeds::find_by_link(&db_connection, link).is_none());
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn restore_subscription_restores_unsubscribed_feed_with_its_settings() {
+        let db_connection = db::establish_connection();
+        let new_chat = NewTelegramChat {
+            id: 42,
+            kind: "private".to_string(),
+            username: Some("Username".to_string()),
+            first_name: Some("First".to_string()),
+            last_name: Some("Last".to_string()),
+            title: None,
+        };
+
+        db_connection.test_transaction::<(), super::SubscriptionError, _>(|| {
+            let chat = telegram::create_chat(&db_connection, new_chat).unwrap();
+            let feed =
+                feeds::create(&db_connection, "Link".to_string(), "rss".to_string()).unwrap();
+            let subscription = telegram::create_subscription(
+                &db_connection,
+                NewTelegramSubscription {
+                    chat_id: chat.id,
+                    feed_id: feed.id,
+                    user_id: None,
+                },
+            )
+            .unwrap();
+            telegram::set_template(&db_connection, &subscription, Some("{{title}}".to_string()))
+                .unwrap();
+
+            assert_eq!(
+                super::restore_subscription(&db_connection, chat.id, "Link".to_string(), 20),
+                Err("restore_not_found")
+            );
+
+            super::delete_subscription(&db_connection, chat.id, "Link".to_string()).unwrap();
+
+            assert_eq!(
+                telegram::count_subscriptions_for_chat(&db_connection, chat.id),
+                0
+            );
+            assert_eq!(
+                super::restore_subscription(&db_connection, chat.id, "Link".to_string(), 0),
+                Err("subscription_limit_exceeded")
+            );
+            assert_eq!(
+                super::restore_subscription(&db_connection, chat.id, "Link".to_string(), 20),
+                Ok("Link".to_string())
+            );
+
+            let subscription =
+                super::find_subscription_by_link(&db_connection, chat.id, "Link".to_string())
+                    .unwrap();
+
+            assert_eq!(subscription.template, Some("{{title}}".to_string()));
+            assert_eq!(subscription.deleted_at, None);
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn random_item_renders_item_with_subscription_template() {
+        let db_connection = db::establish_connection();
+        let new_chat = NewTelegramChat {
+            id: 42,
+            kind: "private".to_string(),
+            username: Some("Username".to_string()),
+            first_name: Some("First".to_string()),
+            last_name: Some("Last".to_string()),
+            title: None,
+        };
+
+        db_connection.test_transaction::<(), super::SubscriptionError, _>(|| {
+            let chat = telegram::create_chat(&db_connection, new_chat).unwrap();
+            let feed =
+                feeds::create(&db_connection, "Link".to_string(), "rss".to_string()).unwrap();
+            let subscription = telegram::create_subscription(
+                &db_connection,
+                NewTelegramSubscription {
+                    chat_id: chat.id,
+                    feed_id: feed.id,
+                    user_id: None,
+                },
+            )
+            .unwrap();
+
+            assert_eq!(super::random_item(&db_connection, chat.id), Err("no_items"));
+
+            telegram::set_template(
+                &db_connection,
+                &subscription,
+                Some("{{title}} {{link}}".to_string()),
+            )
+            .unwrap();
+            feed_items::create(
+                &db_connection,
+                feed.id,
+                vec![FetchedFeedItem {
+                    title: "Title".to_string(),
+                    description: None,
+                    link: "https://example.com/item".to_string(),
+                    author: None,
+                    guid: None,
+                    publication_date: db::current_time(),
+                }],
+            )
+            .unwrap();
+
+            assert_eq!(
+                super::random_item(&db_connection, chat.id),
+                Ok("Title https://example.com/item".to_string())
+            );
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn delete_subscription_by_index_removes_subscription_with_the_given_number() {
+        let db_connection = db::establish_connection();
+        let new_chat = NewTelegramChat {
+            id: 42,
+            kind: "private".to_string(),
+            username: Some("Username".to_string()),
+            first_name: Some("First".to_string()),
+            last_name: Some("Last".to_string()),
+            title: None,
+        };
+
+        db_connection.test_transaction::<(), super::SubscriptionError, _>(|| {
+            let chat = telegram::create_chat(&db_connection, new_chat).unwrap();
+
+            for link in vec!["Link1", "Link2"] {
+                let feed =
+                    feeds::create(&db_connection, link.to_string(), "rss".to_string()).unwrap();
+
+                telegram::create_subscription(
+                    &db_connection,
+                    NewTelegramSubscription {
+                        chat_id: chat.id,
+                        feed_id: feed.id,
+                        user_id: None,
+                    },
+                )
+                .unwrap();
+            }
+
+            let result = super::delete_subscription_by_index(&db_connection, chat.id, 2);
+            assert_eq!(result.ok(), Some("Link2".to_string()));
+
+            let result = super::delete_subscription_by_index(&db_connection, chat.id, 2);
+            assert!(result.is_err());
+
+            let remaining_feeds =
+                telegram::find_feeds_by_chat_id(&db_connection, chat.id, None).unwrap();
+            assert_eq!(remaining_feeds.len(), 1);
+            assert_eq!(remaining_feeds[0].link, "Link1".to_string());
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn resume_skips_items_published_during_the_pause() {
+        let db_connection = db::establish_connection();
+        let new_chat = NewTelegramChat {
+            id: 42,
+            kind: "private".to_string(),
+            username: Some("Username".to_string()),
+            first_name: Some("First".to_string()),
+            last_name: Some("Last".to_string()),
+            title: None,
+        };
+
+        db_connection.test_transaction::<(), super::SubscriptionError, _>(|| {
+            let chat = telegram::create_chat(&db_connection, new_chat).unwrap();
+            let feed =
+                feeds::create(&db_connection, "Link".to_string(), "rss".to_string()).unwrap();
+            let subscription = telegram::create_subscription(
+                &db_connection,
+                NewTelegramSubscription {
+                    chat_id: chat.id,
+                    feed_id: feed.id,
+                    user_id: None,
+                },
+            )
+            .unwrap();
+            telegram::set_subscription_last_delivered_at(
+                &db_connection,
+                &subscription,
+                db::current_time() - Duration::hours(2),
+            )
+            .unwrap();
+
+            assert_eq!(
+                super::pause(&db_connection, chat.id, "en"),
+                "Your deliveries were paused. Use /resume to resume them".to_string()
+            );
+
+            feed_items::create(
+                &db_connection,
+                feed.id,
+                vec![FetchedFeedItem {
+                    title: "FeedItem1".to_string(),
+                    description: None,
+                    link: "Link1".to_string(),
+                    author: None,
+                    guid: None,
+                    publication_date: db::current_time() - Duration::hours(1),
+                }],
+            )
+            .unwrap();
+
+            assert_eq!(
+                super::resume(&db_connection, chat.id, "en"),
+                "Your deliveries were resumed. 1 items published during the pause were skipped"
+                    .to_string()
+            );
+
+            let chat = telegram::find_chat(&db_connection, chat.id).unwrap();
+            assert!(!chat.paused);
+
+            let subscription = telegram::find_subscriptions_for_chat(&db_connection, chat.id)
+                .unwrap()
+                .pop()
+                .unwrap();
+            assert_eq!(
+                telegram::count_undelivered_feed_items(&db_connection, &subscription).unwrap(),
+                0
+            );
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn find_feeds_by_chat_id_lists_feed_titles_with_links() {
+        let db_connection = db::establish_connection();
+        let new_chat = NewTelegramChat {
+            id: 42,
+            kind: "private".to_string(),
+            username: Some("Username".to_string()),
+            first_name: Some("First".to_string()),
+            last_name: Some("Last".to_string()),
+            title: None,
+        };
+
+        db_connection.test_transaction::<(), super::SubscriptionError, _>(|| {
+            let chat = telegram::create_chat(&db_connection, new_chat).unwrap();
+            let feed1 =
+                feeds::create(&db_connection, "Link1".to_string(), "rss".to_string()).unwrap();
+            let feed2 =
+                feeds::create(&db_connection, "Link2".to_string(), "rss".to_string()).unwrap();
+            feeds::set_title(&db_connection, &feed1, "Title1".to_string()).unwrap();
+
+            for feed in vec![&feed1, &feed2] {
+                telegram::create_subscription(
+                    &db_connection,
+                    NewTelegramSubscription {
+                        chat_id: chat.id,
+                        feed_id: feed.id,
+                        user_id: None,
+                    },
+                )
+                .unwrap();
+            }
+
+            assert_eq!(
+                super::find_feeds_by_chat_id(&db_connection, chat.id, "".to_string(), "en"),
+                "1. [RSS] Title1 — Link1\n2. [RSS] Link2".to_string()
+            );
+            assert_eq!(
+                super::find_feeds_by_chat_id(&db_connection, chat.id, "rss".to_string(), "en"),
+                "1. [RSS] Title1 — Link1\n2. [RSS] Link2".to_string()
+            );
+            assert_eq!(
+                super::find_feeds_by_chat_id(&db_connection, chat.id, "atom".to_string(), "en"),
+                "You don't have any Atom subscriptions".to_string()
+            );
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn add_filter_adds_lowercased_keyword() {
+        let db_connection = db::establish_connection();
+        let new_chat = NewTelegramChat {
+            id: 42,
+            kind: "private".to_string(),
+            username: Some("Username".to_string()),
+            first_name: Some("First".to_string()),
+            last_name: Some("Last".to_string()),
+            title: None,
+        };
+
+        db_connection.test_transaction::<(), super::FilterError, _>(|| {
+            let chat = telegram::create_chat(&db_connection, new_chat).unwrap();
+            let feed =
+                feeds::create(&db_connection, "Link".to_string(), "rss".to_string()).unwrap();
+            telegram::create_subscription(
+                &db_connection,
+                NewTelegramSubscription {
+                    chat_id: chat.id,
+                    feed_id: feed.id,
+                    user_id: None,
+                },
+            )
+            .unwrap();
+
+            let filter = super::add_filter(
+                &db_connection,
+                chat.id,
+                "Link".to_string(),
+                " Rust ".to_string(),
+            )
+            .unwrap();
+            assert_eq!(filter.keyword, "rust".to_string());
+
+            let result = super::add_filter(
+                &db_connection,
+                chat.id,
+                "Link".to_string(),
+                "RUST".to_string(),
+            );
+            assert_eq!(result.err(), Some(super::FilterError::FilterAlreadyExists));
+
+            let keywords = super::list_filters(&db_connection, chat.id, "Link".to_string());
+            assert_eq!(keywords, Ok(vec!["rust".to_string()]));
+
+            assert_eq!(
+                super::remove_filter(
+                    &db_connection,
+                    chat.id,
+                    "Link".to_string(),
+                    "rust".to_string()
+                ),
+                Ok(())
+            );
+            assert_eq!(
+                super::remove_filter(
+                    &db_connection,
+                    chat.id,
+                    "Link".to_string(),
+                    "rust".to_string()
+                ),
+                Err(super::FilterError::FilterNotFound)
+            );
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn add_filter_fails_if_subscription_does_not_exist() {
+        let db_connection = db::establish_connection();
+
+        db_connection.test_transaction::<(), super::FilterError, _>(|| {
+            let result =
+                super::add_filter(&db_connection, 42, "Link".to_string(), "rust".to_string());
+            assert_eq!(result.err(), Some(super::FilterError::SubscriptionNotFound));
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn item_matches_filters_matches_title_and_description_case_insensitively() {
+        let item = FeedItem {
+            feed_id: 1,
+            title: "Announcing Rust 1.45".to_string(),
+            description: Some("The Tokio runtime got faster".to_string()),
+            link: "Link".to_string(),
+            author: None,
+            guid: None,
+            publication_date: db::current_time(),
+            created_at: db::current_time(),
+            updated_at: db::current_time(),
+            content_hash: None,
+        };
+
+        assert!(super::item_matches_filters(&item, &[]));
+        assert!(super::item_matches_filters(&item, &["rust".to_string()]));
+        assert!(super::item_matches_filters(&item, &["TOKIO".to_string()]));
+        assert!(super::item_matches_filters(
+            &item,
+            &["python".to_string(), "tokio".to_string()]
+        ));
+        assert!(!super::item_matches_filters(&item, &["python".to_string()]));
+    }
+
+    #[test]
+    fn matching_watchwords_finds_keywords_mentioned_by_item() {
+        let item = FeedItem {
+            feed_id: 1,
+            title: "Announcing Rust 1.45".to_string(),
+            description: Some("The Tokio runtime got faster".to_string()),
+            link: "Link".to_string(),
+            author: None,
+            guid: None,
+            publication_date: db::current_time(),
+            created_at: db::current_time(),
+            updated_at: db::current_time(),
+            content_hash: None,
+        };
+        let watchwords = vec![
+            "python".to_string(),
+            "rust".to_string(),
+            "tokio".to_string(),
+        ];
+
+        assert_eq!(
+            super::matching_watchwords(&item, &watchwords),
+            vec!["rust".to_string(), "tokio".to_string()]
+        );
+        assert!(super::matching_watchwords(&item, &["go".to_string()]).is_empty());
+        assert!(super::matching_watchwords(&item, &[]).is_empty());
+    }
+
+    #[test]
+    fn format_watchword_match_highlights_keywords() {
+        let item = build_feed_item();
+
+        assert_eq!(
+            super::format_watchword_match(
+                &Some("Feed".to_string()),
+                &item,
+                &["title".to_string(), "description".to_string()],
+                "en"
+            ),
+            "🔔 Mentions \"title\", \"description\"\n\nFeed\nTitle\nhttps://example.com/item"
+        );
+        assert_eq!(
+            super::format_watchword_match(&None, &item, &["title".to_string()], "en"),
+            "🔔 Mentions \"title\"\n\nTitle\nhttps://example.com/item"
+        );
+    }
+
+    #[test]
+    fn watchwords_are_added_listed_and_removed() {
+        let db_connection = db::establish_connection();
+        let new_chat = NewTelegramChat {
+            id: 42,
+            kind: "private".to_string(),
+            username: Some("Username".to_string()),
+            first_name: Some("First".to_string()),
+            last_name: Some("Last".to_string()),
+            title: None,
+        };
+
+        db_connection.test_transaction::<(), super::WatchwordError, _>(|| {
+            let watchword =
+                super::add_watchword(&db_connection, new_chat.clone(), " Rust ".to_string())
+                    .unwrap();
+            assert_eq!(watchword.keyword, "rust".to_string());
+
+            assert_eq!(
+                super::add_watchword(&db_connection, new_chat.clone(), "RUST".to_string()).err(),
+                Some(super::WatchwordError::WatchwordAlreadyExists)
+            );
+            assert_eq!(
+                super::add_watchword(&db_connection, new_chat, " ".to_string()).err(),
+                Some(super::WatchwordError::KeywordNotProvided)
+            );
+            assert_eq!(
+                super::list_watchwords(&db_connection, 42),
+                Ok(vec!["rust".to_string()])
+            );
+            assert_eq!(
+                super::remove_watchword(&db_connection, 42, "rust".to_string()),
+                Ok(())
+            );
+            assert_eq!(
+                super::remove_watchword(&db_connection, 42, "rust".to_string()),
+                Err(super::WatchwordError::WatchwordNotFound)
+            );
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn validate_offset_accepts_offset_in_minutes() {
+        assert_eq!(super::validate_offset("600".to_string()), Ok((600, None)));
+        assert_eq!(
+            super::validate_offset("601".to_string()),
+            Err("offset_not_divisible")
+        );
+    }
+
+    #[test]
+    fn validate_offset_accepts_timezone_name() {
+        let (offset, name) = super::validate_offset("America/New_York".to_string()).unwrap();
+
+        assert_eq!(name, Some("America/New_York".to_string()));
+        assert!(offset == -300 || offset == -240);
+
+        let (offset, name) = super::validate_offset("Asia/Kolkata".to_string()).unwrap();
+
+        assert_eq!(name, Some("Asia/Kolkata".to_string()));
+        assert_eq!(offset, 330);
+    }
+
+    #[test]
+    fn validate_offset_rejects_unknown_timezone_name() {
+        assert!(super::validate_offset("Mars/Olympus_Mons".to_string()).is_err());
+    }
+
+    #[test]
+    fn format_preview_formats_item() {
+        let item = FetchedFeedItem {
+            title: "Title".to_string(),
+            description: Some("Description".to_string()),
+            link: "Link".to_string(),
+            author: None,
+            guid: None,
+            publication_date: db::current_time(),
+        };
+
+        assert_eq!(
+            super::format_preview("Feed", &item),
+            "Feed\n\nTitle\n\nDescription\n\nLink".to_string()
+        );
+        assert_eq!(
+            super::format_preview("", &item),
+            "Title\n\nDescription\n\nLink".to_string()
+        );
+    }
+
+    #[test]
+    fn truncate_truncates_long_strings() {
+        assert_eq!(super::truncate("Привет мир", 6), "Привет...".to_string());
+        assert_eq!(super::truncate("Hello", 10), "Hello".to_string());
+    }
+
+    #[test]
+    fn parse_list_arguments_parses_feed_type_and_page() {
+        assert_eq!(super::parse_list_arguments("".to_string()), Ok((None, 1)));
+        assert_eq!(super::parse_list_arguments("2".to_string()), Ok((None, 2)));
+        assert_eq!(
+            super::parse_list_arguments("RSS".to_string()),
+            Ok((Some("rss".to_string()), 1))
+        );
+        assert_eq!(
+            super::parse_list_arguments("atom 3".to_string()),
+            Ok((Some("atom".to_string()), 3))
+        );
+        assert_eq!(
+            super::parse_list_arguments("xml".to_string()),
+            Err("page_not_positive")
+        );
+        assert_eq!(
+            super::parse_list_arguments("json 1 2".to_string()),
+            Err("wrong_list_arguments")
+        );
+    }
+
+    #[test]
+    fn format_feeds_page_paginates_feeds() {
+        let feeds = (1..=12)
+            .map(|id| Feed {
+                id,
+                title: None,
+                link: format!("Link{}", id),
+                error: None,
+                description: None,
+                synced_at: None,
+                created_at: db::current_time(),
+                updated_at: db::current_time(),
+                feed_type: "rss".to_string(),
+                failures_count: 0,
+                dead: false,
+                etag: None,
+                last_modified: None,
+                credentials_key: "".to_string(),
+            })
+            .collect::<Vec<Feed>>();
+
+        let first_page = super::format_feeds_page(feeds.clone(), 1, "en");
+        assert!(first_page.starts_with("1. [RSS] Link1\n"));
+        assert!(first_page.ends_with("10. [RSS] Link10\n\nPage 1 of 2"));
+
+        assert_eq!(
+            super::format_feeds_page(feeds.clone(), 2, "en"),
+            "11. [RSS] Link11\n12. [RSS] Link12\n\nPage 2 of 2".to_string()
+        );
+
+        assert_eq!(
+            super::format_feeds_page(feeds, 3, "en"),
+            "Page 3 doesn't exist. The number of pages is 2".to_string()
+        );
+    }
+
+    #[test]
+    fn format_feeds_page_does_not_show_footer_for_single_page() {
+        let feeds = vec![Feed {
+            id: 1,
+            title: Some("Title".to_string()),
+            link: "Link".to_string(),
+            error: None,
+            description: None,
+            synced_at: None,
+            created_at: db::current_time(),
+            updated_at: db::current_time(),
+            feed_type: "rss".to_string(),
+            failures_count: 0,
+            dead: false,
+            etag: None,
+            last_modified: None,
+            credentials_key: "".to_string(),
+        }];
+
+        assert_eq!(
+            super::format_feeds_page(feeds, 1, "en"),
+            "1. [RSS] Title — Link".to_string()
+        );
+        assert_eq!(
+            super::format_feeds_page(vec![], 1, "en"),
+            "You don't have any subscriptions".to_string()
+        );
+    }
+
+    #[test]
+    fn format_chat_stats_formats_stats() {
+        let stats = ChatStats {
+            subscriptions_count: 2,
+            delivered_count: 15,
+            delivered_at: Some(
+                chrono::DateTime::parse_from_rfc3339("2020-08-08T10:15:00Z")
+                    .unwrap()
+                    .into(),
+            ),
+        };
+
+        assert_eq!(
+            super::format_chat_stats(stats, &build_chat(Some(180), None), "en"),
+            "Subscriptions: 2\nDelivered items: 15\nLast delivery: 2020-08-08 13:15:00 +03:00"
+                .to_string()
+        );
+    }
+
+    #[test]
+    fn format_chat_stats_formats_stats_without_deliveries() {
+        let stats = ChatStats {
+            subscriptions_count: 1,
+            delivered_count: 0,
+            delivered_at: None,
+        };
+
+        assert_eq!(
+            super::format_chat_stats(stats, &build_chat(None, None), "en"),
+            "Subscriptions: 1\nDelivered items: 0\nLast delivery: never".to_string()
+        );
+    }
+
+    #[test]
+    fn format_top_feeds_formats_latest_items() {
+        let published_at = chrono::DateTime::parse_from_rfc3339("2020-08-08T10:15:00Z")
+            .unwrap()
+            .into();
+        let items = vec![
+            LatestFeedItem {
+                link: "https://example.com/rss".to_string(),
+                feed_title: Some("Example".to_string()),
+                item_title: "Latest".to_string(),
+                published_at,
+            },
+            LatestFeedItem {
+                link: "https://example.com/atom".to_string(),
+                feed_title: None,
+                item_title: "Older".to_string(),
+                published_at,
+            },
+        ];
+
+        assert_eq!(
+            super::format_top_feeds(&items, &build_chat(Some(180), None)),
+            "1. Example | 2020-08-08 13:15:00 +03:00\nLatest\n\n2. https://example.com/atom | 2020-08-08 13:15:00 +03:00\nOlder"
+                .to_string()
+        );
+    }
+
+    #[test]
+    fn format_unread_counts_lists_feeds_and_total() {
+        let build_feed = |title: Option<&str>, link: &str| Feed {
+            id: 1,
+            title: title.map(|title| title.to_string()),
+            link: link.to_string(),
+            error: None,
+            description: None,
+            synced_at: None,
+            created_at: db::current_time(),
+            updated_at: db::current_time(),
+            feed_type: "rss".to_string(),
+            failures_count: 0,
+            dead: false,
+            etag: None,
+            last_modified: None,
+            credentials_key: "".to_string(),
+        };
+
+        let counts = vec![
+            (
+                build_feed(Some("Rust Blog"), "https://blog.rust-lang.org"),
+                3,
+            ),
+            (build_feed(None, "https://example.com/feed.xml"), 0),
+        ];
+
+        assert_eq!(
+            super::format_unread_counts(&counts, "en"),
+            "Unread items of your feeds:\nRust Blog: 3\nhttps://example.com/feed.xml: 0\n\nTotal: 3"
+                .to_string()
+        );
+    }
+
+    #[test]
+    fn format_chat_health_formats_feed_statuses() {
+        let synced_at = chrono::DateTime::parse_from_rfc3339("2020-08-08T10:15:00Z")
+            .unwrap()
+            .into();
+        let health = vec![
+            FeedHealth {
+                link: "https://example.com/rss".to_string(),
+                synced_at: Some(synced_at),
+                failures_count: 0,
+                dead: false,
+            },
+            FeedHealth {
+                link: "https://example.com/atom".to_string(),
+                synced_at: Some(synced_at),
+                failures_count: 3,
+                dead: false,
+            },
+            FeedHealth {
+                link: "https://example.com/json".to_string(),
+                synced_at: None,
+                failures_count: 10,
+                dead: true,
+            },
+        ];
+
+        assert_eq!(
+            super::format_chat_health(&health, &build_chat(Some(180), None), "en"),
+            vec![
+                "Status | Last successful sync | Feed",
+                "ok | 2020-08-08 13:15:00 +03:00 | https://example.com/rss",
+                "failing (3) | 2020-08-08 13:15:00 +03:00 | https://example.com/atom",
+                "dead | never | https://example.com/json"
+            ]
+            .join("\n")
+        );
+    }
+
+    fn build_chat(utc_offset_minutes: Option<i32>, timezone_name: Option<&str>) -> TelegramChat {
+        TelegramChat {
+            id: 42,
+            kind: "private".to_string(),
+            username: None,
+            first_name: None,
+            last_name: None,
+            created_at: db::current_time(),
+            updated_at: db::current_time(),
+            title: None,
+            utc_offset_minutes,
+            max_subscriptions: None,
+            paused: false,
+            active: true,
+            timezone_name: timezone_name.map(|name| name.to_string()),
+            digest: false,
+            digest_hour: 9,
+            digest_sent_at: None,
+            description_limit: None,
+            language: None,
+            quiet_hours_start: None,
+            quiet_hours_end: None,
+            track_per_user: false,
+            item_prefix: None,
+            forbidden_errors_count: 0,
+            backfill_newest_first: false,
+            show_author: false,
+        }
+    }
+
+    #[test]
+    fn format_timestamp_converts_time_to_chat_timezone() {
+        let time: chrono::DateTime<chrono::Utc> =
+            chrono::DateTime::parse_from_rfc3339("2020-08-08T10:15:00Z")
+                .unwrap()
+                .into();
+
+        assert_eq!(
+            super::format_timestamp(time, &build_chat(None, None)),
+            "2020-08-08 10:15:00 +00:00".to_string()
+        );
+        assert_eq!(
+            super::format_timestamp(time, &build_chat(Some(600), None)),
+            "2020-08-08 20:15:00 +10:00".to_string()
+        );
+        assert_eq!(
+            super::format_timestamp(time, &build_chat(Some(-240), None)),
+            "2020-08-08 06:15:00 -04:00".to_string()
+        );
+        assert_eq!(
+            super::format_timestamp(time, &build_chat(Some(330), None)),
+            "2020-08-08 15:45:00 +05:30".to_string()
+        );
+        assert_eq!(
+            super::format_timestamp(time, &build_chat(Some(0), Some("Australia/Sydney"))),
+            "2020-08-08 20:15:00 +10:00".to_string()
+        );
+    }
+
+    #[test]
+    fn cursor_reset_allowed_limits_resets_to_one_per_hour() {
+        let now: chrono::DateTime<chrono::Utc> =
+            chrono::DateTime::parse_from_rfc3339("2020-08-08T10:15:00Z")
+                .unwrap()
+                .into();
+
+        assert!(super::cursor_reset_allowed(None, now));
+        assert!(!super::cursor_reset_allowed(
+            Some(now - Duration::minutes(59)),
+            now
+        ));
+        assert!(super::cursor_reset_allowed(
+            Some(now - Duration::minutes(60)),
+            now
+        ));
+    }
+
+    #[test]
+    fn format_popular_feeds_formats_rank_count_and_link() {
+        let time = db::current_time();
+        let build_feed = |id: i64, link: &str| Feed {
+            id,
+            title: None,
+            link: link.to_string(),
+            error: None,
+            description: None,
+            synced_at: None,
+            created_at: time,
+            updated_at: time,
+            feed_type: "rss".to_string(),
+            failures_count: 0,
+            dead: false,
+            etag: None,
+            last_modified: None,
+            credentials_key: "".to_string(),
+        };
+
+        let feeds = vec![
+            (build_feed(1, "https://example.com/popular"), 12),
+            (build_feed(2, "https://example.com/other"), 3),
+        ];
+
+        assert_eq!(
+            super::format_popular_feeds(&feeds),
+            "1. 12 - https://example.com/popular\n2. 3 - https://example.com/other".to_string()
+        );
+    }
+
+    #[test]
+    fn format_last_item_marks_already_delivered_items() {
+        assert_eq!(
+            super::format_last_item("Item".to_string(), false, "en"),
+            "Item".to_string()
+        );
+        assert_eq!(
+            super::format_last_item("Item".to_string(), true, "en"),
+            "This is the newest item, it was already delivered to you\n\nItem".to_string()
+        );
+    }
+
+    #[test]
+    fn format_disabled_chats_lists_chats_with_errors() {
+        let mut group = build_chat(None, None);
+        group.id = -100;
+        group.title = Some("Group".to_string());
+        group.forbidden_errors_count = 3;
+
+        let mut user = build_chat(None, None);
+        user.username = Some("Username".to_string());
+        user.forbidden_errors_count = 5;
+
+        assert_eq!(
+            super::format_disabled_chats(&[group, user], "en"),
+            "1. -100 Group - 3 errors\n2. 42 @Username - 5 errors".to_string()
+        );
+    }
+
+    #[test]
+    fn format_chat_description_formats_chat() {
+        assert_eq!(
+            super::format_chat_description(42, "supergroup", true, "en"),
+            "Chat id: 42\nType: supergroup\nRegistered: yes".to_string()
+        );
+        assert_eq!(
+            super::format_chat_description(-7, "private", false, "en"),
+            "Chat id: -7\nType: private\nRegistered: no".to_string()
+        );
+    }
+
+    #[test]
+    fn format_feed_info_formats_feed_info() {
+        let time: chrono::DateTime<chrono::Utc> =
+            chrono::DateTime::parse_from_rfc3339("2020-08-08T10:15:00Z")
+                .unwrap()
+                .into();
+
+        let info = FeedInfo {
+            feed: Feed {
+                id: 1,
+                title: Some("Title".to_string()),
+                link: "https://example.com/rss".to_string(),
+                error: Some("Timeout".to_string()),
+                description: None,
+                synced_at: None,
+                created_at: time,
+                updated_at: time,
+                feed_type: "atom".to_string(),
+                failures_count: 0,
+                dead: false,
+                etag: None,
+                last_modified: None,
+                credentials_key: "".to_string(),
+            },
+            items_count: 5,
+            last_item_published_at: Some(time),
+        };
+
+        let subscription = TelegramSubscription {
+            chat_id: 42,
+            feed_id: 1,
+            last_delivered_at: None,
+            created_at: time,
+            updated_at: time,
+            delivered_count: 3,
+            delivered_at: None,
+            initial_items_count: 10,
+            template: None,
+            silent: false,
+            auth_username: None,
+            auth_password: None,
+            cursor_reset_at: None,
+            min_delivery_interval_secs: None,
+            link_preview: true,
+            user_id: None,
+            snoozed_until: None,
+            full_text: None,
+            deleted_at: None,
+            markdown: false,
+            html: false,
+        };
+
+        assert_eq!(
+            super::format_feed_info(&info, &subscription, &build_chat(Some(-330), None), "en"),
+            vec![
+                "Title: Title",
+                "Link: https://example.com/rss",
+                "Type: Atom",
+                "Added: 2020-08-08 04:45:00 -05:30",
+                "Subscribed: 2020-08-08 04:45:00 -05:30",
+                "Last fetched: 2020-08-08 04:45:00 -05:30",
+                "Last successful sync: never",
+                "Last error: Timeout",
+                "Latest item published: 2020-08-08 04:45:00 -05:30",
+                "Stored items: 5",
+                "Items delivered to you: 3"
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn parse_subscription_argument_parses_url_and_initial_items_count() {
+        assert_eq!(
+            super::parse_subscription_argument("https://example.com/rss".to_string()),
+            Ok(("https://example.com/rss".to_string(), None))
+        );
+        assert_eq!(
+            super::parse_subscription_argument(" https://example.com/rss  5 ".to_string()),
+            Ok(("https://example.com/rss".to_string(), Some(5)))
+        );
+        assert_eq!(
+            super::parse_subscription_argument("https://example.com/rss five".to_string()),
+            Err(super::SubscriptionError::InvalidInitialItemsCount)
+        );
+    }
+
+    #[test]
+    fn extract_url_finds_first_url_in_text() {
+        assert_eq!(
+            super::extract_url("Look at this https://example.com/rss and http://example.com"),
+            Some("https://example.com/rss".to_string())
+        );
+        assert_eq!(super::extract_url("no links here"), None);
+    }
+
+    #[test]
+    fn format_feed_warnings_lists_every_warning() {
+        assert_eq!(super::format_feed_warnings(&[], "en"), None);
+        assert_eq!(
+            super::format_feed_warnings(&[FeedWarning::MissingDates, FeedWarning::InvalidEncoding], "en"),
+            Some(
+                "Note: this feed has items without publication dates, their order may be unreliable\nNote: the feed's encoding is invalid, some characters may be displayed incorrectly"
+                    .to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn validate_rss_url_rejects_internal_addresses() {
+        assert_eq!(
+            super::validate_rss_url("http://127.0.0.1/feed.xml", None),
+            Err(super::SubscriptionError::UnsafeUrl)
+        );
+        assert_eq!(
+            super::validate_rss_url("http://169.254.169.254/latest/meta-data/", None),
+            Err(super::SubscriptionError::UnsafeUrl)
+        );
+        assert_eq!(
+            super::validate_rss_url("http://10.0.0.1/rss", None),
+            Err(super::SubscriptionError::UnsafeUrl)
+        );
+    }
+
+    #[test]
+    fn clean_url_removes_angle_brackets() {
+        assert_eq!(
+            super::clean_url("<http://x/rss>"),
+            "http://x/rss".to_string()
+        );
+    }
+
+    #[test]
+    fn clean_url_removes_trailing_punctuation() {
+        assert_eq!(
+            super::clean_url("http://x/rss."),
+            "http://x/rss".to_string()
+        );
+        assert_eq!(
+            super::clean_url(" http://x/rss); "),
+            "http://x/rss".to_string()
+        );
+        assert_eq!(
+            super::clean_url("http://x/wiki/Rust_(language)"),
+            "http://x/wiki/Rust_(language)".to_string()
+        );
+    }
+
+    #[test]
+    fn set_timezone_creates_chat_if_it_does_not_exist() {
+        let db_connection = db::establish_connection();
+        let new_chat = NewTelegramChat {
+            id: 42,
+            kind: "private".to_string(),
+            username: Some("Username".to_string()),
+            first_name: Some("First".to_string()),
+            last_name: Some("Last".to_string()),
+            title: None,
+        };
+
+        db_connection.test_transaction::<(), super::SubscriptionError, _>(|| {
+            assert!(telegram::find_chat(&db_connection, new_chat.id).is_none());
+
+            assert_eq!(
+                super::set_timezone(&db_connection, new_chat.clone(), "600".to_string()),
+                Ok(600)
+            );
+
+            let chat = telegram::find_chat(&db_connection, new_chat.id).unwrap();
+
+            assert_eq!(chat.utc_offset_minutes, Some(600));
+            assert_eq!(
+                super::get_timezone(&db_connection, chat.id, "en"),
+                "Your timezone offset is 600 minutes".to_string()
+            );
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn set_language_stores_supported_language() {
+        let db_connection = db::establish_connection();
+        let new_chat = NewTelegramChat {
+            id: 42,
+            kind: "private".to_string(),
+            username: Some("Username".to_string()),
+            first_name: Some("First".to_string()),
+            last_name: Some("Last".to_string()),
+            title: None,
+        };
+
+        db_connection.test_transaction::<(), super::SubscriptionError, _>(|| {
+            assert_eq!(super::chat_language(&db_connection, new_chat.id), "en");
+
+            assert_eq!(
+                super::set_language(&db_connection, new_chat.clone(), "de".to_string(), "en"),
+                Err("The language is not supported. Available languages: en, ru".to_string())
+            );
+            assert_eq!(
+                super::set_language(&db_connection, new_chat.clone(), "RU".to_string(), "en"),
+                Ok("ru".to_string())
+            );
+
+            assert_eq!(super::chat_language(&db_connection, new_chat.id), "ru");
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn set_subscription_silent_mutes_subscription() {
+        let db_connection = db::establish_connection();
+        let new_chat = NewTelegramChat {
+            id: 42,
+            kind: "private".to_string(),
+            username: Some("Username".to_string()),
+            first_name: Some("First".to_string()),
+            last_name: Some("Last".to_string()),
+            title: None,
+        };
+
+        db_connection.test_transaction::<(), super::SubscriptionError, _>(|| {
+            let chat = telegram::create_chat(&db_connection, new_chat).unwrap();
+            let feed =
+                feeds::create(&db_connection, "Link".to_string(), "rss".to_string()).unwrap();
+            let new_subscription = NewTelegramSubscription {
+                chat_id: chat.id,
+                feed_id: feed.id,
+                user_id: None,
+            };
+            telegram::create_subscription(&db_connection, new_subscription).unwrap();
+
+            assert_eq!(
+                super::set_subscription_silent(&db_connection, chat.id, "Link".to_string(), true),
+                Ok(())
+            );
+            assert!(
+                telegram::find_subscription(&db_connection, new_subscription)
+                    .unwrap()
+                    .silent
+            );
+            assert_eq!(
+                super::set_subscription_silent(&db_connection, chat.id, "Other".to_string(), true),
+                Err("subscription_not_found")
+            );
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn format_feedback_adds_chat_id_and_validates_text() {
+        assert_eq!(
+            super::format_feedback(42, "  Images are not shown "),
+            Ok("Feedback from chat 42:\n\nImages are not shown".to_string())
+        );
+        assert_eq!(super::format_feedback(42, " "), Err("feedback_missing"));
+        assert_eq!(
+            super::format_feedback(42, &"a".repeat(1001)),
+            Err("feedback_too_long")
+        );
+    }
+
+    #[test]
+    fn parse_duration_parses_minutes_hours_and_days() {
+        assert_eq!(
+            super::parse_duration("30m"),
+            Some(chrono::Duration::minutes(30))
+        );
+        assert_eq!(
+            super::parse_duration(" 2H "),
+            Some(chrono::Duration::hours(2))
+        );
+        assert_eq!(super::parse_duration("1d"), Some(chrono::Duration::days(1)));
+        assert_eq!(
+            super::parse_duration("30d"),
+            Some(chrono::Duration::days(30))
+        );
+    }
+
+    #[test]
+    fn parse_duration_rejects_invalid_durations() {
+        for duration in &[
+            "",
+            "2",
+            "h",
+            "0h",
+            "-1h",
+            "1.5h",
+            "2w",
+            "31d",
+            "9223372036854775807d",
+        ] {
+            assert_eq!(super::parse_duration(duration), None, "{}", duration);
+        }
+    }
+
+    #[test]
+    fn snooze_sets_and_clears_snoozed_until() {
+        let db_connection = db::establish_connection();
+        let new_chat = NewTelegramChat {
+            id: 42,
+            kind: "private".to_string(),
+            username: Some("Username".to_string()),
+            first_name: Some("First".to_string()),
+            last_name: Some("Last".to_string()),
+            title: None,
+        };
+
+        db_connection.test_transaction::<(), super::SubscriptionError, _>(|| {
+            let chat = telegram::create_chat(&db_connection, new_chat).unwrap();
+            let feed =
+                feeds::create(&db_connection, "Link".to_string(), "rss".to_string()).unwrap();
+            let new_subscription = NewTelegramSubscription {
+                chat_id: chat.id,
+                feed_id: feed.id,
+                user_id: None,
+            };
+            telegram::create_subscription(&db_connection, new_subscription).unwrap();
+
+            assert!(super::snooze(
+                &db_connection,
+                chat.id,
+                "Link".to_string(),
+                "2h".to_string(),
+                "en"
+            )
+            .starts_with("The feed is snoozed until "));
+
+            let snoozed_until = telegram::find_subscription(&db_connection, new_subscription)
+                .unwrap()
+                .snoozed_until
+                .unwrap();
+
+            assert!(snoozed_until > db::current_time() + chrono::Duration::minutes(119));
+
+            assert_eq!(
+                super::snooze(
+                    &db_connection,
+                    chat.id,
+                    "Link".to_string(),
+                    "off".to_string(),
+                    "en"
+                ),
+                "The feed is not snoozed anymore".to_string()
+            );
+            assert_eq!(
+                telegram::find_subscription(&db_connection, new_subscription)
+                    .unwrap()
+                    .snoozed_until,
+                None
+            );
+            assert_eq!(
+                super::snooze(
+                    &db_connection,
+                    chat.id,
+                    "Other".to_string(),
+                    "2h".to_string(),
+                    "en"
+                ),
+                "Subscription does not exist".to_string()
+            );
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn unsubscribe_all_requires_confirmation() {
+        let db_connection = db::establish_connection();
+        let new_chat = NewTelegramChat {
+            id: 42,
+            kind: "private".to_string(),
+            username: Some("Username".to_string()),
+            first_name: Some("First".to_string()),
+            last_name: Some("Last".to_string()),
+            title: None,
+        };
+
+        db_connection.test_transaction::<(), super::SubscriptionError, _>(|| {
+            assert_eq!(
+                super::unsubscribe_all(&db_connection, new_chat.id, "confirm".to_string(), "en"),
+                "You don't have any subscriptions".to_string()
+            );
+
+            let chat = telegram::create_chat(&db_connection, new_chat).unwrap();
+            let feed =
+                feeds::create(&db_connection, "Link".to_string(), "rss".to_string()).unwrap();
+            telegram::create_subscription(
+                &db_connection,
+                NewTelegramSubscription {
+                    chat_id: chat.id,
+                    feed_id: feed.id,
+                    user_id: None,
+                },
+            )
+            .unwrap();
+
+            assert_eq!(
+                super::unsubscribe_all(&db_connection, chat.id, "".to_string(), "en"),
+                "All your 1 subscriptions will be removed. Send /unsubscribe_all confirm to continue"
+                    .to_string()
+            );
+            assert_eq!(telegram::count_subscriptions_for_chat(&db_connection, chat.id), 1);
+
+            assert_eq!(
+                super::unsubscribe_all(&db_connection, chat.id, "confirm".to_string(), "en"),
+                "You were unsubscribed from 1 feeds".to_string()
+            );
+            assert_eq!(telegram::count_subscriptions_for_chat(&db_connection, chat.id), 0);
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn clone_subscriptions_skips_duplicates_and_respects_limit() {
+        let db_connection = db::establish_connection();
+        let source_chat = NewTelegramChat {
+            id: 42,
+            kind: "private".to_string(),
+            username: Some("Username".to_string()),
+            first_name: Some("First".to_string()),
+            last_name: Some("Last".to_string()),
+            title: None,
+        };
+        let group_chat = NewTelegramChat {
+            id: -42,
+            kind: "group".to_string(),
+            title: Some("Group".to_string()),
+            ..source_chat.clone()
+        };
+
+        db_connection.test_transaction::<(), super::SubscriptionError, _>(|| {
+            assert_eq!(
+                super::clone_subscriptions(&db_connection, 42, group_chat.clone(), 20, "en"),
+                "The chat doesn't have any subscriptions".to_string()
+            );
+
+            let chat = telegram::create_chat(&db_connection, source_chat).unwrap();
+
+            for link in &["Link1", "Link2", "Link3"] {
+                let feed =
+                    feeds::create(&db_connection, link.to_string(), "rss".to_string()).unwrap();
+
+                telegram::create_subscription(
+                    &db_connection,
+                    NewTelegramSubscription {
+                        chat_id: chat.id,
+                        feed_id: feed.id,
+                        user_id: None,
+                    },
+                )
+                .unwrap();
+            }
+
+            assert_eq!(
+                super::clone_subscriptions(&db_connection, 42, group_chat.clone(), 2, "en"),
+                "Copied 2 subscriptions, skipped 0 duplicates, 1 over the subscription limit"
+                    .to_string()
+            );
+            assert_eq!(
+                super::clone_subscriptions(&db_connection, 42, group_chat.clone(), 20, "en"),
+                "Copied 1 subscriptions, skipped 2 duplicates".to_string()
+            );
+            assert_eq!(
+                telegram::count_subscriptions_for_chat(&db_connection, -42),
+                3
+            );
+
+            let private_feed = feeds::create_with_credentials_key(
+                &db_connection,
+                "Link4".to_string(),
+                "rss".to_string(),
+                "key".to_string(),
+            )
+            .unwrap();
+            let private_subscription = telegram::create_subscription(
+                &db_connection,
+                NewTelegramSubscription {
+                    chat_id: chat.id,
+                    feed_id: private_feed.id,
+                    user_id: None,
+                },
+            )
+            .unwrap();
+            telegram::set_credentials(
+                &db_connection,
+                &private_subscription,
+                "user".to_string(),
+                "password".to_string(),
+            )
+            .unwrap();
+
+            assert_eq!(
+                super::clone_subscriptions(&db_connection, 42, group_chat.clone(), 20, "en"),
+                "Copied 0 subscriptions, skipped 3 duplicates\nSubscriptions with credentials aren't copied, subscribe to them with the credentials: 1"
+                    .to_string()
+            );
+            assert_eq!(
+                telegram::count_subscriptions_for_chat(&db_connection, -42),
+                3
+            );
+            assert_eq!(
+                super::clone_subscriptions(&db_connection, -42, group_chat, 20, "en"),
+                "You can't copy subscriptions of a chat to the same chat".to_string()
+            );
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn set_link_preview_toggles_link_preview() {
+        let db_connection = db::establish_connection();
+        let new_chat = NewTelegramChat {
+            id: 42,
+            kind: "private".to_string(),
+            username: Some("Username".to_string()),
+            first_name: Some("First".to_string()),
+            last_name: Some("Last".to_string()),
+            title: None,
+        };
+
+        db_connection.test_transaction::<(), super::SubscriptionError, _>(|| {
+            let chat = telegram::create_chat(&db_connection, new_chat).unwrap();
+            let feed =
+                feeds::create(&db_connection, "Link".to_string(), "rss".to_string()).unwrap();
+            let new_subscription = NewTelegramSubscription {
+                chat_id: chat.id,
+                feed_id: feed.id,
+                user_id: None,
+            };
+            telegram::create_subscription(&db_connection, new_subscription).unwrap();
+
+            assert_eq!(
+                super::set_link_preview(
+                    &db_connection,
+                    chat.id,
+                    "Link".to_string(),
+                    "off".to_string()
+                ),
+                Ok(false)
+            );
+            assert!(
+                !telegram::find_subscription(&db_connection, new_subscription)
+                    .unwrap()
+                    .link_preview
+            );
+            assert_eq!(
+                super::set_link_preview(
+                    &db_connection,
+                    chat.id,
+                    "Link".to_string(),
+                    "ON".to_string()
+                ),
+                Ok(true)
+            );
+            assert_eq!(
+                super::set_link_preview(
+                    &db_connection,
+                    chat.id,
+                    "Link".to_string(),
+                    "yes".to_string()
+                ),
+                Err("link_preview_usage")
+            );
+            assert_eq!(
+                super::set_link_preview(
+                    &db_connection,
+                    chat.id,
+                    "Other".to_string(),
+                    "on".to_string()
+                ),
+                Err("subscription_not_found")
+            );
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn set_throttle_sets_and_disables_interval() {
+        let db_connection = db::establish_connection();
+        let new_chat = NewTelegramChat {
+            id: 42,
+            kind: "private".to_string(),
+            username: Some("Username".to_string()),
+            first_name: Some("First".to_string()),
+            last_name: Some("Last".to_string()),
+            title: None,
+        };
+
+        db_connection.test_transaction::<(), super::SubscriptionError, _>(|| {
+            let chat = telegram::create_chat(&db_connection, new_chat).unwrap();
+            let feed =
+                feeds::create(&db_connection, "Link".to_string(), "rss".to_string()).unwrap();
+            let new_subscription = NewTelegramSubscription {
+                chat_id: chat.id,
+                feed_id: feed.id,
+                user_id: None,
+            };
+            telegram::create_subscription(&db_connection, new_subscription).unwrap();
+
+            assert_eq!(
+                super::set_throttle(
+                    &db_connection,
+                    chat.id,
+                    "Link".to_string(),
+                    "300".to_string(),
+                    "en"
+                ),
+                Ok(300)
+            );
+            assert_eq!(
+                telegram::find_subscription(&db_connection, new_subscription)
+                    .unwrap()
+                    .min_delivery_interval_secs,
+                Some(300)
+            );
+
+            assert_eq!(
+                super::set_throttle(
+                    &db_connection,
+                    chat.id,
+                    "Link".to_string(),
+                    "0".to_string(),
+                    "en"
+                ),
+                Ok(0)
+            );
+            assert_eq!(
+                telegram::find_subscription(&db_connection, new_subscription)
+                    .unwrap()
+                    .min_delivery_interval_secs,
+                None
+            );
+
+            assert_eq!(
+                super::set_throttle(
+                    &db_connection,
+                    chat.id,
+                    "Link".to_string(),
+                    "-5".to_string(),
+                    "en"
+                ),
+                Err("The interval must be a number of seconds from 0 to 86400".to_string())
+            );
+            assert_eq!(
+                super::set_throttle(
+                    &db_connection,
+                    chat.id,
+                    "Other".to_string(),
+                    "60".to_string(),
+                    "en"
+                ),
+                Err("Subscription does not exist".to_string())
+            );
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn delete_subscription_distinguishes_missing_chat_feed_and_subscription() {
+        let db_connection = db::establish_connection();
+        let new_chat = NewTelegramChat {
+            id: 42,
+            kind: "private".to_string(),
+            username: Some("Username".to_string()),
+            first_name: Some("First".to_string()),
+            last_name: Some("Last".to_string()),
+            title: None,
+        };
+
+        db_connection.test_transaction::<(), super::SubscriptionError, _>(|| {
+            assert_eq!(
+                super::delete_subscription(&db_connection, new_chat.id, "Link".to_string()),
+                Err(super::DeleteSubscriptionError::ChatNotFound)
+            );
+
+            let chat = telegram::create_chat(&db_connection, new_chat).unwrap();
+
+            assert_eq!(
+                super::delete_subscription(&db_connection, chat.id, "Link".to_string()),
+                Err(super::DeleteSubscriptionError::FeedNotFound)
+            );
+
+            feeds::create(&db_connection, "Link".to_string(), "rss".to_string()).unwrap();
+
+            assert_eq!(
+                super::delete_subscription(&db_connection, chat.id, "Link".to_string()),
+                Err(super::DeleteSubscriptionError::SubscriptionNotFound)
+            );
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn delete_subscription_deletes_subscription_with_similar_link() {
+        let db_connection = db::establish_connection();
+        let new_chat = NewTelegramChat {
+            id: 42,
+            kind: "private".to_string(),
+            username: Some("Username".to_string()),
+            first_name: Some("First".to_string()),
+            last_name: Some("Last".to_string()),
+            title: None,
+        };
+
+        db_connection.test_transaction::<(), super::SubscriptionError, _>(|| {
+            let chat = telegram::create_chat(&db_connection, new_chat).unwrap();
+
+            for link in vec!["https://www.example.com/rss", "http://example.com/rss"] {
+                let feed =
+                    feeds::create(&db_connection, link.to_string(), "rss".to_string()).unwrap();
+                telegram::create_subscription(
+                    &db_connection,
+                    NewTelegramSubscription {
+                        chat_id: chat.id,
+                        feed_id: feed.id,
+                        user_id: None,
+                    },
+                )
+                .unwrap();
+            }
+
+            assert_eq!(
+                super::delete_subscription(
+                    &db_connection,
+                    chat.id,
+                    "https://example.com/rss".to_string()
+                ),
+                Err(super::DeleteSubscriptionError::AmbiguousLink(vec![
+                    "https://www.example.com/rss".to_string(),
+                    "http://example.com/rss".to_string()
+                ]))
+            );
+
+            assert_eq!(
+                super::delete_subscription(
+                    &db_connection,
+                    chat.id,
+                    "http://example.com/rss".to_string()
+                ),
+                Ok("http://example.com/rss".to_string())
+            );
+
+            assert_eq!(
+                super::delete_subscription(
+                    &db_connection,
+                    chat.id,
+                    "https://example.com/rss/".to_string()
+                ),
+                Ok("https://www.example.com/rss".to_string())
+            );
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn format_opml_formats_feeds_as_opml() {
+        let feeds = vec![
+            Feed {
+                id: 1,
+                title: Some("Rust & Diesel".to_string()),
+                link: "https://example.com/rss?a=1&b=2".to_string(),
+                error: None,
+                description: None,
+                synced_at: None,
+                created_at: db::current_time(),
+                updated_at: db::current_time(),
+                feed_type: "rss".to_string(),
+                failures_count: 0,
+                dead: false,
+                etag: None,
+                last_modified: None,
+                credentials_key: "".to_string(),
+            },
+            Feed {
+                id: 2,
+                title: None,
+                link: "https://example.com/atom".to_string(),
+                error: None,
+                description: None,
+                synced_at: None,
+                created_at: db::current_time(),
+                updated_at: db::current_time(),
+                feed_type: "atom".to_string(),
+                failures_count: 0,
+                dead: false,
+                etag: None,
+                last_modified: None,
+                credentials_key: "".to_string(),
+            },
+        ];
+
+        let expected = vec![
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>",
+            "<opml version=\"2.0\">",
+            "  <head>",
+            "    <title>El Monitorro subscriptions</title>",
+            "  </head>",
+            "  <body>",
+            "    <outline type=\"rss\" text=\"Rust &amp; Diesel\" title=\"Rust &amp; Diesel\" xmlUrl=\"https://example.com/rss?a=1&amp;b=2\"/>",
+            "    <outline type=\"rss\" text=\"https://example.com/atom\" title=\"https://example.com/atom\" xmlUrl=\"https://example.com/atom\"/>",
+            "  </body>",
+            "</opml>",
+        ]
+        .join("\n");
+
+        assert_eq!(super::format_opml(&feeds), expected);
+    }
+
+    #[test]
+    fn parse_opml_parses_feed_urls() {
+        let opml = vec![
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>",
+            "<opml version=\"2.0\">",
+            "  <head><title>Feeds</title></head>",
+            "  <body>",
+            "    <outline text=\"Tech\">",
+            "      <outline type=\"rss\" text=\"One\" xmlUrl=\"https://example.com/rss?a=1&amp;b=2\"/>",
+            "      <outline type=\"rss\" text=\"Two\" xmlUrl=\"https://example.com/atom\"></outline>",
+            "    </outline>",
+            "    <outline type=\"rss\" text=\"Duplicate\" xmlUrl=\"https://example.com/atom\"/>",
+            "  </body>",
+            "</opml>",
+        ]
+        .join("\n");
+
+        assert_eq!(
+            super::parse_opml(&opml),
+            Ok(vec![
+                "https://example.com/rss?a=1&b=2".to_string(),
+                "https://example.com/atom".to_string()
+            ])
+        );
+    }
+
+    #[test]
+    fn parse_opml_fails_to_parse_invalid_document() {
+        assert!(super::parse_opml("<opml><body><outline xmlUrl=></body>").is_err());
+    }
+
+    #[test]
+    fn import_opml_does_not_subscribe_over_chat_limit() {
+        let db_connection = db::establish_connection();
+        let new_chat = NewTelegramChat {
+            id: 42,
+            kind: "private".to_string(),
+            username: Some("Username".to_string()),
+            first_name: Some("First".to_string()),
+            last_name: Some("Last".to_string()),
+            title: None,
+        };
+        let opml = vec![
+            "<opml version=\"2.0\">",
+            "  <body>",
+            "    <outline type=\"rss\" xmlUrl=\"https://example.invalid/one\"/>",
+            "    <outline type=\"rss\" xmlUrl=\"https://example.invalid/two\"/>",
+            "  </body>",
+            "</opml>",
+        ]
+        .join("\n");
+
+        db_connection.test_transaction::<(), super::SubscriptionError, _>(|| {
+            let chat = telegram::create_chat(&db_connection, new_chat.clone()).unwrap();
+            telegram::set_max_subscriptions(&db_connection, &chat, 0).unwrap();
+
+            let results = super::import_opml(
+                &db_connection,
+                new_chat,
+                &opml,
+                super::DEFAULT_SUBSCRIPTION_LIMIT,
+            )
+            .unwrap();
+
+            assert_eq!(results.len(), 2);
+
+            for (_, result) in results {
+                assert_eq!(
+                    result.err(),
+                    Some(super::SubscriptionError::SubscriptionCountLimit)
+                );
+            }
+
+            assert_eq!(
+                telegram::count_subscriptions_for_chat(&db_connection, 42),
+                0
+            );
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn format_import_summary_counts_results() {
+        let results = vec![
+            (
+                "Link1".to_string(),
+                Err(super::SubscriptionError::SubscriptionAlreadyExists(
+                    "2020-08-08 10:15:00 +00:00".to_string(),
+                )),
+            ),
+            (
+                "Link2".to_string(),
+                Err(super::SubscriptionError::UrlIsNotFeed),
+            ),
+            (
+                "Link3".to_string(),
+                Err(super::SubscriptionError::SubscriptionCountLimit),
+            ),
+        ];
+
+        assert_eq!(
+            super::format_import_summary(&results, "en"),
+            "Subscribed to 0, skipped 1 duplicates, 1 invalid, 1 over the subscription limit"
+                .to_string()
+        );
+    }
+
+    #[test]
+    fn render_item_renders_default_template() {
+        let item = build_feed_item();
+
+        assert_eq!(
+            super::render_item(
+                None,
+                &Some("Feed".to_string()),
+                &item,
+                "2020-08-10",
+                300,
+                None,
+                None
+            ),
+            "Feed\n\nTitle\n\n2020-08-10\n\nhttps://example.com/item".to_string()
+        );
+        assert_eq!(
+            super::render_item(None, &None, &item, "2020-08-10", 300, None, None),
+            "Title\n\n2020-08-10\n\nhttps://example.com/item".to_string()
+        );
+    }
+
+    #[test]
+    fn render_item_renders_custom_template() {
+        let mut item = build_feed_item();
+        item.title = "Title with {{link}}".to_string();
+
+        assert_eq!(
+            super::render_item(
+                Some("{{ title }}: {{description}} {{unknown}} {{link}} {{"),
+                &None,
+                &item,
+                "2020-08-10",
+                300,
+                None,
+                None
+            ),
+            "Title with {{link}}: Description {{unknown}} https://example.com/item {{".to_string()
+        );
+    }
 
-pub fn set_timezone(db_connection: &PgConnection, chat_id: i64, data: String) -> Result<(), &str> {
-    let offset = validate_offset(data)?;
+    #[test]
+    fn render_item_truncates_description() {
+        let mut item = build_feed_item();
+        item.description = Some("<p>Long <b>description</b></p>".to_string());
 
-    match telegram::find_chat(db_connection, chat_id) {
-        None => Err(
-            "You'll be able to set your timezone only after you'll have at least one subscription",
-        ),
-        Some(chat) => match telegram::set_utc_offset_minutes(db_connection, &chat, offset) {
-            Ok(_) => Ok(()),
-            Err(_) => Err("Failed to set your timezone"),
-        },
+        assert_eq!(
+            super::render_item(
+                Some("{{description}}"),
+                &None,
+                &item,
+                "2020-08-10",
+                8,
+                None,
+                None
+            ),
+            "Long des...".to_string()
+        );
     }
-}
 
-pub fn get_timezone(db_connection: &PgConnection, chat_id: i64) -> String {
-    match telegram::find_chat(db_connection, chat_id) {
-        None => "You don't have timezone set".to_string(),
-        Some(chat) => match chat.utc_offset_minutes {
-            None => "You don't have timezone set".to_string(),
-            Some(value) => format!("Your timezone offset is {} minutes", value),
-        },
+    #[test]
+    fn render_item_prepends_prefix() {
+        let item = build_feed_item();
+
+        assert_eq!(
+            super::render_item(
+                Some("{{title}}"),
+                &None,
+                &item,
+                "2020-08-10",
+                300,
+                Some("📰"),
+                None
+            ),
+            "📰 Title".to_string()
+        );
     }
-}
 
-fn validate_offset(offset_string: String) -> Result<i32, &'static str> {
-    let offset = match offset_string.parse::<i32>() {
-        Ok(result) => result,
-        Err(_) => return Err("Passed value is not a number"),
-    };
+    #[test]
+    fn render_item_delivers_full_text_when_it_is_on() {
+        let mut item = build_feed_item();
+        item.description = Some("<p>Long <b>description</b></p>".to_string());
 
-    if offset % 30 != 0 {
-        return Err("Offset must be divisible by 30");
+        assert_eq!(
+            super::render_item(None, &None, &item, "2020-08-10", 8, None, Some(true)),
+            "Title\n\n2020-08-10\n\nLong description\n\nhttps://example.com/item".to_string()
+        );
     }
 
-    if offset < -720 || offset > 840 {
-        return Err("Offset must be >= -720 (UTC -12) and <= 840 (UTC +14)");
+    #[test]
+    fn render_item_delivers_only_title_and_link_when_full_text_is_off() {
+        let item = build_feed_item();
+
+        assert_eq!(
+            super::render_item(
+                Some("{{description}} {{link}}"),
+                &Some("Feed".to_string()),
+                &item,
+                "2020-08-10",
+                300,
+                None,
+                Some(false)
+            ),
+            "Title\n\nhttps://example.com/item".to_string()
+        );
     }
 
-    Ok(offset)
-}
+    #[test]
+    fn escape_markdown_v2_escapes_reserved_characters() {
+        assert_eq!(super::escape_markdown_v2("Plain text"), "Plain text");
+        assert_eq!(
+            super::escape_markdown_v2("_*[]()~`>#+-=|{}.!"),
+            "\\_\\*\\[\\]\\(\\)\\~\\`\\>\\#\\+\\-\\=\\|\\{\\}\\.\\!"
+        );
+        assert_eq!(
+            super::escape_markdown_v2("C:\\path\\to"),
+            "C:\\\\path\\\\to"
+        );
+        assert_eq!(
+            super::escape_markdown_v2("Rust 1.45 (stable) — 50% faster!"),
+            "Rust 1\\.45 \\(stable\\) — 50% faster\\!"
+        );
+        assert_eq!(
+            super::escape_markdown_v2("https://example.com/a_b?c=d#e"),
+            "https://example\\.com/a\\_b?c\\=d\\#e"
+        );
+    }
 
-pub fn delete_subscription(
-    db_connection: &PgConnection,
-    chat_id: i64,
-    link: String,
-) -> Result<(), DeleteSubscriptionError> {
-    let feed = match feeds::find_by_link(db_connection, link) {
-        Some(feed) => feed,
-        None => return Err(DeleteSubscriptionError::FeedNotFound),
-    };
+    #[test]
+    fn escape_markdown_v2_url_escapes_only_parenthesis_and_backslash() {
+        assert_eq!(
+            super::escape_markdown_v2_url("https://en.wikipedia.org/wiki/Rust_(language)"),
+            "https://en.wikipedia.org/wiki/Rust_(language\\)"
+        );
+        assert_eq!(
+            super::escape_markdown_v2_url("https://example.com/a\\b"),
+            "https://example.com/a\\\\b"
+        );
+    }
 
-    let chat = match telegram::find_chat(db_connection, chat_id) {
-        Some(chat) => chat,
-        None => return Err(DeleteSubscriptionError::ChatNotFound),
-    };
+    #[test]
+    fn render_markdown_item_escapes_placeholders() {
+        let mut item = build_feed_item();
+        item.title = "Rust 1.45 [stable]".to_string();
+        item.link = "https://example.com/rust_(1.45)".to_string();
 
-    let telegram_subscription = NewTelegramSubscription {
-        chat_id: chat.id,
-        feed_id: feed.id,
-    };
+        assert_eq!(
+            super::render_markdown_item(
+                None,
+                &Some("This Week in Rust!".to_string()),
+                &item,
+                "2020-08-10",
+                300,
+                Some("[News]"),
+                None
+            ),
+            "\\[News\\] *This Week in Rust\\!*\n\n\
+             [Rust 1\\.45 \\[stable\\]](https://example.com/rust_(1.45\\))\n\n\
+             2020\\-08\\-10"
+                .to_string()
+        );
+        assert_eq!(
+            super::render_markdown_item(
+                Some("_{{title}}_ {{link}} {{unknown}}"),
+                &None,
+                &item,
+                "2020-08-10",
+                300,
+                None,
+                None
+            ),
+            "_Rust 1\\.45 \\[stable\\]_ https://example\\.com/rust\\_\\(1\\.45\\) {{unknown}}"
+                .to_string()
+        );
+        assert_eq!(
+            super::render_markdown_item(None, &None, &item, "2020-08-10", 300, None, Some(false)),
+            "[Rust 1\\.45 \\[stable\\]](https://example.com/rust_(1.45\\))".to_string()
+        );
+    }
 
-    let _subscription = match telegram::find_subscription(db_connection, telegram_subscription) {
-        Some(subscription) => subscription,
-        None => return Err(DeleteSubscriptionError::SubscriptionNotFound),
-    };
+    #[test]
+    fn render_html_item_escapes_placeholders() {
+        let mut item = build_feed_item();
+        item.title = "Rust 1.45 &lt;stable&gt; & more".to_string();
+        item.link = "https://example.com/?a=1&b=2".to_string();
+        item.description =
+            Some("<p>Read <strong>more</strong> &amp; <span>less</span></p>".to_string());
 
-    match telegram::remove_subscription(db_connection, telegram_subscription) {
-        Ok(_) => Ok(()),
-        _ => Err(DeleteSubscriptionError::DbError),
+        assert_eq!(
+            super::render_html_item(
+                None,
+                &Some("This Week in Rust".to_string()),
+                &item,
+                "2020-08-10",
+                300,
+                Some("<News>"),
+                None
+            ),
+            "&lt;News&gt; <b>This Week in Rust</b>\n\n\
+             <a href=\"https://example.com/?a=1&amp;b=2\">Rust 1.45 &lt;stable&gt; &amp; more</a>\n\n\
+             2020-08-10"
+                .to_string()
+        );
+        assert_eq!(
+            super::render_html_item(
+                Some("<i>{{description}}</i>"),
+                &None,
+                &item,
+                "2020-08-10",
+                300,
+                None,
+                None
+            ),
+            "<i>Read more &amp; less</i>".to_string()
+        );
+        assert_eq!(
+            super::render_html_item(None, &None, &item, "2020-08-10", 300, None, Some(false)),
+            "<a href=\"https://example.com/?a=1&amp;b=2\">Rust 1.45 &lt;stable&gt; &amp; more</a>"
+                .to_string()
+        );
     }
-}
 
-pub fn create_subscription(
-    db_connection: &PgConnection,
-    new_chat: NewTelegramChat,
-    rss_url: Option<String>,
-) -> Result<TelegramSubscription, SubscriptionError> {
-    if rss_url.is_none() {
-        return Err(SubscriptionError::RssUrlNotProvided);
+    #[test]
+    fn render_html_item_keeps_formatting_of_full_text() {
+        let mut item = build_feed_item();
+        item.description =
+            Some("<p>Read <strong>more</strong> &amp; <span>less</span></p>".to_string());
+
+        assert_eq!(
+            super::render_html_item(
+                None,
+                &Some("Feed".to_string()),
+                &item,
+                "2020-08-10",
+                300,
+                None,
+                Some(true)
+            ),
+            "<b>Feed</b>\n\n<a href=\"https://example.com/item\">Title</a>\n\n\
+             2020-08-10\n\nRead <b>more</b> &amp; less"
+                .to_string()
+        );
     }
 
-    let url = rss_url.unwrap();
+    #[test]
+    fn append_author_adds_author_when_it_is_known() {
+        let mut item = build_feed_item();
 
-    let feed_type = validate_rss_url(&url)?;
+        assert_eq!(
+            super::append_author("Title".to_string(), &item, MessageFormat::PlainText),
+            "Title".to_string()
+        );
 
-    db_connection.transaction::<TelegramSubscription, SubscriptionError, _>(|| {
-        let chat = telegram::create_chat(db_connection, new_chat).unwrap();
-        let feed = feeds::create(db_connection, url, feed_type).unwrap();
+        item.author = Some("Jane Doe (jane.doe)".to_string());
 
-        let new_telegram_subscription = NewTelegramSubscription {
-            chat_id: chat.id,
-            feed_id: feed.id,
-        };
+        assert_eq!(
+            super::append_author("Title".to_string(), &item, MessageFormat::PlainText),
+            "Title\n\n✍️ Jane Doe (jane.doe)".to_string()
+        );
+        assert_eq!(
+            super::append_author("Title".to_string(), &item, MessageFormat::MarkdownV2),
+            "Title\n\n✍️ Jane Doe \\(jane\\.doe\\)".to_string()
+        );
 
-        check_if_subscription_exists(db_connection, new_telegram_subscription)?;
-        check_number_of_subscriptions(db_connection, chat.id)?;
+        item.author = Some("Tom &amp; Jerry".to_string());
 
-        let subscription =
-            telegram::create_subscription(db_connection, new_telegram_subscription).unwrap();
+        assert_eq!(
+            super::append_author("Title".to_string(), &item, MessageFormat::Html),
+            "Title\n\n✍️ Tom &amp; Jerry".to_string()
+        );
+    }
 
-        Ok(subscription)
-    })
-}
+    #[test]
+    fn validate_item_prefix_rejects_long_and_empty_prefixes() {
+        assert_eq!(super::validate_item_prefix(" 📰 "), Ok("📰".to_string()));
+        assert_eq!(
+            super::validate_item_prefix("[News feed]"),
+            Err("prefix_too_long")
+        );
+        assert_eq!(super::validate_item_prefix("  "), Err("prefix_missing"));
+    }
 
-fn validate_rss_url(rss_url: &str) -> Result<String, SubscriptionError> {
-    match Url::parse(rss_url) {
-        Ok(_) => match reader::validate_rss_url(rss_url) {
-            Ok(feed_type) => Ok(feed_type),
-            _ => Err(SubscriptionError::UrlIsNotFeed),
-        },
-        _ => Err(SubscriptionError::InvalidUrl),
+    #[test]
+    fn truncate_description_strips_tags_and_collapses_whitespace() {
+        assert_eq!(
+            super::truncate_description(
+                "<p>Hello,\n\n  <a href=\"https://example.com\">world</a></p>",
+                100
+            ),
+            "Hello, world".to_string()
+        );
+        assert_eq!(
+            super::truncate_description("Line<br/>break", 100),
+            "Line break".to_string()
+        );
     }
-}
 
-fn check_if_subscription_exists(
-    connection: &PgConnection,
-    subscription: NewTelegramSubscription,
-) -> Result<(), SubscriptionError> {
-    match telegram::find_subscription(connection, subscription) {
-        None => Ok(()),
-        Some(_) => Err(SubscriptionError::SubscriptionAlreadyExists),
+    #[test]
+    fn sanitize_html_decodes_entities() {
+        assert_eq!(
+            super::sanitize_html(
+                "Tom &amp; Jerry &lt;3 &quot;cheese&quot; &#39;n&#x27; crackers&nbsp;&hellip; &#x1F9C0;",
+                HtmlMode::PlainText
+            ),
+            "Tom & Jerry <3 \"cheese\" 'n' crackers … 🧀".to_string()
+        );
+        assert_eq!(
+            super::sanitize_html("AT&T &unknown; &amp", HtmlMode::PlainText),
+            "AT&T &unknown; &amp".to_string()
+        );
+        assert_eq!(
+            super::sanitize_html("Tom &amp; Jerry &lt;3", HtmlMode::TelegramHtml),
+            "Tom &amp; Jerry &lt;3".to_string()
+        );
     }
-}
 
-fn check_number_of_subscriptions(
-    connection: &PgConnection,
-    chat_id: i64,
-) -> Result<(), SubscriptionError> {
-    let result = telegram::count_subscriptions_for_chat(connection, chat_id);
+    #[test]
+    fn sanitize_html_drops_tags_and_collapses_whitespace() {
+        let html = "<div class=\"post\">\n  <p>Hello,\n <b>world</b>!</p><p>Second<br/>line</p>\n\
+                    <img src=\"image.png\"/><a href=\"https://example.com\">Link</a> 5 < 6</div>";
 
-    if result <= 20 {
-        Ok(())
-    } else {
-        Err(SubscriptionError::SubscriptionCountLimit)
+        assert_eq!(
+            super::sanitize_html(html, HtmlMode::PlainText),
+            "Hello, world! Second line Link 5 < 6".to_string()
+        );
     }
-}
-
-#[cfg(test)]
-mod tests {
-    use crate::db;
-    use crate::db::feeds;
-    use crate::db::telegram;
-    use crate::db::telegram::NewTelegramChat;
-    use diesel::connection::Connection;
 
     #[test]
-    fn create_subscription_creates_new_subscription() {
-        let db_connection = db::establish_connection();
-        let new_chat = NewTelegramChat {
-            id: 42,
-            kind: "private".to_string(),
-            username: Some("Username".to_string()),
-            first_name: Some("First".to_string()),
-            last_name: Some("Last".to_string()),
-            title: None,
-        };
+    fn sanitize_html_converts_to_telegram_html() {
+        let html = "<p><strong>Bold</strong> <em>italic <del>struck</del></em> \
+                    <a href='https://example.com/?a=1&amp;b=2' target=_blank>link</a> \
+                    <span>span</span> <code>x < y</code> <b>unclosed";
 
-        db_connection.test_transaction::<(), super::SubscriptionError, _>(|| {
-            let subscription = super::create_subscription(
-                &db_connection,
-                new_chat,
-                Some("https://feeds.npr.org/1004/feed.json".to_string()),
-            )
-            .unwrap();
+        assert_eq!(
+            super::sanitize_html(html, HtmlMode::TelegramHtml),
+            "<b>Bold</b> <i>italic <s>struck</s></i> \
+             <a href=\"https://example.com/?a=1&amp;b=2\">link</a> \
+             span <code>x &lt; y</code> <b>unclosed</b>"
+                .to_string()
+        );
+        assert_eq!(
+            super::sanitize_html("<i>one<b>two</i>three</b>", HtmlMode::TelegramHtml),
+            "<i>one<b>two</b></i>three".to_string()
+        );
+    }
 
-            assert!(feeds::find(&db_connection, subscription.feed_id).is_some());
-            assert!(telegram::find_chat(&db_connection, subscription.chat_id).is_some());
+    #[test]
+    fn truncate_description_does_not_split_multibyte_characters() {
+        assert_eq!(
+            super::truncate_description("<i>Привет</i>, мир 👋🏽", 8),
+            "Привет, ...".to_string()
+        );
+        assert_eq!(
+            super::truncate_description("日本語のテキスト", 3),
+            "日本語...".to_string()
+        );
+        assert_eq!(super::truncate_description("👋🏽👋🏽", 1), "👋...".to_string());
+        assert_eq!(
+            super::truncate_description("Короткий", 8),
+            "Короткий".to_string()
+        );
+    }
 
-            Ok(())
-        });
+    fn build_feed_item() -> FeedItem {
+        FeedItem {
+            feed_id: 1,
+            title: "Title".to_string(),
+            description: Some("Description".to_string()),
+            link: "https://example.com/item".to_string(),
+            author: None,
+            guid: None,
+            publication_date: db::current_time(),
+            created_at: db::current_time(),
+            updated_at: db::current_time(),
+            content_hash: None,
+        }
     }
 
     #[test]
-    fn create_subscription_fails_to_create_chat_when_rss_url_is_invalid() {
-        let db_connection = db::establish_connection();
-        let new_chat = NewTelegramChat {
-            id: 42,
-            kind: "private".to_string(),
-            username: Some("Username".to_string()),
-            first_name: Some("First".to_string()),
-            last_name: Some("Last".to_string()),
-            title: None,
-        };
+    fn format_search_results_notes_when_more_items_exist() {
+        let items = (1..=11)
+            .map(|index| TelegramDeliveredItem {
+                chat_id: 1,
+                feed_id: 1,
+                title: format!("Title{}", index),
+                link: format!("Link{}", index),
+                published_at: db::current_time(),
+                created_at: db::current_time(),
+                updated_at: db::current_time(),
+                user_id: None,
+            })
+            .collect::<Vec<TelegramDeliveredItem>>();
 
-        db_connection.test_transaction::<(), super::SubscriptionError, _>(|| {
-            let result =
-                super::create_subscription(&db_connection, new_chat, Some("11".to_string()));
-            assert_eq!(result.err(), Some(super::SubscriptionError::InvalidUrl));
+        assert_eq!(
+            super::format_search_results(&items[..2], "en"),
+            "1. Title1\nLink1\n\n2. Title2\nLink2".to_string()
+        );
 
-            Ok(())
-        });
+        let result = super::format_search_results(&items, "en");
+
+        assert!(result.contains("10. Title10\nLink10"));
+        assert!(!result.contains("Title11"));
+        assert!(result
+            .ends_with("Only the latest 10 items are shown. Refine your search to find others"));
     }
 
     #[test]
-    fn create_subscription_fails_to_create_chat_when_rss_url_is_not_rss() {
+    fn parse_digest_arguments_parses_flag_and_hour() {
+        assert_eq!(
+            super::parse_digest_arguments("on".to_string()),
+            Ok((true, None))
+        );
+        assert_eq!(
+            super::parse_digest_arguments("off".to_string()),
+            Ok((false, None))
+        );
+        assert_eq!(
+            super::parse_digest_arguments("on 18".to_string()),
+            Ok((true, Some(18)))
+        );
+        assert!(super::parse_digest_arguments("on 24".to_string()).is_err());
+        assert!(super::parse_digest_arguments("off 18".to_string()).is_err());
+        assert!(super::parse_digest_arguments("".to_string()).is_err());
+    }
+
+    #[test]
+    fn set_track_per_user_is_available_only_in_groups() {
         let db_connection = db::establish_connection();
-        let new_chat = NewTelegramChat {
+        let private_chat = NewTelegramChat {
             id: 42,
             kind: "private".to_string(),
             username: Some("Username".to_string()),
@@ -252,21 +6362,43 @@ mod tests {
             last_name: Some("Last".to_string()),
             title: None,
         };
+        let group_chat = NewTelegramChat {
+            id: -42,
+            kind: "group".to_string(),
+            title: Some("Group".to_string()),
+            ..private_chat.clone()
+        };
 
         db_connection.test_transaction::<(), super::SubscriptionError, _>(|| {
-            let result = super::create_subscription(
-                &db_connection,
-                new_chat,
-                Some("http://google.com".to_string()),
+            assert_eq!(
+                super::set_track_per_user(&db_connection, private_chat, "on".to_string(), "en"),
+                "Per-user tracking is available only in groups"
+            );
+            assert_eq!(
+                super::set_track_per_user(
+                    &db_connection,
+                    group_chat.clone(),
+                    "yes".to_string(),
+                    "en"
+                ),
+                "Use /track_per_user on or /track_per_user off"
+            );
+            assert_eq!(
+                super::set_track_per_user(&db_connection, group_chat, "on".to_string(), "en"),
+                "New subscriptions will remember the group member who subscribed"
+            );
+            assert!(
+                telegram::find_chat(&db_connection, -42)
+                    .unwrap()
+                    .track_per_user
             );
-            assert_eq!(result.err(), Some(super::SubscriptionError::UrlIsNotFeed));
 
             Ok(())
         });
     }
 
     #[test]
-    fn create_subscription_fails_to_create_a_subscription_if_it_already_exists() {
+    fn set_backfill_order_stores_preference() {
         let db_connection = db::establish_connection();
         let new_chat = NewTelegramChat {
             id: 42,
@@ -278,24 +6410,27 @@ mod tests {
         };
 
         db_connection.test_transaction::<(), super::SubscriptionError, _>(|| {
-            let subscription = super::create_subscription(
-                &db_connection,
-                new_chat.clone(),
-                Some("https://feeds.npr.org/1004/feed.json".to_string()),
-            )
-            .unwrap();
-
-            assert!(feeds::find(&db_connection, subscription.feed_id).is_some());
-            assert!(telegram::find_chat(&db_connection, subscription.chat_id).is_some());
-
-            let result = super::create_subscription(
-                &db_connection,
-                new_chat,
-                Some("https://feeds.npr.org/1004/feed.json".to_string()),
+            assert_eq!(
+                super::set_backfill_order(&db_connection, new_chat.clone(), "latest".to_string(), "en"),
+                "Use /set_backfill_order oldest or /set_backfill_order newest"
             );
             assert_eq!(
-                result.err(),
-                Some(super::SubscriptionError::SubscriptionAlreadyExists)
+                super::set_backfill_order(&db_connection, new_chat.clone(), "Newest".to_string(), "en"),
+                "The first items of new subscriptions will be delivered from the newest to the oldest"
+            );
+            assert!(
+                telegram::find_chat(&db_connection, 42)
+                    .unwrap()
+                    .backfill_newest_first
+            );
+            assert_eq!(
+                super::set_backfill_order(&db_connection, new_chat, "oldest".to_string(), "en"),
+                "The first items of new subscriptions will be delivered from the oldest to the newest"
+            );
+            assert!(
+                !telegram::find_chat(&db_connection, 42)
+                    .unwrap()
+                    .backfill_newest_first
             );
 
             Ok(())
@@ -303,8 +6438,7 @@ mod tests {
     }
 
     #[test]
-    #[ignore]
-    fn create_subscription_fails_to_create_a_subscription_if_it_already_has_5_suscriptions() {
+    fn set_author_stores_preference() {
         let db_connection = db::establish_connection();
         let new_chat = NewTelegramChat {
             id: 42,
@@ -316,36 +6450,186 @@ mod tests {
         };
 
         db_connection.test_transaction::<(), super::SubscriptionError, _>(|| {
-            for rss_url in vec![
-                "https://rss.nytimes.com/services/xml/rss/nyt/HomePage.xml",
-                "https://www.eurekalert.org/rss/technology_engineering.xml",
-                "https://www.sciencedaily.com/rss/matter_energy/engineering.xml",
-                "https://www.france24.com/fr/france/rss",
-                "http://feeds.reuters.com/reuters/technologyNews",
-            ] {
-                assert!(super::create_subscription(
-                    &db_connection,
-                    new_chat.clone(),
-                    Some(rss_url.to_string()),
-                )
-                .is_ok());
-            }
-
-            let result = super::create_subscription(
-                &db_connection,
-                new_chat,
-                Some("http://www.engadget.com/rss.xml".to_string()),
+            assert_eq!(
+                super::set_author(&db_connection, new_chat.clone(), "yes".to_string(), "en"),
+                "Use /set_author on or /set_author off"
             );
-
             assert_eq!(
-                result.err(),
-                Some(super::SubscriptionError::SubscriptionCountLimit)
+                super::set_author(&db_connection, new_chat.clone(), "ON".to_string(), "en"),
+                "Delivered items will include their authors"
+            );
+            assert!(telegram::find_chat(&db_connection, 42).unwrap().show_author);
+            assert_eq!(
+                super::set_author(&db_connection, new_chat, "off".to_string(), "en"),
+                "Delivered items will not include their authors"
             );
+            assert!(!telegram::find_chat(&db_connection, 42).unwrap().show_author);
 
             Ok(())
         });
     }
 
+    #[test]
+    fn parse_quiet_hours_arguments_parses_hours() {
+        assert_eq!(
+            super::parse_quiet_hours_arguments("22 7".to_string()),
+            Ok(Some((22, 7)))
+        );
+        assert_eq!(
+            super::parse_quiet_hours_arguments("off".to_string()),
+            Ok(None)
+        );
+        assert!(super::parse_quiet_hours_arguments("22 24".to_string()).is_err());
+        assert!(super::parse_quiet_hours_arguments("7 7".to_string()).is_err());
+        assert!(super::parse_quiet_hours_arguments("22".to_string()).is_err());
+        assert!(super::parse_quiet_hours_arguments("".to_string()).is_err());
+    }
+
+    #[test]
+    fn is_within_quiet_hours_checks_hours_of_the_same_day() {
+        assert!(super::is_within_quiet_hours(13, 15, 13));
+        assert!(super::is_within_quiet_hours(13, 15, 14));
+        assert!(!super::is_within_quiet_hours(13, 15, 15));
+        assert!(!super::is_within_quiet_hours(13, 15, 12));
+    }
+
+    #[test]
+    fn is_within_quiet_hours_wraps_around_midnight() {
+        assert!(super::is_within_quiet_hours(22, 7, 22));
+        assert!(super::is_within_quiet_hours(22, 7, 23));
+        assert!(super::is_within_quiet_hours(22, 7, 0));
+        assert!(super::is_within_quiet_hours(22, 7, 6));
+        assert!(!super::is_within_quiet_hours(22, 7, 7));
+        assert!(!super::is_within_quiet_hours(22, 7, 12));
+        assert!(!super::is_within_quiet_hours(22, 7, 21));
+    }
+
+    #[test]
+    fn in_quiet_hours_uses_chat_timezone() {
+        let mut chat = build_chat(Some(180), None);
+        let now: chrono::DateTime<chrono::Utc> =
+            chrono::DateTime::parse_from_rfc3339("2020-08-12T20:30:00Z")
+                .unwrap()
+                .into();
+
+        assert!(!super::in_quiet_hours(&chat, now));
+
+        chat.quiet_hours_start = Some(22);
+        chat.quiet_hours_end = Some(7);
+
+        assert!(super::in_quiet_hours(&chat, now));
+
+        chat.utc_offset_minutes = Some(0);
+
+        assert!(!super::in_quiet_hours(&chat, now));
+    }
+
+    #[test]
+    fn format_digest_groups_items_by_feed() {
+        let item1 = build_feed_item();
+        let mut item2 = build_feed_item();
+        item2.title = "Another title".to_string();
+        item2.link = "https://example.com/another".to_string();
+
+        let sections = vec![
+            ("Feed1".to_string(), vec![&item1, &item2]),
+            ("Feed2".to_string(), vec![]),
+            ("Feed3".to_string(), vec![&item1]),
+        ];
+
+        assert_eq!(
+            super::format_digest(&sections, "en"),
+            "Your daily digest\n\n\
+             Feed1\n\
+             • Title\nhttps://example.com/item\n\
+             • Another title\nhttps://example.com/another\n\n\
+             Feed3\n\
+             • Title\nhttps://example.com/item"
+        );
+    }
+
+    #[test]
+    fn parse_page_parses_page_number() {
+        assert_eq!(super::parse_page("".to_string()), Ok(1));
+        assert_eq!(super::parse_page("2".to_string()), Ok(2));
+        assert!(super::parse_page("0".to_string()).is_err());
+        assert!(super::parse_page("two".to_string()).is_err());
+    }
+
+    #[test]
+    fn parse_interval_secs_uses_default_when_not_set() {
+        assert_eq!(super::parse_interval_secs("INTERVAL", None, 60), 60);
+        assert_eq!(
+            super::parse_interval_secs("INTERVAL", Some("300".to_string()), 60),
+            300
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "INTERVAL must be a positive number of seconds, 0 was given")]
+    fn parse_interval_secs_rejects_zero() {
+        super::parse_interval_secs("INTERVAL", Some("0".to_string()), 60);
+    }
+
+    #[test]
+    #[should_panic(expected = "INTERVAL must be a positive number of seconds, 1m was given")]
+    fn parse_interval_secs_rejects_invalid_values() {
+        super::parse_interval_secs("INTERVAL", Some("1m".to_string()), 60);
+    }
+
+    #[test]
+    fn format_interval_uses_minutes_when_possible() {
+        assert_eq!(super::format_interval(1, "en"), "1 second".to_string());
+        assert_eq!(super::format_interval(45, "en"), "45 seconds".to_string());
+        assert_eq!(super::format_interval(60, "en"), "1 minute".to_string());
+        assert_eq!(super::format_interval(90, "en"), "90 seconds".to_string());
+        assert_eq!(super::format_interval(300, "en"), "5 minutes".to_string());
+        assert_eq!(super::format_interval(300, "ru"), "5 мин.".to_string());
+    }
+
+    #[test]
+    fn format_uptime_shows_days_hours_and_minutes() {
+        assert_eq!(super::format_uptime(59), "0m".to_string());
+        assert_eq!(super::format_uptime(61 * 60), "1h 1m".to_string());
+        assert_eq!(
+            super::format_uptime(2 * 24 * 60 * 60 + 3 * 60 * 60 + 15 * 60),
+            "2d 3h 15m".to_string()
+        );
+    }
+
+    #[test]
+    fn parse_subscription_limit_uses_default_when_not_set() {
+        assert_eq!(super::parse_subscription_limit(None), 20);
+        assert_eq!(super::parse_subscription_limit(Some("50".to_string())), 50);
+    }
+
+    #[test]
+    #[should_panic(expected = "SUBSCRIPTION_LIMIT must be a positive number")]
+    fn parse_subscription_limit_rejects_invalid_values() {
+        super::parse_subscription_limit(Some("twenty".to_string()));
+    }
+
+    #[test]
+    fn validate_limit_accepts_value_within_ceiling() {
+        assert_eq!(super::validate_limit("50".to_string(), 100, "en"), Ok(50));
+    }
+
+    #[test]
+    fn validate_limit_rejects_invalid_values() {
+        assert_eq!(
+            super::validate_limit("fifty".to_string(), 100, "en"),
+            Err("Passed value is not a number".to_string())
+        );
+        assert_eq!(
+            super::validate_limit("-5".to_string(), 100, "en"),
+            Err("Limit must be >= 1 and <= 100".to_string())
+        );
+        assert_eq!(
+            super::validate_limit("101".to_string(), 100, "en"),
+            Err("Limit must be >= 1 and <= 100".to_string())
+        );
+    }
+
     #[test]
     fn create_subscription_fails_if_url_is_not_provided() {
         let db_connection = db::establish_connection();
@@ -359,7 +6643,14 @@ mod tests {
         };
 
         db_connection.test_transaction::<(), super::SubscriptionError, _>(|| {
-            let result = super::create_subscription(&db_connection, new_chat.clone(), None);
+            let result = super::create_subscription(
+                &db_connection,
+                new_chat.clone(),
+                None,
+                None,
+                None,
+                super::DEFAULT_SUBSCRIPTION_LIMIT,
+            );
 
             assert_eq!(
                 result.err(),
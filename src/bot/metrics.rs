@@ -0,0 +1,237 @@
+use crate::db;
+use crate::db::feeds;
+use crate::db::telegram;
+use std::collections::BTreeMap;
+use std::env;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+static DEFAULT_METRICS_PORT: u16 = 9090;
+static METRICS_PATH: &str = "/metrics";
+
+#[derive(Debug, PartialEq)]
+pub struct Gauges {
+    pub subscriptions: i64,
+    pub active_chats: i64,
+    pub feeds: i64,
+}
+
+#[derive(Default)]
+pub struct Metrics {
+    commands: Mutex<BTreeMap<String, u64>>,
+}
+
+impl Metrics {
+    pub fn increment_command(&self, command: &str) {
+        let mut commands = self.commands.lock().unwrap();
+
+        *commands.entry(command.to_string()).or_insert(0) += 1;
+    }
+
+    pub fn render(&self, gauges: &Gauges) -> String {
+        let mut lines = vec![];
+
+        push_metric(
+            &mut lines,
+            "el_monitorro_subscriptions",
+            "gauge",
+            "Total number of subscriptions",
+        );
+        lines.push(format!(
+            "el_monitorro_subscriptions {}",
+            gauges.subscriptions
+        ));
+
+        push_metric(
+            &mut lines,
+            "el_monitorro_active_chats",
+            "gauge",
+            "Number of chats that didn't block the bot",
+        );
+        lines.push(format!("el_monitorro_active_chats {}", gauges.active_chats));
+
+        push_metric(
+            &mut lines,
+            "el_monitorro_feeds",
+            "gauge",
+            "Total number of feeds",
+        );
+        lines.push(format!("el_monitorro_feeds {}", gauges.feeds));
+
+        push_metric(
+            &mut lines,
+            "el_monitorro_commands_total",
+            "counter",
+            "Number of processed commands by command",
+        );
+
+        for (command, count) in self.commands.lock().unwrap().iter() {
+            lines.push(format!(
+                "el_monitorro_commands_total{{command=\"{}\"}} {}",
+                command, count
+            ));
+        }
+
+        format!("{}\n", lines.join("\n"))
+    }
+}
+
+fn push_metric(lines: &mut Vec<String>, name: &str, kind: &str, help: &str) {
+    lines.push(format!("# HELP {} {}", name, help));
+    lines.push(format!("# TYPE {} {}", name, kind));
+}
+
+pub fn spawn_server(metrics: Arc<Metrics>, pool: db::Pool) {
+    let enabled = env::var("METRICS_ENABLED").map_or(false, |value| value == "true");
+
+    if !enabled {
+        return;
+    }
+
+    let port = match env::var("METRICS_PORT") {
+        Ok(value) => value
+            .parse::<u16>()
+            .expect("METRICS_PORT must be a valid port"),
+        Err(_) => DEFAULT_METRICS_PORT,
+    };
+
+    tokio::spawn(async move {
+        if let Err(error) = serve(metrics, pool, port).await {
+            log::error!("Metrics server stopped: {}", error);
+        }
+    });
+}
+
+async fn serve(metrics: Arc<Metrics>, pool: db::Pool, port: u16) -> Result<(), io::Error> {
+    let address = SocketAddr::from(([0, 0, 0, 0], port));
+    let mut listener = TcpListener::bind(address).await?;
+
+    log::info!("Serving metrics on {}", address);
+
+    loop {
+        let (stream, _) = listener.accept().await?;
+        let metrics = metrics.clone();
+        let pool = pool.clone();
+
+        tokio::spawn(async move {
+            if let Err(error) = respond(stream, metrics, pool).await {
+                log::error!("Failed to serve metrics: {}", error);
+            }
+        });
+    }
+}
+
+async fn respond(
+    mut stream: TcpStream,
+    metrics: Arc<Metrics>,
+    pool: db::Pool,
+) -> Result<(), io::Error> {
+    let mut buffer = [0; 1024];
+    let size = stream.read(&mut buffer).await?;
+    let request = String::from_utf8_lossy(&buffer[..size]);
+
+    let response = if request_path(&request) != Some(METRICS_PATH) {
+        http_response("404 Not Found", "Not found\n".to_string())
+    } else {
+        let gauges = tokio::task::spawn_blocking(move || fetch_gauges(&pool))
+            .await
+            .map_err(|error| error.to_string())
+            .and_then(|result| result.map_err(|error| error.to_string()));
+
+        match gauges {
+            Ok(gauges) => http_response("200 OK", metrics.render(&gauges)),
+            Err(error) => {
+                log::error!("Failed to fetch metrics: {}", error);
+
+                http_response(
+                    "500 Internal Server Error",
+                    "Failed to fetch metrics\n".to_string(),
+                )
+            }
+        }
+    };
+
+    stream.write_all(response.as_bytes()).await
+}
+
+fn fetch_gauges(pool: &db::Pool) -> Result<Gauges, diesel::result::Error> {
+    let connection = db::get_connection(pool);
+
+    Ok(Gauges {
+        subscriptions: telegram::count_subscriptions(&connection)?,
+        active_chats: telegram::count_active_chats(&connection)?,
+        feeds: feeds::count_feeds(&connection)?,
+    })
+}
+
+fn request_path(request: &str) -> Option<&str> {
+    let mut parts = request.lines().next()?.split_whitespace();
+
+    match (parts.next(), parts.next()) {
+        (Some("GET"), Some(path)) => path.split('?').next(),
+        _ => None,
+    }
+}
+
+fn http_response(status: &str, body: String) -> String {
+    format!(
+        "HTTP/1.1 {}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Gauges, Metrics};
+
+    #[test]
+    fn render_renders_gauges_and_counters() {
+        let metrics = Metrics::default();
+
+        metrics.increment_command("/subscribe");
+        metrics.increment_command("/help");
+        metrics.increment_command("/subscribe");
+
+        let result = metrics.render(&Gauges {
+            subscriptions: 10,
+            active_chats: 3,
+            feeds: 7,
+        });
+
+        assert_eq!(
+            result,
+            "# HELP el_monitorro_subscriptions Total number of subscriptions\n\
+             # TYPE el_monitorro_subscriptions gauge\n\
+             el_monitorro_subscriptions 10\n\
+             # HELP el_monitorro_active_chats Number of chats that didn't block the bot\n\
+             # TYPE el_monitorro_active_chats gauge\n\
+             el_monitorro_active_chats 3\n\
+             # HELP el_monitorro_feeds Total number of feeds\n\
+             # TYPE el_monitorro_feeds gauge\n\
+             el_monitorro_feeds 7\n\
+             # HELP el_monitorro_commands_total Number of processed commands by command\n\
+             # TYPE el_monitorro_commands_total counter\n\
+             el_monitorro_commands_total{command=\"/help\"} 1\n\
+             el_monitorro_commands_total{command=\"/subscribe\"} 2\n"
+        );
+    }
+
+    #[test]
+    fn request_path_parses_get_requests() {
+        assert_eq!(
+            super::request_path("GET /metrics HTTP/1.1\r\nHost: localhost\r\n\r\n"),
+            Some("/metrics")
+        );
+        assert_eq!(
+            super::request_path("GET /metrics?format=text HTTP/1.1\r\n"),
+            Some("/metrics")
+        );
+        assert_eq!(super::request_path("POST /metrics HTTP/1.1\r\n"), None);
+        assert_eq!(super::request_path(""), None);
+    }
+}
@@ -1,3 +1,11 @@
 pub mod api;
+pub mod api_pool;
+pub mod blocklist;
+pub mod commands_menu;
 pub mod deliver_job;
+pub mod i18n;
 pub mod logic;
+pub mod metrics;
+pub mod rate_limiter;
+pub mod validation_cache;
+pub mod webhook;
@@ -0,0 +1,97 @@
+use std::collections::HashMap;
+use std::env;
+use std::sync::Mutex;
+use std::time::Instant;
+
+static DEFAULT_COMMANDS_PER_MINUTE: u32 = 20;
+
+struct Bucket {
+    tokens: f64,
+    updated_at: Instant,
+}
+
+pub struct RateLimiter {
+    commands_per_minute: u32,
+    buckets: Mutex<HashMap<i64, Bucket>>,
+}
+
+impl RateLimiter {
+    pub fn new(commands_per_minute: u32) -> Self {
+        RateLimiter {
+            commands_per_minute,
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn from_env() -> Self {
+        let commands_per_minute = match env::var("COMMANDS_PER_MINUTE") {
+            Ok(value) => value
+                .parse::<u32>()
+                .ok()
+                .filter(|rate| *rate > 0)
+                .expect("COMMANDS_PER_MINUTE must be a positive number"),
+            Err(_) => DEFAULT_COMMANDS_PER_MINUTE,
+        };
+
+        Self::new(commands_per_minute)
+    }
+
+    pub fn check(&self, chat_id: i64) -> bool {
+        self.check_at(chat_id, Instant::now())
+    }
+
+    fn check_at(&self, chat_id: i64, now: Instant) -> bool {
+        let capacity = self.commands_per_minute as f64;
+        let mut buckets = self.buckets.lock().unwrap();
+
+        buckets.retain(|_, bucket| now.saturating_duration_since(bucket.updated_at).as_secs() < 60);
+
+        let bucket = buckets.entry(chat_id).or_insert(Bucket {
+            tokens: capacity,
+            updated_at: now,
+        });
+
+        let elapsed = now.saturating_duration_since(bucket.updated_at);
+        bucket.tokens = (bucket.tokens + elapsed.as_secs_f64() * capacity / 60.0).min(capacity);
+        bucket.updated_at = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RateLimiter;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn check_allows_commands_until_bucket_is_empty() {
+        let limiter = RateLimiter::new(3);
+        let now = Instant::now();
+
+        assert!(limiter.check_at(1, now));
+        assert!(limiter.check_at(1, now));
+        assert!(limiter.check_at(1, now));
+        assert!(!limiter.check_at(1, now));
+
+        assert!(limiter.check_at(2, now));
+    }
+
+    #[test]
+    fn check_refills_bucket_over_time() {
+        let limiter = RateLimiter::new(2);
+        let now = Instant::now();
+
+        assert!(limiter.check_at(1, now));
+        assert!(limiter.check_at(1, now));
+        assert!(!limiter.check_at(1, now + Duration::from_secs(10)));
+
+        assert!(limiter.check_at(1, now + Duration::from_secs(40)));
+        assert!(!limiter.check_at(1, now + Duration::from_secs(40)));
+    }
+}
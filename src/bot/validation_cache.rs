@@ -0,0 +1,242 @@
+use crate::sync::reader::{FeedWarning, FetchedFeed, ValidatedFeed};
+use lazy_static::lazy_static;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+static CAPACITY: usize = 1000;
+static TTL_SECS: u64 = 600;
+
+lazy_static! {
+    static ref VALIDATION_CACHE: ValidationCache =
+        ValidationCache::new(CAPACITY, Duration::from_secs(TTL_SECS));
+}
+
+struct Entry {
+    feed_type: String,
+    warnings: Vec<FeedWarning>,
+    validated_at: Instant,
+    used_at: Instant,
+}
+
+pub struct ValidationCache {
+    capacity: usize,
+    ttl: Duration,
+    entries: Mutex<HashMap<String, Entry>>,
+}
+
+impl ValidationCache {
+    pub fn new(capacity: usize, ttl: Duration) -> Self {
+        ValidationCache {
+            capacity,
+            ttl,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn validate<E, F>(&self, url: &str, validate: F) -> Result<ValidatedFeed, E>
+    where
+        F: FnOnce() -> Result<ValidatedFeed, E>,
+    {
+        self.validate_at(url, Instant::now(), validate)
+    }
+
+    fn validate_at<E, F>(&self, url: &str, now: Instant, validate: F) -> Result<ValidatedFeed, E>
+    where
+        F: FnOnce() -> Result<ValidatedFeed, E>,
+    {
+        if let Some((feed_type, warnings)) = self.get(url, now) {
+            let feed = FetchedFeed {
+                title: "".to_string(),
+                link: url.to_string(),
+                description: "".to_string(),
+                feed_type,
+                items: vec![],
+            };
+
+            return Ok(ValidatedFeed { feed, warnings });
+        }
+
+        let validated_feed = validate()?;
+
+        self.insert(
+            url,
+            &validated_feed.feed.feed_type,
+            &validated_feed.warnings,
+            now,
+        );
+
+        Ok(validated_feed)
+    }
+
+    fn get(&self, url: &str, now: Instant) -> Option<(String, Vec<FeedWarning>)> {
+        let mut entries = self.entries.lock().unwrap();
+
+        match entries.get_mut(url) {
+            Some(entry) if now.saturating_duration_since(entry.validated_at) < self.ttl => {
+                entry.used_at = now;
+
+                Some((entry.feed_type.clone(), entry.warnings.clone()))
+            }
+            Some(_) => {
+                entries.remove(url);
+
+                None
+            }
+            None => None,
+        }
+    }
+
+    fn insert(&self, url: &str, feed_type: &str, warnings: &[FeedWarning], now: Instant) {
+        let ttl = self.ttl;
+        let mut entries = self.entries.lock().unwrap();
+
+        entries.retain(|_, entry| now.saturating_duration_since(entry.validated_at) < ttl);
+
+        if entries.len() >= self.capacity && !entries.contains_key(url) {
+            let least_recently_used = entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.used_at)
+                .map(|(url, _)| url.clone());
+
+            if let Some(least_recently_used) = least_recently_used {
+                entries.remove(&least_recently_used);
+            }
+        }
+
+        entries.insert(
+            url.to_string(),
+            Entry {
+                feed_type: feed_type.to_string(),
+                warnings: warnings.to_vec(),
+                validated_at: now,
+                used_at: now,
+            },
+        );
+    }
+}
+
+pub fn validate<E, F>(url: &str, validate: F) -> Result<ValidatedFeed, E>
+where
+    F: FnOnce() -> Result<ValidatedFeed, E>,
+{
+    VALIDATION_CACHE.validate(url, validate)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ValidationCache;
+    use crate::sync::reader::{FeedWarning, FetchedFeed, ValidatedFeed};
+    use std::cell::Cell;
+    use std::time::{Duration, Instant};
+
+    fn validated_feed(url: &str) -> ValidatedFeed {
+        ValidatedFeed {
+            feed: FetchedFeed {
+                title: "Feed".to_string(),
+                link: url.to_string(),
+                description: "".to_string(),
+                feed_type: "rss".to_string(),
+                items: vec![],
+            },
+            warnings: vec![FeedWarning::MissingDates],
+        }
+    }
+
+    fn validate(
+        cache: &ValidationCache,
+        url: &str,
+        now: Instant,
+        fetches: &Cell<u32>,
+    ) -> Result<ValidatedFeed, ()> {
+        cache.validate_at(url, now, || {
+            fetches.set(fetches.get() + 1);
+
+            Ok(validated_feed(url))
+        })
+    }
+
+    #[test]
+    fn validate_at_skips_fetch_on_cache_hit() {
+        let cache = ValidationCache::new(10, Duration::from_secs(60));
+        let fetches = Cell::new(0);
+        let now = Instant::now();
+
+        let first = validate(&cache, "https://example.com/feed", now, &fetches).unwrap();
+        let second = validate(&cache, "https://example.com/feed", now, &fetches).unwrap();
+
+        assert_eq!(fetches.get(), 1);
+        assert_eq!(first.feed.feed_type, "rss".to_string());
+        assert_eq!(second.feed.feed_type, "rss".to_string());
+        assert_eq!(second.feed.link, "https://example.com/feed".to_string());
+        assert_eq!(second.warnings, vec![FeedWarning::MissingDates]);
+    }
+
+    #[test]
+    fn validate_at_fetches_again_after_ttl() {
+        let cache = ValidationCache::new(10, Duration::from_secs(60));
+        let fetches = Cell::new(0);
+        let now = Instant::now();
+
+        validate(&cache, "https://example.com/feed", now, &fetches).unwrap();
+        validate(
+            &cache,
+            "https://example.com/feed",
+            now + Duration::from_secs(60),
+            &fetches,
+        )
+        .unwrap();
+
+        assert_eq!(fetches.get(), 2);
+    }
+
+    #[test]
+    fn validate_at_does_not_cache_failures() {
+        let cache = ValidationCache::new(10, Duration::from_secs(60));
+        let now = Instant::now();
+
+        let result: Result<ValidatedFeed, ()> =
+            cache.validate_at("https://example.com/feed", now, || Err(()));
+
+        assert!(result.is_err());
+        assert_eq!(cache.get("https://example.com/feed", now), None);
+    }
+
+    #[test]
+    fn validate_at_evicts_least_recently_used_url() {
+        let cache = ValidationCache::new(2, Duration::from_secs(60));
+        let fetches = Cell::new(0);
+        let now = Instant::now();
+
+        validate(&cache, "https://example.com/1", now, &fetches).unwrap();
+        validate(
+            &cache,
+            "https://example.com/2",
+            now + Duration::from_secs(1),
+            &fetches,
+        )
+        .unwrap();
+        validate(
+            &cache,
+            "https://example.com/1",
+            now + Duration::from_secs(2),
+            &fetches,
+        )
+        .unwrap();
+        validate(
+            &cache,
+            "https://example.com/3",
+            now + Duration::from_secs(3),
+            &fetches,
+        )
+        .unwrap();
+
+        assert_eq!(fetches.get(), 3);
+        assert!(cache
+            .get("https://example.com/1", now + Duration::from_secs(4))
+            .is_some());
+        assert!(cache
+            .get("https://example.com/2", now + Duration::from_secs(4))
+            .is_none());
+    }
+}
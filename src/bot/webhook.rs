@@ -0,0 +1,344 @@
+use hyper::body::HttpBody;
+use hyper::header::CONTENT_LENGTH;
+use hyper::server::conn::Http;
+use hyper::service::service_fn;
+use hyper::{Body, Method, Request, Response, StatusCode};
+use openssl::memcmp;
+use std::convert::Infallible;
+use std::env;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use telegram_bot::Update;
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+use tokio::time;
+
+static DEFAULT_WEBHOOK_PORT: u16 = 8443;
+static WEBHOOK_PATH_PREFIX: &str = "/webhook/";
+static SECRET_TOKEN_HEADER: &str = "x-telegram-bot-api-secret-token";
+static MAX_SECRET_LENGTH: usize = 256;
+static MAX_BODY_SIZE: usize = 1024 * 1024;
+static MAX_HEADERS_SIZE: usize = 16 * 1024;
+static CONNECTION_TIMEOUT_SECS: u64 = 10;
+static UPDATES_BUFFER: usize = 100;
+
+#[derive(Debug, PartialEq)]
+pub enum BotMode {
+    Polling,
+    Webhook,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct WebhookConfig {
+    pub port: u16,
+    pub secret: String,
+}
+
+pub fn bot_mode() -> BotMode {
+    match env::var("BOT_MODE") {
+        Ok(value) if value.trim().eq_ignore_ascii_case("webhook") => BotMode::Webhook,
+        _ => BotMode::Polling,
+    }
+}
+
+impl WebhookConfig {
+    pub fn from_env() -> Self {
+        let secret = env::var("WEBHOOK_SECRET").expect("WEBHOOK_SECRET not set");
+
+        if !is_valid_secret(secret.trim()) {
+            panic!("WEBHOOK_SECRET must be 1-256 characters long and contain only A-Z, a-z, 0-9, _ and -");
+        }
+
+        let port = match env::var("WEBHOOK_PORT") {
+            Ok(value) => value
+                .parse::<u16>()
+                .expect("WEBHOOK_PORT must be a valid port"),
+            Err(_) => DEFAULT_WEBHOOK_PORT,
+        };
+
+        WebhookConfig {
+            port,
+            secret: secret.trim().to_string(),
+        }
+    }
+
+    fn path(&self) -> String {
+        format!("{}{}", WEBHOOK_PATH_PREFIX, self.secret)
+    }
+}
+
+// the secret is also passed to setWebhook as secret_token, which allows only these characters
+fn is_valid_secret(secret: &str) -> bool {
+    !secret.is_empty()
+        && secret.len() <= MAX_SECRET_LENGTH
+        && secret.chars().all(|character| {
+            character.is_ascii_alphanumeric() || character == '_' || character == '-'
+        })
+}
+
+/// Serves Telegram updates posted to `http://0.0.0.0:WEBHOOK_PORT/webhook/WEBHOOK_SECRET`
+/// and returns them as a channel which the bot reads the same way as the long polling stream.
+///
+/// The server speaks plain HTTP, TLS must be terminated by a reverse proxy or a load balancer
+/// because Telegram sends updates only to HTTPS urls. The webhook has to be registered with
+/// the same url and with the secret as its secret token, for example, with
+/// `setWebhook?url=https://example.com/webhook/SECRET&secret_token=SECRET`, so Telegram sends
+/// the secret in the `X-Telegram-Bot-Api-Secret-Token` header of every update.
+/// Requests to other paths get 404 and requests without the header get 401 without touching
+/// the bot. Connections are closed after 10 seconds and bodies are limited to 1 MiB.
+pub fn spawn_server(config: WebhookConfig) -> mpsc::Receiver<Update> {
+    let (sender, receiver) = mpsc::channel(UPDATES_BUFFER);
+
+    tokio::spawn(async move {
+        if let Err(error) = serve(config, sender).await {
+            log::error!("Webhook server stopped: {}", error);
+        }
+    });
+
+    receiver
+}
+
+async fn serve(config: WebhookConfig, sender: mpsc::Sender<Update>) -> Result<(), io::Error> {
+    let address = SocketAddr::from(([0, 0, 0, 0], config.port));
+    let mut listener = TcpListener::bind(address).await?;
+    let config = Arc::new(config);
+
+    log::info!("Receiving updates with a webhook on {}", address);
+
+    loop {
+        let (stream, _) = listener.accept().await?;
+        let sender = sender.clone();
+        let config = config.clone();
+
+        tokio::spawn(async move {
+            let service =
+                service_fn(move |request| respond(request, config.clone(), sender.clone()));
+
+            let connection = Http::new()
+                .http1_only(true)
+                .http1_keep_alive(false)
+                .max_buf_size(MAX_HEADERS_SIZE)
+                .serve_connection(stream, service);
+
+            match time::timeout(Duration::from_secs(CONNECTION_TIMEOUT_SECS), connection).await {
+                Ok(Ok(())) => (),
+                Ok(Err(error)) => log::error!("Failed to receive an update: {}", error),
+                Err(_) => log::warn!("Closed a webhook connection which took too long"),
+            }
+        });
+    }
+}
+
+async fn respond(
+    request: Request<Body>,
+    config: Arc<WebhookConfig>,
+    mut sender: mpsc::Sender<Update>,
+) -> Result<Response<Body>, Infallible> {
+    let status = match check_request(&request, &config) {
+        Err(status) => status,
+        Ok(()) => match read_body(request.into_body()).await {
+            Err(status) => status,
+            Ok(body) => {
+                match serde_json::from_slice::<Update>(&body) {
+                    Ok(update) => {
+                        if sender.send(update).await.is_err() {
+                            log::error!("The bot stopped reading updates");
+                        }
+                    }
+                    Err(error) => log::warn!("Skipped an update which can't be parsed: {}", error),
+                }
+
+                StatusCode::OK
+            }
+        },
+    };
+
+    let mut response = Response::new(Body::empty());
+    *response.status_mut() = status;
+
+    Ok(response)
+}
+
+fn check_request(request: &Request<Body>, config: &WebhookConfig) -> Result<(), StatusCode> {
+    let path = request.uri().path().as_bytes();
+
+    if request.method() != Method::POST || !secret_matches(&config.path(), path) {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    let secret_token = request
+        .headers()
+        .get(SECRET_TOKEN_HEADER)
+        .map(|value| value.as_bytes())
+        .unwrap_or_default();
+
+    if !secret_matches(&config.secret, secret_token) {
+        return Err(StatusCode::UNAUTHORIZED);
+    }
+
+    let content_length = request
+        .headers()
+        .get(CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<usize>().ok());
+
+    match content_length {
+        Some(length) if length > MAX_BODY_SIZE => Err(StatusCode::PAYLOAD_TOO_LARGE),
+        _ => Ok(()),
+    }
+}
+
+// compared in constant time, so the secret can't be guessed by response times
+fn secret_matches(expected: &str, actual: &[u8]) -> bool {
+    expected.len() == actual.len() && memcmp::eq(expected.as_bytes(), actual)
+}
+
+// the body is read in chunks because Content-Length can be missing with chunked encoding
+async fn read_body(mut body: Body) -> Result<Vec<u8>, StatusCode> {
+    let mut data = vec![];
+
+    while let Some(chunk) = body.data().await {
+        let chunk = chunk.map_err(|error| {
+            log::warn!("Failed to read an update: {}", error);
+
+            StatusCode::BAD_REQUEST
+        })?;
+
+        if data.len() + chunk.len() > MAX_BODY_SIZE {
+            return Err(StatusCode::PAYLOAD_TOO_LARGE);
+        }
+
+        data.extend_from_slice(&chunk);
+    }
+
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::WebhookConfig;
+    use hyper::{Body, Request, StatusCode};
+    use telegram_bot::{Update, UpdateKind};
+
+    fn config() -> WebhookConfig {
+        WebhookConfig {
+            port: 8443,
+            secret: "secret".to_string(),
+        }
+    }
+
+    fn request(method: &str, path: &str, secret_token: Option<&str>) -> Request<Body> {
+        let mut builder = Request::builder().method(method).uri(path);
+
+        if let Some(secret_token) = secret_token {
+            builder = builder.header("X-Telegram-Bot-Api-Secret-Token", secret_token);
+        }
+
+        builder.body(Body::empty()).unwrap()
+    }
+
+    #[test]
+    fn webhook_path_includes_secret() {
+        assert_eq!(config().path(), "/webhook/secret".to_string());
+    }
+
+    #[test]
+    fn is_valid_secret_allows_only_secret_token_characters() {
+        assert!(super::is_valid_secret("Abc_123-xyz"));
+        assert!(super::is_valid_secret(&"a".repeat(256)));
+        assert!(!super::is_valid_secret(""));
+        assert!(!super::is_valid_secret(&"a".repeat(257)));
+        assert!(!super::is_valid_secret("secret/path"));
+        assert!(!super::is_valid_secret("secret token"));
+    }
+
+    #[test]
+    fn check_request_accepts_updates_with_secret_path_and_header() {
+        assert_eq!(
+            super::check_request(
+                &request("POST", "/webhook/secret", Some("secret")),
+                &config()
+            ),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn check_request_rejects_other_paths_and_methods() {
+        assert_eq!(
+            super::check_request(
+                &request("POST", "/webhook/other", Some("secret")),
+                &config()
+            ),
+            Err(StatusCode::NOT_FOUND)
+        );
+        assert_eq!(
+            super::check_request(
+                &request("POST", "/webhook/secre", Some("secret")),
+                &config()
+            ),
+            Err(StatusCode::NOT_FOUND)
+        );
+        assert_eq!(
+            super::check_request(
+                &request("GET", "/webhook/secret", Some("secret")),
+                &config()
+            ),
+            Err(StatusCode::NOT_FOUND)
+        );
+    }
+
+    #[test]
+    fn check_request_requires_secret_token_header() {
+        assert_eq!(
+            super::check_request(&request("POST", "/webhook/secret", None), &config()),
+            Err(StatusCode::UNAUTHORIZED)
+        );
+        assert_eq!(
+            super::check_request(
+                &request("POST", "/webhook/secret", Some("other")),
+                &config()
+            ),
+            Err(StatusCode::UNAUTHORIZED)
+        );
+    }
+
+    #[test]
+    fn check_request_rejects_large_bodies() {
+        let request = Request::builder()
+            .method("POST")
+            .uri("/webhook/secret")
+            .header("X-Telegram-Bot-Api-Secret-Token", "secret")
+            .header("Content-Length", (1024 * 1024 + 1).to_string())
+            .body(Body::empty())
+            .unwrap();
+
+        assert_eq!(
+            super::check_request(&request, &config()),
+            Err(StatusCode::PAYLOAD_TOO_LARGE)
+        );
+    }
+
+    #[test]
+    fn updates_are_parsed_from_telegram_json() {
+        let body = r#"{
+            "update_id": 1,
+            "message": {
+                "message_id": 2,
+                "date": 1597000000,
+                "chat": {"id": 3, "type": "private", "first_name": "First"},
+                "from": {"id": 3, "is_bot": false, "first_name": "First"},
+                "text": "/help"
+            }
+        }"#;
+
+        let update = serde_json::from_str::<Update>(body).unwrap();
+
+        match update.kind {
+            UpdateKind::Message(_) => (),
+            other => panic!("expected a message, got {:?}", other),
+        }
+    }
+}
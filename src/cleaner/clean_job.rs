@@ -1,5 +1,5 @@
 use crate::db;
-use crate::db::{feed_items, feeds};
+use crate::db::{feed_items, feeds, telegram};
 use diesel::result::Error;
 use diesel::PgConnection;
 use tokio::time;
@@ -29,6 +29,8 @@ impl CleanJob {
         let mut page = 1;
         let mut total_number = 0;
 
+        delete_inactive_chats(&db_connection);
+        delete_expired_subscriptions(&db_connection);
         delete_feeds_without_subscriptions(&db_connection);
 
         loop {
@@ -76,6 +78,27 @@ fn clean_feeds() {
     }
 }
 
+fn delete_inactive_chats(conn: &PgConnection) {
+    log::info!(
+        "Started removing chats inactive for {} days",
+        telegram::INACTIVE_CHAT_GRACE_PERIOD_DAYS
+    );
+
+    match telegram::remove_inactive_chats(conn) {
+        Ok(count) => log::info!("Removed {} inactive chats", count),
+        Err(error) => log::error!("Failed to remove inactive chats {:?}", error),
+    };
+}
+
+fn delete_expired_subscriptions(conn: &PgConnection) {
+    log::info!("Started removing expired unsubscribed subscriptions");
+
+    match telegram::remove_expired_subscriptions(conn) {
+        Ok(count) => log::info!("Removed {} expired unsubscribed subscriptions", count),
+        Err(error) => log::error!("Failed to remove expired subscriptions {:?}", error),
+    };
+}
+
 fn delete_feeds_without_subscriptions(conn: &PgConnection) {
     log::info!("Started removing feeds without subscriptions");
 
@@ -0,0 +1,214 @@
+use crate::sync::reader::Credentials;
+use openssl::base64;
+use openssl::hash::MessageDigest;
+use openssl::pkey::PKey;
+use openssl::rand::rand_bytes;
+use openssl::sha::sha256;
+use openssl::sign::Signer;
+use openssl::symm::{decrypt_aead, encrypt_aead, Cipher};
+use std::env;
+
+static ENCRYPTED_PREFIX: &str = "v1:";
+static NONCE_LENGTH: usize = 12;
+static TAG_LENGTH: usize = 16;
+
+#[derive(Debug, Fail, PartialEq)]
+pub enum CredentialsError {
+    #[fail(display = "CREDENTIALS_SECRET is not set")]
+    SecretNotSet,
+    #[fail(display = "failed to encrypt credentials: {}", msg)]
+    EncryptionError { msg: String },
+    #[fail(display = "failed to decrypt credentials: {}", msg)]
+    DecryptionError { msg: String },
+}
+
+fn secret() -> Option<String> {
+    env::var("CREDENTIALS_SECRET")
+        .ok()
+        .filter(|secret| !secret.is_empty())
+}
+
+pub fn encrypt_password(password: &str) -> Result<String, CredentialsError> {
+    match secret() {
+        Some(secret) => encrypt(&secret, password),
+        None => Err(CredentialsError::SecretNotSet),
+    }
+}
+
+pub fn decrypt_password(stored: &str) -> Result<String, CredentialsError> {
+    if !is_encrypted(stored) {
+        return Ok(stored.to_string());
+    }
+
+    match secret() {
+        Some(secret) => decrypt(&secret, stored),
+        None => Err(CredentialsError::SecretNotSet),
+    }
+}
+
+pub fn is_encrypted(stored: &str) -> bool {
+    stored.starts_with(ENCRYPTED_PREFIX)
+}
+
+// feeds fetched with different credentials must not share items, so they are stored
+// under a key which identifies the credentials without revealing them
+pub fn credentials_key(credentials: &Credentials) -> Result<String, CredentialsError> {
+    match secret() {
+        Some(secret) => keyed_digest(&secret, credentials),
+        None => Err(CredentialsError::SecretNotSet),
+    }
+}
+
+fn encrypt(secret: &str, password: &str) -> Result<String, CredentialsError> {
+    let mut nonce = vec![0; NONCE_LENGTH];
+    let mut tag = vec![0; TAG_LENGTH];
+
+    rand_bytes(&mut nonce).map_err(|error| CredentialsError::EncryptionError {
+        msg: format!("{:?}", error),
+    })?;
+
+    let ciphertext = encrypt_aead(
+        Cipher::aes_256_gcm(),
+        &encryption_key(secret),
+        Some(&nonce),
+        &[],
+        password.as_bytes(),
+        &mut tag,
+    )
+    .map_err(|error| CredentialsError::EncryptionError {
+        msg: format!("{:?}", error),
+    })?;
+
+    let mut payload = nonce;
+    payload.extend(ciphertext);
+    payload.extend(tag);
+
+    Ok(format!(
+        "{}{}",
+        ENCRYPTED_PREFIX,
+        base64::encode_block(&payload)
+    ))
+}
+
+fn decrypt(secret: &str, stored: &str) -> Result<String, CredentialsError> {
+    let payload = base64::decode_block(&stored[ENCRYPTED_PREFIX.len()..]).map_err(|error| {
+        CredentialsError::DecryptionError {
+            msg: format!("{:?}", error),
+        }
+    })?;
+
+    if payload.len() < NONCE_LENGTH + TAG_LENGTH {
+        return Err(CredentialsError::DecryptionError {
+            msg: "the value is too short".to_string(),
+        });
+    }
+
+    let (nonce, rest) = payload.split_at(NONCE_LENGTH);
+    let (ciphertext, tag) = rest.split_at(rest.len() - TAG_LENGTH);
+
+    let password = decrypt_aead(
+        Cipher::aes_256_gcm(),
+        &encryption_key(secret),
+        Some(nonce),
+        &[],
+        ciphertext,
+        tag,
+    )
+    .map_err(|error| CredentialsError::DecryptionError {
+        msg: format!("{:?}", error),
+    })?;
+
+    String::from_utf8(password).map_err(|error| CredentialsError::DecryptionError {
+        msg: format!("{:?}", error),
+    })
+}
+
+fn keyed_digest(secret: &str, credentials: &Credentials) -> Result<String, CredentialsError> {
+    let to_error = |error: openssl::error::ErrorStack| CredentialsError::EncryptionError {
+        msg: format!("{:?}", error),
+    };
+
+    let key =
+        PKey::hmac(&sha256(format!("credentials_key:{}", secret).as_bytes())).map_err(to_error)?;
+    let mut signer = Signer::new(MessageDigest::sha256(), &key).map_err(to_error)?;
+
+    signer
+        .update(credentials.username.as_bytes())
+        .map_err(to_error)?;
+    signer.update(b"\0").map_err(to_error)?;
+    signer
+        .update(credentials.password.as_bytes())
+        .map_err(to_error)?;
+
+    let digest = signer.sign_to_vec().map_err(to_error)?;
+
+    Ok(digest.iter().map(|byte| format!("{:02x}", byte)).collect())
+}
+
+fn encryption_key(secret: &str) -> [u8; 32] {
+    sha256(secret.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::sync::reader::Credentials;
+
+    #[test]
+    fn encrypt_and_decrypt_roundtrip() {
+        let encrypted = super::encrypt("secret", "p@ssword").unwrap();
+
+        assert!(super::is_encrypted(&encrypted));
+        assert!(!encrypted.contains("p@ssword"));
+        assert_eq!(
+            super::decrypt("secret", &encrypted),
+            Ok("p@ssword".to_string())
+        );
+        assert_ne!(super::encrypt("secret", "p@ssword").unwrap(), encrypted);
+    }
+
+    #[test]
+    fn decrypt_fails_with_another_secret_or_tampered_value() {
+        let encrypted = super::encrypt("secret", "password").unwrap();
+        let mut tampered = encrypted.clone().into_bytes();
+        let last = tampered.len() - 3;
+        tampered[last] = if tampered[last] == b'A' { b'B' } else { b'A' };
+
+        assert!(super::decrypt("other", &encrypted).is_err());
+        assert!(super::decrypt("secret", &String::from_utf8(tampered).unwrap()).is_err());
+        assert!(super::decrypt("secret", "v1:c2hvcnQ=").is_err());
+    }
+
+    #[test]
+    fn decrypt_password_passes_legacy_plaintext_through() {
+        assert_eq!(super::decrypt_password("plain"), Ok("plain".to_string()));
+    }
+
+    #[test]
+    fn keyed_digest_depends_on_credentials_and_secret() {
+        let credentials = |username: &str, password: &str| Credentials {
+            username: username.to_string(),
+            password: password.to_string(),
+        };
+
+        let key = super::keyed_digest("secret", &credentials("user", "pass")).unwrap();
+
+        assert_eq!(key.len(), 64);
+        assert!(!key.contains("pass"));
+        assert_eq!(
+            super::keyed_digest("secret", &credentials("user", "pass")),
+            Ok(key.clone())
+        );
+        assert_ne!(
+            super::keyed_digest("secret", &credentials("user", "other")),
+            Ok(key.clone())
+        );
+        assert_ne!(
+            super::keyed_digest("secret", &credentials("use", "rpass")),
+            Ok(key.clone())
+        );
+        assert_ne!(
+            super::keyed_digest("other", &credentials("user", "pass")),
+            Ok(key)
+        );
+    }
+}
@@ -1,9 +1,13 @@
+use crate::db;
 use crate::models::feed_item::FeedItem;
 use crate::schema::feed_items;
 use crate::sync::FetchedFeedItem;
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Duration, Utc};
 use diesel::result::Error;
 use diesel::{ExpressionMethods, PgConnection, QueryDsl, RunQueryDsl};
+use std::collections::HashSet;
+
+static DUPLICATE_WINDOW_DAYS: i64 = 7;
 
 #[derive(Insertable, AsChangeset)]
 #[table_name = "feed_items"]
@@ -15,6 +19,7 @@ pub struct NewFeedItem {
     pub author: Option<String>,
     pub guid: Option<String>,
     pub publication_date: DateTime<Utc>,
+    pub content_hash: Option<String>,
 }
 
 pub fn create(
@@ -22,9 +27,18 @@ pub fn create(
     feed_id: i64,
     fetched_items: Vec<FetchedFeedItem>,
 ) -> Result<Vec<FeedItem>, Error> {
+    let hashes = fetched_items
+        .iter()
+        .map(|item| content_hash(&item.title, &item.link))
+        .collect::<Vec<String>>();
+
+    let mut known_hashes = find_recent_content_hashes(conn, feed_id, &hashes)?;
+
     let new_feed_items = fetched_items
         .into_iter()
-        .map(|fetched_feed_item| NewFeedItem {
+        .zip(hashes.into_iter())
+        .filter(|(_, hash)| known_hashes.insert(hash.clone()))
+        .map(|(fetched_feed_item, hash)| NewFeedItem {
             feed_id: feed_id,
             title: fetched_feed_item.title,
             description: fetched_feed_item.description,
@@ -32,9 +46,14 @@ pub fn create(
             author: fetched_feed_item.author,
             guid: fetched_feed_item.guid,
             publication_date: fetched_feed_item.publication_date,
+            content_hash: Some(hash),
         })
         .collect::<Vec<NewFeedItem>>();
 
+    if new_feed_items.is_empty() {
+        return Ok(vec![]);
+    }
+
     diesel::insert_into(feed_items::table)
         .values(new_feed_items)
         .on_conflict((feed_items::feed_id, feed_items::title, feed_items::link))
@@ -42,6 +61,48 @@ pub fn create(
         .get_results(conn)
 }
 
+fn find_recent_content_hashes(
+    conn: &PgConnection,
+    feed_id: i64,
+    hashes: &[String],
+) -> Result<HashSet<String>, Error> {
+    let found_hashes = feed_items::table
+        .filter(feed_items::feed_id.eq(feed_id))
+        .filter(feed_items::content_hash.eq_any(hashes))
+        .filter(
+            feed_items::created_at.gt(db::current_time() - Duration::days(DUPLICATE_WINDOW_DAYS)),
+        )
+        .select(feed_items::content_hash)
+        .load::<Option<String>>(conn)?;
+
+    Ok(found_hashes.into_iter().flatten().collect())
+}
+
+pub fn content_hash(title: &str, link: &str) -> String {
+    let title = title
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ")
+        .to_lowercase();
+
+    let link = link.trim();
+    let link = link.split('#').next().unwrap_or(link);
+    let link = link.trim_end_matches('/').to_lowercase();
+
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+
+    for byte in title
+        .bytes()
+        .chain(b"\n".iter().copied())
+        .chain(link.bytes())
+    {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+
+    format!("{:016x}", hash)
+}
+
 pub fn find(conn: &PgConnection, feed_id: i64) -> Option<Vec<FeedItem>> {
     match feed_items::table
         .filter(feed_items::feed_id.eq(feed_id))
@@ -188,6 +249,88 @@ mod tests {
         });
     }
 
+    #[test]
+    fn create_skips_republished_feed_items() {
+        let connection = db::establish_connection();
+
+        connection.test_transaction::<_, Error, _>(|| {
+            let feed = feeds::create(&connection, "Link".to_string(), "rss".to_string()).unwrap();
+            let publication_date = db::current_time();
+            let feed_items = vec![FetchedFeedItem {
+                title: "FeedItem1".to_string(),
+                description: Some("Description1".to_string()),
+                link: "https://example.com/item1".to_string(),
+                author: Some("Author1".to_string()),
+                guid: Some("Guid1".to_string()),
+                publication_date: publication_date,
+            }];
+
+            let result = super::create(&connection, feed.id, feed_items).unwrap();
+
+            assert_eq!(result.len(), 1);
+            assert_eq!(
+                result[0].content_hash,
+                Some(super::content_hash(
+                    "FeedItem1",
+                    "https://example.com/item1"
+                ))
+            );
+
+            let republished_feed_items = vec![
+                FetchedFeedItem {
+                    title: "FEEDITEM1 ".to_string(),
+                    description: Some("Description1".to_string()),
+                    link: "https://example.com/item1/".to_string(),
+                    author: Some("Author1".to_string()),
+                    guid: Some("Guid2".to_string()),
+                    publication_date: publication_date,
+                },
+                FetchedFeedItem {
+                    title: "FeedItem2".to_string(),
+                    description: Some("Description2".to_string()),
+                    link: "https://example.com/item2".to_string(),
+                    author: Some("Author2".to_string()),
+                    guid: Some("Guid3".to_string()),
+                    publication_date: publication_date,
+                },
+                FetchedFeedItem {
+                    title: "FeedItem2".to_string(),
+                    description: Some("Description2".to_string()),
+                    link: "https://example.com/item2#comments".to_string(),
+                    author: Some("Author2".to_string()),
+                    guid: Some("Guid4".to_string()),
+                    publication_date: publication_date,
+                },
+            ];
+
+            let result = super::create(&connection, feed.id, republished_feed_items).unwrap();
+
+            assert_eq!(result.len(), 1);
+            assert_eq!(result[0].guid, Some("Guid3".to_string()));
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn content_hash_normalizes_title_and_link() {
+        let hash = super::content_hash("Title  of the item", "https://example.com/item");
+
+        assert_eq!(hash.len(), 16);
+        assert_eq!(
+            hash,
+            super::content_hash(" title of\nthe ITEM", "https://EXAMPLE.com/item/#top")
+        );
+        assert_ne!(
+            hash,
+            super::content_hash("Title of the item", "https://example.com/other")
+        );
+        assert_ne!(
+            hash,
+            super::content_hash("Another title", "https://example.com/item")
+        );
+    }
+
     #[test]
     fn delete_old_feed_items() {
         let connection = db::establish_connection();
@@ -1,18 +1,38 @@
 use crate::db;
 use crate::models::feed::Feed;
-use crate::schema::{feeds, telegram_subscriptions};
+use crate::schema::{feed_items, feeds, telegram_subscriptions};
+use crate::sync::reader::CacheHeaders;
 use chrono::{DateTime, Utc};
+use diesel::dsl::{count, max};
 use diesel::result::Error;
 use diesel::{ExpressionMethods, PgConnection, QueryDsl, RunQueryDsl};
+use url::Url;
+
+#[derive(Debug)]
+pub struct FeedInfo {
+    pub feed: Feed,
+    pub items_count: i64,
+    pub last_item_published_at: Option<DateTime<Utc>>,
+}
 
 #[derive(Insertable, AsChangeset)]
 #[table_name = "feeds"]
 struct NewFeed {
     link: String,
     feed_type: String,
+    credentials_key: String,
 }
 
 pub fn create(conn: &PgConnection, link: String, feed_type: String) -> Result<Feed, Error> {
+    create_with_credentials_key(conn, link, feed_type, "".to_string())
+}
+
+pub fn create_with_credentials_key(
+    conn: &PgConnection,
+    link: String,
+    feed_type: String,
+    credentials_key: String,
+) -> Result<Feed, Error> {
     if feed_type != "atom".to_string()
         && feed_type != "rss".to_string()
         && feed_type != "json".to_string()
@@ -21,13 +41,14 @@ pub fn create(conn: &PgConnection, link: String, feed_type: String) -> Result<Fe
     }
 
     let new_feed = NewFeed {
-        link: link.trim().to_string(),
+        link: canonicalize_link(&link),
         feed_type: feed_type,
+        credentials_key,
     };
 
     diesel::insert_into(feeds::table)
         .values(new_feed)
-        .on_conflict(feeds::link)
+        .on_conflict((feeds::link, feeds::credentials_key))
         .do_update()
         .set(feeds::updated_at.eq(db::current_time()))
         .get_result::<Feed>(conn)
@@ -42,6 +63,35 @@ pub fn set_error(conn: &PgConnection, feed: &Feed, error: &str) -> Result<Feed,
         .get_result::<Feed>(conn)
 }
 
+pub fn record_failure(
+    conn: &PgConnection,
+    feed: &Feed,
+    dead_threshold: i32,
+) -> Result<Feed, Error> {
+    diesel::update(feed)
+        .set((
+            feeds::failures_count.eq(feeds::failures_count + 1),
+            feeds::dead.eq(feed.failures_count + 1 >= dead_threshold),
+            feeds::updated_at.eq(db::current_time()),
+        ))
+        .get_result::<Feed>(conn)
+}
+
+pub fn reset_failures(conn: &PgConnection, feed: &Feed) -> Result<Feed, Error> {
+    diesel::update(feed)
+        .set((feeds::failures_count.eq(0), feeds::dead.eq(false)))
+        .get_result::<Feed>(conn)
+}
+
+pub fn set_title(conn: &PgConnection, feed: &Feed, title: String) -> Result<Feed, Error> {
+    diesel::update(feed)
+        .set((
+            feeds::title.eq(title),
+            feeds::updated_at.eq(db::current_time()),
+        ))
+        .get_result::<Feed>(conn)
+}
+
 pub fn set_synced_at(
     conn: &PgConnection,
     feed: &Feed,
@@ -61,6 +111,19 @@ pub fn set_synced_at(
         .get_result::<Feed>(conn)
 }
 
+pub fn set_cache_headers(
+    conn: &PgConnection,
+    feed: &Feed,
+    cache_headers: &CacheHeaders,
+) -> Result<Feed, Error> {
+    diesel::update(feed)
+        .set((
+            feeds::etag.eq(&cache_headers.etag),
+            feeds::last_modified.eq(&cache_headers.last_modified),
+        ))
+        .get_result::<Feed>(conn)
+}
+
 pub fn find(conn: &PgConnection, id: i64) -> Option<Feed> {
     match feeds::table.filter(feeds::id.eq(id)).first::<Feed>(conn) {
         Ok(record) => Some(record),
@@ -68,9 +131,38 @@ pub fn find(conn: &PgConnection, id: i64) -> Option<Feed> {
     }
 }
 
+pub fn count_feeds(conn: &PgConnection) -> Result<i64, Error> {
+    feeds::table.count().get_result::<i64>(conn)
+}
+
 pub fn find_by_link(conn: &PgConnection, link: String) -> Option<Feed> {
+    find_by_link_and_credentials_key(conn, link, "")
+}
+
+pub fn find_by_link_and_credentials_key(
+    conn: &PgConnection,
+    link: String,
+    credentials_key: &str,
+) -> Option<Feed> {
+    match feeds::table
+        .filter(feeds::link.eq(canonicalize_link(&link)))
+        .filter(feeds::credentials_key.eq(credentials_key))
+        .first::<Feed>(conn)
+    {
+        Ok(record) => Some(record),
+        _ => None,
+    }
+}
+
+// the feed of the chat's subscription to the link, whichever credentials it uses.
+// active subscriptions go first, removed ones are kept for restoring
+pub fn find_chat_feed(conn: &PgConnection, chat_id: i64, link: String) -> Option<Feed> {
     match feeds::table
-        .filter(feeds::link.eq(link))
+        .inner_join(telegram_subscriptions::table)
+        .filter(telegram_subscriptions::chat_id.eq(chat_id))
+        .filter(feeds::link.eq(canonicalize_link(&link)))
+        .order(telegram_subscriptions::deleted_at.desc())
+        .select(feeds::all_columns)
         .first::<Feed>(conn)
     {
         Ok(record) => Some(record),
@@ -78,6 +170,58 @@ pub fn find_by_link(conn: &PgConnection, link: String) -> Option<Feed> {
     }
 }
 
+pub fn find_by_link_fuzzy(
+    conn: &PgConnection,
+    chat_id: i64,
+    link: String,
+) -> Result<Vec<Feed>, Error> {
+    let link = fuzzy_link(&link);
+
+    let chat_feeds = feeds::table
+        .inner_join(telegram_subscriptions::table)
+        .filter(telegram_subscriptions::chat_id.eq(chat_id))
+        .filter(telegram_subscriptions::deleted_at.is_null())
+        .order(feeds::id)
+        .select(feeds::all_columns)
+        .load::<Feed>(conn)?;
+
+    let matching_feeds = chat_feeds
+        .into_iter()
+        .filter(|feed| fuzzy_link(&feed.link) == link)
+        .collect();
+
+    Ok(matching_feeds)
+}
+
+pub fn feed_info(conn: &PgConnection, feed: Feed) -> Result<FeedInfo, Error> {
+    let items_count = feed_items::table
+        .filter(feed_items::feed_id.eq(feed.id))
+        .count()
+        .get_result::<i64>(conn)?;
+
+    let last_item_published_at = feed_items::table
+        .filter(feed_items::feed_id.eq(feed.id))
+        .select(max(feed_items::publication_date))
+        .first::<Option<DateTime<Utc>>>(conn)?;
+
+    Ok(FeedInfo {
+        feed,
+        items_count,
+        last_item_published_at,
+    })
+}
+
+pub fn most_subscribed(conn: &PgConnection, limit: i64) -> Result<Vec<(Feed, i64)>, Error> {
+    feeds::table
+        .inner_join(telegram_subscriptions::table)
+        .filter(telegram_subscriptions::deleted_at.is_null())
+        .group_by(feeds::id)
+        .select((feeds::all_columns, count(telegram_subscriptions::chat_id)))
+        .order((count(telegram_subscriptions::chat_id).desc(), feeds::id))
+        .limit(limit)
+        .load::<(Feed, i64)>(conn)
+}
+
 pub fn remove_feed(conn: &PgConnection, feed_id: i64) -> Result<usize, Error> {
     let record_query = feeds::table.filter(feeds::id.eq(feed_id));
 
@@ -94,8 +238,13 @@ pub fn find_unsynced_feeds(
 
     feeds::table
         .inner_join(telegram_subscriptions::table)
-        .filter(feeds::synced_at.lt(last_updated_at))
-        .or_filter(feeds::synced_at.is_null())
+        .filter(
+            feeds::synced_at
+                .lt(last_updated_at)
+                .or(feeds::synced_at.is_null()),
+        )
+        .filter(feeds::dead.eq(false))
+        .filter(telegram_subscriptions::deleted_at.is_null())
         .select(feeds::id)
         .order(feeds::id)
         .distinct()
@@ -126,14 +275,67 @@ pub fn delete_feeds_without_subscriptions(conn: &PgConnection) -> Result<usize,
     diesel::delete(delete_query).execute(conn)
 }
 
+pub fn canonicalize_link(link: &str) -> String {
+    let link = link.trim();
+
+    let mut url = match Url::parse(link) {
+        Ok(url) => url,
+        Err(_) => return link.to_string(),
+    };
+
+    if url.cannot_be_a_base() {
+        return url.to_string();
+    }
+
+    let path = url.path().trim_end_matches('/').to_string();
+    url.set_path(&path);
+
+    let mut query_pairs = url
+        .query_pairs()
+        .into_owned()
+        .collect::<Vec<(String, String)>>();
+
+    if query_pairs.is_empty() {
+        url.set_query(None);
+    } else {
+        query_pairs.sort();
+
+        url.query_pairs_mut().clear().extend_pairs(query_pairs);
+    }
+
+    url.to_string()
+}
+
+fn fuzzy_link(link: &str) -> String {
+    let link = canonicalize_link(link);
+
+    match Url::parse(&link) {
+        Ok(url) if !url.cannot_be_a_base() => {
+            let host = url.host_str().unwrap_or("").trim_start_matches("www.");
+            let mut fuzzy_link = format!("{}{}", host, url.path().trim_end_matches('/'));
+
+            if let Some(query) = url.query() {
+                fuzzy_link.push('?');
+                fuzzy_link.push_str(query);
+            }
+
+            fuzzy_link
+        }
+        _ => link.to_lowercase(),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::db;
+    use crate::db::feed_items;
     use crate::db::telegram;
     use crate::db::telegram::{NewTelegramChat, NewTelegramSubscription};
     use crate::models::feed::Feed;
     use crate::models::telegram_subscription::TelegramSubscription;
     use crate::schema::feeds;
+    use crate::sync::reader::CacheHeaders;
+    use crate::sync::FetchedFeedItem;
     use chrono::{Duration, Utc};
     use diesel::connection::Connection;
     use diesel::result::Error;
@@ -153,6 +355,96 @@ mod tests {
         assert_eq!(result.description, None);
     }
 
+    #[test]
+    fn count_feeds_counts_all_feeds() {
+        let connection = db::establish_connection();
+
+        connection.test_transaction::<(), Error, _>(|| {
+            let count = super::count_feeds(&connection).unwrap();
+
+            super::create(&connection, "Link".to_string(), "atom".to_string()).unwrap();
+            super::create(&connection, "Link".to_string(), "atom".to_string()).unwrap();
+            super::create(&connection, "Link2".to_string(), "rss".to_string()).unwrap();
+
+            assert_eq!(super::count_feeds(&connection).unwrap(), count + 2);
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn create_reuses_feed_with_equivalent_link() {
+        let connection = db::establish_connection();
+
+        connection.test_transaction::<_, Error, _>(|| {
+            let feed = super::create(
+                &connection,
+                "http://example.com/rss?b=2&a=1".to_string(),
+                "rss".to_string(),
+            )
+            .unwrap();
+
+            let same_feed = super::create(
+                &connection,
+                "HTTP://Example.COM:80/rss/?a=1&b=2".to_string(),
+                "rss".to_string(),
+            )
+            .unwrap();
+
+            assert_eq!(feed.id, same_feed.id);
+            assert_eq!(feed.link, "http://example.com/rss?a=1&b=2".to_string());
+
+            let found_feed =
+                super::find_by_link(&connection, "http://example.com/rss/?b=2&a=1".to_string())
+                    .unwrap();
+
+            assert_eq!(feed.id, found_feed.id);
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn canonicalize_link_strips_trailing_slash() {
+        assert_eq!(
+            super::canonicalize_link("http://example.com/rss/"),
+            "http://example.com/rss".to_string()
+        );
+        assert_eq!(
+            super::canonicalize_link("http://example.com/"),
+            super::canonicalize_link("http://example.com")
+        );
+    }
+
+    #[test]
+    fn canonicalize_link_lowercases_scheme_and_host_and_drops_default_port() {
+        assert_eq!(
+            super::canonicalize_link("HTTPS://Example.COM:443/Feed"),
+            "https://example.com/Feed".to_string()
+        );
+        assert_eq!(
+            super::canonicalize_link("http://example.com:8080/feed"),
+            "http://example.com:8080/feed".to_string()
+        );
+    }
+
+    #[test]
+    fn canonicalize_link_sorts_query_params() {
+        assert_eq!(
+            super::canonicalize_link("http://example.com/feed?format=rss&category=tech"),
+            super::canonicalize_link("http://example.com/feed/?category=tech&format=rss")
+        );
+        assert_eq!(
+            super::canonicalize_link("http://example.com/feed?"),
+            "http://example.com/feed".to_string()
+        );
+    }
+
+    #[test]
+    fn canonicalize_link_keeps_non_url_links() {
+        assert_eq!(super::canonicalize_link(" Link "), "Link".to_string());
+    }
+
     #[test]
     fn create_fails_to_create_feed_without_link() {
         let link = "".to_string();
@@ -173,6 +465,23 @@ mod tests {
         });
     }
 
+    #[test]
+    fn set_title_sets_title_to_feed() {
+        let connection = db::establish_connection();
+
+        connection.test_transaction::<_, Error, _>(|| {
+            let feed = super::create(&connection, "Link".to_string(), "rss".to_string()).unwrap();
+
+            assert_eq!(feed.title, None);
+
+            let updated_feed = super::set_title(&connection, &feed, "Title".to_string()).unwrap();
+
+            assert_eq!(updated_feed.title, Some("Title".to_string()));
+
+            Ok(())
+        });
+    }
+
     #[test]
     fn set_synced_at_sets_description_and_title_to_feed() {
         let link = "Link".to_string();
@@ -280,6 +589,159 @@ mod tests {
         });
     }
 
+    #[test]
+    fn create_with_credentials_key_keeps_feeds_apart() {
+        let connection = db::establish_connection();
+
+        connection.test_transaction::<_, Error, _>(|| {
+            let link = "Link".to_string();
+            let public_feed = super::create(&connection, link.clone(), "rss".to_string()).unwrap();
+            let private_feed = super::create_with_credentials_key(
+                &connection,
+                link.clone(),
+                "rss".to_string(),
+                "key".to_string(),
+            )
+            .unwrap();
+            let same_private_feed = super::create_with_credentials_key(
+                &connection,
+                link.clone(),
+                "rss".to_string(),
+                "key".to_string(),
+            )
+            .unwrap();
+
+            assert_ne!(public_feed.id, private_feed.id);
+            assert_eq!(private_feed.id, same_private_feed.id);
+            assert_eq!(private_feed.credentials_key, "key".to_string());
+            assert_eq!(
+                super::find_by_link(&connection, link.clone()).map(|feed| feed.id),
+                Some(public_feed.id)
+            );
+            assert_eq!(
+                super::find_by_link_and_credentials_key(&connection, link.clone(), "key")
+                    .map(|feed| feed.id),
+                Some(private_feed.id)
+            );
+            assert!(super::find_by_link_and_credentials_key(&connection, link, "other").is_none());
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn find_chat_feed_finds_feed_of_chat_subscription() {
+        let connection = db::establish_connection();
+
+        connection.test_transaction::<_, Error, _>(|| {
+            let link = "Link".to_string();
+            super::create(&connection, link.clone(), "rss".to_string()).unwrap();
+            let private_feed = super::create_with_credentials_key(
+                &connection,
+                link.clone(),
+                "rss".to_string(),
+                "key".to_string(),
+            )
+            .unwrap();
+            create_telegram_subscription(&connection, &private_feed);
+
+            assert_eq!(
+                super::find_chat_feed(&connection, 42, link.clone()),
+                Some(private_feed)
+            );
+            assert!(super::find_chat_feed(&connection, 43, link).is_none());
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn fuzzy_link_ignores_scheme_and_www() {
+        assert_eq!(
+            super::fuzzy_link("http://www.example.com/rss/"),
+            super::fuzzy_link("https://example.com/rss")
+        );
+        assert_ne!(
+            super::fuzzy_link("https://example.com/rss"),
+            super::fuzzy_link("https://example.com/atom")
+        );
+    }
+
+    #[test]
+    fn find_by_link_fuzzy_finds_chat_feeds_with_similar_links() {
+        let connection = db::establish_connection();
+
+        connection.test_transaction::<(), Error, _>(|| {
+            let feed = super::create(
+                &connection,
+                "https://www.example.com/rss".to_string(),
+                "rss".to_string(),
+            )
+            .unwrap();
+            create_telegram_subscription(&connection, &feed);
+
+            let result =
+                super::find_by_link_fuzzy(&connection, 42, "http://example.com/rss".to_string())
+                    .unwrap();
+
+            assert_eq!(result, vec![feed]);
+
+            let result =
+                super::find_by_link_fuzzy(&connection, 43, "http://example.com/rss".to_string())
+                    .unwrap();
+
+            assert!(result.is_empty());
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn feed_info_counts_feed_items() {
+        let connection = db::establish_connection();
+
+        connection.test_transaction::<(), Error, _>(|| {
+            let feed = super::create(&connection, "Link".to_string(), "rss".to_string()).unwrap();
+
+            let info = super::feed_info(&connection, feed.clone()).unwrap();
+
+            assert_eq!(info.items_count, 0);
+            assert!(info.last_item_published_at.is_none());
+
+            let publication_date = db::current_time() - Duration::hours(1);
+            let items = vec![
+                FetchedFeedItem {
+                    title: "FeedItem1".to_string(),
+                    description: None,
+                    link: "Link1".to_string(),
+                    author: None,
+                    guid: None,
+                    publication_date: publication_date - Duration::hours(1),
+                },
+                FetchedFeedItem {
+                    title: "FeedItem2".to_string(),
+                    description: None,
+                    link: "Link2".to_string(),
+                    author: None,
+                    guid: None,
+                    publication_date,
+                },
+            ];
+
+            feed_items::create(&connection, feed.id, items).unwrap();
+
+            let info = super::feed_info(&connection, feed).unwrap();
+
+            assert_eq!(info.items_count, 2);
+            assert_eq!(
+                info.last_item_published_at.map(|date| date.timestamp()),
+                Some(publication_date.timestamp())
+            );
+
+            Ok(())
+        });
+    }
+
     #[test]
     fn find_cant_find_feed() {
         let connection = db::establish_connection();
@@ -481,6 +943,132 @@ mod tests {
         })
     }
 
+    #[test]
+    fn set_cache_headers_stores_headers() {
+        let connection = db::establish_connection();
+
+        connection.test_transaction::<_, Error, _>(|| {
+            let feed = super::create(&connection, "Link".to_string(), "rss".to_string()).unwrap();
+
+            assert_eq!(
+                (feed.etag.clone(), feed.last_modified.clone()),
+                (None, None)
+            );
+
+            let cache_headers = CacheHeaders {
+                etag: Some("\"abc\"".to_string()),
+                last_modified: Some("Sat, 22 Aug 2020 10:00:00 GMT".to_string()),
+            };
+
+            let feed = super::set_cache_headers(&connection, &feed, &cache_headers).unwrap();
+
+            assert_eq!(feed.etag, cache_headers.etag);
+            assert_eq!(feed.last_modified, cache_headers.last_modified);
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn record_failure_marks_feed_dead_after_threshold() {
+        let connection = db::establish_connection();
+
+        connection.test_transaction::<_, Error, _>(|| {
+            let feed = super::create(&connection, "Link".to_string(), "rss".to_string()).unwrap();
+
+            let feed = super::record_failure(&connection, &feed, 2).unwrap();
+
+            assert_eq!(feed.failures_count, 1);
+            assert!(!feed.dead);
+
+            let feed = super::record_failure(&connection, &feed, 2).unwrap();
+
+            assert_eq!(feed.failures_count, 2);
+            assert!(feed.dead);
+
+            let feed = super::reset_failures(&connection, &feed).unwrap();
+
+            assert_eq!(feed.failures_count, 0);
+            assert!(!feed.dead);
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn find_unsynced_feeds_skips_dead_feeds() {
+        let connection = db::establish_connection();
+
+        connection.test_transaction::<_, Error, _>(|| {
+            let feed = super::create(&connection, "Link".to_string(), "rss".to_string()).unwrap();
+            create_telegram_subscription(&connection, &feed);
+
+            let found_unsynced_feeds =
+                super::find_unsynced_feeds(&connection, Utc::now(), 1, 1).unwrap();
+
+            assert_eq!(found_unsynced_feeds, vec![feed.id]);
+
+            super::record_failure(&connection, &feed, 1).unwrap();
+
+            let found_unsynced_feeds =
+                super::find_unsynced_feeds(&connection, Utc::now(), 1, 1).unwrap();
+
+            assert!(found_unsynced_feeds.is_empty());
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn most_subscribed_orders_feeds_by_number_of_subscriptions() {
+        let connection = db::establish_connection();
+
+        connection.test_transaction::<_, Error, _>(|| {
+            let popular_feed =
+                super::create(&connection, "Link1".to_string(), "rss".to_string()).unwrap();
+            let other_feed =
+                super::create(&connection, "Link2".to_string(), "rss".to_string()).unwrap();
+            super::create(&connection, "Link3".to_string(), "rss".to_string()).unwrap();
+
+            create_telegram_subscription(&connection, &other_feed);
+            create_telegram_subscription(&connection, &popular_feed);
+
+            let new_chat = NewTelegramChat {
+                id: 43,
+                kind: "private".to_string(),
+                username: None,
+                first_name: Some("Other".to_string()),
+                last_name: None,
+                title: None,
+            };
+            let chat = telegram::create_chat(&connection, new_chat).unwrap();
+
+            telegram::create_subscription(
+                &connection,
+                NewTelegramSubscription {
+                    feed_id: popular_feed.id,
+                    chat_id: chat.id,
+                    user_id: None,
+                },
+            )
+            .unwrap();
+
+            let result = super::most_subscribed(&connection, 10).unwrap();
+
+            assert_eq!(result.len(), 2);
+            assert_eq!(result[0].0.id, popular_feed.id);
+            assert_eq!(result[0].1, 2);
+            assert_eq!(result[1].0.id, other_feed.id);
+            assert_eq!(result[1].1, 1);
+
+            let result = super::most_subscribed(&connection, 1).unwrap();
+
+            assert_eq!(result.len(), 1);
+
+            Ok(())
+        })
+    }
+
     fn create_telegram_subscription(
         connection: &PgConnection,
         feed: &Feed,
@@ -498,6 +1086,7 @@ mod tests {
         let new_subscription = NewTelegramSubscription {
             feed_id: feed.id,
             chat_id: chat.id,
+            user_id: None,
         };
 
         telegram::create_subscription(connection, new_subscription).unwrap()
@@ -2,13 +2,28 @@ use chrono::prelude::*;
 use chrono::{DateTime, Utc};
 use diesel::connection::Connection;
 use diesel::pg::PgConnection;
+use diesel::r2d2::ConnectionManager;
+use diesel::ConnectionError;
 use dotenv::dotenv;
 use std::env;
+use std::fmt::Display;
+use std::thread;
+use std::time::{Duration, Instant};
 
+pub mod credentials;
 pub mod feed_items;
 pub mod feeds;
 pub mod telegram;
 
+static DEFAULT_POOL_SIZE: u32 = 10;
+static DEFAULT_CONNECT_ATTEMPTS: u32 = 10;
+static DEFAULT_CONNECT_TIMEOUT_SECS: u64 = 120;
+static CONNECT_RETRY_DELAY_SECS: u64 = 1;
+static MAX_CONNECT_RETRY_DELAY_SECS: u64 = 30;
+
+pub type Pool = diesel::r2d2::Pool<ConnectionManager<PgConnection>>;
+pub type PooledConnection = diesel::r2d2::PooledConnection<ConnectionManager<PgConnection>>;
+
 pub fn establish_connection() -> PgConnection {
     dotenv().ok();
 
@@ -16,6 +31,179 @@ pub fn establish_connection() -> PgConnection {
     PgConnection::establish(&database_url).expect(&format!("Error connecting to {}", database_url))
 }
 
+pub fn wait_for_database() -> Result<(), ConnectionError> {
+    dotenv().ok();
+
+    let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+
+    wait_for(
+        connect_attempts(),
+        Duration::from_secs(connect_timeout_secs()),
+        Duration::from_secs(CONNECT_RETRY_DELAY_SECS),
+        || PgConnection::establish(&database_url).map(|_| ()),
+    )
+}
+
+fn wait_for<T, E, F>(
+    attempts: u32,
+    timeout: Duration,
+    initial_delay: Duration,
+    mut connect: F,
+) -> Result<T, E>
+where
+    E: Display,
+    F: FnMut() -> Result<T, E>,
+{
+    let started_at = Instant::now();
+    let mut delay = initial_delay;
+    let mut attempt = 1;
+
+    loop {
+        log::info!(
+            "Connecting to the database, attempt {} of {}",
+            attempt,
+            attempts
+        );
+
+        match connect() {
+            Ok(result) => return Ok(result),
+            Err(error) if attempt >= attempts || started_at.elapsed() + delay > timeout => {
+                log::error!("Failed to connect to the database: {}", error);
+
+                return Err(error);
+            }
+            Err(error) => {
+                log::warn!(
+                    "Failed to connect to the database: {}. Retrying in {} ms",
+                    error,
+                    delay.as_millis()
+                );
+
+                thread::sleep(delay);
+
+                delay = (delay * 2).min(Duration::from_secs(MAX_CONNECT_RETRY_DELAY_SECS));
+                attempt += 1;
+            }
+        }
+    }
+}
+
+fn connect_attempts() -> u32 {
+    match env::var("DATABASE_CONNECT_ATTEMPTS") {
+        Ok(value) => value
+            .parse::<u32>()
+            .ok()
+            .filter(|attempts| *attempts > 0)
+            .unwrap_or(DEFAULT_CONNECT_ATTEMPTS),
+        Err(_) => DEFAULT_CONNECT_ATTEMPTS,
+    }
+}
+
+fn connect_timeout_secs() -> u64 {
+    match env::var("DATABASE_CONNECT_TIMEOUT_SECONDS") {
+        Ok(value) => value.parse::<u64>().unwrap_or(DEFAULT_CONNECT_TIMEOUT_SECS),
+        Err(_) => DEFAULT_CONNECT_TIMEOUT_SECS,
+    }
+}
+
+pub fn create_connection_pool() -> Pool {
+    dotenv().ok();
+
+    let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+    let pool_size = match env::var("DATABASE_POOL_SIZE") {
+        Ok(value) => value
+            .parse::<u32>()
+            .ok()
+            .filter(|size| *size > 0)
+            .expect("DATABASE_POOL_SIZE must be a positive number"),
+        Err(_) => DEFAULT_POOL_SIZE,
+    };
+
+    Pool::builder()
+        .max_size(pool_size)
+        .build(ConnectionManager::<PgConnection>::new(database_url))
+        .expect("Failed to create a connection pool")
+}
+
+pub fn get_connection(pool: &Pool) -> PooledConnection {
+    pool.get()
+        .expect("Failed to get a connection from the pool")
+}
+
 pub fn current_time() -> DateTime<Utc> {
     Utc::now().round_subsecs(0)
 }
+
+#[cfg(test)]
+mod tests {
+    use diesel::connection::Connection;
+    use std::time::Duration;
+
+    #[test]
+    fn create_connection_pool_hands_out_working_connections() {
+        let pool = super::create_connection_pool();
+
+        let first = super::get_connection(&pool);
+        let second = super::get_connection(&pool);
+
+        assert!(first.execute("SELECT 1").is_ok());
+        assert!(second.execute("SELECT 1").is_ok());
+    }
+
+    #[test]
+    fn wait_for_database_connects_to_available_database() {
+        assert!(super::wait_for_database().is_ok());
+    }
+
+    #[test]
+    fn wait_for_retries_until_connected() {
+        let mut calls = 0;
+
+        let result: Result<u32, String> =
+            super::wait_for(5, Duration::from_secs(60), Duration::from_millis(0), || {
+                calls += 1;
+
+                if calls < 3 {
+                    Err("connection refused".to_string())
+                } else {
+                    Ok(calls)
+                }
+            });
+
+        assert_eq!(result, Ok(3));
+    }
+
+    #[test]
+    fn wait_for_gives_up_after_attempts() {
+        let mut calls = 0;
+
+        let result: Result<(), String> =
+            super::wait_for(3, Duration::from_secs(60), Duration::from_millis(0), || {
+                calls += 1;
+
+                Err(format!("attempt {}", calls))
+            });
+
+        assert_eq!(result, Err("attempt 3".to_string()));
+        assert_eq!(calls, 3);
+    }
+
+    #[test]
+    fn wait_for_gives_up_when_next_attempt_exceeds_timeout() {
+        let mut calls = 0;
+
+        let result: Result<(), String> = super::wait_for(
+            10,
+            Duration::from_millis(50),
+            Duration::from_secs(1),
+            || {
+                calls += 1;
+
+                Err("connection refused".to_string())
+            },
+        );
+
+        assert!(result.is_err());
+        assert_eq!(calls, 1);
+    }
+}
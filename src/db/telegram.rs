@@ -2,15 +2,38 @@ use crate::db;
 use crate::models::feed::Feed;
 use crate::models::feed_item::FeedItem;
 use crate::models::telegram_chat::TelegramChat;
+use crate::models::telegram_chat_watchword::TelegramChatWatchword;
+use crate::models::telegram_delivered_item::TelegramDeliveredItem;
 use crate::models::telegram_subscription::TelegramSubscription;
+use crate::models::telegram_subscription_filter::TelegramSubscriptionFilter;
 use crate::schema::feed_items;
-use crate::schema::{feeds, telegram_chats, telegram_subscriptions};
+use crate::schema::{
+    feeds, telegram_chat_watchwords, telegram_chats, telegram_delivered_items,
+    telegram_subscription_filters, telegram_subscriptions,
+};
 
 use chrono::{DateTime, Duration, Utc};
 use diesel::dsl::*;
 use diesel::pg::upsert::excluded;
 use diesel::result::Error;
-use diesel::{ExpressionMethods, PgConnection, QueryDsl, RunQueryDsl};
+use diesel::{
+    BoolExpressionMethods, Connection, ExpressionMethods, JoinOnDsl, OptionalExtension,
+    PgConnection, PgTextExpressionMethods, QueryDsl, RunQueryDsl,
+};
+
+pub static RESTORE_PERIOD_HOURS: i64 = 24;
+pub static RANDOM_ITEM_PERIOD_DAYS: i64 = 30;
+pub static MAX_FORBIDDEN_ERRORS_COUNT: i32 = 3;
+pub static INACTIVE_CHAT_GRACE_PERIOD_DAYS: i64 = 30;
+pub static INITIAL_ITEMS_PERIOD_DAYS: i64 = 365;
+
+no_arg_sql_function!(random, diesel::sql_types::Double);
+sql_function!(
+    fn coalesce(
+        x: diesel::sql_types::Nullable<diesel::sql_types::Timestamptz>,
+        y: diesel::sql_types::Timestamptz
+    ) -> diesel::sql_types::Timestamptz
+);
 
 #[derive(Insertable, Clone)]
 #[table_name = "telegram_chats"]
@@ -28,6 +51,56 @@ pub struct NewTelegramChat {
 pub struct NewTelegramSubscription {
     pub chat_id: i64,
     pub feed_id: i64,
+    pub user_id: Option<i64>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ChatStats {
+    pub subscriptions_count: i64,
+    pub delivered_count: i64,
+    pub delivered_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Queryable, Debug, PartialEq)]
+pub struct FeedHealth {
+    pub link: String,
+    pub synced_at: Option<DateTime<Utc>>,
+    pub failures_count: i32,
+    pub dead: bool,
+}
+
+#[derive(Queryable, Debug, PartialEq)]
+pub struct LatestFeedItem {
+    pub link: String,
+    pub feed_title: Option<String>,
+    pub item_title: String,
+    pub published_at: DateTime<Utc>,
+}
+
+#[derive(Insertable, Clone)]
+#[table_name = "telegram_delivered_items"]
+pub struct NewTelegramDeliveredItem {
+    pub chat_id: i64,
+    pub feed_id: i64,
+    pub title: String,
+    pub link: String,
+    pub published_at: DateTime<Utc>,
+    pub user_id: Option<i64>,
+}
+
+#[derive(Insertable, Clone)]
+#[table_name = "telegram_subscription_filters"]
+pub struct NewTelegramSubscriptionFilter {
+    pub chat_id: i64,
+    pub feed_id: i64,
+    pub keyword: String,
+}
+
+#[derive(Insertable, Clone)]
+#[table_name = "telegram_chat_watchwords"]
+pub struct NewTelegramChatWatchword {
+    pub chat_id: i64,
+    pub keyword: String,
 }
 
 pub fn create_chat(conn: &PgConnection, new_chat: NewTelegramChat) -> Result<TelegramChat, Error> {
@@ -42,6 +115,7 @@ pub fn create_chat(conn: &PgConnection, new_chat: NewTelegramChat) -> Result<Tel
             telegram_chats::first_name.eq(excluded(telegram_chats::first_name)),
             telegram_chats::last_name.eq(excluded(telegram_chats::last_name)),
             telegram_chats::title.eq(excluded(telegram_chats::title)),
+            telegram_chats::active.eq(true),
         ))
         .get_result::<TelegramChat>(conn)
 }
@@ -61,170 +135,966 @@ pub fn set_utc_offset_minutes(
     chat: &TelegramChat,
     offset: i32,
 ) -> Result<TelegramChat, Error> {
+    let timezone_name: Option<String> = None;
+
     diesel::update(chat)
-        .set(telegram_chats::utc_offset_minutes.eq(offset))
+        .set((
+            telegram_chats::utc_offset_minutes.eq(offset),
+            telegram_chats::timezone_name.eq(timezone_name),
+        ))
         .get_result::<TelegramChat>(conn)
 }
 
-pub fn create_subscription(
+pub fn set_timezone_name(
     conn: &PgConnection,
-    subscription: NewTelegramSubscription,
-) -> Result<TelegramSubscription, Error> {
-    diesel::insert_into(telegram_subscriptions::table)
-        .values(subscription)
-        .get_result::<TelegramSubscription>(conn)
+    chat: &TelegramChat,
+    timezone_name: String,
+    offset: i32,
+) -> Result<TelegramChat, Error> {
+    diesel::update(chat)
+        .set((
+            telegram_chats::utc_offset_minutes.eq(offset),
+            telegram_chats::timezone_name.eq(timezone_name),
+        ))
+        .get_result::<TelegramChat>(conn)
 }
 
-pub fn find_subscription(
+pub fn set_max_subscriptions(
     conn: &PgConnection,
-    subscription: NewTelegramSubscription,
-) -> Option<TelegramSubscription> {
-    match telegram_subscriptions::table
-        .filter(telegram_subscriptions::chat_id.eq(subscription.chat_id))
-        .filter(telegram_subscriptions::feed_id.eq(subscription.feed_id))
-        .first::<TelegramSubscription>(conn)
-    {
-        Ok(record) => Some(record),
-        _ => None,
-    }
+    chat: &TelegramChat,
+    max_subscriptions: i32,
+) -> Result<TelegramChat, Error> {
+    diesel::update(chat)
+        .set(telegram_chats::max_subscriptions.eq(max_subscriptions))
+        .get_result::<TelegramChat>(conn)
 }
 
-pub fn remove_subscription(
+pub fn set_paused(
     conn: &PgConnection,
-    subscription: NewTelegramSubscription,
-) -> Result<usize, Error> {
-    let record_query = telegram_subscriptions::table
-        .filter(telegram_subscriptions::chat_id.eq(subscription.chat_id))
-        .filter(telegram_subscriptions::feed_id.eq(subscription.feed_id));
+    chat: &TelegramChat,
+    paused: bool,
+) -> Result<TelegramChat, Error> {
+    diesel::update(chat)
+        .set(telegram_chats::paused.eq(paused))
+        .get_result::<TelegramChat>(conn)
+}
 
-    diesel::delete(record_query).execute(conn)
+pub fn set_description_limit(
+    conn: &PgConnection,
+    chat: &TelegramChat,
+    description_limit: i32,
+) -> Result<TelegramChat, Error> {
+    diesel::update(chat)
+        .set(telegram_chats::description_limit.eq(description_limit))
+        .get_result::<TelegramChat>(conn)
 }
 
-pub fn remove_chat(conn: &PgConnection, chat_id: i64) -> Result<usize, Error> {
-    let record_query = telegram_chats::table.filter(telegram_chats::id.eq(chat_id));
+pub fn set_language(
+    conn: &PgConnection,
+    chat: &TelegramChat,
+    language: String,
+) -> Result<TelegramChat, Error> {
+    diesel::update(chat)
+        .set(telegram_chats::language.eq(language))
+        .get_result::<TelegramChat>(conn)
+}
 
-    diesel::delete(record_query).execute(conn)
+pub fn set_quiet_hours(
+    conn: &PgConnection,
+    chat: &TelegramChat,
+    quiet_hours: Option<(i32, i32)>,
+) -> Result<TelegramChat, Error> {
+    diesel::update(chat)
+        .set((
+            telegram_chats::quiet_hours_start.eq(quiet_hours.map(|(start, _)| start)),
+            telegram_chats::quiet_hours_end.eq(quiet_hours.map(|(_, end)| end)),
+        ))
+        .get_result::<TelegramChat>(conn)
 }
 
-pub fn count_subscriptions_for_chat(conn: &PgConnection, chat_id: i64) -> i64 {
-    telegram_subscriptions::table
-        .filter(telegram_subscriptions::chat_id.eq(chat_id))
-        .count()
-        .get_result::<i64>(conn)
-        .unwrap()
+pub fn set_track_per_user(
+    conn: &PgConnection,
+    chat: &TelegramChat,
+    track_per_user: bool,
+) -> Result<TelegramChat, Error> {
+    diesel::update(chat)
+        .set(telegram_chats::track_per_user.eq(track_per_user))
+        .get_result::<TelegramChat>(conn)
 }
 
-pub fn find_feeds_by_chat_id(conn: &PgConnection, chat_id: i64) -> Result<Vec<Feed>, Error> {
-    let feed_ids = telegram_subscriptions::table
-        .filter(telegram_subscriptions::chat_id.eq(chat_id))
-        .select(telegram_subscriptions::feed_id);
+pub fn set_backfill_newest_first(
+    conn: &PgConnection,
+    chat: &TelegramChat,
+    backfill_newest_first: bool,
+) -> Result<TelegramChat, Error> {
+    diesel::update(chat)
+        .set(telegram_chats::backfill_newest_first.eq(backfill_newest_first))
+        .get_result::<TelegramChat>(conn)
+}
 
-    feeds::table
-        .filter(feeds::id.eq(any(feed_ids)))
-        .get_results::<Feed>(conn)
+pub fn set_show_author(
+    conn: &PgConnection,
+    chat: &TelegramChat,
+    show_author: bool,
+) -> Result<TelegramChat, Error> {
+    diesel::update(chat)
+        .set(telegram_chats::show_author.eq(show_author))
+        .get_result::<TelegramChat>(conn)
 }
 
-pub fn find_chats_by_feed_id(
+pub fn set_item_prefix(
     conn: &PgConnection,
-    feed_id: i64,
-) -> Result<Vec<TelegramChat>, Error> {
-    let chat_ids = telegram_subscriptions::table
-        .filter(telegram_subscriptions::feed_id.eq(feed_id))
-        .select(telegram_subscriptions::chat_id);
+    chat: &TelegramChat,
+    item_prefix: Option<String>,
+) -> Result<TelegramChat, Error> {
+    diesel::update(chat)
+        .set(telegram_chats::item_prefix.eq(item_prefix))
+        .get_result::<TelegramChat>(conn)
+}
+
+pub fn set_digest(
+    conn: &PgConnection,
+    chat: &TelegramChat,
+    digest: bool,
+    hour: i32,
+) -> Result<TelegramChat, Error> {
+    diesel::update(chat)
+        .set((
+            telegram_chats::digest.eq(digest),
+            telegram_chats::digest_hour.eq(hour),
+        ))
+        .get_result::<TelegramChat>(conn)
+}
+
+pub fn set_digest_sent_at(
+    conn: &PgConnection,
+    chat: &TelegramChat,
+    sent_at: DateTime<Utc>,
+) -> Result<TelegramChat, Error> {
+    diesel::update(chat)
+        .set(telegram_chats::digest_sent_at.eq(sent_at))
+        .get_result::<TelegramChat>(conn)
+}
+
+pub fn increment_forbidden_errors_count(
+    conn: &PgConnection,
+    chat_id: i64,
+) -> Result<TelegramChat, Error> {
+    let record_query = telegram_chats::table.filter(telegram_chats::id.eq(chat_id));
+
+    diesel::update(record_query)
+        .set(telegram_chats::forbidden_errors_count.eq(telegram_chats::forbidden_errors_count + 1))
+        .get_result::<TelegramChat>(conn)
+}
+
+pub fn reset_forbidden_errors_count(
+    conn: &PgConnection,
+    chat: &TelegramChat,
+) -> Result<TelegramChat, Error> {
+    diesel::update(chat)
+        .set(telegram_chats::forbidden_errors_count.eq(0))
+        .get_result::<TelegramChat>(conn)
+}
 
+pub fn find_disabled_chats(conn: &PgConnection) -> Result<Vec<TelegramChat>, Error> {
     telegram_chats::table
-        .filter(telegram_chats::id.eq(any(chat_ids)))
+        .filter(telegram_chats::paused.eq(true))
+        .filter(telegram_chats::forbidden_errors_count.ge(MAX_FORBIDDEN_ERRORS_COUNT))
+        .order(telegram_chats::id)
         .get_results::<TelegramChat>(conn)
 }
 
-pub fn fetch_subscriptions(
+pub fn find_digest_chats(conn: &PgConnection) -> Result<Vec<TelegramChat>, Error> {
+    telegram_chats::table
+        .filter(telegram_chats::digest.eq(true))
+        .filter(telegram_chats::paused.eq(false))
+        .filter(telegram_chats::active.eq(true))
+        .order(telegram_chats::id)
+        .get_results::<TelegramChat>(conn)
+}
+
+pub fn create_subscription(
     conn: &PgConnection,
-    page: i64,
-    count: i64,
-) -> Result<Vec<TelegramSubscription>, Error> {
-    let offset = (page - 1) * count;
+    subscription: NewTelegramSubscription,
+) -> Result<TelegramSubscription, Error> {
+    let deleted_query = telegram_subscriptions::table
+        .filter(telegram_subscriptions::chat_id.eq(subscription.chat_id))
+        .filter(telegram_subscriptions::feed_id.eq(subscription.feed_id))
+        .filter(telegram_subscriptions::deleted_at.is_not_null());
 
-    telegram_subscriptions::table
-        .order(telegram_subscriptions::chat_id)
-        .limit(count)
-        .offset(offset)
-        .get_results(conn)
+    diesel::delete(deleted_query).execute(conn)?;
+
+    diesel::insert_into(telegram_subscriptions::table)
+        .values(subscription)
+        .get_result::<TelegramSubscription>(conn)
 }
 
-pub fn find_undelivered_feed_items(
+pub fn copy_subscription(
     conn: &PgConnection,
-    subscription: &TelegramSubscription,
-) -> Result<Vec<FeedItem>, Error> {
-    let last_delivered_at = match subscription.last_delivered_at {
-        Some(value) => value,
-        None => db::current_time() - Duration::days(365),
-    };
+    source: &TelegramSubscription,
+    chat_id: i64,
+) -> Result<TelegramSubscription, Error> {
+    conn.transaction::<TelegramSubscription, Error, _>(|| {
+        let subscription = create_subscription(
+            conn,
+            NewTelegramSubscription {
+                chat_id,
+                feed_id: source.feed_id,
+                user_id: None,
+            },
+        )?;
+
+        let subscription = diesel::update(&subscription)
+            .set((
+                telegram_subscriptions::initial_items_count.eq(source.initial_items_count),
+                telegram_subscriptions::template.eq(source.template.clone()),
+                telegram_subscriptions::silent.eq(source.silent),
+                telegram_subscriptions::min_delivery_interval_secs
+                    .eq(source.min_delivery_interval_secs),
+                telegram_subscriptions::link_preview.eq(source.link_preview),
+                telegram_subscriptions::full_text.eq(source.full_text),
+                telegram_subscriptions::markdown.eq(source.markdown),
+                telegram_subscriptions::html.eq(source.html),
+            ))
+            .get_result::<TelegramSubscription>(conn)?;
+
+        let filters = find_filters(
+            conn,
+            NewTelegramSubscription {
+                chat_id: source.chat_id,
+                feed_id: source.feed_id,
+                user_id: None,
+            },
+        )?;
+
+        for filter in filters {
+            create_filter(
+                conn,
+                NewTelegramSubscriptionFilter {
+                    chat_id,
+                    feed_id: source.feed_id,
+                    keyword: filter.keyword,
+                },
+            )?;
+        }
 
-    feed_items::table
-        .filter(feed_items::publication_date.gt(last_delivered_at))
-        .filter(feed_items::feed_id.eq(subscription.feed_id))
-        .order(feed_items::publication_date.desc())
-        .limit(10)
-        .get_results(conn)
+        Ok(subscription)
+    })
 }
 
-pub fn count_undelivered_feed_items(
+pub fn set_initial_items_count(
     conn: &PgConnection,
     subscription: &TelegramSubscription,
-) -> i64 {
-    let last_delivered_at = match subscription.last_delivered_at {
-        Some(value) => value,
-        None => db::current_time() - Duration::days(1),
-    };
-
-    feed_items::table
-        .filter(feed_items::publication_date.gt(last_delivered_at))
-        .filter(feed_items::feed_id.eq(subscription.feed_id))
-        .count()
-        .get_result::<i64>(conn)
-        .unwrap()
+    initial_items_count: i32,
+) -> Result<TelegramSubscription, Error> {
+    diesel::update(subscription)
+        .set(telegram_subscriptions::initial_items_count.eq(initial_items_count))
+        .get_result::<TelegramSubscription>(conn)
 }
 
-pub fn set_subscription_last_delivered_at(
+pub fn set_template(
     conn: &PgConnection,
     subscription: &TelegramSubscription,
-    last_delivered_at: DateTime<Utc>,
+    template: Option<String>,
 ) -> Result<TelegramSubscription, Error> {
     diesel::update(subscription)
-        .set(telegram_subscriptions::last_delivered_at.eq(last_delivered_at))
+        .set(telegram_subscriptions::template.eq(template))
         .get_result::<TelegramSubscription>(conn)
 }
 
-#[cfg(test)]
-mod tests {
-    use super::NewTelegramChat;
-    use super::NewTelegramSubscription;
-    use crate::db;
-    use crate::db::feeds;
-    use crate::models::telegram_chat::TelegramChat;
-    use diesel::connection::Connection;
-    use diesel::result::Error;
+pub fn set_silent(
+    conn: &PgConnection,
+    subscription: &TelegramSubscription,
+    silent: bool,
+) -> Result<TelegramSubscription, Error> {
+    diesel::update(subscription)
+        .set(telegram_subscriptions::silent.eq(silent))
+        .get_result::<TelegramSubscription>(conn)
+}
 
-    #[test]
-    fn create_chat_creates_new_telegram_chat() {
-        let new_chat = build_new_chat();
-        let connection = db::establish_connection();
+pub fn set_link_preview(
+    conn: &PgConnection,
+    subscription: &TelegramSubscription,
+    link_preview: bool,
+) -> Result<TelegramSubscription, Error> {
+    diesel::update(subscription)
+        .set(telegram_subscriptions::link_preview.eq(link_preview))
+        .get_result::<TelegramSubscription>(conn)
+}
 
-        let result = connection.test_transaction::<TelegramChat, Error, _>(|| {
-            super::create_chat(&connection, new_chat.clone())
-        });
+pub fn set_min_delivery_interval(
+    conn: &PgConnection,
+    subscription: &TelegramSubscription,
+    interval_secs: Option<i32>,
+) -> Result<TelegramSubscription, Error> {
+    diesel::update(subscription)
+        .set(telegram_subscriptions::min_delivery_interval_secs.eq(interval_secs))
+        .get_result::<TelegramSubscription>(conn)
+}
 
-        assert_eq!(result.id, new_chat.id);
-        assert_eq!(result.kind, new_chat.kind);
-        assert_eq!(result.username, new_chat.username);
-        assert_eq!(result.first_name, new_chat.first_name);
-        assert_eq!(result.last_name, new_chat.last_name);
-    }
+pub fn set_full_text(
+    conn: &PgConnection,
+    subscription: &TelegramSubscription,
+    full_text: bool,
+) -> Result<TelegramSubscription, Error> {
+    diesel::update(subscription)
+        .set(telegram_subscriptions::full_text.eq(full_text))
+        .get_result::<TelegramSubscription>(conn)
+}
 
-    #[test]
-    fn it_updates_telegram_chat() {
-        let new_chat = NewTelegramChat {
-            id: 42,
+pub fn set_markdown(
+    conn: &PgConnection,
+    subscription: &TelegramSubscription,
+    markdown: bool,
+) -> Result<TelegramSubscription, Error> {
+    // items are delivered only in one format, so the other one is turned off
+    diesel::update(subscription)
+        .set((
+            telegram_subscriptions::markdown.eq(markdown),
+            telegram_subscriptions::html.eq(subscription.html && !markdown),
+        ))
+        .get_result::<TelegramSubscription>(conn)
+}
+
+pub fn set_html(
+    conn: &PgConnection,
+    subscription: &TelegramSubscription,
+    html: bool,
+) -> Result<TelegramSubscription, Error> {
+    diesel::update(subscription)
+        .set((
+            telegram_subscriptions::html.eq(html),
+            telegram_subscriptions::markdown.eq(subscription.markdown && !html),
+        ))
+        .get_result::<TelegramSubscription>(conn)
+}
+
+pub fn set_snoozed_until(
+    conn: &PgConnection,
+    subscription: &TelegramSubscription,
+    snoozed_until: Option<DateTime<Utc>>,
+) -> Result<TelegramSubscription, Error> {
+    diesel::update(subscription)
+        .set(telegram_subscriptions::snoozed_until.eq(snoozed_until))
+        .get_result::<TelegramSubscription>(conn)
+}
+
+pub fn set_credentials(
+    conn: &PgConnection,
+    subscription: &TelegramSubscription,
+    username: String,
+    password: String,
+) -> Result<TelegramSubscription, Error> {
+    diesel::update(subscription)
+        .set((
+            telegram_subscriptions::auth_username.eq(username),
+            telegram_subscriptions::auth_password.eq(password),
+        ))
+        .get_result::<TelegramSubscription>(conn)
+}
+
+pub fn set_subscription_feed(
+    conn: &PgConnection,
+    subscription: &TelegramSubscription,
+    feed_id: i64,
+) -> Result<TelegramSubscription, Error> {
+    let deleted_query = telegram_subscriptions::table
+        .filter(telegram_subscriptions::chat_id.eq(subscription.chat_id))
+        .filter(telegram_subscriptions::feed_id.eq(feed_id))
+        .filter(telegram_subscriptions::deleted_at.is_not_null());
+
+    diesel::delete(deleted_query).execute(conn)?;
+
+    diesel::update(subscription)
+        .set(telegram_subscriptions::feed_id.eq(feed_id))
+        .get_result::<TelegramSubscription>(conn)
+}
+
+pub fn find_authenticated_subscription(
+    conn: &PgConnection,
+    feed_id: i64,
+) -> Option<TelegramSubscription> {
+    match telegram_subscriptions::table
+        .filter(telegram_subscriptions::feed_id.eq(feed_id))
+        .filter(telegram_subscriptions::auth_username.is_not_null())
+        .filter(telegram_subscriptions::deleted_at.is_null())
+        .order(telegram_subscriptions::created_at)
+        .first::<TelegramSubscription>(conn)
+    {
+        Ok(record) => Some(record),
+        _ => None,
+    }
+}
+
+pub fn find_subscription(
+    conn: &PgConnection,
+    subscription: NewTelegramSubscription,
+) -> Option<TelegramSubscription> {
+    match telegram_subscriptions::table
+        .filter(telegram_subscriptions::chat_id.eq(subscription.chat_id))
+        .filter(telegram_subscriptions::feed_id.eq(subscription.feed_id))
+        .filter(telegram_subscriptions::deleted_at.is_null())
+        .first::<TelegramSubscription>(conn)
+    {
+        Ok(record) => Some(record),
+        _ => None,
+    }
+}
+
+pub fn remove_subscription(
+    conn: &PgConnection,
+    subscription: NewTelegramSubscription,
+) -> Result<usize, Error> {
+    let record_query = telegram_subscriptions::table
+        .filter(telegram_subscriptions::chat_id.eq(subscription.chat_id))
+        .filter(telegram_subscriptions::feed_id.eq(subscription.feed_id))
+        .filter(telegram_subscriptions::deleted_at.is_null());
+
+    diesel::update(record_query)
+        .set(telegram_subscriptions::deleted_at.eq(db::current_time()))
+        .execute(conn)
+}
+
+pub fn find_restorable_subscription(
+    conn: &PgConnection,
+    subscription: NewTelegramSubscription,
+) -> Option<TelegramSubscription> {
+    let deleted_after = db::current_time() - Duration::hours(RESTORE_PERIOD_HOURS);
+
+    match telegram_subscriptions::table
+        .filter(telegram_subscriptions::chat_id.eq(subscription.chat_id))
+        .filter(telegram_subscriptions::feed_id.eq(subscription.feed_id))
+        .filter(telegram_subscriptions::deleted_at.gt(deleted_after))
+        .first::<TelegramSubscription>(conn)
+    {
+        Ok(record) => Some(record),
+        _ => None,
+    }
+}
+
+pub fn restore_subscription(
+    conn: &PgConnection,
+    subscription: &TelegramSubscription,
+) -> Result<TelegramSubscription, Error> {
+    diesel::update(subscription)
+        .set(telegram_subscriptions::deleted_at.eq(None::<DateTime<Utc>>))
+        .get_result::<TelegramSubscription>(conn)
+}
+
+pub fn remove_expired_subscriptions(conn: &PgConnection) -> Result<usize, Error> {
+    let deleted_before = db::current_time() - Duration::hours(RESTORE_PERIOD_HOURS);
+
+    let record_query =
+        telegram_subscriptions::table.filter(telegram_subscriptions::deleted_at.le(deleted_before));
+
+    diesel::delete(record_query).execute(conn)
+}
+
+pub fn remove_all_subscriptions_for_chat(
+    conn: &PgConnection,
+    chat_id: i64,
+) -> Result<usize, Error> {
+    let record_query = telegram_subscriptions::table
+        .filter(telegram_subscriptions::chat_id.eq(chat_id))
+        .filter(telegram_subscriptions::deleted_at.is_null());
+
+    diesel::update(record_query)
+        .set(telegram_subscriptions::deleted_at.eq(db::current_time()))
+        .execute(conn)
+}
+
+pub fn remove_chat(conn: &PgConnection, chat_id: i64) -> Result<usize, Error> {
+    let record_query = telegram_chats::table.filter(telegram_chats::id.eq(chat_id));
+
+    diesel::delete(record_query).execute(conn)
+}
+
+pub fn delete_chat_cascade(conn: &PgConnection, chat_id: i64) -> Result<usize, Error> {
+    conn.transaction::<usize, Error, _>(|| {
+        let subscriptions_query =
+            telegram_subscriptions::table.filter(telegram_subscriptions::chat_id.eq(chat_id));
+
+        diesel::delete(subscriptions_query).execute(conn)?;
+
+        remove_chat(conn, chat_id)
+    })
+}
+
+pub fn mark_chat_inactive(conn: &PgConnection, chat_id: i64) -> Result<usize, Error> {
+    let record_query = telegram_chats::table.filter(telegram_chats::id.eq(chat_id));
+
+    diesel::update(record_query)
+        .set((
+            telegram_chats::active.eq(false),
+            telegram_chats::updated_at.eq(db::current_time()),
+        ))
+        .execute(conn)
+}
+
+pub fn remove_inactive_chats(conn: &PgConnection) -> Result<usize, Error> {
+    let inactive_before = db::current_time() - Duration::days(INACTIVE_CHAT_GRACE_PERIOD_DAYS);
+
+    let record_query = telegram_chats::table
+        .filter(telegram_chats::active.eq(false))
+        .filter(telegram_chats::updated_at.le(inactive_before));
+
+    diesel::delete(record_query).execute(conn)
+}
+
+pub fn count_subscriptions_for_chat(conn: &PgConnection, chat_id: i64) -> i64 {
+    telegram_subscriptions::table
+        .filter(telegram_subscriptions::chat_id.eq(chat_id))
+        .filter(telegram_subscriptions::deleted_at.is_null())
+        .count()
+        .get_result::<i64>(conn)
+        .unwrap()
+}
+
+pub fn count_subscriptions(conn: &PgConnection) -> Result<i64, Error> {
+    telegram_subscriptions::table
+        .filter(telegram_subscriptions::deleted_at.is_null())
+        .count()
+        .get_result::<i64>(conn)
+}
+
+pub fn count_active_chats(conn: &PgConnection) -> Result<i64, Error> {
+    telegram_chats::table
+        .filter(telegram_chats::active.eq(true))
+        .count()
+        .get_result::<i64>(conn)
+}
+
+pub fn find_feeds_by_chat_id(
+    conn: &PgConnection,
+    chat_id: i64,
+    feed_type: Option<&str>,
+) -> Result<Vec<Feed>, Error> {
+    let mut query = telegram_subscriptions::table
+        .inner_join(feeds::table)
+        .filter(telegram_subscriptions::chat_id.eq(chat_id))
+        .filter(telegram_subscriptions::deleted_at.is_null())
+        .select(feeds::all_columns)
+        .into_boxed();
+
+    if let Some(feed_type) = feed_type {
+        query = query.filter(feeds::feed_type.eq(feed_type));
+    }
+
+    query
+        .order((telegram_subscriptions::created_at, feeds::id))
+        .get_results::<Feed>(conn)
+}
+
+pub fn subscriptions_with_health(
+    conn: &PgConnection,
+    chat_id: i64,
+) -> Result<Vec<FeedHealth>, Error> {
+    telegram_subscriptions::table
+        .inner_join(feeds::table)
+        .filter(telegram_subscriptions::chat_id.eq(chat_id))
+        .filter(telegram_subscriptions::deleted_at.is_null())
+        .select((
+            feeds::link,
+            feeds::synced_at,
+            feeds::failures_count,
+            feeds::dead,
+        ))
+        .order((telegram_subscriptions::created_at, feeds::id))
+        .get_results::<FeedHealth>(conn)
+}
+
+pub fn latest_items_of_subscriptions(
+    conn: &PgConnection,
+    chat_id: i64,
+    limit: usize,
+) -> Result<Vec<LatestFeedItem>, Error> {
+    let mut items = telegram_subscriptions::table
+        .inner_join(feeds::table.inner_join(feed_items::table))
+        .filter(telegram_subscriptions::chat_id.eq(chat_id))
+        .filter(telegram_subscriptions::deleted_at.is_null())
+        .select((
+            feeds::link,
+            feeds::title,
+            feed_items::title,
+            feed_items::publication_date,
+        ))
+        .distinct_on(feeds::id)
+        .order((feeds::id, feed_items::publication_date.desc()))
+        .get_results::<LatestFeedItem>(conn)?;
+
+    items.sort_by(|item1, item2| item2.published_at.cmp(&item1.published_at));
+    items.truncate(limit);
+
+    Ok(items)
+}
+
+pub fn unread_counts(conn: &PgConnection, chat_id: i64) -> Result<Vec<(Feed, i64)>, Error> {
+    let initial_cursor = db::current_time() - Duration::days(INITIAL_ITEMS_PERIOD_DAYS);
+
+    let counts = telegram_subscriptions::table
+        .inner_join(feeds::table)
+        .left_join(feed_items::table.on(
+            feed_items::feed_id.eq(telegram_subscriptions::feed_id).and(
+                feed_items::publication_date.gt(coalesce(
+                    telegram_subscriptions::last_delivered_at,
+                    initial_cursor,
+                )),
+            ),
+        ))
+        .filter(telegram_subscriptions::chat_id.eq(chat_id))
+        .filter(telegram_subscriptions::deleted_at.is_null())
+        .group_by((
+            feeds::id,
+            telegram_subscriptions::chat_id,
+            telegram_subscriptions::feed_id,
+        ))
+        .select((
+            feeds::all_columns,
+            telegram_subscriptions::last_delivered_at,
+            telegram_subscriptions::initial_items_count,
+            count(feed_items::link.nullable()),
+        ))
+        .order((telegram_subscriptions::created_at, feeds::id))
+        .load::<(Feed, Option<DateTime<Utc>>, i32, i64)>(conn)?;
+
+    Ok(counts
+        .into_iter()
+        .map(|(feed, last_delivered_at, initial_items_count, count)| {
+            (
+                feed,
+                cap_undelivered_count(last_delivered_at, initial_items_count, count),
+            )
+        })
+        .collect())
+}
+
+pub fn random_feed_item(conn: &PgConnection, chat_id: i64) -> Result<Option<FeedItem>, Error> {
+    let published_after = db::current_time() - Duration::days(RANDOM_ITEM_PERIOD_DAYS);
+    let feed_ids = telegram_subscriptions::table
+        .filter(telegram_subscriptions::chat_id.eq(chat_id))
+        .filter(telegram_subscriptions::deleted_at.is_null())
+        .select(telegram_subscriptions::feed_id);
+
+    // only items which were delivered to the chat, feeds keep items older than subscriptions
+    telegram_delivered_items::table
+        .inner_join(
+            feed_items::table.on(feed_items::feed_id
+                .eq(telegram_delivered_items::feed_id)
+                .and(feed_items::link.eq(telegram_delivered_items::link))),
+        )
+        .filter(telegram_delivered_items::chat_id.eq(chat_id))
+        .filter(telegram_delivered_items::feed_id.eq(any(feed_ids)))
+        .filter(telegram_delivered_items::published_at.gt(published_after))
+        .select(feed_items::all_columns)
+        .order(random)
+        .first::<FeedItem>(conn)
+        .optional()
+}
+
+pub fn find_chats_by_feed_id(
+    conn: &PgConnection,
+    feed_id: i64,
+) -> Result<Vec<TelegramChat>, Error> {
+    let chat_ids = telegram_subscriptions::table
+        .filter(telegram_subscriptions::feed_id.eq(feed_id))
+        .filter(telegram_subscriptions::deleted_at.is_null())
+        .select(telegram_subscriptions::chat_id);
+
+    telegram_chats::table
+        .filter(telegram_chats::id.eq(any(chat_ids)))
+        .get_results::<TelegramChat>(conn)
+}
+
+pub fn all_chat_ids(conn: &PgConnection) -> Result<Vec<i64>, Error> {
+    telegram_subscriptions::table
+        .inner_join(telegram_chats::table)
+        .filter(telegram_chats::active.eq(true))
+        .filter(telegram_subscriptions::deleted_at.is_null())
+        .select(telegram_subscriptions::chat_id)
+        .distinct()
+        .order(telegram_subscriptions::chat_id)
+        .get_results::<i64>(conn)
+}
+
+pub fn fetch_subscriptions(
+    conn: &PgConnection,
+    page: i64,
+    count: i64,
+) -> Result<Vec<TelegramSubscription>, Error> {
+    let offset = (page - 1) * count;
+
+    telegram_subscriptions::table
+        .inner_join(telegram_chats::table)
+        .filter(telegram_chats::paused.eq(false))
+        .filter(telegram_chats::active.eq(true))
+        .filter(telegram_chats::digest.eq(false))
+        .filter(telegram_subscriptions::deleted_at.is_null())
+        .order(telegram_subscriptions::chat_id)
+        .select(telegram_subscriptions::all_columns)
+        .limit(count)
+        .offset(offset)
+        .get_results(conn)
+}
+
+pub fn find_subscriptions_for_chat(
+    conn: &PgConnection,
+    chat_id: i64,
+) -> Result<Vec<TelegramSubscription>, Error> {
+    telegram_subscriptions::table
+        .filter(telegram_subscriptions::chat_id.eq(chat_id))
+        .filter(telegram_subscriptions::deleted_at.is_null())
+        .get_results(conn)
+}
+
+pub fn find_undelivered_feed_items(
+    conn: &PgConnection,
+    subscription: &TelegramSubscription,
+) -> Result<Vec<FeedItem>, Error> {
+    let (last_delivered_at, limit) = match subscription.last_delivered_at {
+        Some(value) => (value, 10),
+        None => (
+            db::current_time() - Duration::days(INITIAL_ITEMS_PERIOD_DAYS),
+            subscription.initial_items_count as i64,
+        ),
+    };
+
+    feed_items::table
+        .filter(feed_items::publication_date.gt(last_delivered_at))
+        .filter(feed_items::feed_id.eq(subscription.feed_id))
+        .order(feed_items::publication_date.desc())
+        .limit(limit)
+        .get_results(conn)
+}
+
+pub fn count_undelivered_feed_items(
+    conn: &PgConnection,
+    subscription: &TelegramSubscription,
+) -> Result<i64, Error> {
+    let last_delivered_at = match subscription.last_delivered_at {
+        Some(value) => value,
+        None => db::current_time() - Duration::days(INITIAL_ITEMS_PERIOD_DAYS),
+    };
+
+    let count = feed_items::table
+        .filter(feed_items::publication_date.gt(last_delivered_at))
+        .filter(feed_items::feed_id.eq(subscription.feed_id))
+        .count()
+        .get_result::<i64>(conn)?;
+
+    Ok(cap_undelivered_count(
+        subscription.last_delivered_at,
+        subscription.initial_items_count,
+        count,
+    ))
+}
+
+// subscriptions without deliveries only get their initial items
+fn cap_undelivered_count(
+    last_delivered_at: Option<DateTime<Utc>>,
+    initial_items_count: i32,
+    count: i64,
+) -> i64 {
+    match last_delivered_at {
+        Some(_) => count,
+        None => count.min(initial_items_count as i64),
+    }
+}
+
+pub fn create_filter(
+    conn: &PgConnection,
+    filter: NewTelegramSubscriptionFilter,
+) -> Result<TelegramSubscriptionFilter, Error> {
+    diesel::insert_into(telegram_subscription_filters::table)
+        .values(filter)
+        .get_result::<TelegramSubscriptionFilter>(conn)
+}
+
+pub fn find_filters(
+    conn: &PgConnection,
+    subscription: NewTelegramSubscription,
+) -> Result<Vec<TelegramSubscriptionFilter>, Error> {
+    telegram_subscription_filters::table
+        .filter(telegram_subscription_filters::chat_id.eq(subscription.chat_id))
+        .filter(telegram_subscription_filters::feed_id.eq(subscription.feed_id))
+        .order(telegram_subscription_filters::keyword)
+        .get_results::<TelegramSubscriptionFilter>(conn)
+}
+
+pub fn remove_filter(
+    conn: &PgConnection,
+    filter: NewTelegramSubscriptionFilter,
+) -> Result<usize, Error> {
+    let record_query = telegram_subscription_filters::table
+        .filter(telegram_subscription_filters::chat_id.eq(filter.chat_id))
+        .filter(telegram_subscription_filters::feed_id.eq(filter.feed_id))
+        .filter(telegram_subscription_filters::keyword.eq(filter.keyword));
+
+    diesel::delete(record_query).execute(conn)
+}
+
+pub fn create_watchword(
+    conn: &PgConnection,
+    watchword: NewTelegramChatWatchword,
+) -> Result<TelegramChatWatchword, Error> {
+    diesel::insert_into(telegram_chat_watchwords::table)
+        .values(watchword)
+        .get_result::<TelegramChatWatchword>(conn)
+}
+
+pub fn find_watchwords(
+    conn: &PgConnection,
+    chat_id: i64,
+) -> Result<Vec<TelegramChatWatchword>, Error> {
+    telegram_chat_watchwords::table
+        .filter(telegram_chat_watchwords::chat_id.eq(chat_id))
+        .order(telegram_chat_watchwords::keyword)
+        .get_results::<TelegramChatWatchword>(conn)
+}
+
+pub fn remove_watchword(
+    conn: &PgConnection,
+    watchword: NewTelegramChatWatchword,
+) -> Result<usize, Error> {
+    let record_query = telegram_chat_watchwords::table
+        .filter(telegram_chat_watchwords::chat_id.eq(watchword.chat_id))
+        .filter(telegram_chat_watchwords::keyword.eq(watchword.keyword));
+
+    diesel::delete(record_query).execute(conn)
+}
+
+pub fn set_subscription_last_delivered_at(
+    conn: &PgConnection,
+    subscription: &TelegramSubscription,
+    last_delivered_at: DateTime<Utc>,
+) -> Result<TelegramSubscription, Error> {
+    diesel::update(subscription)
+        .set(telegram_subscriptions::last_delivered_at.eq(last_delivered_at))
+        .get_result::<TelegramSubscription>(conn)
+}
+
+pub fn reset_subscription_cursor(
+    conn: &PgConnection,
+    subscription: &TelegramSubscription,
+) -> Result<TelegramSubscription, Error> {
+    diesel::update(subscription)
+        .set((
+            telegram_subscriptions::last_delivered_at.eq(None::<DateTime<Utc>>),
+            telegram_subscriptions::cursor_reset_at.eq(db::current_time()),
+        ))
+        .get_result::<TelegramSubscription>(conn)
+}
+
+pub fn create_delivered_items(
+    conn: &PgConnection,
+    items: Vec<NewTelegramDeliveredItem>,
+) -> Result<usize, Error> {
+    if items.is_empty() {
+        return Ok(0);
+    }
+
+    diesel::insert_into(telegram_delivered_items::table)
+        .values(items)
+        .on_conflict_do_nothing()
+        .execute(conn)
+}
+
+pub fn search_delivered_items(
+    conn: &PgConnection,
+    chat_id: i64,
+    query: &str,
+    limit: i64,
+) -> Result<Vec<TelegramDeliveredItem>, Error> {
+    let pattern = format!(
+        "%{}%",
+        query
+            .replace('\\', "\\\\")
+            .replace('%', "\\%")
+            .replace('_', "\\_")
+    );
+
+    let feed_ids = telegram_subscriptions::table
+        .filter(telegram_subscriptions::chat_id.eq(chat_id))
+        .filter(telegram_subscriptions::deleted_at.is_null())
+        .select(telegram_subscriptions::feed_id);
+
+    telegram_delivered_items::table
+        .filter(telegram_delivered_items::chat_id.eq(chat_id))
+        .filter(telegram_delivered_items::feed_id.eq(any(feed_ids)))
+        .filter(telegram_delivered_items::title.ilike(pattern))
+        .order(telegram_delivered_items::published_at.desc())
+        .limit(limit)
+        .get_results::<TelegramDeliveredItem>(conn)
+}
+
+pub fn increment_delivered_count(
+    conn: &PgConnection,
+    subscription: &TelegramSubscription,
+    count: i64,
+) -> Result<TelegramSubscription, Error> {
+    diesel::update(subscription)
+        .set((
+            telegram_subscriptions::delivered_count
+                .eq(telegram_subscriptions::delivered_count + count),
+            telegram_subscriptions::delivered_at.eq(db::current_time()),
+        ))
+        .get_result::<TelegramSubscription>(conn)
+}
+
+pub fn chat_stats(conn: &PgConnection, chat_id: i64) -> Result<ChatStats, Error> {
+    let subscriptions = find_subscriptions_for_chat(conn, chat_id)?;
+
+    let stats = ChatStats {
+        subscriptions_count: subscriptions.len() as i64,
+        delivered_count: subscriptions
+            .iter()
+            .map(|subscription| subscription.delivered_count)
+            .sum(),
+        delivered_at: subscriptions
+            .iter()
+            .filter_map(|subscription| subscription.delivered_at)
+            .max(),
+    };
+
+    Ok(stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::NewTelegramChat;
+    use super::NewTelegramChatWatchword;
+    use super::NewTelegramDeliveredItem;
+    use super::NewTelegramSubscription;
+    use super::NewTelegramSubscriptionFilter;
+    use crate::db;
+    use crate::db::feed_items;
+    use crate::db::feeds;
+    use crate::models::telegram_chat::TelegramChat;
+    use crate::sync::FetchedFeedItem;
+    use chrono::Duration;
+    use diesel::connection::Connection;
+    use diesel::result::Error;
+    use diesel::{ExpressionMethods, RunQueryDsl};
+
+    #[test]
+    fn create_chat_creates_new_telegram_chat() {
+        let new_chat = build_new_chat();
+        let connection = db::establish_connection();
+
+        let result = connection.test_transaction::<TelegramChat, Error, _>(|| {
+            super::create_chat(&connection, new_chat.clone())
+        });
+
+        assert_eq!(result.id, new_chat.id);
+        assert_eq!(result.kind, new_chat.kind);
+        assert_eq!(result.username, new_chat.username);
+        assert_eq!(result.first_name, new_chat.first_name);
+        assert_eq!(result.last_name, new_chat.last_name);
+    }
+
+    #[test]
+    fn it_updates_telegram_chat() {
+        let new_chat = NewTelegramChat {
+            id: 42,
             kind: "private".to_string(),
             username: Some("Username".to_string()),
             first_name: Some("First".to_string()),
@@ -241,209 +1111,1769 @@ mod tests {
         };
         let connection = db::establish_connection();
 
-        let new_result = connection.test_transaction::<TelegramChat, Error, _>(|| {
-            let result = super::create_chat(&connection, new_chat.clone()).unwrap();
+        let new_result = connection.test_transaction::<TelegramChat, Error, _>(|| {
+            let result = super::create_chat(&connection, new_chat.clone()).unwrap();
+
+            assert_eq!(result.id, new_chat.id);
+            assert_eq!(result.kind, new_chat.kind);
+            assert_eq!(result.username, new_chat.username);
+            assert_eq!(result.first_name, new_chat.first_name);
+            assert_eq!(result.last_name, new_chat.last_name);
+
+            super::create_chat(&connection, updated_chat.clone())
+        });
+
+        assert_eq!(new_result.id, updated_chat.id);
+        assert_eq!(new_result.kind, updated_chat.kind);
+        assert_eq!(new_result.username, updated_chat.username);
+        assert_eq!(new_result.first_name, updated_chat.first_name);
+        assert_eq!(new_result.last_name, updated_chat.last_name);
+    }
+
+    #[test]
+    fn create_subscription_creates_new_subscription() {
+        let connection = db::establish_connection();
+
+        let new_chat = NewTelegramChat {
+            id: 42,
+            kind: "private".to_string(),
+            username: Some("Username".to_string()),
+            first_name: Some("First".to_string()),
+            last_name: Some("Last".to_string()),
+            title: None,
+        };
+
+        connection.test_transaction::<(), Error, _>(|| {
+            let feed = feeds::create(&connection, "Link".to_string(), "rss".to_string()).unwrap();
+            let chat = super::create_chat(&connection, new_chat).unwrap();
+
+            let new_subscription = NewTelegramSubscription {
+                feed_id: feed.id,
+                chat_id: chat.id,
+                user_id: None,
+            };
+
+            let new_subscription =
+                super::create_subscription(&connection, new_subscription).unwrap();
+
+            assert_eq!(new_subscription.feed_id, feed.id);
+            assert_eq!(new_subscription.chat_id, chat.id);
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn create_subscription_fails_to_create_new_subscription_if_it_already_exists() {
+        let connection = db::establish_connection();
+
+        let new_chat = build_new_chat();
+
+        connection.test_transaction::<(), Error, _>(|| {
+            let feed = feeds::create(&connection, "Link".to_string(), "atom".to_string()).unwrap();
+            let chat = super::create_chat(&connection, new_chat).unwrap();
+
+            let new_subscription = NewTelegramSubscription {
+                feed_id: feed.id,
+                chat_id: chat.id,
+                user_id: None,
+            };
+
+            let new_subscription =
+                super::create_subscription(&connection, new_subscription).unwrap();
+
+            assert_eq!(new_subscription.feed_id, feed.id);
+            assert_eq!(new_subscription.chat_id, chat.id);
+
+            let result = super::create_subscription(
+                &connection,
+                NewTelegramSubscription {
+                    feed_id: feed.id,
+                    chat_id: chat.id,
+                    user_id: None,
+                },
+            );
+
+            match result.err().unwrap() {
+                Error::DatabaseError(_, error_info) => assert_eq!(
+                    "duplicate key value violates unique constraint \"telegram_subscriptions_pkey\"",
+                    error_info.message()
+                ),
+                _ => panic!("Error doesn't match"),
+            };
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn create_subscription_fails_to_create_new_subscription_if_it_chat_does_not_exist() {
+        let connection = db::establish_connection();
+
+        connection.test_transaction::<(), Error, _>(|| {
+            let feed = feeds::create(&connection, "Link".to_string(), "atom".to_string()).unwrap();
+
+            let result = super::create_subscription(
+                &connection,
+                NewTelegramSubscription {
+                    feed_id: feed.id,
+                    chat_id: 42,
+                    user_id: None,
+                },
+            );
+
+            match result.err().unwrap() {
+                Error::DatabaseError(_, error_info) => assert_eq!(
+                    "insert or update on table \"telegram_subscriptions\" violates foreign key constraint \"telegram_subscriptions_chat_id_fkey\"",
+                    error_info.message()
+                ),
+                _ => panic!("Error doesn't match"),
+            };
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn find_subscription_finds_subscription() {
+        let connection = db::establish_connection();
+
+        let new_chat = build_new_chat();
+
+        connection.test_transaction::<(), Error, _>(|| {
+            let feed = feeds::create(&connection, "Link".to_string(), "rss".to_string()).unwrap();
+            let chat = super::create_chat(&connection, new_chat).unwrap();
+
+            let new_subscription = NewTelegramSubscription {
+                feed_id: feed.id,
+                chat_id: chat.id,
+                user_id: None,
+            };
+
+            let new_subscription =
+                super::create_subscription(&connection, new_subscription).unwrap();
+
+            assert_eq!(new_subscription.feed_id, feed.id);
+            assert_eq!(new_subscription.chat_id, chat.id);
+
+            let result = super::find_subscription(
+                &connection,
+                NewTelegramSubscription {
+                    feed_id: feed.id,
+                    chat_id: chat.id,
+                    user_id: None,
+                },
+            )
+            .unwrap();
+
+            assert_eq!(result.feed_id, feed.id);
+            assert_eq!(result.chat_id, chat.id);
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn find_subscription_fails_to_find_a_subscription() {
+        let connection = db::establish_connection();
+
+        connection.test_transaction::<(), Error, _>(|| {
+            let result = super::find_subscription(
+                &connection,
+                NewTelegramSubscription {
+                    feed_id: 42,
+                    chat_id: 42,
+                    user_id: None,
+                },
+            );
+
+            assert!(result.is_none());
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn count_subscriptions_for_chat_counts_the_number_of_subscriptions() {
+        let connection = db::establish_connection();
+
+        let new_chat = build_new_chat();
+
+        connection.test_transaction::<(), Error, _>(|| {
+            let feed = feeds::create(&connection, "Link".to_string(), "atom".to_string()).unwrap();
+            let chat = super::create_chat(&connection, new_chat).unwrap();
+
+            let new_subscription = NewTelegramSubscription {
+                feed_id: feed.id,
+                chat_id: chat.id,
+                user_id: None,
+            };
+
+            super::create_subscription(&connection, new_subscription).unwrap();
+
+            let result = super::count_subscriptions_for_chat(&connection, chat.id);
+
+            assert_eq!(result, 1);
+            assert_eq!(super::count_subscriptions_for_chat(&connection, 99), 0);
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn count_subscriptions_and_active_chats_count_all_records() {
+        let connection = db::establish_connection();
+
+        let new_chat = build_new_chat();
+
+        connection.test_transaction::<(), Error, _>(|| {
+            let subscriptions_count = super::count_subscriptions(&connection).unwrap();
+            let chats_count = super::count_active_chats(&connection).unwrap();
+
+            let feed = feeds::create(&connection, "Link".to_string(), "atom".to_string()).unwrap();
+            let chat = super::create_chat(&connection, new_chat).unwrap();
+
+            super::create_subscription(
+                &connection,
+                NewTelegramSubscription {
+                    feed_id: feed.id,
+                    chat_id: chat.id,
+                    user_id: None,
+                },
+            )
+            .unwrap();
+
+            assert_eq!(
+                super::count_subscriptions(&connection).unwrap(),
+                subscriptions_count + 1
+            );
+            assert_eq!(
+                super::count_active_chats(&connection).unwrap(),
+                chats_count + 1
+            );
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn set_subscription_last_delivered_at_updates_last_delivered_at() {
+        let connection = db::establish_connection();
+
+        let new_chat = build_new_chat();
+
+        connection.test_transaction::<(), Error, _>(|| {
+            let feed = feeds::create(&connection, "Link".to_string(), "rss".to_string()).unwrap();
+            let chat = super::create_chat(&connection, new_chat).unwrap();
+
+            let new_subscription = NewTelegramSubscription {
+                feed_id: feed.id,
+                chat_id: chat.id,
+                user_id: None,
+            };
+
+            let subscription = super::create_subscription(&connection, new_subscription).unwrap();
+
+            assert!(subscription.last_delivered_at.is_none());
+
+            let updated_subscription = super::set_subscription_last_delivered_at(
+                &connection,
+                &subscription,
+                db::current_time(),
+            )
+            .unwrap();
+
+            assert!(updated_subscription.last_delivered_at.is_some());
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn reset_subscription_cursor_clears_last_delivered_at() {
+        let connection = db::establish_connection();
+
+        let new_chat = build_new_chat();
+
+        connection.test_transaction::<(), Error, _>(|| {
+            let feed = feeds::create(&connection, "Link".to_string(), "rss".to_string()).unwrap();
+            let chat = super::create_chat(&connection, new_chat).unwrap();
+
+            let new_subscription = NewTelegramSubscription {
+                feed_id: feed.id,
+                chat_id: chat.id,
+                user_id: None,
+            };
+
+            let subscription = super::create_subscription(&connection, new_subscription).unwrap();
+            let subscription = super::set_subscription_last_delivered_at(
+                &connection,
+                &subscription,
+                db::current_time(),
+            )
+            .unwrap();
+
+            assert!(subscription.last_delivered_at.is_some());
+            assert!(subscription.cursor_reset_at.is_none());
+
+            let subscription =
+                super::reset_subscription_cursor(&connection, &subscription).unwrap();
+
+            assert!(subscription.last_delivered_at.is_none());
+            assert!(subscription.cursor_reset_at.is_some());
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn find_undelivered_feed_items_respects_initial_items_count() {
+        let connection = db::establish_connection();
+
+        let new_chat = build_new_chat();
+
+        connection.test_transaction::<(), Error, _>(|| {
+            let feed = feeds::create(&connection, "Link".to_string(), "rss".to_string()).unwrap();
+            let chat = super::create_chat(&connection, new_chat).unwrap();
+
+            let new_subscription = NewTelegramSubscription {
+                feed_id: feed.id,
+                chat_id: chat.id,
+                user_id: None,
+            };
+
+            let subscription = super::create_subscription(&connection, new_subscription).unwrap();
+
+            assert_eq!(subscription.initial_items_count, 10);
+
+            let items = (1..=5)
+                .map(|number| FetchedFeedItem {
+                    title: format!("FeedItem{}", number),
+                    description: None,
+                    link: format!("Link{}", number),
+                    author: None,
+                    guid: None,
+                    publication_date: db::current_time() - Duration::hours(number),
+                })
+                .collect();
+
+            feed_items::create(&connection, feed.id, items).unwrap();
+
+            let subscription =
+                super::set_initial_items_count(&connection, &subscription, 3).unwrap();
+
+            let result = super::find_undelivered_feed_items(&connection, &subscription).unwrap();
+
+            assert_eq!(result.len(), 3);
+            assert_eq!(result[0].title, "FeedItem1".to_string());
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn set_template_sets_and_resets_template() {
+        let connection = db::establish_connection();
+
+        let new_chat = build_new_chat();
+
+        connection.test_transaction::<(), Error, _>(|| {
+            let feed = feeds::create(&connection, "Link".to_string(), "rss".to_string()).unwrap();
+            let chat = super::create_chat(&connection, new_chat).unwrap();
+
+            let new_subscription = NewTelegramSubscription {
+                feed_id: feed.id,
+                chat_id: chat.id,
+                user_id: None,
+            };
+
+            let subscription = super::create_subscription(&connection, new_subscription).unwrap();
+
+            assert!(subscription.template.is_none());
+
+            let subscription =
+                super::set_template(&connection, &subscription, Some("{{title}}".to_string()))
+                    .unwrap();
+
+            assert_eq!(subscription.template, Some("{{title}}".to_string()));
+
+            let subscription = super::set_template(&connection, &subscription, None).unwrap();
+
+            assert!(subscription.template.is_none());
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn set_silent_updates_silent() {
+        let connection = db::establish_connection();
+
+        let new_chat = build_new_chat();
+
+        connection.test_transaction::<(), Error, _>(|| {
+            let feed = feeds::create(&connection, "Link".to_string(), "rss".to_string()).unwrap();
+            let chat = super::create_chat(&connection, new_chat).unwrap();
+
+            let new_subscription = NewTelegramSubscription {
+                feed_id: feed.id,
+                chat_id: chat.id,
+                user_id: None,
+            };
+
+            let subscription = super::create_subscription(&connection, new_subscription).unwrap();
+
+            assert!(!subscription.silent);
+
+            let subscription = super::set_silent(&connection, &subscription, true).unwrap();
+
+            assert!(subscription.silent);
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn set_link_preview_updates_link_preview() {
+        let connection = db::establish_connection();
+
+        let new_chat = build_new_chat();
+
+        connection.test_transaction::<(), Error, _>(|| {
+            let feed = feeds::create(&connection, "Link".to_string(), "rss".to_string()).unwrap();
+            let chat = super::create_chat(&connection, new_chat).unwrap();
+
+            let new_subscription = NewTelegramSubscription {
+                feed_id: feed.id,
+                chat_id: chat.id,
+                user_id: None,
+            };
+
+            let subscription = super::create_subscription(&connection, new_subscription).unwrap();
+
+            assert!(subscription.link_preview);
+
+            let subscription = super::set_link_preview(&connection, &subscription, false).unwrap();
+
+            assert!(!subscription.link_preview);
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn remove_subscription_keeps_subscription_until_restore_period_expires() {
+        let connection = db::establish_connection();
+
+        let new_chat = build_new_chat();
+
+        connection.test_transaction::<(), Error, _>(|| {
+            let feed = feeds::create(&connection, "Link".to_string(), "rss".to_string()).unwrap();
+            let chat = super::create_chat(&connection, new_chat).unwrap();
+
+            let new_subscription = NewTelegramSubscription {
+                feed_id: feed.id,
+                chat_id: chat.id,
+                user_id: None,
+            };
+
+            super::create_subscription(&connection, new_subscription).unwrap();
+            super::remove_subscription(&connection, new_subscription).unwrap();
+
+            assert!(super::find_subscription(&connection, new_subscription).is_none());
+            assert_eq!(super::count_subscriptions_for_chat(&connection, chat.id), 0);
+
+            let subscription =
+                super::find_restorable_subscription(&connection, new_subscription).unwrap();
+
+            assert!(subscription.deleted_at.is_some());
+            assert_eq!(super::remove_expired_subscriptions(&connection).unwrap(), 0);
+
+            let subscription = super::restore_subscription(&connection, &subscription).unwrap();
+
+            assert_eq!(subscription.deleted_at, None);
+            assert!(super::find_subscription(&connection, new_subscription).is_some());
+
+            super::remove_subscription(&connection, new_subscription).unwrap();
+
+            diesel::update(&subscription)
+                .set(
+                    crate::schema::telegram_subscriptions::deleted_at
+                        .eq(db::current_time() - Duration::hours(25)),
+                )
+                .execute(&connection)
+                .unwrap();
+
+            assert!(super::find_restorable_subscription(&connection, new_subscription).is_none());
+            assert_eq!(super::remove_expired_subscriptions(&connection).unwrap(), 1);
+
+            super::create_subscription(&connection, new_subscription).unwrap();
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn unread_counts_counts_items_after_subscription_cursor() {
+        let connection = db::establish_connection();
+
+        let new_chat = build_new_chat();
+
+        connection.test_transaction::<(), Error, _>(|| {
+            let feed = feeds::create(&connection, "Link".to_string(), "rss".to_string()).unwrap();
+            let chat = super::create_chat(&connection, new_chat).unwrap();
+
+            let new_subscription = NewTelegramSubscription {
+                feed_id: feed.id,
+                chat_id: chat.id,
+                user_id: None,
+            };
+
+            let subscription = super::create_subscription(&connection, new_subscription).unwrap();
+
+            let publication_date = db::current_time();
+            let items = (1..4)
+                .map(|index| FetchedFeedItem {
+                    title: format!("Title {}", index),
+                    description: None,
+                    link: format!("Link {}", index),
+                    author: None,
+                    guid: None,
+                    publication_date: publication_date - Duration::minutes(index),
+                })
+                .collect::<Vec<FetchedFeedItem>>();
+
+            feed_items::create(&connection, feed.id, items).unwrap();
+
+            super::set_subscription_last_delivered_at(
+                &connection,
+                &subscription,
+                publication_date - Duration::minutes(2),
+            )
+            .unwrap();
+
+            let result = super::unread_counts(&connection, chat.id).unwrap();
+
+            assert_eq!(result.len(), 1);
+            assert_eq!(result[0].0.id, feed.id);
+            assert_eq!(result[0].1, 1);
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn unread_counts_caps_new_subscriptions_by_initial_items_count() {
+        let connection = db::establish_connection();
+
+        let new_chat = build_new_chat();
+
+        connection.test_transaction::<(), Error, _>(|| {
+            let feed = feeds::create(&connection, "Link".to_string(), "rss".to_string()).unwrap();
+            let empty_feed =
+                feeds::create(&connection, "Link2".to_string(), "rss".to_string()).unwrap();
+            let chat = super::create_chat(&connection, new_chat).unwrap();
+
+            let subscription = super::create_subscription(
+                &connection,
+                NewTelegramSubscription {
+                    feed_id: feed.id,
+                    chat_id: chat.id,
+                    user_id: None,
+                },
+            )
+            .unwrap();
+
+            super::create_subscription(
+                &connection,
+                NewTelegramSubscription {
+                    feed_id: empty_feed.id,
+                    chat_id: chat.id,
+                    user_id: None,
+                },
+            )
+            .unwrap();
+
+            let items = (1..=5)
+                .map(|number| FetchedFeedItem {
+                    title: format!("FeedItem{}", number),
+                    description: None,
+                    link: format!("Link{}", number),
+                    author: None,
+                    guid: None,
+                    publication_date: db::current_time() - Duration::days(number * 30),
+                })
+                .collect();
+
+            feed_items::create(&connection, feed.id, items).unwrap();
+
+            let result = super::unread_counts(&connection, chat.id).unwrap();
+
+            assert_eq!(result.len(), 2);
+            assert_eq!(result[0].1, 5);
+            assert_eq!(result[1].0.id, empty_feed.id);
+            assert_eq!(result[1].1, 0);
+
+            let subscription =
+                super::set_initial_items_count(&connection, &subscription, 3).unwrap();
+
+            let result = super::unread_counts(&connection, chat.id).unwrap();
+
+            assert_eq!(result[0].1, 3);
+            assert_eq!(
+                super::count_undelivered_feed_items(&connection, &subscription).unwrap(),
+                3
+            );
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn random_feed_item_picks_delivered_item_of_subscribed_feeds() {
+        let connection = db::establish_connection();
+
+        let new_chat = build_new_chat();
+
+        connection.test_transaction::<(), Error, _>(|| {
+            let feed = feeds::create(&connection, "Link".to_string(), "rss".to_string()).unwrap();
+            let other_feed =
+                feeds::create(&connection, "Other".to_string(), "rss".to_string()).unwrap();
+            let chat = super::create_chat(&connection, new_chat).unwrap();
+
+            assert!(super::random_feed_item(&connection, chat.id)
+                .unwrap()
+                .is_none());
+
+            let new_subscription = NewTelegramSubscription {
+                feed_id: feed.id,
+                chat_id: chat.id,
+                user_id: None,
+            };
+
+            super::create_subscription(&connection, new_subscription).unwrap();
+
+            for (feed_id, link) in vec![
+                (feed.id, "Link 1"),
+                (feed.id, "Undelivered"),
+                (other_feed.id, "Link 2"),
+            ] {
+                let item = FetchedFeedItem {
+                    title: "Title".to_string(),
+                    description: None,
+                    link: link.to_string(),
+                    author: None,
+                    guid: None,
+                    publication_date: db::current_time(),
+                };
+
+                feed_items::create(&connection, feed_id, vec![item]).unwrap();
+            }
+
+            assert!(super::random_feed_item(&connection, chat.id)
+                .unwrap()
+                .is_none());
+
+            for (feed_id, link) in vec![(feed.id, "Link 1"), (other_feed.id, "Link 2")] {
+                super::create_delivered_items(
+                    &connection,
+                    vec![NewTelegramDeliveredItem {
+                        chat_id: chat.id,
+                        feed_id,
+                        title: "Title".to_string(),
+                        link: link.to_string(),
+                        published_at: db::current_time(),
+                        user_id: None,
+                    }],
+                )
+                .unwrap();
+            }
+
+            for _ in 0..5 {
+                let item = super::random_feed_item(&connection, chat.id)
+                    .unwrap()
+                    .unwrap();
+
+                assert_eq!(item.link, "Link 1".to_string());
+            }
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn set_full_text_updates_full_text() {
+        let connection = db::establish_connection();
+
+        let new_chat = build_new_chat();
+
+        connection.test_transaction::<(), Error, _>(|| {
+            let feed = feeds::create(&connection, "Link".to_string(), "rss".to_string()).unwrap();
+            let chat = super::create_chat(&connection, new_chat).unwrap();
+
+            let new_subscription = NewTelegramSubscription {
+                feed_id: feed.id,
+                chat_id: chat.id,
+                user_id: None,
+            };
+
+            let subscription = super::create_subscription(&connection, new_subscription).unwrap();
+
+            assert_eq!(subscription.full_text, None);
+
+            let subscription = super::set_full_text(&connection, &subscription, true).unwrap();
+
+            assert_eq!(subscription.full_text, Some(true));
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn set_html_and_set_markdown_turn_off_each_other() {
+        let connection = db::establish_connection();
+
+        let new_chat = build_new_chat();
+
+        connection.test_transaction::<(), Error, _>(|| {
+            let feed = feeds::create(&connection, "Link".to_string(), "rss".to_string()).unwrap();
+            let chat = super::create_chat(&connection, new_chat).unwrap();
+
+            let new_subscription = NewTelegramSubscription {
+                feed_id: feed.id,
+                chat_id: chat.id,
+                user_id: None,
+            };
+
+            let subscription = super::create_subscription(&connection, new_subscription).unwrap();
+
+            assert!(!subscription.html);
+
+            let subscription = super::set_markdown(&connection, &subscription, true).unwrap();
+            let subscription = super::set_html(&connection, &subscription, true).unwrap();
+
+            assert!(subscription.html);
+            assert!(!subscription.markdown);
+
+            let subscription = super::set_markdown(&connection, &subscription, true).unwrap();
+
+            assert!(!subscription.html);
+            assert!(subscription.markdown);
+
+            let subscription = super::set_html(&connection, &subscription, false).unwrap();
+
+            assert!(!subscription.html);
+            assert!(subscription.markdown);
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn set_min_delivery_interval_sets_and_resets_interval() {
+        let connection = db::establish_connection();
+
+        let new_chat = build_new_chat();
+
+        connection.test_transaction::<(), Error, _>(|| {
+            let feed = feeds::create(&connection, "Link".to_string(), "rss".to_string()).unwrap();
+            let chat = super::create_chat(&connection, new_chat).unwrap();
+
+            let new_subscription = NewTelegramSubscription {
+                feed_id: feed.id,
+                chat_id: chat.id,
+                user_id: None,
+            };
+
+            let subscription = super::create_subscription(&connection, new_subscription).unwrap();
+
+            assert_eq!(subscription.min_delivery_interval_secs, None);
+
+            let subscription =
+                super::set_min_delivery_interval(&connection, &subscription, Some(600)).unwrap();
+
+            assert_eq!(subscription.min_delivery_interval_secs, Some(600));
+
+            let subscription =
+                super::set_min_delivery_interval(&connection, &subscription, None).unwrap();
+
+            assert_eq!(subscription.min_delivery_interval_secs, None);
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn copy_subscription_copies_settings_and_filters() {
+        let connection = db::establish_connection();
+
+        connection.test_transaction::<(), Error, _>(|| {
+            let feed = feeds::create(&connection, "Link".to_string(), "rss".to_string()).unwrap();
+            let source_chat = super::create_chat(&connection, build_new_chat()).unwrap();
+            let target_chat = super::create_chat(&connection, build_new_chat_with_id(-42)).unwrap();
+
+            let source = super::create_subscription(
+                &connection,
+                NewTelegramSubscription {
+                    chat_id: source_chat.id,
+                    feed_id: feed.id,
+                    user_id: None,
+                },
+            )
+            .unwrap();
+            let source =
+                super::set_template(&connection, &source, Some("{{title}}".to_string())).unwrap();
+            let source = super::set_markdown(&connection, &source, true).unwrap();
+            let source = super::set_credentials(
+                &connection,
+                &source,
+                "user".to_string(),
+                "password".to_string(),
+            )
+            .unwrap();
+
+            super::create_filter(
+                &connection,
+                NewTelegramSubscriptionFilter {
+                    chat_id: source_chat.id,
+                    feed_id: feed.id,
+                    keyword: "rust".to_string(),
+                },
+            )
+            .unwrap();
+
+            let copy = super::copy_subscription(&connection, &source, target_chat.id).unwrap();
+
+            assert_eq!(copy.chat_id, target_chat.id);
+            assert_eq!(copy.feed_id, feed.id);
+            assert_eq!(copy.template, Some("{{title}}".to_string()));
+            assert!(copy.markdown);
+            assert_eq!(copy.last_delivered_at, None);
+            assert_eq!(copy.auth_username, None);
+            assert_eq!(copy.auth_password, None);
+
+            let filters = super::find_filters(
+                &connection,
+                NewTelegramSubscription {
+                    chat_id: target_chat.id,
+                    feed_id: feed.id,
+                    user_id: None,
+                },
+            )
+            .unwrap();
+
+            assert_eq!(filters.len(), 1);
+            assert_eq!(filters[0].keyword, "rust".to_string());
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn set_credentials_updates_credentials() {
+        let connection = db::establish_connection();
+
+        let new_chat = build_new_chat();
+
+        connection.test_transaction::<(), Error, _>(|| {
+            let feed = feeds::create(&connection, "Link".to_string(), "rss".to_string()).unwrap();
+            let chat = super::create_chat(&connection, new_chat).unwrap();
+
+            let new_subscription = NewTelegramSubscription {
+                feed_id: feed.id,
+                chat_id: chat.id,
+                user_id: None,
+            };
+
+            let subscription = super::create_subscription(&connection, new_subscription).unwrap();
+
+            assert!(super::find_authenticated_subscription(&connection, feed.id).is_none());
+
+            let subscription = super::set_credentials(
+                &connection,
+                &subscription,
+                "user".to_string(),
+                "secret".to_string(),
+            )
+            .unwrap();
+
+            assert_eq!(subscription.auth_username, Some("user".to_string()));
+            assert_eq!(subscription.auth_password, Some("secret".to_string()));
+
+            let found = super::find_authenticated_subscription(&connection, feed.id).unwrap();
+
+            assert_eq!(found.chat_id, chat.id);
+            assert_eq!(found.auth_username, Some("user".to_string()));
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn set_subscription_feed_moves_subscription_with_its_filters() {
+        let connection = db::establish_connection();
+
+        let new_chat = build_new_chat();
+
+        connection.test_transaction::<(), Error, _>(|| {
+            let feed = feeds::create(&connection, "Link".to_string(), "rss".to_string()).unwrap();
+            let new_feed =
+                feeds::create(&connection, "NewLink".to_string(), "rss".to_string()).unwrap();
+            let chat = super::create_chat(&connection, new_chat).unwrap();
+
+            let new_subscription = NewTelegramSubscription {
+                feed_id: feed.id,
+                chat_id: chat.id,
+                user_id: None,
+            };
+
+            let subscription = super::create_subscription(&connection, new_subscription).unwrap();
+            let subscription =
+                super::set_template(&connection, &subscription, Some("{{title}}".to_string()))
+                    .unwrap();
+
+            super::create_filter(
+                &connection,
+                NewTelegramSubscriptionFilter {
+                    chat_id: chat.id,
+                    feed_id: feed.id,
+                    keyword: "rust".to_string(),
+                },
+            )
+            .unwrap();
+
+            let moved =
+                super::set_subscription_feed(&connection, &subscription, new_feed.id).unwrap();
+
+            assert_eq!(moved.feed_id, new_feed.id);
+            assert_eq!(moved.template, Some("{{title}}".to_string()));
+
+            let filters = super::find_filters(
+                &connection,
+                NewTelegramSubscription {
+                    chat_id: chat.id,
+                    feed_id: new_feed.id,
+                    user_id: None,
+                },
+            )
+            .unwrap();
+
+            assert_eq!(filters.len(), 1);
+            assert_eq!(filters[0].keyword, "rust".to_string());
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn remove_subscription_removes_subscription() {
+        let connection = db::establish_connection();
+
+        let new_chat = build_new_chat();
+
+        connection.test_transaction::<(), Error, _>(|| {
+            let feed = feeds::create(&connection, "Link".to_string(), "rss".to_string()).unwrap();
+            let chat = super::create_chat(&connection, new_chat).unwrap();
+
+            let new_subscription = NewTelegramSubscription {
+                feed_id: feed.id,
+                chat_id: chat.id,
+                user_id: None,
+            };
+
+            super::create_subscription(&connection, new_subscription.clone()).unwrap();
+
+            let result = super::remove_subscription(&connection, new_subscription).unwrap();
+
+            assert_eq!(result, 1);
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn remove_all_subscriptions_for_chat_removes_only_chat_subscriptions() {
+        let connection = db::establish_connection();
+
+        connection.test_transaction::<(), Error, _>(|| {
+            let feed = feeds::create(&connection, "Link".to_string(), "rss".to_string()).unwrap();
+            let feed2 = feeds::create(&connection, "Link2".to_string(), "rss".to_string()).unwrap();
+            let chat = super::create_chat(&connection, build_new_chat_with_id(1)).unwrap();
+            let other_chat = super::create_chat(&connection, build_new_chat_with_id(2)).unwrap();
+
+            for (chat_id, feed_id) in &[
+                (chat.id, feed.id),
+                (chat.id, feed2.id),
+                (other_chat.id, feed.id),
+            ] {
+                super::create_subscription(
+                    &connection,
+                    NewTelegramSubscription {
+                        chat_id: *chat_id,
+                        feed_id: *feed_id,
+                        user_id: None,
+                    },
+                )
+                .unwrap();
+            }
+
+            let result = super::remove_all_subscriptions_for_chat(&connection, chat.id).unwrap();
+
+            assert_eq!(result, 2);
+            assert_eq!(super::count_subscriptions_for_chat(&connection, chat.id), 0);
+            assert_eq!(
+                super::count_subscriptions_for_chat(&connection, other_chat.id),
+                1
+            );
+            assert!(super::find_restorable_subscription(
+                &connection,
+                NewTelegramSubscription {
+                    chat_id: chat.id,
+                    feed_id: feed.id,
+                    user_id: None,
+                }
+            )
+            .is_some());
+            assert_eq!(
+                super::remove_all_subscriptions_for_chat(&connection, chat.id),
+                Ok(0)
+            );
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn remove_chat_removes_chat() {
+        let connection = db::establish_connection();
+
+        let new_chat = build_new_chat();
+
+        connection.test_transaction::<(), Error, _>(|| {
+            let chat = super::create_chat(&connection, new_chat).unwrap();
+
+            assert!(super::find_chat(&connection, chat.id).is_some());
+
+            let result = super::remove_chat(&connection, chat.id).unwrap();
+
+            assert_eq!(result, 1);
+
+            assert!(super::find_chat(&connection, chat.id).is_none());
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn delete_chat_cascade_removes_chat_and_its_subscriptions() {
+        let connection = db::establish_connection();
+
+        let new_chat = build_new_chat();
+
+        connection.test_transaction::<(), Error, _>(|| {
+            let feed = feeds::create(&connection, "Link".to_string(), "rss".to_string()).unwrap();
+            let chat = super::create_chat(&connection, new_chat).unwrap();
+
+            let new_subscription = NewTelegramSubscription {
+                feed_id: feed.id,
+                chat_id: chat.id,
+                user_id: None,
+            };
+
+            super::create_subscription(&connection, new_subscription).unwrap();
+
+            let result = super::delete_chat_cascade(&connection, chat.id).unwrap();
+
+            assert_eq!(result, 1);
+            assert!(super::find_chat(&connection, chat.id).is_none());
+            assert_eq!(super::count_subscriptions_for_chat(&connection, chat.id), 0);
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn set_utc_offset_minutes_sets_offset() {
+        let connection = db::establish_connection();
+
+        let new_chat = build_new_chat();
+
+        connection.test_transaction::<(), Error, _>(|| {
+            let chat = super::create_chat(&connection, new_chat).unwrap();
+
+            assert!(super::find_chat(&connection, chat.id).is_some());
+
+            let result = super::set_utc_offset_minutes(&connection, &chat, 180).unwrap();
+
+            assert_eq!(result.utc_offset_minutes.unwrap(), 180);
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn set_max_subscriptions_sets_limit() {
+        let connection = db::establish_connection();
+
+        let new_chat = build_new_chat();
+
+        connection.test_transaction::<(), Error, _>(|| {
+            let chat = super::create_chat(&connection, new_chat).unwrap();
+
+            assert!(chat.max_subscriptions.is_none());
+
+            let result = super::set_max_subscriptions(&connection, &chat, 50).unwrap();
+
+            assert_eq!(result.max_subscriptions.unwrap(), 50);
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn set_description_limit_sets_limit() {
+        let connection = db::establish_connection();
+
+        let new_chat = build_new_chat();
+
+        connection.test_transaction::<(), Error, _>(|| {
+            let chat = super::create_chat(&connection, new_chat).unwrap();
+
+            assert_eq!(chat.description_limit, None);
+
+            let result = super::set_description_limit(&connection, &chat, 100).unwrap();
+
+            assert_eq!(result.description_limit, Some(100));
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn set_quiet_hours_sets_and_clears_hours() {
+        let connection = db::establish_connection();
+
+        let new_chat = build_new_chat();
+
+        connection.test_transaction::<(), Error, _>(|| {
+            let chat = super::create_chat(&connection, new_chat).unwrap();
+
+            assert_eq!((chat.quiet_hours_start, chat.quiet_hours_end), (None, None));
+
+            let result = super::set_quiet_hours(&connection, &chat, Some((22, 7))).unwrap();
+
+            assert_eq!(
+                (result.quiet_hours_start, result.quiet_hours_end),
+                (Some(22), Some(7))
+            );
+
+            let result = super::set_quiet_hours(&connection, &result, None).unwrap();
+
+            assert_eq!(
+                (result.quiet_hours_start, result.quiet_hours_end),
+                (None, None)
+            );
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn create_subscription_stores_user_id() {
+        let connection = db::establish_connection();
+
+        let new_chat = build_new_chat();
+
+        connection.test_transaction::<(), Error, _>(|| {
+            let feed = feeds::create(&connection, "Link".to_string(), "rss".to_string()).unwrap();
+            let chat = super::create_chat(&connection, new_chat).unwrap();
+
+            assert!(!chat.track_per_user);
+
+            let chat = super::set_track_per_user(&connection, &chat, true).unwrap();
+
+            assert!(chat.track_per_user);
+
+            let subscription = super::create_subscription(
+                &connection,
+                NewTelegramSubscription {
+                    chat_id: chat.id,
+                    feed_id: feed.id,
+                    user_id: Some(7),
+                },
+            )
+            .unwrap();
+
+            assert_eq!(subscription.user_id, Some(7));
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn set_backfill_newest_first_updates_chat() {
+        let connection = db::establish_connection();
+
+        let new_chat = build_new_chat();
+
+        connection.test_transaction::<(), Error, _>(|| {
+            let chat = super::create_chat(&connection, new_chat).unwrap();
+
+            assert!(!chat.backfill_newest_first);
+
+            let chat = super::set_backfill_newest_first(&connection, &chat, true).unwrap();
+
+            assert!(chat.backfill_newest_first);
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn set_show_author_updates_chat() {
+        let connection = db::establish_connection();
+
+        let new_chat = build_new_chat();
+
+        connection.test_transaction::<(), Error, _>(|| {
+            let chat = super::create_chat(&connection, new_chat).unwrap();
+
+            assert!(!chat.show_author);
+
+            let chat = super::set_show_author(&connection, &chat, true).unwrap();
+
+            assert!(chat.show_author);
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn watchwords_are_created_found_and_removed() {
+        let connection = db::establish_connection();
+
+        let new_chat = build_new_chat();
+
+        connection.test_transaction::<(), Error, _>(|| {
+            let chat = super::create_chat(&connection, new_chat).unwrap();
+
+            for keyword in &["tokio", "rust"] {
+                super::create_watchword(
+                    &connection,
+                    NewTelegramChatWatchword {
+                        chat_id: chat.id,
+                        keyword: keyword.to_string(),
+                    },
+                )
+                .unwrap();
+            }
+
+            let keywords = super::find_watchwords(&connection, chat.id)
+                .unwrap()
+                .into_iter()
+                .map(|watchword| watchword.keyword)
+                .collect::<Vec<String>>();
+
+            assert_eq!(keywords, vec!["rust".to_string(), "tokio".to_string()]);
+
+            let removed_count = super::remove_watchword(
+                &connection,
+                NewTelegramChatWatchword {
+                    chat_id: chat.id,
+                    keyword: "rust".to_string(),
+                },
+            )
+            .unwrap();
+
+            assert_eq!(removed_count, 1);
+            assert_eq!(
+                super::find_watchwords(&connection, chat.id).unwrap().len(),
+                1
+            );
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn set_item_prefix_sets_and_clears_prefix() {
+        let connection = db::establish_connection();
+
+        let new_chat = build_new_chat();
+
+        connection.test_transaction::<(), Error, _>(|| {
+            let chat = super::create_chat(&connection, new_chat).unwrap();
+
+            assert_eq!(chat.item_prefix, None);
+
+            let result =
+                super::set_item_prefix(&connection, &chat, Some("📰".to_string())).unwrap();
+
+            assert_eq!(result.item_prefix, Some("📰".to_string()));
+
+            let result = super::set_item_prefix(&connection, &result, None).unwrap();
+
+            assert_eq!(result.item_prefix, None);
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn set_language_sets_language() {
+        let connection = db::establish_connection();
+
+        let new_chat = build_new_chat();
+
+        connection.test_transaction::<(), Error, _>(|| {
+            let chat = super::create_chat(&connection, new_chat).unwrap();
+
+            assert_eq!(chat.language, None);
+
+            let result = super::set_language(&connection, &chat, "ru".to_string()).unwrap();
+
+            assert_eq!(result.language, Some("ru".to_string()));
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn set_paused_pauses_chat() {
+        let connection = db::establish_connection();
+
+        let new_chat = build_new_chat();
+
+        connection.test_transaction::<(), Error, _>(|| {
+            let chat = super::create_chat(&connection, new_chat).unwrap();
+
+            assert!(!chat.paused);
+
+            let result = super::set_paused(&connection, &chat, true).unwrap();
+
+            assert!(result.paused);
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn forbidden_errors_count_is_incremented_and_reset() {
+        let connection = db::establish_connection();
+
+        let new_chat = build_new_chat();
+
+        connection.test_transaction::<(), Error, _>(|| {
+            let chat = super::create_chat(&connection, new_chat).unwrap();
+
+            assert_eq!(chat.forbidden_errors_count, 0);
+
+            for _ in 0..3 {
+                super::increment_forbidden_errors_count(&connection, chat.id).unwrap();
+            }
+
+            let chat = super::find_chat(&connection, chat.id).unwrap();
+
+            assert_eq!(chat.forbidden_errors_count, 3);
+            assert!(super::find_disabled_chats(&connection).unwrap().is_empty());
+
+            let chat = super::set_paused(&connection, &chat, true).unwrap();
+            let disabled_chats = super::find_disabled_chats(&connection).unwrap();
+
+            assert_eq!(disabled_chats.len(), 1);
+            assert_eq!(disabled_chats[0].id, chat.id);
+
+            let chat = super::reset_forbidden_errors_count(&connection, &chat).unwrap();
+
+            assert_eq!(chat.forbidden_errors_count, 0);
+            assert!(super::find_disabled_chats(&connection).unwrap().is_empty());
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn fetch_subscriptions_skips_paused_chats() {
+        let connection = db::establish_connection();
+
+        let new_chat1 = build_new_chat_with_id(10);
+        let new_chat2 = build_new_chat_with_id(20);
+
+        connection.test_transaction::<(), Error, _>(|| {
+            let feed = feeds::create(&connection, "Link".to_string(), "rss".to_string()).unwrap();
+            let chat1 = super::create_chat(&connection, new_chat1).unwrap();
+            let chat2 = super::create_chat(&connection, new_chat2).unwrap();
+
+            for chat in vec![&chat1, &chat2] {
+                let new_subscription = NewTelegramSubscription {
+                    feed_id: feed.id,
+                    chat_id: chat.id,
+                    user_id: None,
+                };
+
+                super::create_subscription(&connection, new_subscription).unwrap();
+            }
+
+            super::set_paused(&connection, &chat2, true).unwrap();
+
+            let result = super::fetch_subscriptions(&connection, 1, 1000).unwrap();
+
+            assert_eq!(result.len(), 1);
+            assert_eq!(result[0].chat_id, chat1.id);
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn set_digest_enables_digest_and_excludes_chat_from_delivery() {
+        let connection = db::establish_connection();
+
+        let new_chat1 = build_new_chat_with_id(10);
+        let new_chat2 = build_new_chat_with_id(20);
+
+        connection.test_transaction::<(), Error, _>(|| {
+            let feed = feeds::create(&connection, "Link".to_string(), "rss".to_string()).unwrap();
+            let chat1 = super::create_chat(&connection, new_chat1).unwrap();
+            let chat2 = super::create_chat(&connection, new_chat2).unwrap();
+
+            assert!(!chat1.digest);
+
+            for chat in vec![&chat1, &chat2] {
+                let new_subscription = NewTelegramSubscription {
+                    feed_id: feed.id,
+                    chat_id: chat.id,
+                    user_id: None,
+                };
+
+                super::create_subscription(&connection, new_subscription).unwrap();
+            }
 
-            assert_eq!(result.id, new_chat.id);
-            assert_eq!(result.kind, new_chat.kind);
-            assert_eq!(result.username, new_chat.username);
-            assert_eq!(result.first_name, new_chat.first_name);
-            assert_eq!(result.last_name, new_chat.last_name);
+            let chat2 = super::set_digest(&connection, &chat2, true, 18).unwrap();
 
-            super::create_chat(&connection, updated_chat.clone())
-        });
+            assert!(chat2.digest);
+            assert_eq!(chat2.digest_hour, 18);
 
-        assert_eq!(new_result.id, updated_chat.id);
-        assert_eq!(new_result.kind, updated_chat.kind);
-        assert_eq!(new_result.username, updated_chat.username);
-        assert_eq!(new_result.first_name, updated_chat.first_name);
-        assert_eq!(new_result.last_name, updated_chat.last_name);
+            let result = super::fetch_subscriptions(&connection, 1, 1000).unwrap();
+
+            assert_eq!(result.len(), 1);
+            assert_eq!(result[0].chat_id, chat1.id);
+
+            let digest_chats = super::find_digest_chats(&connection).unwrap();
+
+            assert_eq!(digest_chats.len(), 1);
+            assert_eq!(digest_chats[0].id, chat2.id);
+
+            let sent_at = db::current_time();
+            let chat2 = super::set_digest_sent_at(&connection, &chat2, sent_at).unwrap();
+
+            assert_eq!(chat2.digest_sent_at, Some(sent_at));
+
+            Ok(())
+        });
     }
 
     #[test]
-    fn create_subscription_creates_new_subscription() {
+    fn mark_chat_inactive_excludes_chat_from_delivery_until_it_is_updated() {
         let connection = db::establish_connection();
 
-        let new_chat = NewTelegramChat {
-            id: 42,
-            kind: "private".to_string(),
-            username: Some("Username".to_string()),
-            first_name: Some("First".to_string()),
-            last_name: Some("Last".to_string()),
-            title: None,
-        };
+        let new_chat = build_new_chat();
 
         connection.test_transaction::<(), Error, _>(|| {
             let feed = feeds::create(&connection, "Link".to_string(), "rss".to_string()).unwrap();
-            let chat = super::create_chat(&connection, new_chat).unwrap();
+            let chat = super::create_chat(&connection, new_chat.clone()).unwrap();
 
             let new_subscription = NewTelegramSubscription {
                 feed_id: feed.id,
                 chat_id: chat.id,
+                user_id: None,
             };
 
-            let new_subscription =
-                super::create_subscription(&connection, new_subscription).unwrap();
+            super::create_subscription(&connection, new_subscription).unwrap();
 
-            assert_eq!(new_subscription.feed_id, feed.id);
-            assert_eq!(new_subscription.chat_id, chat.id);
+            assert_eq!(super::mark_chat_inactive(&connection, chat.id).unwrap(), 1);
+            assert!(!super::find_chat(&connection, chat.id).unwrap().active);
+            assert!(super::fetch_subscriptions(&connection, 1, 1000)
+                .unwrap()
+                .is_empty());
+
+            let updated_chat = super::create_chat(&connection, new_chat).unwrap();
+
+            assert!(updated_chat.active);
+            assert_eq!(
+                super::fetch_subscriptions(&connection, 1, 1000)
+                    .unwrap()
+                    .len(),
+                1
+            );
 
             Ok(())
         });
     }
 
     #[test]
-    fn create_subscription_fails_to_create_new_subscription_if_it_already_exists() {
+    fn remove_inactive_chats_removes_only_inactive_chats() {
+        let connection = db::establish_connection();
+
+        let new_chat1 = build_new_chat_with_id(10);
+        let new_chat2 = build_new_chat_with_id(20);
+
+        connection.test_transaction::<(), Error, _>(|| {
+            let chat1 = super::create_chat(&connection, new_chat1).unwrap();
+            let chat2 = super::create_chat(&connection, new_chat2).unwrap();
+
+            super::mark_chat_inactive(&connection, chat2.id).unwrap();
+
+            assert_eq!(super::remove_inactive_chats(&connection).unwrap(), 0);
+            assert!(super::find_chat(&connection, chat2.id).is_some());
+
+            diesel::update(&chat2)
+                .set(
+                    crate::schema::telegram_chats::updated_at
+                        .eq(db::current_time() - Duration::days(31)),
+                )
+                .execute(&connection)
+                .unwrap();
+
+            assert_eq!(super::remove_inactive_chats(&connection).unwrap(), 1);
+            assert!(super::find_chat(&connection, chat1.id).is_some());
+            assert!(super::find_chat(&connection, chat2.id).is_none());
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn set_timezone_name_sets_name_and_offset() {
         let connection = db::establish_connection();
 
         let new_chat = build_new_chat();
 
         connection.test_transaction::<(), Error, _>(|| {
-            let feed = feeds::create(&connection, "Link".to_string(), "atom".to_string()).unwrap();
             let chat = super::create_chat(&connection, new_chat).unwrap();
 
-            let new_subscription = NewTelegramSubscription {
+            let result =
+                super::set_timezone_name(&connection, &chat, "America/New_York".to_string(), -240)
+                    .unwrap();
+
+            assert_eq!(result.timezone_name, Some("America/New_York".to_string()));
+            assert_eq!(result.utc_offset_minutes, Some(-240));
+
+            let result = super::set_utc_offset_minutes(&connection, &result, 180).unwrap();
+
+            assert_eq!(result.timezone_name, None);
+            assert_eq!(result.utc_offset_minutes, Some(180));
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn find_chats_by_feed_id_find_chats() {
+        let connection = db::establish_connection();
+
+        let new_chat1 = build_new_chat_with_id(10);
+        let new_chat2 = build_new_chat_with_id(20);
+
+        connection.test_transaction::<(), Error, _>(|| {
+            let feed = feeds::create(&connection, "Link".to_string(), "rss".to_string()).unwrap();
+            let chat1 = super::create_chat(&connection, new_chat1).unwrap();
+            let chat2 = super::create_chat(&connection, new_chat2).unwrap();
+
+            let new_subscription1 = NewTelegramSubscription {
                 feed_id: feed.id,
-                chat_id: chat.id,
+                chat_id: chat1.id,
+                user_id: None,
             };
 
-            let new_subscription =
-                super::create_subscription(&connection, new_subscription).unwrap();
+            super::create_subscription(&connection, new_subscription1.clone()).unwrap();
 
-            assert_eq!(new_subscription.feed_id, feed.id);
-            assert_eq!(new_subscription.chat_id, chat.id);
+            let new_subscription2 = NewTelegramSubscription {
+                feed_id: feed.id,
+                chat_id: chat2.id,
+                user_id: None,
+            };
 
-            let result = super::create_subscription(
-                &connection,
-                NewTelegramSubscription {
-                    feed_id: feed.id,
-                    chat_id: chat.id,
-                },
-            );
+            super::create_subscription(&connection, new_subscription2.clone()).unwrap();
 
-            match result.err().unwrap() {
-                Error::DatabaseError(_, error_info) => assert_eq!(
-                    "duplicate key value violates unique constraint \"telegram_subscriptions_pkey\"",
-                    error_info.message()
-                ),
-                _ => panic!("Error doesn't match"),
-            };
+            let result = super::find_chats_by_feed_id(&connection, feed.id).unwrap();
+
+            assert_eq!(result.len(), 2);
 
             Ok(())
         });
     }
 
     #[test]
-    fn create_subscription_fails_to_create_new_subscription_if_it_chat_does_not_exist() {
+    fn find_feeds_by_chat_id_returns_feeds_in_subscription_order() {
         let connection = db::establish_connection();
 
+        let new_chat = build_new_chat();
+
         connection.test_transaction::<(), Error, _>(|| {
-            let feed = feeds::create(&connection, "Link".to_string(), "atom".to_string()).unwrap();
+            let feed1 = feeds::create(&connection, "Link2".to_string(), "rss".to_string()).unwrap();
+            let feed2 = feeds::create(&connection, "Link1".to_string(), "rss".to_string()).unwrap();
+            let chat = super::create_chat(&connection, new_chat).unwrap();
 
-            let result = super::create_subscription(
-                &connection,
-                NewTelegramSubscription {
+            for feed in vec![&feed1, &feed2] {
+                let new_subscription = NewTelegramSubscription {
                     feed_id: feed.id,
-                    chat_id: 42,
-                },
-            );
+                    chat_id: chat.id,
+                    user_id: None,
+                };
 
-            match result.err().unwrap() {
-                Error::DatabaseError(_, error_info) => assert_eq!(
-                    "insert or update on table \"telegram_subscriptions\" violates foreign key constraint \"telegram_subscriptions_chat_id_fkey\"",
-                    error_info.message()
-                ),
-                _ => panic!("Error doesn't match"),
-            };
+                super::create_subscription(&connection, new_subscription).unwrap();
+            }
+
+            let result = super::find_feeds_by_chat_id(&connection, chat.id, None).unwrap();
+
+            assert_eq!(result.len(), 2);
+            assert_eq!(result[0].id, feed1.id);
+            assert_eq!(result[1].id, feed2.id);
 
             Ok(())
         });
     }
 
     #[test]
-    fn find_subscription_finds_subscription() {
+    fn find_feeds_by_chat_id_filters_feeds_by_type() {
         let connection = db::establish_connection();
 
         let new_chat = build_new_chat();
 
         connection.test_transaction::<(), Error, _>(|| {
-            let feed = feeds::create(&connection, "Link".to_string(), "rss".to_string()).unwrap();
+            let feed1 = feeds::create(&connection, "Link1".to_string(), "rss".to_string()).unwrap();
+            let feed2 =
+                feeds::create(&connection, "Link2".to_string(), "atom".to_string()).unwrap();
             let chat = super::create_chat(&connection, new_chat).unwrap();
 
-            let new_subscription = NewTelegramSubscription {
-                feed_id: feed.id,
-                chat_id: chat.id,
-            };
+            for feed in vec![&feed1, &feed2] {
+                let new_subscription = NewTelegramSubscription {
+                    feed_id: feed.id,
+                    chat_id: chat.id,
+                    user_id: None,
+                };
 
-            let new_subscription =
                 super::create_subscription(&connection, new_subscription).unwrap();
+            }
 
-            assert_eq!(new_subscription.feed_id, feed.id);
-            assert_eq!(new_subscription.chat_id, chat.id);
+            let result = super::find_feeds_by_chat_id(&connection, chat.id, Some("atom")).unwrap();
 
-            let result = super::find_subscription(
-                &connection,
-                NewTelegramSubscription {
-                    feed_id: feed.id,
-                    chat_id: chat.id,
-                },
-            )
-            .unwrap();
+            assert_eq!(result.len(), 1);
+            assert_eq!(result[0].id, feed2.id);
 
-            assert_eq!(result.feed_id, feed.id);
-            assert_eq!(result.chat_id, chat.id);
+            let result = super::find_feeds_by_chat_id(&connection, chat.id, Some("json")).unwrap();
+
+            assert!(result.is_empty());
 
             Ok(())
         });
     }
 
     #[test]
-    fn find_subscription_fails_to_find_a_subscription() {
+    fn all_chat_ids_returns_distinct_ids_of_active_chats() {
         let connection = db::establish_connection();
 
         connection.test_transaction::<(), Error, _>(|| {
-            let result = super::find_subscription(
-                &connection,
-                NewTelegramSubscription {
-                    feed_id: 42,
-                    chat_id: 42,
-                },
+            let feed1 = feeds::create(&connection, "Link1".to_string(), "rss".to_string()).unwrap();
+            let feed2 = feeds::create(&connection, "Link2".to_string(), "rss".to_string()).unwrap();
+            let chat1 = super::create_chat(&connection, build_new_chat_with_id(10)).unwrap();
+            let chat2 = super::create_chat(&connection, build_new_chat_with_id(20)).unwrap();
+
+            for feed_id in vec![feed1.id, feed2.id] {
+                for chat_id in vec![chat1.id, chat2.id] {
+                    super::create_subscription(
+                        &connection,
+                        NewTelegramSubscription {
+                            feed_id,
+                            chat_id,
+                            user_id: None,
+                        },
+                    )
+                    .unwrap();
+                }
+            }
+
+            assert_eq!(
+                super::all_chat_ids(&connection).unwrap(),
+                vec![chat1.id, chat2.id]
             );
 
-            assert!(result.is_none());
+            super::mark_chat_inactive(&connection, chat2.id).unwrap();
+
+            assert_eq!(super::all_chat_ids(&connection).unwrap(), vec![chat1.id]);
 
             Ok(())
         });
     }
 
     #[test]
-    fn count_subscriptions_for_chat_counts_the_number_of_subscriptions() {
+    fn search_delivered_items_finds_items_by_title_case_insensitively() {
         let connection = db::establish_connection();
 
         let new_chat = build_new_chat();
 
         connection.test_transaction::<(), Error, _>(|| {
-            let feed = feeds::create(&connection, "Link".to_string(), "atom".to_string()).unwrap();
+            let feed = feeds::create(&connection, "Link".to_string(), "rss".to_string()).unwrap();
             let chat = super::create_chat(&connection, new_chat).unwrap();
 
             let new_subscription = NewTelegramSubscription {
                 feed_id: feed.id,
                 chat_id: chat.id,
+                user_id: None,
             };
 
             super::create_subscription(&connection, new_subscription).unwrap();
 
-            let result = super::count_subscriptions_for_chat(&connection, chat.id);
+            let items = vec!["Rust 1.45 released", "Diesel 2.0", "Why rust?", "100% Rust"]
+                .into_iter()
+                .enumerate()
+                .map(|(index, title)| NewTelegramDeliveredItem {
+                    chat_id: chat.id,
+                    feed_id: feed.id,
+                    title: title.to_string(),
+                    link: format!("https://example.com/{}", index),
+                    published_at: db::current_time() - Duration::hours(index as i64),
+                    user_id: None,
+                })
+                .collect::<Vec<NewTelegramDeliveredItem>>();
+
+            assert_eq!(
+                super::create_delivered_items(&connection, items.clone()).unwrap(),
+                4
+            );
+            assert_eq!(
+                super::create_delivered_items(&connection, items).unwrap(),
+                0
+            );
 
-            assert_eq!(result, 1);
-            assert_eq!(super::count_subscriptions_for_chat(&connection, 99), 0);
+            let result = super::search_delivered_items(&connection, chat.id, "RUST", 10).unwrap();
+
+            assert_eq!(result.len(), 3);
+            assert_eq!(result[0].title, "Rust 1.45 released".to_string());
+            assert_eq!(result[1].title, "Why rust?".to_string());
+
+            let result = super::search_delivered_items(&connection, chat.id, "rust", 1).unwrap();
+
+            assert_eq!(result.len(), 1);
+
+            let result = super::search_delivered_items(&connection, chat.id, "0%", 10).unwrap();
+
+            assert_eq!(result.len(), 1);
+            assert_eq!(result[0].title, "100% Rust".to_string());
+
+            super::remove_subscription(&connection, new_subscription).unwrap();
+
+            let result = super::search_delivered_items(&connection, chat.id, "rust", 10).unwrap();
+
+            assert!(result.is_empty());
 
             Ok(())
         });
     }
 
     #[test]
-    fn set_subscription_last_delivered_at_updates_last_delivered_at() {
+    fn create_filter_creates_new_filter() {
         let connection = db::establish_connection();
 
         let new_chat = build_new_chat();
@@ -455,27 +2885,33 @@ mod tests {
             let new_subscription = NewTelegramSubscription {
                 feed_id: feed.id,
                 chat_id: chat.id,
+                user_id: None,
             };
 
-            let subscription = super::create_subscription(&connection, new_subscription).unwrap();
+            super::create_subscription(&connection, new_subscription).unwrap();
 
-            assert!(subscription.last_delivered_at.is_none());
+            for keyword in vec!["rust", "diesel"] {
+                let new_filter = NewTelegramSubscriptionFilter {
+                    chat_id: chat.id,
+                    feed_id: feed.id,
+                    keyword: keyword.to_string(),
+                };
 
-            let updated_subscription = super::set_subscription_last_delivered_at(
-                &connection,
-                &subscription,
-                db::current_time(),
-            )
-            .unwrap();
+                super::create_filter(&connection, new_filter).unwrap();
+            }
 
-            assert!(updated_subscription.last_delivered_at.is_some());
+            let result = super::find_filters(&connection, new_subscription).unwrap();
+
+            assert_eq!(result.len(), 2);
+            assert_eq!(result[0].keyword, "diesel".to_string());
+            assert_eq!(result[1].keyword, "rust".to_string());
 
             Ok(())
         });
     }
 
     #[test]
-    fn remove_subscription_removes_subscription() {
+    fn remove_filter_removes_filter() {
         let connection = db::establish_connection();
 
         let new_chat = build_new_chat();
@@ -487,87 +2923,184 @@ mod tests {
             let new_subscription = NewTelegramSubscription {
                 feed_id: feed.id,
                 chat_id: chat.id,
+                user_id: None,
             };
 
-            super::create_subscription(&connection, new_subscription.clone()).unwrap();
+            super::create_subscription(&connection, new_subscription).unwrap();
 
-            let result = super::remove_subscription(&connection, new_subscription).unwrap();
+            let new_filter = NewTelegramSubscriptionFilter {
+                chat_id: chat.id,
+                feed_id: feed.id,
+                keyword: "rust".to_string(),
+            };
+
+            super::create_filter(&connection, new_filter.clone()).unwrap();
+
+            let result = super::remove_filter(&connection, new_filter).unwrap();
 
             assert_eq!(result, 1);
+            assert!(super::find_filters(&connection, new_subscription)
+                .unwrap()
+                .is_empty());
 
             Ok(())
         });
     }
 
     #[test]
-    fn remove_chat_removes_chat() {
+    fn subscriptions_with_health_returns_failures_of_chat_feeds() {
         let connection = db::establish_connection();
 
         let new_chat = build_new_chat();
 
         connection.test_transaction::<(), Error, _>(|| {
+            let feed1 = feeds::create(&connection, "Link1".to_string(), "rss".to_string()).unwrap();
+            let feed2 = feeds::create(&connection, "Link2".to_string(), "rss".to_string()).unwrap();
+            feeds::create(&connection, "Link3".to_string(), "rss".to_string()).unwrap();
             let chat = super::create_chat(&connection, new_chat).unwrap();
 
-            assert!(super::find_chat(&connection, chat.id).is_some());
-
-            let result = super::remove_chat(&connection, chat.id).unwrap();
-
-            assert_eq!(result, 1);
-
-            assert!(super::find_chat(&connection, chat.id).is_none());
+            for feed in &[&feed1, &feed2] {
+                super::create_subscription(
+                    &connection,
+                    NewTelegramSubscription {
+                        feed_id: feed.id,
+                        chat_id: chat.id,
+                        user_id: None,
+                    },
+                )
+                .unwrap();
+            }
+
+            feeds::set_synced_at(&connection, &feed1, None, None).unwrap();
+            feeds::record_failure(&connection, &feed2, 1).unwrap();
+
+            let health = super::subscriptions_with_health(&connection, chat.id).unwrap();
+
+            assert_eq!(health.len(), 2);
+            assert_eq!(health[0].link, "Link1".to_string());
+            assert!(health[0].synced_at.is_some());
+            assert_eq!(health[0].failures_count, 0);
+            assert!(!health[0].dead);
+            assert_eq!(health[1].link, "Link2".to_string());
+            assert!(health[1].synced_at.is_none());
+            assert_eq!(health[1].failures_count, 1);
+            assert!(health[1].dead);
 
             Ok(())
         });
     }
 
     #[test]
-    fn set_utc_offset_minutes_sets_offset() {
+    fn latest_items_of_subscriptions_returns_newest_item_of_each_feed() {
         let connection = db::establish_connection();
 
         let new_chat = build_new_chat();
 
         connection.test_transaction::<(), Error, _>(|| {
+            let feed1 = feeds::create(&connection, "Link1".to_string(), "rss".to_string()).unwrap();
+            let feed2 = feeds::create(&connection, "Link2".to_string(), "rss".to_string()).unwrap();
+            let feed3 = feeds::create(&connection, "Link3".to_string(), "rss".to_string()).unwrap();
             let chat = super::create_chat(&connection, new_chat).unwrap();
+            let now = db::current_time();
+
+            for feed in &[&feed1, &feed2] {
+                super::create_subscription(
+                    &connection,
+                    NewTelegramSubscription {
+                        feed_id: feed.id,
+                        chat_id: chat.id,
+                        user_id: None,
+                    },
+                )
+                .unwrap();
+            }
+
+            for (feed, title, hours_ago) in &[
+                (&feed1, "Old1", 5),
+                (&feed1, "New1", 3),
+                (&feed2, "Old2", 4),
+                (&feed2, "New2", 1),
+                (&feed3, "New3", 0),
+            ] {
+                feed_items::create(
+                    &connection,
+                    feed.id,
+                    vec![FetchedFeedItem {
+                        title: title.to_string(),
+                        description: None,
+                        link: format!("https://example.com/{}", title),
+                        author: None,
+                        guid: None,
+                        publication_date: now - Duration::hours(*hours_ago),
+                    }],
+                )
+                .unwrap();
+            }
+
+            let items = super::latest_items_of_subscriptions(&connection, chat.id, 5).unwrap();
+
+            assert_eq!(
+                items
+                    .iter()
+                    .map(|item| (item.link.as_str(), item.item_title.as_str()))
+                    .collect::<Vec<(&str, &str)>>(),
+                vec![("Link2", "New2"), ("Link1", "New1")]
+            );
 
-            assert!(super::find_chat(&connection, chat.id).is_some());
-
-            let result = super::set_utc_offset_minutes(&connection, &chat, 180).unwrap();
+            let items = super::latest_items_of_subscriptions(&connection, chat.id, 1).unwrap();
 
-            assert_eq!(result.utc_offset_minutes.unwrap(), 180);
+            assert_eq!(items.len(), 1);
+            assert_eq!(items[0].item_title, "New2".to_string());
 
             Ok(())
         });
     }
 
     #[test]
-    fn find_chats_by_feed_id_find_chats() {
+    fn chat_stats_aggregates_delivery_stats_of_subscriptions() {
         let connection = db::establish_connection();
 
-        let new_chat1 = build_new_chat_with_id(10);
-        let new_chat2 = build_new_chat_with_id(20);
+        let new_chat = build_new_chat();
 
         connection.test_transaction::<(), Error, _>(|| {
-            let feed = feeds::create(&connection, "Link".to_string(), "rss".to_string()).unwrap();
-            let chat1 = super::create_chat(&connection, new_chat1).unwrap();
-            let chat2 = super::create_chat(&connection, new_chat2).unwrap();
+            let feed1 = feeds::create(&connection, "Link1".to_string(), "rss".to_string()).unwrap();
+            let feed2 = feeds::create(&connection, "Link2".to_string(), "rss".to_string()).unwrap();
+            let chat = super::create_chat(&connection, new_chat).unwrap();
 
-            let new_subscription1 = NewTelegramSubscription {
-                feed_id: feed.id,
-                chat_id: chat1.id,
-            };
+            let subscription1 = super::create_subscription(
+                &connection,
+                NewTelegramSubscription {
+                    feed_id: feed1.id,
+                    chat_id: chat.id,
+                    user_id: None,
+                },
+            )
+            .unwrap();
+            super::create_subscription(
+                &connection,
+                NewTelegramSubscription {
+                    feed_id: feed2.id,
+                    chat_id: chat.id,
+                    user_id: None,
+                },
+            )
+            .unwrap();
 
-            super::create_subscription(&connection, new_subscription1.clone()).unwrap();
+            let empty_stats = super::chat_stats(&connection, chat.id).unwrap();
 
-            let new_subscription2 = NewTelegramSubscription {
-                feed_id: feed.id,
-                chat_id: chat2.id,
-            };
+            assert_eq!(empty_stats.subscriptions_count, 2);
+            assert_eq!(empty_stats.delivered_count, 0);
+            assert!(empty_stats.delivered_at.is_none());
 
-            super::create_subscription(&connection, new_subscription2.clone()).unwrap();
+            let updated_subscription =
+                super::increment_delivered_count(&connection, &subscription1, 3).unwrap();
+            super::increment_delivered_count(&connection, &updated_subscription, 2).unwrap();
 
-            let result = super::find_chats_by_feed_id(&connection, feed.id).unwrap();
+            let stats = super::chat_stats(&connection, chat.id).unwrap();
 
-            assert_eq!(result.len(), 2);
+            assert_eq!(stats.subscriptions_count, 2);
+            assert_eq!(stats.delivered_count, 5);
+            assert!(stats.delivered_at.is_some());
 
             Ok(())
         });
@@ -6,10 +6,16 @@ extern crate log;
 #[macro_use]
 extern crate failure;
 extern crate atom_syndication;
+extern crate chrono_tz;
 extern crate dotenv;
 extern crate feed_rs;
 extern crate futures;
+extern crate hyper;
 extern crate isahc;
+extern crate lazy_static;
+extern crate openssl;
+extern crate percent_encoding;
+extern crate quick_xml;
 extern crate rss;
 extern crate serde_json;
 extern crate telegram_bot;
@@ -1,7 +1,7 @@
 use crate::schema::feeds;
 use chrono::{DateTime, Utc};
 
-#[derive(Queryable, Identifiable, Debug, Eq, PartialEq)]
+#[derive(Queryable, Identifiable, Debug, Clone, Eq, PartialEq)]
 #[table_name = "feeds"]
 pub struct Feed {
     pub id: i64,
@@ -15,4 +15,12 @@ pub struct Feed {
     pub updated_at: DateTime<Utc>,
 
     pub feed_type: String,
+
+    pub failures_count: i32,
+    pub dead: bool,
+
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+
+    pub credentials_key: String,
 }
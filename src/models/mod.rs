@@ -1,4 +1,7 @@
 pub mod feed;
 pub mod feed_item;
 pub mod telegram_chat;
+pub mod telegram_chat_watchword;
+pub mod telegram_delivered_item;
 pub mod telegram_subscription;
+pub mod telegram_subscription_filter;
@@ -14,4 +14,29 @@ pub struct TelegramChat {
     pub updated_at: DateTime<Utc>,
     pub title: Option<String>,
     pub utc_offset_minutes: Option<i32>,
+    pub max_subscriptions: Option<i32>,
+    pub paused: bool,
+    pub active: bool,
+    pub timezone_name: Option<String>,
+
+    pub digest: bool,
+    pub digest_hour: i32,
+    pub digest_sent_at: Option<DateTime<Utc>>,
+
+    pub description_limit: Option<i32>,
+
+    pub language: Option<String>,
+
+    pub quiet_hours_start: Option<i32>,
+    pub quiet_hours_end: Option<i32>,
+
+    pub track_per_user: bool,
+
+    pub item_prefix: Option<String>,
+
+    pub forbidden_errors_count: i32,
+
+    pub backfill_newest_first: bool,
+
+    pub show_author: bool,
 }
@@ -0,0 +1,13 @@
+use crate::schema::telegram_chat_watchwords;
+use chrono::{DateTime, Utc};
+
+#[derive(Queryable, Identifiable, Debug)]
+#[table_name = "telegram_chat_watchwords"]
+#[primary_key(chat_id, keyword)]
+pub struct TelegramChatWatchword {
+    pub chat_id: i64,
+    pub keyword: String,
+
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
@@ -0,0 +1,18 @@
+use crate::schema::telegram_delivered_items;
+use chrono::{DateTime, Utc};
+
+#[derive(Queryable, Identifiable, Debug)]
+#[table_name = "telegram_delivered_items"]
+#[primary_key(chat_id, feed_id, link)]
+pub struct TelegramDeliveredItem {
+    pub chat_id: i64,
+    pub feed_id: i64,
+    pub title: String,
+    pub link: String,
+    pub published_at: DateTime<Utc>,
+
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+
+    pub user_id: Option<i64>,
+}
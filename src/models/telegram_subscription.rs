@@ -11,4 +11,32 @@ pub struct TelegramSubscription {
     pub last_delivered_at: Option<DateTime<Utc>>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+
+    pub delivered_count: i64,
+    pub delivered_at: Option<DateTime<Utc>>,
+
+    pub initial_items_count: i32,
+    pub template: Option<String>,
+    pub silent: bool,
+
+    pub auth_username: Option<String>,
+    pub auth_password: Option<String>,
+
+    pub cursor_reset_at: Option<DateTime<Utc>>,
+
+    pub min_delivery_interval_secs: Option<i32>,
+
+    pub link_preview: bool,
+
+    pub user_id: Option<i64>,
+
+    pub snoozed_until: Option<DateTime<Utc>>,
+
+    pub full_text: Option<bool>,
+
+    pub deleted_at: Option<DateTime<Utc>>,
+
+    pub markdown: bool,
+
+    pub html: bool,
 }
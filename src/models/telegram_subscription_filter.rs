@@ -0,0 +1,14 @@
+use crate::schema::telegram_subscription_filters;
+use chrono::{DateTime, Utc};
+
+#[derive(Queryable, Identifiable, Debug)]
+#[table_name = "telegram_subscription_filters"]
+#[primary_key(chat_id, feed_id, keyword)]
+pub struct TelegramSubscriptionFilter {
+    pub chat_id: i64,
+    pub feed_id: i64,
+    pub keyword: String,
+
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
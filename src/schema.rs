@@ -9,6 +9,7 @@ table! {
         publication_date -> Timestamptz,
         created_at -> Timestamptz,
         updated_at -> Timestamptz,
+        content_hash -> Nullable<Text>,
     }
 }
 
@@ -23,6 +24,11 @@ table! {
         created_at -> Timestamptz,
         updated_at -> Timestamptz,
         feed_type -> Text,
+        failures_count -> Int4,
+        dead -> Bool,
+        etag -> Nullable<Text>,
+        last_modified -> Nullable<Text>,
+        credentials_key -> Text,
     }
 }
 
@@ -37,6 +43,54 @@ table! {
         updated_at -> Timestamptz,
         title -> Nullable<Text>,
         utc_offset_minutes -> Nullable<Int4>,
+        max_subscriptions -> Nullable<Int4>,
+        paused -> Bool,
+        active -> Bool,
+        timezone_name -> Nullable<Text>,
+        digest -> Bool,
+        digest_hour -> Int4,
+        digest_sent_at -> Nullable<Timestamptz>,
+        description_limit -> Nullable<Int4>,
+        language -> Nullable<Text>,
+        quiet_hours_start -> Nullable<Int4>,
+        quiet_hours_end -> Nullable<Int4>,
+        track_per_user -> Bool,
+        item_prefix -> Nullable<Text>,
+        forbidden_errors_count -> Int4,
+        backfill_newest_first -> Bool,
+        show_author -> Bool,
+    }
+}
+
+table! {
+    telegram_chat_watchwords (chat_id, keyword) {
+        chat_id -> Int8,
+        keyword -> Text,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+    }
+}
+
+table! {
+    telegram_delivered_items (chat_id, feed_id, link) {
+        chat_id -> Int8,
+        feed_id -> Int8,
+        title -> Text,
+        link -> Text,
+        published_at -> Timestamptz,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+        user_id -> Nullable<Int8>,
+    }
+}
+
+table! {
+    telegram_subscription_filters (chat_id, feed_id, keyword) {
+        chat_id -> Int8,
+        feed_id -> Int8,
+        keyword -> Text,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
     }
 }
 
@@ -47,16 +101,36 @@ table! {
         last_delivered_at -> Nullable<Timestamptz>,
         created_at -> Timestamptz,
         updated_at -> Timestamptz,
+        delivered_count -> Int8,
+        delivered_at -> Nullable<Timestamptz>,
+        initial_items_count -> Int4,
+        template -> Nullable<Text>,
+        silent -> Bool,
+        auth_username -> Nullable<Text>,
+        auth_password -> Nullable<Text>,
+        cursor_reset_at -> Nullable<Timestamptz>,
+        min_delivery_interval_secs -> Nullable<Int4>,
+        link_preview -> Bool,
+        user_id -> Nullable<Int8>,
+        snoozed_until -> Nullable<Timestamptz>,
+        full_text -> Nullable<Bool>,
+        deleted_at -> Nullable<Timestamptz>,
+        markdown -> Bool,
+        html -> Bool,
     }
 }
 
 joinable!(feed_items -> feeds (feed_id));
+joinable!(telegram_chat_watchwords -> telegram_chats (chat_id));
 joinable!(telegram_subscriptions -> feeds (feed_id));
 joinable!(telegram_subscriptions -> telegram_chats (chat_id));
 
 allow_tables_to_appear_in_same_query!(
     feed_items,
     feeds,
+    telegram_chat_watchwords,
     telegram_chats,
+    telegram_delivered_items,
+    telegram_subscription_filters,
     telegram_subscriptions,
 );
@@ -1,15 +1,16 @@
 use crate::db;
-use crate::db::{feed_items, feeds};
+use crate::db::{credentials, feed_items, feeds, telegram};
 use crate::models::feed::Feed;
-use crate::sync::reader::atom::AtomReader;
-use crate::sync::reader::json::JsonReader;
-use crate::sync::reader::rss::RssReader;
-use crate::sync::reader::FeedReaderError;
-use crate::sync::reader::ReadFeed;
-use crate::sync::FetchedFeed;
-use chrono::Duration;
+use crate::models::telegram_subscription::TelegramSubscription;
+use crate::sync::reader;
+use crate::sync::reader::{CacheHeaders, ConditionalFetch, Credentials, FeedReaderError};
+use chrono::{DateTime, Duration, Utc};
 use diesel::pg::PgConnection;
 use log::error;
+use std::env;
+
+static DEFAULT_DEAD_FEED_FAILURES: i32 = 10;
+static MAX_BACKOFF_MINUTES: i64 = 60;
 
 #[derive(Debug)]
 pub struct FeedSyncJob {
@@ -24,6 +25,8 @@ pub enum FeedSyncError {
     DbError { msg: String },
     #[fail(display = "failed to insert a feed for too long")]
     StaleError,
+    #[fail(display = "failed to fetch a feed {} times in a row", failures_count)]
+    DeadError { failures_count: i32 },
 }
 
 impl FeedSyncJob {
@@ -35,43 +38,39 @@ impl FeedSyncJob {
         let db_connection = db::establish_connection();
         let feed = feeds::find(&db_connection, self.feed_id).unwrap();
 
-        match read_feed(&feed) {
-            Ok(fetched_feed) => {
-                match feed_items::create(&db_connection, feed.id, fetched_feed.items) {
-                    Err(err) => {
+        if !sync_is_due(&feed, db::current_time()) {
+            return Ok(());
+        }
+
+        match read_feed(&db_connection, &feed) {
+            Ok(fetch) => {
+                if feed.failures_count > 0 {
+                    if let Err(err) = feeds::reset_failures(&db_connection, &feed) {
                         error!(
-                            "Error: failed to create feed items for feed with id {}: {:?}",
+                            "Error: failed to reset failures for feed with id {}: {:?}",
                             self.feed_id, err
                         );
-
-                        let error = FeedSyncError::DbError {
-                            msg: format!("Error: failed to create feed items {:?}", err),
-                        };
-                        Err(error)
                     }
-                    _ => match feeds::set_synced_at(
-                        &db_connection,
-                        &feed,
-                        Some(fetched_feed.title),
-                        Some(fetched_feed.description),
-                    ) {
-                        Err(err) => {
-                            error!(
-                                "Error: failed to update synced_at for feed with id {}: {:?}",
-                                self.feed_id, err
-                            );
-
-                            let error = FeedSyncError::DbError {
-                                msg: format!("Error: failed to update synced_at {:?}", err),
-                            };
-
-                            Err(error)
-                        }
-                        _ => Ok(()),
-                    },
                 }
+
+                save_fetched_feed(&db_connection, &feed, fetch)
             }
             Err(err) => {
+                match feeds::record_failure(&db_connection, &feed, dead_feed_failures()) {
+                    Ok(updated_feed) if updated_feed.dead => {
+                        set_error(&db_connection, &feed, err);
+
+                        return Err(FeedSyncError::DeadError {
+                            failures_count: updated_feed.failures_count,
+                        });
+                    }
+                    Ok(_) => (),
+                    Err(db_err) => error!(
+                        "Error: failed to record a failure for feed with id {}: {:?}",
+                        self.feed_id, db_err
+                    ),
+                }
+
                 let created_at_or_last_synced_at = if feed.synced_at.is_some() {
                     feed.synced_at.unwrap()
                 } else {
@@ -90,6 +89,80 @@ impl FeedSyncJob {
     }
 }
 
+fn save_fetched_feed(
+    db_connection: &PgConnection,
+    feed: &Feed,
+    fetch: ConditionalFetch,
+) -> Result<(), FeedSyncError> {
+    let (title, description) = match fetch {
+        ConditionalFetch::NotModified => (feed.title.clone(), feed.description.clone()),
+        ConditionalFetch::Modified(fetched_feed, cache_headers) => {
+            if let Err(err) = feed_items::create(db_connection, feed.id, fetched_feed.items) {
+                error!(
+                    "Error: failed to create feed items for feed with id {}: {:?}",
+                    feed.id, err
+                );
+
+                let error = FeedSyncError::DbError {
+                    msg: format!("Error: failed to create feed items {:?}", err),
+                };
+
+                return Err(error);
+            }
+
+            if let Err(err) = feeds::set_cache_headers(db_connection, feed, &cache_headers) {
+                error!(
+                    "Error: failed to update cache headers for feed with id {}: {:?}",
+                    feed.id, err
+                );
+            }
+
+            (Some(fetched_feed.title), Some(fetched_feed.description))
+        }
+    };
+
+    match feeds::set_synced_at(db_connection, feed, title, description) {
+        Err(err) => {
+            error!(
+                "Error: failed to update synced_at for feed with id {}: {:?}",
+                feed.id, err
+            );
+
+            let error = FeedSyncError::DbError {
+                msg: format!("Error: failed to update synced_at {:?}", err),
+            };
+
+            Err(error)
+        }
+        _ => Ok(()),
+    }
+}
+
+fn sync_is_due(feed: &Feed, now: DateTime<Utc>) -> bool {
+    if feed.failures_count == 0 {
+        return true;
+    }
+
+    now - feed.updated_at >= backoff(feed.failures_count)
+}
+
+fn backoff(failures_count: i32) -> Duration {
+    let exponent = (failures_count - 1).max(0).min(6) as u32;
+
+    Duration::minutes(2_i64.pow(exponent).min(MAX_BACKOFF_MINUTES))
+}
+
+fn dead_feed_failures() -> i32 {
+    match env::var("DEAD_FEED_FAILURES") {
+        Ok(value) => value
+            .parse::<i32>()
+            .ok()
+            .filter(|count| *count > 0)
+            .unwrap_or(DEFAULT_DEAD_FEED_FAILURES),
+        Err(_) => DEFAULT_DEAD_FEED_FAILURES,
+    }
+}
+
 fn set_error(
     db_connection: &PgConnection,
     feed: &Feed,
@@ -115,23 +188,67 @@ fn set_error(
     }
 }
 
-fn read_feed(feed: &Feed) -> Result<FetchedFeed, FeedReaderError> {
-    if feed.feed_type == "rss".to_string() {
-        RssReader {
-            url: feed.link.clone(),
+fn read_feed(
+    db_connection: &PgConnection,
+    feed: &Feed,
+) -> Result<ConditionalFetch, FeedReaderError> {
+    // feeds without a credentials key are shared, they're never fetched with anyone's credentials
+    let credentials = if feed.credentials_key.is_empty() {
+        None
+    } else {
+        match telegram::find_authenticated_subscription(db_connection, feed.id) {
+            Some(subscription) => Some(subscription_credentials(db_connection, subscription)?),
+            None => None,
         }
-        .read()
-    } else if feed.feed_type == "atom".to_string() {
-        AtomReader {
-            url: feed.link.clone(),
+    };
+
+    let cache_headers = CacheHeaders {
+        etag: feed.etag.clone(),
+        last_modified: feed.last_modified.clone(),
+    };
+
+    reader::read_feed_if_modified(
+        &feed.link,
+        &feed.feed_type,
+        credentials.as_ref(),
+        &cache_headers,
+    )
+}
+
+fn subscription_credentials(
+    db_connection: &PgConnection,
+    subscription: TelegramSubscription,
+) -> Result<Credentials, FeedReaderError> {
+    let username = subscription.auth_username.clone().unwrap_or_default();
+    let stored_password = subscription.auth_password.clone().unwrap_or_default();
+
+    let password = match credentials::decrypt_password(&stored_password) {
+        Ok(password) => password,
+        Err(err) => {
+            let msg = format!("Failed to decrypt credentials: {}", err);
+
+            return Err(FeedReaderError { msg });
         }
-        .read()
-    } else {
-        JsonReader {
-            url: feed.link.clone(),
+    };
+
+    // passwords saved before they were encrypted are encrypted on the first sync
+    if !credentials::is_encrypted(&stored_password) {
+        if let Ok(encrypted_password) = credentials::encrypt_password(&password) {
+            if let Err(err) = telegram::set_credentials(
+                db_connection,
+                &subscription,
+                username.clone(),
+                encrypted_password,
+            ) {
+                error!(
+                    "Error: failed to encrypt credentials for feed with id {}: {:?}",
+                    subscription.feed_id, err
+                );
+            }
         }
-        .read()
     }
+
+    Ok(Credentials { username, password })
 }
 
 #[cfg(test)]
@@ -139,6 +256,83 @@ mod tests {
     use super::FeedSyncJob;
     use crate::db;
     use crate::db::{feed_items, feeds};
+    use crate::models::feed::Feed;
+    use crate::sync::reader::ConditionalFetch;
+    use chrono::Duration;
+    use diesel::connection::Connection;
+    use diesel::result::Error;
+
+    fn build_feed(failures_count: i32, updated_at: chrono::DateTime<chrono::Utc>) -> Feed {
+        Feed {
+            id: 1,
+            title: None,
+            link: "https://example.com/feed.xml".to_string(),
+            error: None,
+            description: None,
+            synced_at: None,
+            created_at: updated_at,
+            updated_at,
+            feed_type: "rss".to_string(),
+            failures_count,
+            dead: false,
+            etag: None,
+            last_modified: None,
+            credentials_key: "".to_string(),
+        }
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_an_hour() {
+        assert_eq!(super::backoff(1), Duration::minutes(1));
+        assert_eq!(super::backoff(2), Duration::minutes(2));
+        assert_eq!(super::backoff(4), Duration::minutes(8));
+        assert_eq!(super::backoff(7), Duration::minutes(60));
+        assert_eq!(super::backoff(100), Duration::minutes(60));
+    }
+
+    #[test]
+    fn sync_is_due_waits_for_backoff_after_failures() {
+        let now = db::current_time();
+
+        assert!(super::sync_is_due(&build_feed(0, now), now));
+        assert!(!super::sync_is_due(
+            &build_feed(3, now - Duration::minutes(3)),
+            now
+        ));
+        assert!(super::sync_is_due(
+            &build_feed(3, now - Duration::minutes(4)),
+            now
+        ));
+    }
+
+    #[test]
+    fn save_fetched_feed_marks_not_modified_feed_synced_without_items() {
+        let connection = db::establish_connection();
+
+        connection.test_transaction::<_, Error, _>(|| {
+            let feed = feeds::create(
+                &connection,
+                "https://example.com/feed.xml".to_string(),
+                "rss".to_string(),
+            )
+            .unwrap();
+
+            let result =
+                super::save_fetched_feed(&connection, &feed, ConditionalFetch::NotModified);
+
+            assert!(result.is_ok());
+            assert_eq!(
+                feed_items::find(&connection, feed.id).map(|items| items.len()),
+                Some(0)
+            );
+            assert!(feeds::find(&connection, feed.id)
+                .unwrap()
+                .synced_at
+                .is_some());
+
+            Ok(())
+        })
+    }
 
     #[test]
     #[ignore]
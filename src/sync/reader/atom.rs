@@ -1,8 +1,13 @@
 use crate::db;
 use crate::sync::reader;
-use crate::sync::reader::{FeedReaderError, FetchedFeed, FetchedFeedItem, ReadFeed};
-use atom_syndication::Feed as AtomFeed;
+use crate::sync::reader::{
+    DescriptionFields, FeedReaderError, FetchedFeed, FetchedFeedItem, ReadFeed,
+};
+use atom_syndication::{Entry, Feed as AtomFeed};
 use chrono::{DateTime, FixedOffset, Utc};
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use url::Url;
 
 pub struct AtomReader {
     pub url: String,
@@ -12,8 +17,20 @@ impl ReadFeed for AtomReader {
     fn read(&self) -> Result<FetchedFeed, FeedReaderError> {
         let body = reader::read_url(&self.url)?;
 
-        match AtomFeed::read_from(&body[..]) {
-            Ok(atom_feed) => Ok(FetchedFeed::from(atom_feed)),
+        self.read_from(&body[..])
+    }
+
+    fn read_from(&self, body: &[u8]) -> Result<FetchedFeed, FeedReaderError> {
+        match AtomFeed::read_from(body) {
+            Ok(atom_feed) => {
+                let mut fetched_feed = FetchedFeed::from(atom_feed);
+
+                if let Some(base) = base_url(&self.url, body) {
+                    resolve_links(&mut fetched_feed, &base);
+                }
+
+                Ok(fetched_feed)
+            }
             Err(err) => {
                 let msg = format!("{}", err);
                 Err(FeedReaderError { msg })
@@ -38,15 +55,9 @@ impl From<AtomFeed> for FetchedFeed {
 
                 FetchedFeedItem {
                     title: item.title().to_string(),
-                    description: item.summary().map(|s| s.to_string()),
+                    description: reader::extract_description(&description_fields(item), "atom"),
                     link: item.links().first().unwrap().href().to_string(),
-                    author: Some(
-                        item.authors()
-                            .into_iter()
-                            .map(|person| person.name.to_string())
-                            .collect::<Vec<String>>()
-                            .join(", "),
-                    ),
+                    author: extract_author(item),
                     guid: Some(item.id().to_string()),
                     publication_date: pub_date,
                 }
@@ -67,6 +78,68 @@ impl From<AtomFeed> for FetchedFeed {
     }
 }
 
+pub fn extract_author(item: &Entry) -> Option<String> {
+    reader::join_authors(item.authors().iter().map(|person| person.name.to_string()))
+}
+
+fn description_fields(item: &Entry) -> DescriptionFields {
+    let content = item
+        .content()
+        .and_then(|content| content.value().map(|value| (content.content_type(), value)));
+
+    let (content_text, content_html) = match content {
+        Some((None, value)) | Some((Some("text"), value)) => (Some(value.to_string()), None),
+        Some((_, value)) => (None, Some(value.to_string())),
+        None => (None, None),
+    };
+
+    DescriptionFields {
+        summary: item.summary().map(|s| s.to_string()),
+        content_text,
+        content_html,
+        ..DescriptionFields::default()
+    }
+}
+
+fn base_url(url: &str, body: &[u8]) -> Option<Url> {
+    let feed_url = Url::parse(url).ok();
+
+    match (xml_base(body), feed_url) {
+        (Some(xml_base), Some(feed_url)) => feed_url.join(&xml_base).ok(),
+        (Some(xml_base), None) => Url::parse(&xml_base).ok(),
+        (None, feed_url) => feed_url,
+    }
+}
+
+fn xml_base(body: &[u8]) -> Option<String> {
+    let mut reader = Reader::from_reader(body);
+    let mut buf = Vec::new();
+
+    loop {
+        match reader.read_event(&mut buf) {
+            Ok(Event::Start(ref element)) | Ok(Event::Empty(ref element)) => {
+                return element
+                    .attributes()
+                    .filter_map(|attribute| attribute.ok())
+                    .find(|attribute| attribute.key == b"xml:base")
+                    .and_then(|attribute| attribute.unescape_and_decode_value(&reader).ok());
+            }
+            Ok(Event::Eof) | Err(_) => return None,
+            _ => (),
+        }
+
+        buf.clear();
+    }
+}
+
+fn resolve_links(feed: &mut FetchedFeed, base: &Url) {
+    feed.link = reader::resolve_link(base, &feed.link);
+
+    for item in feed.items.iter_mut() {
+        item.link = reader::resolve_link(base, &item.link);
+    }
+}
+
 fn parse_time(pub_date: Option<&DateTime<FixedOffset>>) -> DateTime<Utc> {
     match pub_date {
         None => db::current_time(),
@@ -76,7 +149,8 @@ fn parse_time(pub_date: Option<&DateTime<FixedOffset>>) -> DateTime<Utc> {
 
 #[cfg(test)]
 mod tests {
-    use super::{FetchedFeed, FetchedFeedItem};
+    use super::{AtomReader, FetchedFeed, FetchedFeedItem};
+    use crate::sync::reader::ReadFeed;
     use atom_syndication::Feed as AtomFeed;
     use chrono::DateTime;
     use std::fs;
@@ -98,7 +172,7 @@ mod tests {
                 title: "Atom-Powered Robots Run Amok".to_string(),
                 description: Some("Some text.".to_string()),
                 link: "http://example.org/2003/12/13/atom03".to_string(),
-                author: Some("".to_string()),
+                author: None,
                 guid: Some("urn:uuid:1225c695-cfb8-4ebb-aaaa-80da344efa6a".to_string()),
                 publication_date: DateTime::parse_from_rfc3339("2003-12-13T18:30:02Z")
                     .unwrap()
@@ -108,4 +182,62 @@ mod tests {
 
         assert_eq!(expected_result, fetched_feed);
     }
+
+    #[test]
+    fn it_resolves_relative_links_against_xml_base() {
+        let xml_feed = fs::read("./tests/support/atom_feed_relative_links_example.xml").unwrap();
+        let reader = AtomReader {
+            url: "https://feeds.example.org/atom.xml".to_string(),
+        };
+
+        let fetched_feed = reader.read_from(&xml_feed[..]).unwrap();
+
+        assert_eq!(fetched_feed.link, "http://example.org/".to_string());
+        assert_eq!(
+            fetched_feed
+                .items
+                .iter()
+                .map(|item| item.link.clone())
+                .collect::<Vec<String>>(),
+            vec![
+                "http://example.org/blog/2003/12/13/atom03".to_string(),
+                "https://example.com/robots-are-stopped".to_string()
+            ]
+        );
+    }
+
+    #[test]
+    fn it_resolves_relative_links_against_feed_url() {
+        let xml_feed = fs::read_to_string("./tests/support/atom_feed_example.xml")
+            .unwrap()
+            .replace("http://example.org/2003/12/13/atom03", "../posts/atom03");
+        let reader = AtomReader {
+            url: "https://example.org/feeds/atom.xml".to_string(),
+        };
+
+        let fetched_feed = reader.read_from(xml_feed.as_bytes()).unwrap();
+
+        assert_eq!(
+            fetched_feed.items[0].link,
+            "https://example.org/posts/atom03".to_string()
+        );
+        assert_eq!(fetched_feed.link, "http://example.org/".to_string());
+    }
+
+    #[test]
+    fn extract_author_joins_author_names() {
+        let xml_feed = fs::read_to_string("./tests/support/atom_feed_example.xml")
+            .unwrap()
+            .replace(
+                "<id>urn:uuid:1225c695-cfb8-4ebb-aaaa-80da344efa6a</id>",
+                "<id>urn:uuid:1225c695-cfb8-4ebb-aaaa-80da344efa6a</id>\
+                 <author><name>John Doe</name></author><author><name>Jane Roe</name></author>",
+            );
+        let feed = AtomFeed::from_str(&xml_feed).unwrap();
+
+        assert_eq!(
+            super::extract_author(&feed.entries()[0]),
+            Some("John Doe, Jane Roe".to_string())
+        );
+    }
 }
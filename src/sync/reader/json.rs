@@ -1,11 +1,18 @@
 use crate::db;
 use crate::sync::reader;
-use crate::sync::reader::{FeedReaderError, FetchedFeed, FetchedFeedItem, ReadFeed};
+use crate::sync::reader::{
+    DescriptionFields, FeedReaderError, FetchedFeed, FetchedFeedItem, ReadFeed,
+};
 use chrono::{DateTime, Utc};
-use feed_rs::model::Feed;
+use feed_rs::model::{Entry, Feed};
 use feed_rs::parser;
 use serde_json::Value;
 
+static JSON_FEED_VERSIONS: [&str; 2] = [
+    "https://jsonfeed.org/version/1",
+    "https://jsonfeed.org/version/1.1",
+];
+
 pub struct JsonReader {
     pub url: String,
 }
@@ -14,15 +21,23 @@ impl ReadFeed for JsonReader {
     fn read(&self) -> Result<FetchedFeed, FeedReaderError> {
         let body = reader::read_url(&self.url)?;
 
-        match serde_json::from_slice::<Value>(&body[..]) {
-            Ok(_) => (),
+        self.read_from(&body[..])
+    }
+
+    fn read_from(&self, body: &[u8]) -> Result<FetchedFeed, FeedReaderError> {
+        match serde_json::from_slice::<Value>(body) {
+            Ok(value) if is_json_feed(&value) => (),
+            Ok(_) => {
+                let msg = "the document is not a JSON feed".to_string();
+                return Err(FeedReaderError { msg });
+            }
             Err(err) => {
                 let msg = format!("{:?}", err);
                 return Err(FeedReaderError { msg });
             }
         }
 
-        match parser::parse(&body[..]) {
+        match parser::parse(body) {
             Ok(feed) => {
                 let mut fetched_feed = FetchedFeed::from(feed);
                 fetched_feed.link = self.url.clone();
@@ -45,17 +60,14 @@ impl From<Feed> for FetchedFeed {
             .filter(|item| !item.links.is_empty())
             .map(|item| {
                 let pub_date: DateTime<Utc> = parse_time(item.published, item.updated);
+                let description = reader::extract_description(&description_fields(&item), "json");
+                let author = extract_author(&item);
+
                 FetchedFeedItem {
                     title: item.title.map_or_else(|| "".to_string(), |s| s.content),
-                    description: item.summary.map(|s| s.content),
+                    description,
                     link: item.links.first().unwrap().href.clone(),
-                    author: Some(
-                        item.authors
-                            .into_iter()
-                            .map(|person| person.name)
-                            .collect::<Vec<String>>()
-                            .join(", "),
-                    ),
+                    author,
                     guid: Some(item.id),
                     publication_date: pub_date,
                 }
@@ -76,6 +88,56 @@ impl From<Feed> for FetchedFeed {
     }
 }
 
+pub fn extract_author(item: &Entry) -> Option<String> {
+    reader::join_authors(item.authors.iter().map(|person| person.name.clone()))
+}
+
+fn description_fields(item: &Entry) -> DescriptionFields {
+    let content = item.content.as_ref().and_then(|content| {
+        content
+            .body
+            .as_ref()
+            .map(|body| (content.content_type.to_string(), body.clone()))
+    });
+
+    let (content_text, content_html) = match content {
+        Some((content_type, body)) if content_type.starts_with("text/plain") => (Some(body), None),
+        Some((_, body)) => (None, Some(body)),
+        None => (None, None),
+    };
+
+    DescriptionFields {
+        summary: item.summary.as_ref().map(|s| s.content.clone()),
+        content_text,
+        content_html,
+        ..DescriptionFields::default()
+    }
+}
+
+pub fn has_undated_items(body: &[u8]) -> bool {
+    let value = match serde_json::from_slice::<Value>(body) {
+        Ok(value) => value,
+        Err(_) => return false,
+    };
+
+    value["items"].as_array().map_or(false, |items| {
+        items.iter().any(|item| {
+            item["url"].is_string()
+                && !item["date_published"].is_string()
+                && !item["date_modified"].is_string()
+        })
+    })
+}
+
+fn is_json_feed(value: &Value) -> bool {
+    let version = value["version"]
+        .as_str()
+        .unwrap_or("")
+        .trim_end_matches('/');
+
+    JSON_FEED_VERSIONS.contains(&version) && value["items"].is_array()
+}
+
 fn parse_time(pub_date: Option<DateTime<Utc>>, updated: Option<DateTime<Utc>>) -> DateTime<Utc> {
     match pub_date {
         None => match updated {
@@ -91,8 +153,34 @@ mod tests {
     use super::{FetchedFeed, FetchedFeedItem};
     use chrono::DateTime;
     use feed_rs::parser;
+    use serde_json::json;
     use std::fs;
 
+    #[test]
+    fn is_json_feed_checks_version_and_items() {
+        assert!(super::is_json_feed(&json!({
+            "version": "https://jsonfeed.org/version/1",
+            "title": "Feed",
+            "items": []
+        })));
+        assert!(super::is_json_feed(&json!({
+            "version": "https://jsonfeed.org/version/1.1",
+            "title": "Feed",
+            "items": []
+        })));
+        assert!(!super::is_json_feed(&json!({
+            "version": "https://jsonfeed.org/version/1.1",
+            "title": "Feed"
+        })));
+        assert!(!super::is_json_feed(&json!({
+            "version": "2.0",
+            "items": []
+        })));
+        assert!(!super::is_json_feed(
+            &json!({"name": "value", "items": [1, 2]})
+        ));
+    }
+
     #[test]
     fn it_converts_json_feed_to_fetched_feed() {
         let json_feed = fs::read_to_string("./tests/support/json_feed_example.json").unwrap();
@@ -104,4 +192,28 @@ mod tests {
 
         assert_eq!(expected_result, fetched_feed);
     }
+
+    #[test]
+    fn extract_author_reads_author_name() {
+        let feed = |author: &str| {
+            parser::parse(
+                format!(
+                    r#"{{"version": "https://jsonfeed.org/version/1", "title": "Feed",
+                        "items": [{{"id": "1", "url": "https://example.com/1"{}}}]}}"#,
+                    author
+                )
+                .as_bytes(),
+            )
+            .unwrap()
+        };
+
+        let with_author = feed(r#", "author": {"name": "Jane Doe"}"#);
+        let without_author = feed("");
+
+        assert_eq!(
+            super::extract_author(&with_author.entries[0]),
+            Some("Jane Doe".to_string())
+        );
+        assert_eq!(super::extract_author(&without_author.entries[0]), None);
+    }
 }
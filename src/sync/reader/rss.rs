@@ -1,8 +1,10 @@
 use crate::db;
 use crate::sync::reader;
-use crate::sync::reader::{FeedReaderError, FetchedFeed, FetchedFeedItem, ReadFeed};
+use crate::sync::reader::{
+    DescriptionFields, FeedReaderError, FetchedFeed, FetchedFeedItem, ReadFeed,
+};
 use chrono::{DateTime, Utc};
-use rss::Channel;
+use rss::{Channel, Item};
 
 pub struct RssReader {
     pub url: String,
@@ -12,7 +14,11 @@ impl ReadFeed for RssReader {
     fn read(&self) -> Result<FetchedFeed, FeedReaderError> {
         let body = reader::read_url(&self.url)?;
 
-        match Channel::read_from(&body[..]) {
+        self.read_from(&body[..])
+    }
+
+    fn read_from(&self, body: &[u8]) -> Result<FetchedFeed, FeedReaderError> {
+        match Channel::read_from(body) {
             Ok(channel) => Ok(FetchedFeed::from(channel)),
             Err(err) => {
                 let msg = format!("{}", err);
@@ -34,9 +40,16 @@ impl From<Channel> for FetchedFeed {
                     title: item
                         .title()
                         .map_or_else(|| "".to_string(), |s| s.to_string()),
-                    description: item.description().map(|s| s.to_string()),
+                    description: reader::extract_description(
+                        &DescriptionFields {
+                            description: item.description().map(|s| s.to_string()),
+                            content_html: item.content().map(|s| s.to_string()),
+                            ..DescriptionFields::default()
+                        },
+                        "rss",
+                    ),
                     link: item.link().unwrap().to_string(),
-                    author: item.author().map(|s| s.to_string()),
+                    author: extract_author(item),
                     guid: item.guid().map(|s| s.value().to_string()),
                     publication_date: pub_date,
                 }
@@ -55,6 +68,29 @@ impl From<Channel> for FetchedFeed {
     }
 }
 
+pub fn has_undated_items(body: &[u8]) -> bool {
+    match Channel::read_from(body) {
+        Ok(channel) => channel
+            .items()
+            .iter()
+            .filter(|item| item.link().is_some())
+            .any(|item| {
+                item.pub_date()
+                    .map_or(true, |date| DateTime::parse_from_rfc2822(date).is_err())
+            }),
+        Err(_) => false,
+    }
+}
+
+pub fn extract_author(item: &Item) -> Option<String> {
+    let creators = item
+        .dublin_core_ext()
+        .map_or_else(Vec::new, |dublin_core| dublin_core.creators().to_vec());
+
+    reader::join_authors(creators)
+        .or_else(|| reader::join_authors(item.author().map(|author| author.to_string())))
+}
+
 fn parse_time(pub_date: Option<&str>) -> DateTime<Utc> {
     match pub_date {
         None => db::current_time(),
@@ -81,4 +117,53 @@ mod tests {
 
         assert_eq!(fetched_feed.title, "FeedForAll Sample Feed".to_string());
     }
+
+    #[test]
+    fn has_undated_items_finds_items_without_valid_dates() {
+        let feed = |pub_date: &str| {
+            format!(
+                "<rss version=\"2.0\"><channel><title>Feed</title><link>https://example.com</link>\
+                 <description>Feed</description><item><title>Item</title>\
+                 <link>https://example.com/1</link>{}</item></channel></rss>",
+                pub_date
+            )
+        };
+
+        assert!(!super::has_undated_items(
+            feed("<pubDate>Tue, 19 Oct 2004 13:38:55 -0400</pubDate>").as_bytes()
+        ));
+        assert!(super::has_undated_items(
+            feed("<pubDate>yesterday</pubDate>").as_bytes()
+        ));
+        assert!(super::has_undated_items(feed("").as_bytes()));
+    }
+
+    #[test]
+    fn extract_author_prefers_dublin_core_creator() {
+        let channel = |item: &str| {
+            Channel::from_str(&format!(
+                "<rss version=\"2.0\" xmlns:dc=\"http://purl.org/dc/elements/1.1/\"><channel>\
+                 <title>Feed</title><link>https://example.com</link><description>Feed</description>\
+                 <item><title>Item</title><link>https://example.com/1</link>{}</item>\
+                 </channel></rss>",
+                item
+            ))
+            .unwrap()
+        };
+
+        let creator =
+            channel("<author>jane@example.com (Jane)</author><dc:creator>Jane Doe</dc:creator>");
+        let author = channel("<author>jane@example.com (Jane)</author>");
+        let nobody = channel("<dc:creator> </dc:creator>");
+
+        assert_eq!(
+            super::extract_author(&creator.items()[0]),
+            Some("Jane Doe".to_string())
+        );
+        assert_eq!(
+            super::extract_author(&author.items()[0]),
+            Some("jane@example.com (Jane)".to_string())
+        );
+        assert_eq!(super::extract_author(&nobody.items()[0]), None);
+    }
 }
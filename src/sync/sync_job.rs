@@ -1,4 +1,6 @@
 use crate::bot::api;
+use crate::bot::i18n;
+use crate::bot::logic;
 use crate::db;
 use crate::db::feeds;
 use crate::db::telegram;
@@ -71,7 +73,9 @@ pub fn sync_all_feeds() {
 }
 
 pub async fn sync_feeds() {
-    let mut interval = time::interval(std::time::Duration::from_secs(60));
+    let mut interval = time::interval(std::time::Duration::from_secs(
+        logic::sync_interval_secs_from_env(),
+    ));
     loop {
         interval.tick().await;
         sync_all_feeds();
@@ -87,10 +91,14 @@ pub async fn sync_feed(feed_id: i64) {
             let feed = feeds::find(&db_connection, feed_id).unwrap();
             let chats = telegram::find_chats_by_feed_id(&db_connection, feed_id).unwrap();
 
-            let message = format!("{} can not be processed. It was removed.", feed.link);
-
             for chat in chats.into_iter() {
-                match api::send_message(chat.id, message.clone()).await {
+                let message = i18n::tf(
+                    &logic::language_of_chat(&chat),
+                    "feed_stale_removed",
+                    &[&feed.link],
+                );
+
+                match api::send_message(chat.id, message, false, false).await {
                     Ok(_) => (),
                     Err(error) => {
                         log::error!("Failed to send a message: {}", error);
@@ -103,6 +111,49 @@ pub async fn sync_feed(feed_id: i64) {
                 Err(err) => log::error!("Failed to remove feed: {} {}", feed_id, err),
             }
         }
+        Err(FeedSyncError::DeadError { failures_count }) => {
+            log::error!(
+                "Feed {} failed {} times in a row, it's marked as dead",
+                feed_id,
+                failures_count
+            );
+
+            let db_connection = db::establish_connection();
+
+            let feed = match feeds::find(&db_connection, feed_id) {
+                Some(feed) => feed,
+                None => {
+                    log::error!(
+                        "Dead feed {} was removed before notifying its chats",
+                        feed_id
+                    );
+                    return;
+                }
+            };
+
+            let chats = match telegram::find_chats_by_feed_id(&db_connection, feed_id) {
+                Ok(chats) => chats,
+                Err(error) => {
+                    log::error!("Failed to find chats of dead feed {}: {}", feed_id, error);
+                    return;
+                }
+            };
+
+            for chat in chats.into_iter() {
+                let message = i18n::tf(
+                    &logic::language_of_chat(&chat),
+                    "feed_dead",
+                    &[&feed.link, &failures_count, &feed.link],
+                );
+
+                match api::send_message(chat.id, message, false, false).await {
+                    Ok(_) => (),
+                    Err(error) => {
+                        log::error!("Failed to send a message: {}", error);
+                    }
+                }
+            }
+        }
         Err(error) => log::error!("Failed to process feed {}: {:?}", feed_id, error),
         Ok(_) => (),
     }